@@ -0,0 +1,96 @@
+//! A borderless, see-through window with a ball bouncing around in it,
+//! like a desktop pet.
+//!
+//! Press T to toggle whether the window stays on top of all other windows,
+//! and Escape to quit.
+
+use ggez::{
+    conf,
+    event::{self, EventHandler},
+    glam::*,
+    graphics::{self, Color, WindowLevel},
+    input::keyboard::{KeyCode, KeyInput},
+    Context, GameResult,
+};
+
+const SIZE: f32 = 300.0;
+const RADIUS: f32 = 40.0;
+
+struct MainState {
+    ball: graphics::Mesh,
+    pos: Vec2,
+    vel: Vec2,
+    on_top: bool,
+}
+
+impl MainState {
+    fn new(ctx: &mut Context) -> GameResult<MainState> {
+        let ball = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            vec2(0., 0.),
+            RADIUS,
+            0.5,
+            Color::from_rgb(255, 140, 60),
+        )?;
+        ctx.gfx.set_window_level(WindowLevel::AlwaysOnTop);
+
+        Ok(MainState {
+            ball,
+            pos: vec2(SIZE / 2., SIZE / 2.),
+            vel: vec2(90., 140.),
+            on_top: true,
+        })
+    }
+}
+
+impl EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let dt = ctx.time.delta().as_secs_f32();
+        self.pos += self.vel * dt;
+        for axis in 0..2 {
+            if self.pos[axis] < RADIUS || self.pos[axis] > SIZE - RADIUS {
+                self.vel[axis] = -self.vel[axis];
+                self.pos[axis] = self.pos[axis].clamp(RADIUS, SIZE - RADIUS);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        // Clearing to fully transparent black lets the desktop show through.
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::new(0., 0., 0., 0.));
+        canvas.draw(&self.ball, self.pos);
+        canvas.finish(ctx)
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeat: bool) -> GameResult {
+        match input.keycode {
+            Some(KeyCode::T) => {
+                self.on_top = !self.on_top;
+                ctx.gfx.set_window_level(if self.on_top {
+                    WindowLevel::AlwaysOnTop
+                } else {
+                    WindowLevel::Normal
+                });
+            }
+            Some(KeyCode::Escape) => ctx.request_quit(),
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+pub fn main() -> GameResult {
+    let cb = ggez::ContextBuilder::new("transparent", "ggez")
+        .window_setup(conf::WindowSetup::default().title("Desktop pet"))
+        .window_mode(
+            conf::WindowMode::default()
+                .dimensions(SIZE, SIZE)
+                .borderless(true)
+                .transparent(true),
+        );
+    let (mut ctx, event_loop) = cb.build()?;
+    let state = MainState::new(&mut ctx)?;
+    event::run(ctx, event_loop, state)
+}
@@ -9,17 +9,20 @@ use std::fmt;
 use std::io;
 use std::io::Read;
 use std::mem;
+use std::ops::RangeInclusive;
 use std::path;
 use std::time;
 
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::context::Has;
 use crate::error::GameError;
 use crate::error::GameResult;
 use crate::filesystem::Filesystem;
 use crate::filesystem::InternalClone;
+use crate::random::RandContext;
 
 /// A struct that contains all information for tracking sound info.
 ///
@@ -27,8 +30,15 @@ use crate::filesystem::InternalClone;
 /// of your `Context` object.
 pub struct AudioContext {
     fs: Filesystem,
-    _stream: rodio::OutputStream,
-    stream_handle: rodio::OutputStreamHandle,
+    /// `None` for a context created with [`AudioContext::null`].
+    stream: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
+    /// Mixes the sinks of all sounds before they reach the device, so they can be captured.
+    mixer: Option<Arc<rodio::dynamic_mixer::DynamicMixerController<f32>>>,
+    sample_rate: u32,
+    capture: Arc<CaptureSlot>,
+    capture_view: Option<CaptureView>,
+    reverb: Arc<SharedReverb>,
+    oneshots: Mutex<OneshotPool>,
 }
 
 impl AudioContext {
@@ -39,18 +49,213 @@ impl AudioContext {
                 "Could not initialize sound system using default output device (for some reason)",
             ))
         })?;
+
+        // Mix at the device's own rate and channel count, so sounds are only converted once.
+        let (channels, sample_rate) = default_output_format();
+        let (mixer, output) = rodio::dynamic_mixer::mixer(channels, sample_rate);
+        let capture = Arc::new(CaptureSlot::default());
+        stream_handle.play_raw(CaptureTap::new(output, capture.clone()))?;
+
         Ok(Self {
             fs: InternalClone::clone(fs),
-            _stream: stream,
-            stream_handle,
+            stream: Some((stream, stream_handle)),
+            mixer: Some(mixer),
+            sample_rate,
+            capture,
+            capture_view: None,
+            reverb: Arc::new(SharedReverb::new(ReverbParams::default())),
+            oneshots: Mutex::new(OneshotPool::default()),
         })
     }
+
+    /// Create an `AudioContext` that doesn't open an output device.
+    ///
+    /// [`Source`]s created from it decode and queue their data but never make a sound,
+    /// which is useful for headless contexts and tests. [`SpatialSource`]s
+    /// can't be created from it.
+    pub fn null(fs: &Filesystem) -> Self {
+        Self {
+            fs: InternalClone::clone(fs),
+            stream: None,
+            mixer: None,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            capture: Arc::new(CaptureSlot::default()),
+            capture_view: None,
+            reverb: Arc::new(SharedReverb::new(ReverbParams::default())),
+            oneshots: Mutex::new(OneshotPool::default()),
+        }
+    }
 }
 
 impl AudioContext {
     /// Returns the audio device.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a [null context](Self::null), see [`try_device`](Self::try_device).
     pub fn device(&self) -> &rodio::OutputStreamHandle {
-        &self.stream_handle
+        self.try_device().expect("null audio context has no device")
+    }
+
+    /// Returns the audio device, or an error if this is a [null context](Self::null).
+    pub fn try_device(&self) -> GameResult<&rodio::OutputStreamHandle> {
+        self.stream
+            .as_ref()
+            .map(|(_, handle)| handle)
+            .ok_or_else(|| GameError::AudioError(String::from("null audio context has no device")))
+    }
+
+    /// Sets the reverb that [`Source`]s send to with [`Source::set_reverb_send`].
+    ///
+    /// Sounds that are already playing pick up the change smoothly.
+    pub fn set_reverb(&self, params: ReverbParams) {
+        self.reverb.room_size.store(params.room_size.clamp(0., 1.));
+        self.reverb.damping.store(params.damping.clamp(0., 1.));
+        self.reverb.level.store(params.level.max(0.));
+    }
+
+    /// Returns the reverb set with [`set_reverb`](Self::set_reverb).
+    pub fn reverb(&self) -> ReverbParams {
+        ReverbParams {
+            room_size: self.reverb.room_size.load(),
+            damping: self.reverb.damping.load(),
+            level: self.reverb.level.load(),
+        }
+    }
+
+    /// Plays a sound once, without a [`Source`] that has to be kept alive until it ends.
+    ///
+    /// The sound plays on one of the context's one-shot voices, which are freed again
+    /// once their sound has ended and reused by later sounds, so this can be called from
+    /// `update` as often as needed. When all [`max_oneshots`](Self::max_oneshots) voices
+    /// are busy, the oldest sound is cut off to make room.
+    pub fn play_oneshot(&self, data: &SoundData, params: OneshotParams) -> GameResult {
+        use rodio::Source;
+        if !data.can_play() {
+            return Err(GameError::AudioError(
+                "Could not decode the given audio data".to_string(),
+            ));
+        }
+        let sound = rodio::Decoder::new(io::Cursor::new(data.clone()))?
+            .speed(params.pitch)
+            .convert_samples();
+
+        let mut pool = self.oneshots.lock().unwrap(/* not poisoned */);
+        let sink = pool.take_voice(|| self.sink())?;
+        sink.set_volume(params.volume);
+        sink.append(Pan::new(sound, params.pan));
+        pool.voices.push_back(sink);
+        Ok(())
+    }
+
+    /// Returns how many one-shot sounds are still playing.
+    pub fn oneshots_playing(&self) -> usize {
+        let mut pool = self.oneshots.lock().unwrap(/* not poisoned */);
+        pool.clean_up();
+        pool.voices.len()
+    }
+
+    /// Sets how many one-shot sounds can play at the same time, 32 by default.
+    ///
+    /// If more sounds than that are playing, the oldest ones are cut off right away.
+    pub fn set_max_oneshots(&self, max: usize) {
+        let mut pool = self.oneshots.lock().unwrap(/* not poisoned */);
+        pool.max = max.max(1);
+        while pool.voices.len() > pool.max {
+            let _ = pool.voices.pop_front();
+        }
+        let idle = pool.max - pool.voices.len();
+        pool.idle.truncate(idle);
+    }
+
+    /// Returns how many one-shot sounds can play at the same time.
+    pub fn max_oneshots(&self) -> usize {
+        self.oneshots.lock().unwrap(/* not poisoned */).max
+    }
+
+    /// Starts capturing the mixed output, keeping the latest `window_size` samples for
+    /// [`output_waveform`](Self::output_waveform) and
+    /// [`output_spectrum`](Self::output_spectrum), e.g. for a music visualizer.
+    ///
+    /// `window_size` is rounded up to a power of two. Calling this again changes the size
+    /// and starts over with silence.
+    ///
+    /// Samples are captured as the device asks for them, which is shortly before they
+    /// are heard: the capture runs ahead of the speakers by the device's buffer, usually
+    /// 10 to 50 milliseconds. On top of that, the window spans `window_size` samples,
+    /// so a larger window reacts more slowly but resolves lower frequencies. Sounds
+    /// played by a [`SpatialSource`] aren't captured.
+    pub fn enable_output_capture(&mut self, window_size: usize) {
+        let window_size = window_size.max(2).next_power_of_two();
+        let ring = Arc::new(CaptureRing::new(window_size * 2));
+        self.capture.set(Some(ring.clone()));
+        self.capture_view = Some(CaptureView {
+            ring,
+            waveform: vec![0.; window_size],
+            spectrum: vec![0.; window_size / 2],
+            imaginary: vec![0.; window_size],
+        });
+    }
+
+    /// Stops capturing the output, see [`enable_output_capture`](Self::enable_output_capture).
+    pub fn disable_output_capture(&mut self) {
+        self.capture.set(None);
+        self.capture_view = None;
+    }
+
+    /// Returns the latest samples of the mixed output, with all channels averaged,
+    /// oldest first. Empty unless [`enable_output_capture`](Self::enable_output_capture)
+    /// was called.
+    pub fn output_waveform(&mut self) -> &[f32] {
+        match self.capture_view {
+            Some(ref mut view) => {
+                view.ring.read_latest(&mut view.waveform);
+                &view.waveform
+            }
+            None => &[],
+        }
+    }
+
+    /// Returns the frequency spectrum of the latest samples of the mixed output, see
+    /// [`enable_output_capture`](Self::enable_output_capture).
+    ///
+    /// There is one value for each of the lower half of the window's frequency bins,
+    /// with bin `i` centered on `i * output_sample_rate() / window_size` Hz. The values
+    /// are magnitudes, scaled so that a full volume sine wave peaks at about 1.
+    pub fn output_spectrum(&mut self) -> &[f32] {
+        let Some(ref mut view) = self.capture_view else {
+            return &[];
+        };
+        view.ring.read_latest(&mut view.waveform);
+
+        // A Hann window keeps frequencies between bins from smearing over the spectrum.
+        let n = view.waveform.len();
+        for (i, sample) in view.waveform.iter_mut().enumerate() {
+            let (sin, _) = (std::f32::consts::PI * i as f32 / n as f32).sin_cos();
+            *sample *= sin * sin;
+        }
+        view.imaginary.fill(0.);
+        fft(&mut view.waveform, &mut view.imaginary);
+        for (i, bin) in view.spectrum.iter_mut().enumerate() {
+            *bin = view.waveform[i].hypot(view.imaginary[i]) * 4. / n as f32;
+        }
+        // The transform was done in place, so the waveform has to be read again.
+        view.ring.read_latest(&mut view.waveform);
+        &view.spectrum
+    }
+
+    /// Returns the sample rate the output is mixed and captured at.
+    pub fn output_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Creates a new sink playing to the audio device, or to nowhere for a null context.
+    fn sink(&self) -> GameResult<rodio::Sink> {
+        let (sink, output) = rodio::Sink::new_idle();
+        if let Some(ref mixer) = self.mixer {
+            mixer.add(output);
+        }
+        Ok(sink)
     }
 }
 
@@ -60,6 +265,88 @@ impl fmt::Debug for AudioContext {
     }
 }
 
+/// How a sound is played by [`AudioContext::play_oneshot`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OneshotParams {
+    /// The volume, 1 playing the sound as it is.
+    pub volume: f32,
+    /// The pitch ratio, which also changes the speed, like [`SoundSource::set_pitch`].
+    pub pitch: f32,
+    /// Where the sound is between the speakers, from -1 (left) to 1 (right).
+    pub pan: f32,
+}
+
+impl Default for OneshotParams {
+    fn default() -> Self {
+        OneshotParams {
+            volume: 1.,
+            pitch: 1.,
+            pan: 0.,
+        }
+    }
+}
+
+/// The voices of the one-shot sounds of an [`AudioContext`].
+///
+/// A sink stays in the mixer while it's kept, playing silence once its sound has ended,
+/// so finished voices are kept for the next sounds instead of creating a sink for each.
+/// Together, playing and idle voices never number more than `max`. Dropping a voice's
+/// sink cuts its sound off.
+struct OneshotPool {
+    /// Voices that are playing, oldest first.
+    voices: VecDeque<rodio::Sink>,
+    /// Voices whose sound has ended.
+    idle: Vec<rodio::Sink>,
+    max: usize,
+}
+
+impl Default for OneshotPool {
+    fn default() -> Self {
+        OneshotPool {
+            voices: VecDeque::new(),
+            idle: Vec::new(),
+            max: 32,
+        }
+    }
+}
+
+impl OneshotPool {
+    /// Moves the voices whose sound has ended to the idle ones.
+    fn clean_up(&mut self) {
+        let mut i = 0;
+        while i < self.voices.len() {
+            if self.voices[i].empty() {
+                // Unwrap since `i` is in bounds.
+                self.idle.push(self.voices.remove(i).unwrap());
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Returns a voice for a new sound: an idle one if there is one, otherwise a new one
+    /// from `new_sink`, or the oldest playing one, cut off, if all voices are in use.
+    fn take_voice(
+        &mut self,
+        new_sink: impl FnOnce() -> GameResult<rodio::Sink>,
+    ) -> GameResult<rodio::Sink> {
+        self.clean_up();
+        if let Some(sink) = self.idle.pop() {
+            return Ok(sink);
+        }
+        if self.voices.len() < self.max {
+            return new_sink();
+        }
+        // Unwrap since `max` is at least 1, so there is a voice.
+        let sink = self.voices.pop_front().unwrap();
+        // Skipping the playing sound keeps the sink usable, unlike stopping it.
+        for _ in 0..sink.len() {
+            sink.skip_one();
+        }
+        Ok(sink)
+    }
+}
+
 /// Static sound data stored in memory.
 /// It is `Arc`'ed, so cheap to clone.
 #[derive(Clone, Debug)]
@@ -70,8 +357,9 @@ impl SoundData {
     pub fn new<P: AsRef<path::Path>>(fs: &impl Has<Filesystem>, path: P) -> GameResult<Self> {
         let fs = fs.retrieve();
         let path = path.as_ref();
-        let file = &mut fs.open(path)?;
-        SoundData::from_read(file)
+        fs.open(path)
+            .and_then(|mut file| SoundData::from_read(&mut file))
+            .map_err(|e| e.context(format!("loading sound {}", path.display())))
     }
 
     /// Copies the data in the given slice into a new `SoundData` object.
@@ -282,6 +570,8 @@ impl SourceState {
 pub struct Source {
     sink: rodio::Sink,
     state: SourceState,
+    effects: Arc<EffectControls>,
+    reverb: Arc<SharedReverb>,
 }
 
 impl Source {
@@ -301,13 +591,75 @@ impl Source {
                 "Could not decode the given audio data".to_string(),
             ));
         }
-        let sink = rodio::Sink::try_new(audio.device())?;
+        let sink = audio.sink()?;
         let cursor = io::Cursor::new(data);
         Ok(Source {
             sink,
             state: SourceState::new(cursor),
+            effects: Arc::new(EffectControls {
+                lowpass: AtomicF32::new(f32::INFINITY),
+                reverb_send: AtomicF32::new(0.),
+            }),
+            reverb: audio.reverb.clone(),
         })
     }
+
+    /// Sets the cutoff frequency of a low-pass filter, muffling the sound above it, or
+    /// turns the filter off with `None`.
+    ///
+    /// This also affects the sound while it's playing, with the change smoothed over a few
+    /// milliseconds to avoid clicks.
+    pub fn set_lowpass(&mut self, cutoff_hz: impl Into<Option<f32>>) {
+        let cutoff = cutoff_hz.into().map_or(f32::INFINITY, |hz| hz.max(1.));
+        self.effects.lowpass.store(cutoff);
+    }
+
+    /// Returns the cutoff frequency of the low-pass filter, if there is one.
+    pub fn lowpass(&self) -> Option<f32> {
+        Some(self.effects.lowpass.load()).filter(|hz| hz.is_finite())
+    }
+
+    /// Sets how much of the sound is sent to the reverb of the [`AudioContext`], from 0
+    /// (the default, none) to 1.
+    ///
+    /// Like [`set_lowpass`](Self::set_lowpass), this also affects the sound while it's playing.
+    pub fn set_reverb_send(&mut self, level: f32) {
+        self.effects.reverb_send.store(level.clamp(0., 1.));
+    }
+
+    /// Returns how much of the sound is sent to the reverb.
+    pub fn reverb_send(&self) -> f32 {
+        self.effects.reverb_send.load()
+    }
+
+    /// Plays the sound like [`play`](SoundSource::play), at a random pitch within `range`.
+    ///
+    /// Varying the pitch a little, e.g. between `0.9` and `1.1`, keeps sounds that are
+    /// played often from getting monotonous. The pitch set with
+    /// [`set_pitch`](SoundSource::set_pitch) is left as it was.
+    ///
+    /// The pitch is drawn from `rng`, usually `ctx.random`, so it follows the
+    /// [seed](crate::conf::Conf::random_seed) like the rest of the game:
+    ///
+    /// ```rust,no_run
+    /// # use ggez::{audio::Source, Context, GameResult};
+    /// # fn t(ctx: &mut Context, sound: &mut Source) -> GameResult {
+    /// sound.play_with_pitch_variation(&ctx.audio, &mut ctx.random, 0.9..=1.1)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn play_with_pitch_variation(
+        &mut self,
+        audio: &impl Has<AudioContext>,
+        rng: &mut RandContext,
+        range: RangeInclusive<f32>,
+    ) -> GameResult {
+        let t = rng.f32();
+        let pitch = self.state.speed;
+        self.state.speed = range.start() + (range.end() - range.start()) * t;
+        let result = self.play(audio);
+        self.state.speed = pitch;
+        result
+    }
 }
 
 impl SoundSource for Source {
@@ -329,6 +681,8 @@ impl SoundSource for Source {
                 .skip_duration(self.state.skip_duration)
                 .speed(self.state.speed)
                 .fade_in(self.state.fade_in)
+                .convert_samples();
+            let sound = Effects::new(sound, self.effects.clone(), self.reverb.clone())
                 .periodic_access(self.state.query_interval, move |_| {
                     let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
                 });
@@ -338,6 +692,8 @@ impl SoundSource for Source {
                 .skip_duration(self.state.skip_duration)
                 .speed(self.state.speed)
                 .fade_in(self.state.fade_in)
+                .convert_samples();
+            let sound = Effects::new(sound, self.effects.clone(), self.reverb.clone())
                 .periodic_access(self.state.query_interval, move |_| {
                     let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
                 });
@@ -352,7 +708,7 @@ impl SoundSource for Source {
         self.stop(audio)?;
         self.play_later()?;
 
-        let new_sink = rodio::Sink::try_new(audio.device())?;
+        let new_sink = audio.sink()?;
         let old_sink = mem::replace(&mut self.sink, new_sink);
         old_sink.detach();
 
@@ -397,8 +753,7 @@ impl SoundSource for Source {
         // We also need to carry over information from the previous sink.
         let volume = self.volume();
 
-        let device = audio.device();
-        self.sink = rodio::Sink::try_new(device)?;
+        self.sink = audio.sink()?;
         self.state.play_time.store(0, Ordering::SeqCst);
 
         // Restore information from the previous link.
@@ -472,7 +827,7 @@ impl SpatialSource {
             ));
         }
         let sink = rodio::SpatialSink::try_new(
-            audio.device(),
+            audio.try_device()?,
             [0.0, 0.0, 0.0],
             [-1.0, 0.0, 0.0],
             [1.0, 0.0, 0.0],
@@ -533,7 +888,7 @@ impl SoundSource for SpatialSource {
         self.stop(audio)?;
         self.play_later()?;
 
-        let device = audio.device();
+        let device = audio.try_device()?;
         let new_sink = rodio::SpatialSink::try_new(
             device,
             self.emitter_position.into(),
@@ -590,7 +945,7 @@ impl SoundSource for SpatialSource {
         // We also need to carry over information from the previous sink.
         let volume = self.volume();
 
-        let device = audio.device();
+        let device = audio.try_device()?;
         self.sink = rodio::SpatialSink::try_new(
             device,
             self.emitter_position.into(),
@@ -660,3 +1015,701 @@ impl fmt::Debug for SpatialSource {
         write!(f, "<Spatial audio source: {self:p}>")
     }
 }
+
+/// Parameters of the reverb of an [`AudioContext`], see [`AudioContext::set_reverb`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReverbParams {
+    /// How long the reverb rings, from 0 (a small room) to 1 (a large hall).
+    pub room_size: f32,
+    /// How much quicker high frequencies die out than low ones, from 0 to 1.
+    pub damping: f32,
+    /// The volume of the reverb.
+    pub level: f32,
+}
+
+impl Default for ReverbParams {
+    fn default() -> Self {
+        ReverbParams {
+            room_size: 0.5,
+            damping: 0.5,
+            level: 1.,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    fn new(value: f32) -> Self {
+        AtomicF32(AtomicU32::new(value.to_bits()))
+    }
+
+    fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct SharedReverb {
+    room_size: AtomicF32,
+    damping: AtomicF32,
+    level: AtomicF32,
+}
+
+impl SharedReverb {
+    fn new(params: ReverbParams) -> Self {
+        SharedReverb {
+            room_size: AtomicF32::new(params.room_size),
+            damping: AtomicF32::new(params.damping),
+            level: AtomicF32::new(params.level),
+        }
+    }
+}
+
+/// The sample rate mixed at when the device doesn't report one.
+const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// Returns the channel count and sample rate the default output device plays at.
+fn default_output_format() -> (u16, u32) {
+    use rodio::cpal::traits::HostTrait;
+    use rodio::DeviceTrait;
+    rodio::cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.default_output_config().ok())
+        .map_or((2, DEFAULT_SAMPLE_RATE), |config| {
+            (config.channels(), config.sample_rate().0)
+        })
+}
+
+/// Hands the ring buffer to capture into over to the audio thread.
+#[derive(Debug, Default)]
+struct CaptureSlot {
+    changed: AtomicBool,
+    ring: Mutex<Option<Arc<CaptureRing>>>,
+}
+
+impl CaptureSlot {
+    fn set(&self, ring: Option<Arc<CaptureRing>>) {
+        let mut slot = self.ring.lock().unwrap(/* not poisoned */);
+        *slot = ring;
+        self.changed.store(true, Ordering::Release);
+    }
+}
+
+/// Mono samples written by the audio thread and read from the game thread without locking.
+///
+/// The ring holds twice the window, so a read usually finishes before the writer comes
+/// round to the samples being read.
+#[derive(Debug)]
+struct CaptureRing {
+    samples: Box<[AtomicF32]>,
+    written: AtomicUsize,
+}
+
+impl CaptureRing {
+    fn new(len: usize) -> Self {
+        CaptureRing {
+            samples: (0..len).map(|_| AtomicF32::new(0.)).collect(),
+            written: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends a sample; only ever called from one thread.
+    fn push(&self, sample: f32) {
+        let written = self.written.load(Ordering::Relaxed);
+        self.samples[written % self.samples.len()].store(sample);
+        self.written
+            .store(written.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Fills `out` with the latest samples, oldest first, padding with silence at the start.
+    fn read_latest(&self, out: &mut [f32]) {
+        let written = self.written.load(Ordering::Acquire);
+        let len = out.len();
+        let missing = len.saturating_sub(written);
+        for (i, sample) in out.iter_mut().enumerate() {
+            *sample = if i < missing {
+                0.
+            } else {
+                let index = written.wrapping_sub(len - i) % self.samples.len();
+                self.samples[index].load()
+            };
+        }
+    }
+}
+
+/// What the game thread keeps of the capture, so it can hand out slices.
+#[derive(Debug)]
+struct CaptureView {
+    ring: Arc<CaptureRing>,
+    waveform: Vec<f32>,
+    spectrum: Vec<f32>,
+    imaginary: Vec<f32>,
+}
+
+/// Passes the mixed output through to the device, copying it into the capture ring.
+///
+/// This never ends, even while nothing is playing, so the device keeps pulling from it.
+struct CaptureTap<S> {
+    inner: S,
+    slot: Arc<CaptureSlot>,
+    ring: Option<Arc<CaptureRing>>,
+    channels: u16,
+    frame_sum: f32,
+    frame_pos: u16,
+}
+
+impl<S> CaptureTap<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn new(inner: S, slot: Arc<CaptureSlot>) -> Self {
+        CaptureTap {
+            channels: inner.channels().max(1),
+            inner,
+            slot,
+            ring: None,
+            frame_sum: 0.,
+            frame_pos: 0,
+        }
+    }
+}
+
+impl<S> Iterator for CaptureTap<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.slot.changed.load(Ordering::Acquire) {
+            // Never wait for the game thread; if it holds the lock, pick the ring up next time.
+            if let Ok(ring) = self.slot.ring.try_lock() {
+                self.ring = ring.clone();
+                self.slot.changed.store(false, Ordering::Release);
+            }
+        }
+
+        let sample = self.inner.next().unwrap_or(0.);
+        if let Some(ref ring) = self.ring {
+            self.frame_sum += sample;
+            self.frame_pos += 1;
+            if self.frame_pos == self.channels {
+                ring.push(self.frame_sum / f32::from(self.channels));
+                self.frame_sum = 0.;
+                self.frame_pos = 0;
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S> rodio::Source for CaptureTap<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+/// Computes the discrete Fourier transform of a signal in place, with the radix-2
+/// Cooley-Tukey algorithm. The length must be a power of two, and at least 2.
+fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+    let n = real.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let step = -std::f32::consts::TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (step * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let re = real[b] * cos - imaginary[b] * sin;
+                let im = real[b] * sin + imaginary[b] * cos;
+                real[b] = real[a] - re;
+                imaginary[b] = imaginary[a] - im;
+                real[a] += re;
+                imaginary[a] += im;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Effect settings of a [`Source`], read by its playing sounds.
+#[derive(Debug)]
+struct EffectControls {
+    /// The low-pass cutoff in Hz, infinite when the filter is off.
+    lowpass: AtomicF32,
+    reverb_send: AtomicF32,
+}
+
+/// Time over which changes to the effect settings are smoothed, in seconds.
+const EFFECT_SMOOTHING: f32 = 0.01;
+/// The longest a reverb rings on after its sound has ended, in seconds.
+const MAX_REVERB_TAIL: u32 = 5;
+
+/// A rodio source applying the effects of a [`Source`] to its sound.
+///
+/// Works a frame (one sample per channel) at a time, since the reverb mixes all channels.
+struct Effects<S> {
+    inner: S,
+    controls: Arc<EffectControls>,
+    params: Arc<SharedReverb>,
+    channels: u16,
+    sample_rate: u32,
+    frame: Vec<f32>,
+    pos: usize,
+    /// Low-pass filter state of each channel.
+    filters: Vec<f32>,
+    reverb: Reverb,
+    /// The filter coefficient, reverb send and reverb level, smoothed towards their targets.
+    coefficient: f32,
+    send: f32,
+    level: f32,
+    smoothing: f32,
+    /// How many frames in a row the reverb has been silent.
+    quiet: usize,
+    /// How many frames the reverb may still ring for, once the sound has ended.
+    tail: Option<u32>,
+}
+
+impl<S> Effects<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn new(inner: S, controls: Arc<EffectControls>, params: Arc<SharedReverb>) -> Self {
+        let mut effects = Effects {
+            inner,
+            channels: 0,
+            sample_rate: 0,
+            frame: Vec::new(),
+            pos: 0,
+            filters: Vec::new(),
+            reverb: Reverb::new(1),
+            coefficient: 1.,
+            send: controls.reverb_send.load(),
+            level: params.level.load(),
+            smoothing: 1.,
+            quiet: 0,
+            tail: None,
+            controls,
+            params,
+        };
+        effects.configure();
+        effects.coefficient =
+            lowpass_coefficient(effects.controls.lowpass.load(), effects.sample_rate);
+        effects
+    }
+
+    /// Sets up the filters for the current channel count and sample rate of the sound.
+    fn configure(&mut self) {
+        self.channels = self.inner.channels().max(1);
+        self.sample_rate = self.inner.sample_rate().max(1);
+        self.filters = vec![0.; self.channels as usize];
+        self.reverb = Reverb::new(self.sample_rate);
+        self.smoothing = 1. - (-1. / (EFFECT_SMOOTHING * self.sample_rate as f32)).exp();
+        self.quiet = self.reverb.len();
+    }
+
+    /// Reads and processes the next frame, returning `false` at the end of the sound.
+    fn next_frame(&mut self) -> bool {
+        self.frame.clear();
+        self.pos = 0;
+        if self.tail.is_none() {
+            if (self.inner.channels().max(1), self.inner.sample_rate())
+                != (self.channels, self.sample_rate)
+            {
+                self.configure();
+            }
+            let frame = self.inner.by_ref().take(self.channels as usize);
+            self.frame.extend(frame);
+            if self.frame.is_empty() {
+                self.tail = Some(MAX_REVERB_TAIL * self.sample_rate);
+            }
+        }
+        if let Some(tail) = &mut self.tail {
+            // Let the reverb ring out, until it's quiet.
+            if *tail == 0 || self.quiet >= self.reverb.len() {
+                return false;
+            }
+            *tail -= 1;
+            self.frame.resize(self.channels as usize, 0.);
+        }
+
+        let coefficient = lowpass_coefficient(self.controls.lowpass.load(), self.sample_rate);
+        self.coefficient += (coefficient - self.coefficient) * self.smoothing;
+        self.send += (self.controls.reverb_send.load() - self.send) * self.smoothing;
+        self.level += (self.params.level.load() - self.level) * self.smoothing;
+
+        let mut mix = 0.;
+        for (sample, filter) in self.frame.iter_mut().zip(&mut self.filters) {
+            *filter = *sample * self.coefficient + *filter * (1. - self.coefficient);
+            *sample = *filter;
+            mix += *sample;
+        }
+
+        let input = mix / self.frame.len() as f32 * self.send;
+        if input.abs() > 1e-6 || self.quiet < self.reverb.len() {
+            let wet = self.reverb.process(
+                input,
+                self.params.room_size.load(),
+                self.params.damping.load(),
+            ) * self.level;
+            self.quiet = if input.abs() > 1e-6 || wet.abs() > 1e-5 {
+                0
+            } else {
+                self.quiet + 1
+            };
+            for sample in &mut self.frame {
+                *sample += wet;
+            }
+        }
+        true
+    }
+}
+
+impl<S> Iterator for Effects<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos == self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        self.pos += 1;
+        Some(self.frame[self.pos - 1])
+    }
+}
+
+impl<S> rodio::Source for Effects<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let buffered = self.frame.len() - self.pos;
+        match self.tail {
+            Some(_) => Some(buffered),
+            None => self.inner.current_frame_len().map(|len| len + buffered),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+/// A rodio source that plays its sound in stereo, panned between the speakers.
+///
+/// Mono sounds are played on both speakers; channels after the first two are dropped.
+struct Pan<S> {
+    inner: S,
+    gains: [f32; 2],
+    frame: [f32; 2],
+    pos: usize,
+}
+
+impl<S> Pan<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn new(inner: S, pan: f32) -> Self {
+        let pan = pan.clamp(-1., 1.);
+        Pan {
+            inner,
+            gains: [(1. - pan).min(1.), (1. + pan).min(1.)],
+            frame: [0.; 2],
+            pos: 2,
+        }
+    }
+}
+
+impl<S> Iterator for Pan<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.pos == 2 {
+            let channels = self.inner.channels().max(1) as usize;
+            let mut frame = self.inner.by_ref().take(channels);
+            let left = frame.next()?;
+            let right = if channels == 1 {
+                left
+            } else {
+                frame.next().unwrap_or(0.)
+            };
+            frame.for_each(drop);
+            self.frame = [left * self.gains[0], right * self.gains[1]];
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(self.frame[self.pos - 1])
+    }
+}
+
+impl<S> rodio::Source for Pan<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let channels = self.inner.channels().max(1) as usize;
+        let buffered = 2 - self.pos;
+        self.inner
+            .current_frame_len()
+            .map(|len| len / channels * 2 + buffered)
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Returns the coefficient of a one-pole low-pass filter, 1 letting everything through.
+fn lowpass_coefficient(cutoff_hz: f32, sample_rate: u32) -> f32 {
+    1. - (-std::f32::consts::TAU * cutoff_hz / sample_rate as f32).exp()
+}
+
+/// A small Schroeder reverb: parallel comb filters followed by all-pass filters.
+struct Reverb {
+    combs: [(Vec<f32>, usize, f32); 4],
+    allpasses: [(Vec<f32>, usize); 2],
+}
+
+impl Reverb {
+    fn new(sample_rate: u32) -> Self {
+        // Delays of the well-known Freeverb, in samples at 44.1 kHz.
+        let delay = |samples: u32| vec![0.; (samples * sample_rate / 44_100).max(1) as usize];
+        Reverb {
+            combs: [1116, 1188, 1277, 1356].map(|samples| (delay(samples), 0, 0.)),
+            allpasses: [556, 441].map(|samples| (delay(samples), 0)),
+        }
+    }
+
+    /// Returns the length of the longest delay line.
+    fn len(&self) -> usize {
+        self.combs[3].0.len() + self.allpasses[0].0.len()
+    }
+
+    fn process(&mut self, input: f32, room_size: f32, damping: f32) -> f32 {
+        let feedback = 0.7 + 0.28 * room_size;
+        let damping = 0.4 * damping;
+        let mut output = 0.;
+        for (buffer, pos, filter) in &mut self.combs {
+            let delayed = buffer[*pos];
+            *filter = delayed * (1. - damping) + *filter * damping;
+            buffer[*pos] = input * 0.05 + *filter * feedback;
+            *pos = (*pos + 1) % buffer.len();
+            output += delayed;
+        }
+        for (buffer, pos) in &mut self.allpasses {
+            let delayed = buffer[*pos];
+            buffer[*pos] = output + delayed * 0.5;
+            *pos = (*pos + 1) % buffer.len();
+            output = delayed - output;
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::tests::headless_context;
+    use rodio::{buffer::SamplesBuffer, Source as _};
+
+    fn effects(samples: Vec<f32>, lowpass: f32, send: f32) -> Effects<SamplesBuffer<f32>> {
+        let controls = Arc::new(EffectControls {
+            lowpass: AtomicF32::new(lowpass),
+            reverb_send: AtomicF32::new(send),
+        });
+        let params = Arc::new(SharedReverb::new(ReverbParams::default()));
+        Effects::new(SamplesBuffer::new(2, 44_100, samples), controls, params)
+    }
+
+    #[test]
+    fn headless_test_source_effects() {
+        // Without effects, the sound is left alone.
+        let sound = (0..200).map(|i| (i as f32 * 0.1).sin()).collect::<Vec<_>>();
+        let dry = effects(sound.clone(), f32::INFINITY, 0.).collect::<Vec<_>>();
+        assert_eq!(dry, sound);
+
+        // A low-pass filter lets a constant signal through and mutes the highest frequency.
+        let steady = effects(vec![0.5; 8000], 100., 0.).collect::<Vec<_>>();
+        assert!((steady[7999] - 0.5).abs() < 1e-3);
+        let buzz = (0..8000).map(|i| if i / 2 % 2 == 0 { 1. } else { -1. });
+        let muffled = effects(buzz.collect(), 100., 0.).collect::<Vec<_>>();
+        assert!(muffled[4000..].iter().all(|s| s.abs() < 0.05));
+
+        // The reverb rings on after a click, on both channels, and then stops.
+        let mut click = vec![0.; 100];
+        click[0] = 1.;
+        click[1] = 1.;
+        let mut wet = effects(click, f32::INFINITY, 1.);
+        assert_eq!((wet.channels(), wet.sample_rate()), (2, 44_100));
+        let wet = wet.by_ref().collect::<Vec<_>>();
+        assert!(wet.len() > 2 * 44_100 / 10);
+        assert!(wet.len() < 2 * 44_100 * MAX_REVERB_TAIL as usize);
+        assert!(wet[2000..]
+            .chunks(2)
+            .any(|frame| frame[0] != 0. && frame[0] == frame[1]));
+    }
+
+    #[test]
+    fn headless_test_oneshot_pool() {
+        // Voices are reused once their sound has ended, so no new sinks are made for them.
+        let sound = || SamplesBuffer::new(1, 44_100, vec![0.5; 10]);
+        let mut pool = OneshotPool {
+            max: 2,
+            ..OneshotPool::default()
+        };
+        let (sink, mut output) = rodio::Sink::new_idle();
+        sink.append(sound());
+        pool.voices.push_back(sink);
+        // Once the sound is done, the sink plays silence.
+        assert!(output.by_ref().take(100).any(|sample| sample == 0.));
+        let sink = pool
+            .take_voice(|| panic!("the idle voice should be reused"))
+            .unwrap();
+        assert!(pool.voices.is_empty() && pool.idle.is_empty());
+        sink.append(sound());
+        pool.voices.push_back(sink);
+        let sink = pool.take_voice(|| Ok(rodio::Sink::new_idle().0)).unwrap();
+        sink.append(sound());
+        pool.voices.push_back(sink);
+        // With every voice busy, the oldest one is cut off and reused.
+        let _ = pool
+            .take_voice(|| panic!("there can't be more than two voices"))
+            .unwrap();
+        assert_eq!(pool.voices.len(), 1);
+
+        // Mono sounds are spread to both speakers before panning.
+        let mono = Pan::new(SamplesBuffer::new(1, 44_100, vec![1., 0.5]), -1.);
+        assert_eq!(mono.channels(), 2);
+        assert_eq!(mono.collect::<Vec<_>>(), [1., 0., 0.5, 0.]);
+        let stereo = Pan::new(SamplesBuffer::new(2, 44_100, vec![1., 1., 0.5, 0.5]), 0.5);
+        assert_eq!(stereo.collect::<Vec<_>>(), [0.5, 1., 0.25, 0.5]);
+
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+        // Sounds never end in a null context, so the oldest voices are stolen.
+        let data = SoundData::from_bytes(include_bytes!("../resources/pew.wav"));
+        ctx.audio.set_max_oneshots(2);
+        for _ in 0..3 {
+            ctx.audio
+                .play_oneshot(&data, OneshotParams::default())
+                .unwrap();
+        }
+        assert_eq!(ctx.audio.oneshots_playing(), 2);
+        ctx.audio.set_max_oneshots(1);
+        assert_eq!(ctx.audio.oneshots_playing(), 1);
+
+        let garbage = SoundData::from_bytes(b"not a sound");
+        assert!(ctx
+            .audio
+            .play_oneshot(&garbage, OneshotParams::default())
+            .is_err());
+        assert_eq!(ctx.audio.oneshots_playing(), 1);
+    }
+
+    #[test]
+    fn headless_test_pitch_variation() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let data = SoundData::from_bytes(include_bytes!("../resources/pew.wav"));
+        let mut source = Source::from_data(&ctx, data).unwrap();
+
+        // The pitch comes from the given generator, one number per play.
+        ctx.random.set_seed(7);
+        let mut expected = RandContext::new(7);
+        for _ in 0..3 {
+            source
+                .play_with_pitch_variation(&ctx.audio, &mut ctx.random, 0.9..=1.1)
+                .unwrap();
+            let _ = expected.f32();
+        }
+        assert_eq!(ctx.random, expected);
+    }
+
+    #[test]
+    fn headless_test_output_capture() {
+        // The tap passes stereo samples through and captures their average.
+        let slot = Arc::new(CaptureSlot::default());
+        let ring = Arc::new(CaptureRing::new(8));
+        slot.set(Some(ring.clone()));
+        let stereo = SamplesBuffer::new(2, 44_100, vec![1., 0., 0.5, 0.5, -1., 0.]);
+        let tap = CaptureTap::new(stereo, slot);
+        let out = tap.take(8).collect::<Vec<_>>();
+        assert_eq!(out, [1., 0., 0.5, 0.5, -1., 0., 0., 0.]);
+        let mut latest = [9.; 6];
+        ring.read_latest(&mut latest);
+        assert_eq!(latest, [0., 0., 0.5, 0.5, -0.5, 0.]);
+
+        // A full volume sine in the middle of bin 8 shows up there at about 1.
+        let n = 64;
+        let mut real = (0..n)
+            .map(|i| (std::f32::consts::TAU * 8. * i as f32 / n as f32).cos())
+            .collect::<Vec<_>>();
+        let mut imaginary = vec![0.; n];
+        fft(&mut real, &mut imaginary);
+        assert!((real[8].hypot(imaginary[8]) * 2. / n as f32 - 1.).abs() < 1e-4);
+        assert!(real[3].hypot(imaginary[3]) < 1e-3);
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        assert!(ctx.audio.output_spectrum().is_empty());
+        ctx.audio.enable_output_capture(100);
+        assert_eq!(ctx.audio.output_waveform(), [0.; 128]);
+        assert_eq!(ctx.audio.output_spectrum(), [0.; 64]);
+        ctx.audio.disable_output_capture();
+        assert!(ctx.audio.output_waveform().is_empty());
+    }
+}
@@ -14,10 +14,13 @@
 
 use std::convert::TryFrom;
 use std::io;
+use std::path::Path;
 
 use winit::dpi::PhysicalSize;
 
+use crate::context::{Context, Has};
 use crate::error::{GameError, GameResult};
+use crate::filesystem::Filesystem;
 
 /// Possible fullscreen modes.
 #[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -154,6 +157,10 @@ impl WindowMode {
     }
 
     /// Set whether a window should be transparent.
+    ///
+    /// Where the frame is cleared to a transparent color, like `Color::new(0., 0., 0., 0.)`,
+    /// the desktop behind the window shows through. Platforms whose surfaces can't be
+    /// transparent fall back to an opaque window.
     #[must_use]
     pub fn transparent(mut self, transparent: bool) -> Self {
         self.transparent = transparent;
@@ -236,6 +243,7 @@ impl WindowMode {
 ///     vsync: true,
 ///     icon: "".to_owned(),
 ///     srgb: true,
+///     quit_shortcut_event: false,
 /// }
 /// # , WindowSetup::default()); }
 /// ```
@@ -259,8 +267,18 @@ pub struct WindowSetup {
     pub icon: String,
     /// Whether or not to enable sRGB (gamma corrected color)
     /// handling on the display.
+    ///
+    /// `true` selects [`ColorSpaceMode::SrgbCorrect`](crate::graphics::ColorSpaceMode::SrgbCorrect)
+    /// and `false` selects [`ColorSpaceMode::Legacy`](crate::graphics::ColorSpaceMode::Legacy).
     #[default = true]
     pub srgb: bool,
+    /// Whether the platform's quit shortcut (Cmd-Q on macOS) goes through
+    /// [`quit_event`](crate::event::EventHandler::quit_event) instead of terminating the game.
+    ///
+    /// On macOS this replaces the default application menu, which is what handles Cmd-Q
+    /// otherwise. It has no effect on other platforms.
+    #[serde(default)]
+    pub quit_shortcut_event: bool,
 }
 
 impl WindowSetup {
@@ -298,6 +316,13 @@ impl WindowSetup {
         self.srgb = active;
         self
     }
+
+    /// Set whether the quit shortcut goes through `quit_event`.
+    #[must_use]
+    pub fn quit_shortcut_event(mut self, active: bool) -> Self {
+        self.quit_shortcut_event = active;
+        self
+    }
 }
 
 /// Possible graphics backends.
@@ -390,6 +415,7 @@ impl From<NumSamples> for u8 {
 ///     window_mode: WindowMode::default(),
 ///     window_setup: WindowSetup::default(),
 ///     backend: Backend::default(),
+///     random_seed: None,
 /// }
 /// # , Conf::default()); }
 /// ```
@@ -403,6 +429,11 @@ pub struct Conf {
     pub window_setup: WindowSetup,
     /// Graphics backend configuration
     pub backend: Backend,
+    /// Seed for [`ctx.random`](crate::random::RandContext), taken from the clock if unset
+    ///
+    /// This is signed because TOML integers are; negative seeds are used as their `u64` bit
+    /// pattern.
+    pub random_seed: Option<i64>,
 }
 
 impl Conf {
@@ -428,6 +459,54 @@ impl Conf {
         Ok(())
     }
 
+    /// Returns the settings the context is running with right now.
+    ///
+    /// Unlike the `Conf` the context was created with, this includes changes made since,
+    /// like a new [window mode](crate::graphics::GraphicsContext::apply_window_mode), the
+    /// size the user resized the window to, the window title and vsync.
+    pub fn from_context(ctx: &Context) -> Conf {
+        let mut conf = ctx.conf.clone();
+        conf.window_mode = ctx.gfx.window_mode();
+        conf.window_setup.vsync = ctx.gfx.vsync();
+        if let Some(ref window) = ctx.gfx.window {
+            conf.window_setup.title = window.title();
+        }
+        conf
+    }
+
+    /// Saves the `Conf` to `path` in the user directory, like `/conf.toml` to have it
+    /// loaded the next time the game starts. Returns `false` without writing anything if
+    /// the file already holds these settings.
+    ///
+    /// If there is a file at `path` already, in the user directory or the resources, only
+    /// the values that changed are replaced, so its comments and layout are kept. Files
+    /// too unusual to edit this way are written from scratch.
+    pub fn save(&self, fs: &impl Has<Filesystem>, path: impl AsRef<Path>) -> GameResult<bool> {
+        let fs = fs.retrieve();
+        let path = path.as_ref();
+        let old = match fs.open(path) {
+            Ok(mut file) => {
+                let mut old = String::new();
+                let _ = io::Read::read_to_string(&mut file, &mut old)?;
+                Some(old)
+            }
+            Err(GameError::ResourceNotFound(..)) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(ref old) = old {
+            if toml::from_str::<Conf>(old).is_ok_and(|old| old == *self) {
+                return Ok(false);
+            }
+        }
+
+        let new = match old.as_deref().and_then(|old| update_toml(old, self)) {
+            Some(new) => new,
+            None => toml::to_string(self)?,
+        };
+        fs.write_user_file_atomic(path, new.as_bytes())?;
+        Ok(true)
+    }
+
     /// Sets the window mode
     #[must_use]
     pub fn window_mode(mut self, window_mode: WindowMode) -> Self {
@@ -441,6 +520,155 @@ impl Conf {
         self.backend = backend;
         self
     }
+
+    /// Sets the seed for [`ctx.random`](crate::random::RandContext)
+    #[must_use]
+    pub fn random_seed(mut self, seed: i64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+}
+
+/// Rewrites the values in the TOML document `old` to the ones of `conf`, keeping everything
+/// else as it is. Returns `None` if `old` uses syntax this doesn't handle.
+fn update_toml(old: &str, conf: &Conf) -> Option<String> {
+    fn flatten(
+        table: &toml::value::Table,
+        path: &[String],
+        out: &mut Vec<(Vec<String>, String, String)>,
+    ) -> Option<()> {
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(table) => {
+                    let mut path = path.to_vec();
+                    path.push(key.clone());
+                    flatten(table, &path, out)?;
+                }
+                toml::Value::Array(array) if array.iter().any(toml::Value::is_table) => {
+                    return None;
+                }
+                value => out.push((path.to_vec(), key.clone(), value.to_string())),
+            }
+        }
+        Some(())
+    }
+
+    let bare = |key: &str| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    // Where the comment of a value starts, if it has one, skipping `#`s inside strings.
+    let comment_start = |value: &str| {
+        let mut quote = None;
+        let mut escaped = false;
+        for (i, c) in value.char_indices() {
+            match (quote, c) {
+                (Some('"'), '\\') if !escaped => {
+                    escaped = true;
+                    continue;
+                }
+                (Some(q), c) if c == q && !escaped => quote = None,
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '#') => return Some(i),
+                _ => (),
+            }
+            escaped = false;
+        }
+        None
+    };
+
+    let toml::Value::Table(table) = toml::Value::try_from(conf).ok()? else {
+        return None;
+    };
+    let mut values = Vec::new();
+    flatten(&table, &[], &mut values)?;
+
+    let mut lines = Vec::new();
+    let mut done = vec![false; values.len()];
+    let mut table = Vec::new();
+    // Where to insert the missing keys of each table that's in the document.
+    let mut ends = vec![(Vec::new(), 0)];
+    for line in old.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("[[") {
+            return None;
+        }
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let header = &header[..header.find(']')?];
+            table = header.split('.').map(|key| key.trim().to_owned()).collect();
+            if !table.iter().all(|key| bare(key)) {
+                return None;
+            }
+            lines.push(line.to_owned());
+            ends.push((table.clone(), lines.len()));
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            lines.push(line.to_owned());
+            continue;
+        }
+
+        let equals = line.find('=')?;
+        let key = line[..equals].trim();
+        let rest = &line[equals + 1..];
+        let value = &rest[..comment_start(rest).unwrap_or(rest.len())];
+        let value_text = value.trim();
+        let multiline = value_text.starts_with("\"\"\"")
+            || value_text.starts_with("'''")
+            || value_text.matches('[').count() != value_text.matches(']').count()
+            || value_text.matches('{').count() != value_text.matches('}').count();
+        if !bare(key) || multiline {
+            return None;
+        }
+        // Keys that aren't in the new settings, like an unset `random_seed`, are dropped.
+        if let Some(i) = values.iter().position(|(t, k, _)| *t == table && k == key) {
+            let leading = value.len() - value.trim_start().len();
+            let trailing = value.trim_end().len();
+            lines.push(format!(
+                "{}{}{}",
+                &line[..equals + 1 + leading],
+                values[i].2,
+                &rest[trailing..]
+            ));
+            done[i] = true;
+            ends.last_mut().unwrap(/* never empty */).1 = lines.len();
+        }
+    }
+
+    // Missing keys go after the last key of their table, or into a new table at the end.
+    let mut inserts: Vec<(usize, String)> = Vec::new();
+    let mut appended: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+    for (i, (table, key, value)) in values.iter().enumerate() {
+        if done[i] {
+            continue;
+        }
+        let line = format!("{key} = {value}");
+        match ends.iter().rev().find(|(t, _)| t == table) {
+            Some(&(_, end)) => inserts.push((end, line)),
+            None => match appended.iter_mut().find(|(t, _)| t == table) {
+                Some((_, keys)) => keys.push(line),
+                None => appended.push((table.clone(), vec![line])),
+            },
+        }
+    }
+    // Inserting from the back keeps the earlier positions valid, and the keys in order.
+    inserts.sort_by_key(|(end, _)| *end);
+    for (end, line) in inserts.into_iter().rev() {
+        lines.insert(end, line);
+    }
+    for (table, keys) in appended {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", table.join(".")));
+        lines.extend(keys);
+    }
+
+    let mut new = lines.join("\n");
+    new.push('\n');
+    (toml::from_str::<Conf>(&new).ok()? == *conf).then_some(new)
 }
 
 #[cfg(test)]
@@ -458,4 +686,65 @@ mod tests {
         let c2 = conf::Conf::from_toml_file(&mut reader).unwrap();
         assert_eq!(c1, c2);
     }
+
+    #[test]
+    fn headless_test_update_toml() {
+        let old = "# Settings for my game\n\
+                   [window_setup]\n\
+                   title = \"My game\" # shown in the title bar\n\
+                   vsync = true\n\
+                   \n\
+                   [window_mode]\n\
+                   width   = 800.0\n\
+                   height  = 600.0\n";
+        let mut c = conf::Conf::new();
+        c.window_setup.title = "My # game".to_owned();
+        c.window_mode.width = 1024.0;
+        let new = conf::update_toml(old, &c).unwrap();
+        assert_eq!(toml::from_str::<conf::Conf>(&new).unwrap(), c);
+
+        let lines: Vec<&str> = new.lines().collect();
+        assert_eq!(lines[..2], ["# Settings for my game", "[window_setup]"]);
+        assert_eq!(lines[2], "title = \"My # game\" # shown in the title bar");
+        // The other keys are added to the tables they belong to.
+        let setup_end = lines.iter().position(|line| line.is_empty()).unwrap();
+        assert!(lines[..setup_end].contains(&"srgb = true"));
+        assert_eq!(lines[setup_end + 1], "[window_mode]");
+        assert_eq!(lines[setup_end + 2], "width   = 1024.0");
+        assert_eq!(lines[setup_end + 3], "height  = 600.0");
+        assert!(lines.contains(&"[backend]"));
+
+        // Keys that aren't set anymore are removed.
+        let mut seeded = c.clone();
+        seeded.random_seed = Some(5);
+        let with_seed = conf::update_toml(&new, &seeded).unwrap();
+        assert!(with_seed.contains("random_seed = 5"));
+        // Seeds past `i64::MAX` are stored as negative numbers, which TOML can hold.
+        seeded.random_seed = Some(u64::MAX as i64);
+        let negative = conf::update_toml(&new, &seeded).unwrap();
+        assert_eq!(toml::from_str::<conf::Conf>(&negative).unwrap(), seeded);
+        assert_eq!(conf::update_toml(&with_seed, &c).unwrap(), new);
+
+        assert!(conf::update_toml("window_mode.width = 5.0\n", &c).is_none());
+    }
+
+    #[test]
+    fn headless_test_apply_window_mode() {
+        let Some(mut ctx) = crate::context::tests::headless_context() else {
+            return;
+        };
+        let mode = conf::WindowMode::default().dimensions(32., 24.);
+        ctx.gfx.apply_window_mode(&mode).unwrap();
+        assert_eq!(ctx.gfx.drawable_size(), (32., 24.));
+        assert_eq!(conf::Conf::from_context(&ctx).window_mode, mode);
+
+        // A mode that can't be applied leaves the current one alone.
+        let bad = mode
+            .dimensions(64., 64.)
+            .min_dimensions(32., 32.)
+            .max_dimensions(16., 16.);
+        assert!(ctx.gfx.apply_window_mode(&bad).is_err());
+        assert_eq!(ctx.gfx.drawable_size(), (32., 24.));
+        assert_eq!(ctx.gfx.window_mode(), mode);
+    }
 }
@@ -9,10 +9,12 @@ pub use winit;
 use crate::audio;
 use crate::conf;
 use crate::error::GameResult;
-use crate::filesystem::Filesystem;
+use crate::event;
+use crate::filesystem::{Filesystem, ResourceSource};
 use crate::graphics;
 use crate::graphics::GraphicsContext;
 use crate::input;
+use crate::random;
 use crate::timer;
 
 /// A `Context` is an object that holds on to global resources.
@@ -42,6 +44,8 @@ pub struct Context {
     pub gfx: GraphicsContext,
     /// Timer state.
     pub time: timer::TimeContext,
+    /// Random number generator, seeded from [`Conf::random_seed`](crate::conf::Conf::random_seed).
+    pub random: random::RandContext,
     /// Audio context.
     #[cfg(feature = "audio")]
     pub audio: audio::AudioContext,
@@ -52,6 +56,8 @@ pub struct Context {
     /// Gamepad input context.
     #[cfg(feature = "gamepad")]
     pub gamepad: input::gamepad::GamepadContext,
+    /// Input actions, bound to keys, mouse buttons and gamepad input.
+    pub actions: input::action::ActionContext,
 
     /// The Conf object the Context was created with.
     /// It's here just so that we can see the original settings,
@@ -66,6 +72,23 @@ pub struct Context {
     ///
     /// It's exposed here for people who want to roll their own event loop.
     pub quit_requested: bool,
+    /// What caused the quit request, if `quit_requested` is set.
+    pub(crate) quit_source: QuitSource,
+    pub(crate) error_policy: event::ErrorPolicy,
+    pub(crate) error_overlay: graphics::ErrorOverlay,
+}
+
+/// What caused a request to quit the game, see [`Context::quit_requested_by`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuitSource {
+    /// The window's close button was pressed, or the window manager asked the window to close
+    /// in some other way (such as through Alt-F4 on Windows and most Linux desktops).
+    WindowClose,
+    /// The platform's quit shortcut was pressed (Cmd-Q on macOS), see
+    /// [`WindowSetup::quit_shortcut_event`](crate::conf::WindowSetup::quit_shortcut_event).
+    Shortcut,
+    /// The game called [`Context::request_quit`], or set [`Context::quit_requested`] itself.
+    Requested,
 }
 
 impl Context {
@@ -74,7 +97,45 @@ impl Context {
     /// returns `Ok(false)`, then [`Context.continuing`](struct.Context.html#structfield.continuing)
     /// is set to `false` and the loop breaks.
     pub fn request_quit(&mut self) {
-        self.quit_requested = true;
+        self.request_quit_from(QuitSource::Requested);
+    }
+
+    pub(crate) fn request_quit_from(&mut self, source: QuitSource) {
+        if !self.quit_requested {
+            self.quit_requested = true;
+            self.quit_source = source;
+        }
+    }
+
+    /// Returns what caused the pending quit request, or `None` if there is none.
+    ///
+    /// Closing the window, the platform's quit shortcut and [`request_quit`](Self::request_quit)
+    /// all end up calling [`quit_event`](crate::event::EventHandler::quit_event) at the start of
+    /// the next frame, and this can be used there to tell them apart.
+    /// To ask the player for confirmation before quitting, return `true` from `quit_event`,
+    /// show a dialog over the next frames and call `request_quit` again once they confirm.
+    pub fn quit_requested_by(&self) -> Option<QuitSource> {
+        self.quit_requested.then_some(self.quit_source)
+    }
+
+    /// Cancels a quit request before [`quit_event`](crate::event::EventHandler::quit_event) is called for it.
+    pub fn cancel_quit(&mut self) {
+        self.quit_requested = false;
+    }
+
+    /// Returns what the event loop does with fatal errors from `update()` and `draw()`.
+    pub fn error_policy(&self) -> event::ErrorPolicy {
+        self.error_policy
+    }
+
+    /// Sets what the event loop does with fatal errors from `update()` and `draw()`, see
+    /// [`event::run_with()`](crate::event::run_with). Switching back to
+    /// [`ErrorPolicy::Exit`](crate::event::ErrorPolicy::Exit) hides a shown error.
+    pub fn set_error_policy(&mut self, policy: event::ErrorPolicy) {
+        self.error_policy = policy;
+        if policy == event::ErrorPolicy::Exit {
+            self.error_overlay.dismiss();
+        }
     }
 }
 
@@ -174,8 +235,22 @@ impl Context {
     ) -> GameResult<(Context, winit::event_loop::EventLoop<()>)> {
         #[cfg(feature = "audio")]
         let audio_context = audio::AudioContext::new(&fs)?;
-        let events_loop = winit::event_loop::EventLoop::new();
+        #[allow(unused_mut)]
+        let mut events_loop_builder = winit::event_loop::EventLoopBuilder::new();
+        #[cfg(target_os = "macos")]
+        if conf.window_setup.quit_shortcut_event {
+            // Without the default menu, Cmd-Q arrives as a key press instead of terminating the
+            // application, so it can go through `quit_event` (see `event::process_event`).
+            use winit::platform::macos::EventLoopBuilderExtMacOS;
+            let _ = events_loop_builder.with_default_menu(false);
+        }
+        let events_loop = events_loop_builder.build();
         let timer_context = timer::TimeContext::new();
+        let random_context = conf
+            .random_seed
+            .map_or_else(random::RandContext::from_time, |seed| {
+                random::RandContext::new(seed as u64)
+            });
         let graphics_context =
             graphics::context::GraphicsContext::new(game_id, &events_loop, &conf, &fs)?;
 
@@ -185,17 +260,56 @@ impl Context {
             gfx: graphics_context,
             continuing: true,
             quit_requested: false,
+            quit_source: QuitSource::Requested,
             time: timer_context,
+            random: random_context,
             #[cfg(feature = "audio")]
             audio: audio_context,
             keyboard: input::keyboard::KeyboardContext::new(),
             mouse: input::mouse::MouseContext::new(),
             #[cfg(feature = "gamepad")]
             gamepad: input::gamepad::GamepadContext::new()?,
+            actions: input::action::ActionContext::new(),
+            error_policy: event::ErrorPolicy::default(),
+            error_overlay: graphics::ErrorOverlay::default(),
         };
 
         Ok((ctx, events_loop))
     }
+
+    /// Like [`from_conf`](#method.from_conf) but without a window, event loop or audio device.
+    /// Usually called by [`ContextBuilder::build_headless()`](struct.ContextBuilder.html#method.build_headless).
+    fn from_conf_headless(conf: conf::Conf, fs: Filesystem) -> GameResult<Context> {
+        #[cfg(feature = "audio")]
+        let audio_context = audio::AudioContext::null(&fs);
+        let timer_context = timer::TimeContext::new();
+        let random_context = conf
+            .random_seed
+            .map_or_else(random::RandContext::from_time, |seed| {
+                random::RandContext::new(seed as u64)
+            });
+        let graphics_context = graphics::context::GraphicsContext::new_headless(&conf, &fs)?;
+
+        Ok(Context {
+            conf,
+            fs,
+            gfx: graphics_context,
+            continuing: true,
+            quit_requested: false,
+            quit_source: QuitSource::Requested,
+            time: timer_context,
+            random: random_context,
+            #[cfg(feature = "audio")]
+            audio: audio_context,
+            keyboard: input::keyboard::KeyboardContext::new(),
+            mouse: input::mouse::MouseContext::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad: input::gamepad::GamepadContext::new()?,
+            actions: input::action::ActionContext::new(),
+            error_policy: event::ErrorPolicy::default(),
+            error_overlay: graphics::ErrorOverlay::default(),
+        })
+    }
 }
 
 use std::borrow::Cow;
@@ -209,8 +323,7 @@ pub struct ContextBuilder {
     pub(crate) conf: conf::Conf,
     pub(crate) resources_dir_name: path::PathBuf,
     pub(crate) resources_zip_name: path::PathBuf,
-    pub(crate) paths: Vec<path::PathBuf>,
-    pub(crate) memory_zip_files: Vec<Cow<'static, [u8]>>,
+    pub(crate) sources: Vec<(ResourceSource, i32)>,
     pub(crate) load_conf_file: bool,
 }
 
@@ -223,8 +336,7 @@ impl ContextBuilder {
             conf: conf::Conf::default(),
             resources_dir_name: "resources".into(),
             resources_zip_name: "resources.zip".into(),
-            paths: vec![],
-            memory_zip_files: vec![],
+            sources: vec![],
             load_conf_file: true,
         }
     }
@@ -280,12 +392,25 @@ impl ContextBuilder {
 
     /// Add a new read-only filesystem path to the places to search
     /// for resources.
+    ///
+    /// This is the same as `add_resource_source(ResourceSource::Dir(path), 0)`.
     #[must_use]
-    pub fn add_resource_path<T>(mut self, path: T) -> Self
+    pub fn add_resource_path<T>(self, path: T) -> Self
     where
         T: Into<path::PathBuf>,
     {
-        self.paths.push(path.into());
+        self.add_resource_source(ResourceSource::Dir(path.into()), 0)
+    }
+
+    /// Add a directory or zip file to the places to search for resources.
+    ///
+    /// Sources with a higher `priority` are searched first, and sources with the same priority
+    /// in the order they were added. The default resource directory, zip file and user
+    /// directories have a priority of 0. See the [`filesystem`](crate::filesystem) module
+    /// for details.
+    #[must_use]
+    pub fn add_resource_source(mut self, source: ResourceSource, priority: i32) -> Self {
+        self.sources.push((source, priority));
         self
     }
 
@@ -299,14 +424,14 @@ impl ContextBuilder {
     ///     .add_zipfile_bytes(include_bytes!("../resources.zip").to_vec())
     ///     .build();
     /// ```
+    ///
+    /// This is the same as `add_resource_source(ResourceSource::Memory(bytes), 0)`.
     #[must_use]
-    pub fn add_zipfile_bytes<B>(mut self, bytes: B) -> Self
+    pub fn add_zipfile_bytes<B>(self, bytes: B) -> Self
     where
         B: Into<Cow<'static, [u8]>>,
     {
-        let cow = bytes.into();
-        self.memory_zip_files.push(cow);
-        self
+        self.add_resource_source(ResourceSource::Memory(bytes.into()), 0)
     }
 
     /// Specifies whether or not to load the `conf.toml` file if it
@@ -321,6 +446,26 @@ impl ContextBuilder {
 
     /// Build the `Context`.
     pub fn build(self) -> GameResult<(Context, winit::event_loop::EventLoop<()>)> {
+        let game_id = self.game_id.clone();
+        let (config, fs) = self.build_filesystem()?;
+        Context::from_conf(game_id.as_ref(), config, fs)
+    }
+
+    /// Build a headless `Context`, with no window and no event loop.
+    ///
+    /// Drawing goes to off-screen images only: `ctx.gfx.frame()` is sized according to
+    /// the window mode and can be read back with [`Image::to_pixels`](crate::graphics::Image::to_pixels).
+    /// Audio plays into a [null context](crate::audio::AudioContext::null).
+    /// Since there is no event loop, frames have to be driven manually with
+    /// [`GraphicsContext::begin_frame`] and [`GraphicsContext::end_frame`].
+    ///
+    /// This is mostly useful for testing drawing code, for example on CI.
+    pub fn build_headless(self) -> GameResult<Context> {
+        let (config, fs) = self.build_filesystem()?;
+        Context::from_conf_headless(config, fs)
+    }
+
+    fn build_filesystem(self) -> GameResult<(conf::Conf, Filesystem)> {
         let fs = Filesystem::new(
             self.game_id.as_ref(),
             self.author.as_ref(),
@@ -328,12 +473,8 @@ impl ContextBuilder {
             &self.resources_zip_name,
         )?;
 
-        for path in &self.paths {
-            fs.mount(path, true);
-        }
-
-        for zipfile_bytes in self.memory_zip_files {
-            fs.add_zip_file(std::io::Cursor::new(zipfile_bytes))?;
+        for (source, priority) in self.sources {
+            let _ = fs.add_resource_source(source, priority)?;
         }
 
         let config = if self.load_conf_file {
@@ -342,7 +483,7 @@ impl ContextBuilder {
             self.conf
         };
 
-        Context::from_conf(self.game_id.as_ref(), config, fs)
+        Ok((config, fs))
     }
 }
 
@@ -358,11 +499,11 @@ pub fn quit(ctx: &mut Context) {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::{
         context::{Has, HasMut},
-        graphics::GraphicsContext,
-        ContextBuilder,
+        graphics::{self, GraphicsContext},
+        Context, ContextBuilder, GameError,
     };
 
     // This will fail when testing if not running using one thread but is actually fine
@@ -378,4 +519,569 @@ mod tests {
         takes_mut_gfx(&mut ctx);
         takes_mut_gfx(&mut ctx.gfx);
     }
+
+    /// Builds a 16x8 headless context, or returns `None` if there's no usable graphics adapter.
+    pub(crate) fn headless_context() -> Option<Context> {
+        graphics::testing::headless_context(16, 8)
+    }
+
+    #[test]
+    fn headless_test_render_to_frame() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        assert!(ctx.gfx.is_headless());
+        assert_eq!(ctx.gfx.drawable_size(), (16., 8.));
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = graphics::Canvas::from_frame(&ctx, graphics::Color::RED);
+        canvas.draw(
+            &graphics::Quad,
+            graphics::DrawParam::new()
+                .dest([8., 0.])
+                .scale([8., 8.])
+                .color(graphics::Color::BLUE),
+        );
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let frame = ctx.gfx.frame().clone();
+        assert_eq!((frame.width(), frame.height()), (16, 8));
+        let pixels = frame.to_pixels(&ctx).unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 16 + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(15, 7), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn headless_test_resize_callbacks() {
+        use std::{
+            cell::{Cell, RefCell},
+            rc::Rc,
+        };
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let first = {
+            let calls = calls.clone();
+            ctx.gfx.register_resize_callback(move |gfx, w, h| {
+                assert_eq!(gfx.frame().width(), w as u32);
+                calls.borrow_mut().push(("first", w, h));
+            })
+        };
+        {
+            let calls = calls.clone();
+            let _ = ctx.gfx.register_resize_callback(move |_, w, h| {
+                calls.borrow_mut().push(("second", w, h));
+            });
+        }
+
+        ctx.gfx.set_drawable_size(32., 24.).unwrap();
+        assert_eq!(*calls.borrow(), [("first", 32., 24.), ("second", 32., 24.)]);
+
+        assert!(ctx.gfx.unregister_resize_callback(first));
+        assert!(!ctx.gfx.unregister_resize_callback(first));
+        calls.borrow_mut().clear();
+        ctx.gfx.set_drawable_size(8., 8.).unwrap();
+        assert_eq!(*calls.borrow(), [("second", 8., 8.)]);
+
+        // While callbacks run, only the ids of those being run can still be unregistered.
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let this = Rc::new(Cell::new(None));
+        let id = {
+            let (results, this) = (results.clone(), this.clone());
+            ctx.gfx.register_resize_callback(move |gfx, _, _| {
+                let id = this.get().unwrap();
+                results.borrow_mut().extend([
+                    gfx.unregister_resize_callback(first),
+                    gfx.unregister_resize_callback(id),
+                    gfx.unregister_resize_callback(id),
+                ]);
+            })
+        };
+        this.set(Some(id));
+        ctx.gfx.set_drawable_size(16., 8.).unwrap();
+        assert_eq!(*results.borrow(), [false, true, false]);
+        assert!(!ctx.gfx.unregister_resize_callback(id));
+    }
+
+    #[test]
+    fn headless_test_present_callbacks() {
+        use std::{cell::Cell, rc::Rc};
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // An overlay drawing over the game's frame with its own render pass.
+        let calls = Rc::new(Cell::new(0));
+        let overlay = {
+            let calls = calls.clone();
+            ctx.gfx.register_present_callback(move |gfx, view| {
+                calls.set(calls.get() + 1);
+                let device = &gfx.wgpu().device;
+                let mut cmd =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                let _ = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                let _ = gfx.wgpu().queue.submit([cmd.finish()]);
+            })
+        };
+
+        let render = |ctx: &mut Context| {
+            ctx.gfx.begin_frame().unwrap();
+            let canvas = graphics::Canvas::from_frame(ctx, graphics::Color::RED);
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            ctx.gfx.frame().clone().to_pixels(ctx).unwrap()[..4].to_vec()
+        };
+        assert_eq!(render(&mut ctx), [0, 255, 0, 255]);
+        assert_eq!(calls.get(), 1);
+
+        assert!(ctx.gfx.unregister_present_callback(overlay));
+        assert!(!ctx.gfx.unregister_present_callback(overlay));
+        assert_eq!(render(&mut ctx), [255, 0, 0, 255]);
+        assert_eq!(calls.get(), 1);
+
+        // A callback can unregister itself, but not ids that aren't being run.
+        let results = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let this = Rc::new(Cell::new(None));
+        let id = {
+            let (results, this) = (results.clone(), this.clone());
+            ctx.gfx.register_present_callback(move |gfx, _| {
+                let id = this.get().unwrap();
+                results.borrow_mut().extend([
+                    gfx.unregister_present_callback(overlay),
+                    gfx.unregister_present_callback(id),
+                    gfx.unregister_present_callback(id),
+                ]);
+            })
+        };
+        this.set(Some(id));
+        let _ = render(&mut ctx);
+        let _ = render(&mut ctx);
+        assert_eq!(*results.borrow(), [false, true, false]);
+    }
+
+    #[test]
+    fn headless_test_runtime_icons() {
+        use crate::graphics::{Image, ImageFormat};
+        use crate::input::mouse::CursorIcon;
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let icon = Image::from_color(&ctx, 4, 4, Some(crate::graphics::Color::RED));
+        ctx.gfx.set_window_icon_from_image(Some(&icon)).unwrap();
+        ctx.gfx.set_window_icon_from_image(None).unwrap();
+        assert!(ctx
+            .gfx
+            .set_window_icon_from_rgba(Some((vec![0; 15], 2, 2)))
+            .is_err());
+        let r8 = Image::new_canvas_image(&ctx, ImageFormat::R8Unorm, 4, 4, 1);
+        assert!(ctx.gfx.set_window_icon_from_image(Some(&r8)).is_err());
+
+        ctx.mouse.set_cursor_icon(CursorIcon::Hand);
+        assert_eq!(ctx.mouse.cursor_type(), CursorIcon::Hand);
+
+        // The custom cursor is drawn over the frame with its hotspot at the mouse position.
+        ctx.mouse.set_custom_cursor(&icon, [1, 1]);
+        assert!(ctx.mouse.custom_cursor().is_some());
+        ctx.mouse.handle_move(5., 3.);
+        ctx.gfx.begin_frame().unwrap();
+        let canvas = graphics::Canvas::from_frame(&ctx, graphics::Color::BLACK);
+        canvas.finish(&mut ctx).unwrap();
+        ctx.mouse.draw_custom_cursor(&mut ctx.gfx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 16 + x) * 4..][..4];
+        assert_eq!(pixel(3, 1), [0, 0, 0, 255]);
+        assert_eq!(pixel(4, 2), [255, 0, 0, 255]);
+        assert_eq!(pixel(7, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(8, 6), [0, 0, 0, 255]);
+
+        ctx.mouse.clear_custom_cursor();
+        assert!(ctx.mouse.custom_cursor().is_none());
+    }
+
+    #[test]
+    fn headless_test_secondary_windows() {
+        use crate::conf::{WindowMode, WindowSetup};
+        use crate::graphics::{Canvas, WindowId};
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        assert!(matches!(
+            ctx.gfx
+                .create_window(WindowSetup::default(), WindowMode::default()),
+            Err(GameError::WindowError(_))
+        ));
+        assert!(Canvas::from_window_frame(&ctx, WindowId(0), None).is_err());
+        assert!(ctx.gfx.secondary_window(WindowId(0)).is_none());
+        assert!(!ctx.gfx.close_window(WindowId(0)));
+    }
+
+    #[test]
+    fn headless_test_quit_requests() {
+        use crate::{context::QuitSource, event, graphics::Canvas};
+
+        #[derive(Default)]
+        struct State {
+            sources: Vec<Option<QuitSource>>,
+            confirmed: bool,
+        }
+
+        impl event::EventHandler for State {
+            fn update(&mut self, _ctx: &mut Context) -> Result<(), GameError> {
+                Ok(())
+            }
+
+            fn draw(&mut self, ctx: &mut Context) -> Result<(), GameError> {
+                Canvas::from_frame(ctx, None).finish(ctx)
+            }
+
+            fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, GameError> {
+                self.sources.push(ctx.quit_requested_by());
+                Ok(!self.confirmed)
+            }
+        }
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let mut state = State::default();
+
+        // SAFETY: the id is only compared against those of existing windows, of which there are none.
+        #[allow(unsafe_code)]
+        let window_id = unsafe { winit::window::WindowId::dummy() };
+        let mut close = winit::event::Event::WindowEvent {
+            window_id,
+            event: winit::event::WindowEvent::CloseRequested,
+        };
+        event::process_event(&mut ctx, &mut close);
+        assert_eq!(ctx.quit_requested_by(), Some(QuitSource::WindowClose));
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert_eq!(state.sources, [Some(QuitSource::WindowClose)]);
+        assert!(ctx.continuing);
+        assert_eq!(ctx.quit_requested_by(), None);
+
+        ctx.request_quit();
+        ctx.cancel_quit();
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert_eq!(state.sources.len(), 1);
+
+        // Events handed over by a host event loop reach `quit_event` right away.
+        assert_eq!(
+            event::handle_event(&mut ctx, &mut state, &mut close),
+            event::ControlFlow::Poll
+        );
+        assert_eq!(state.sources.len(), 2);
+        assert_eq!(event::frame(&mut ctx, &mut state), event::ControlFlow::Poll);
+
+        state.confirmed = true;
+        ctx.request_quit();
+        assert!(!event::run_frame(&mut ctx, &mut state));
+        assert_eq!(state.sources[2], Some(QuitSource::Requested));
+        assert!(!ctx.continuing);
+    }
+
+    #[test]
+    fn headless_test_scale_factor_change() {
+        use crate::{
+            event,
+            graphics::{Canvas, CoordinateMode, Rect},
+        };
+        use winit::{dpi::PhysicalSize, event::WindowEvent};
+
+        #[derive(Default)]
+        struct State {
+            scales: Vec<f64>,
+            sizes: Vec<(f32, f32)>,
+        }
+
+        impl event::EventHandler for State {
+            fn update(&mut self, _ctx: &mut Context) -> Result<(), GameError> {
+                Ok(())
+            }
+
+            fn draw(&mut self, _ctx: &mut Context) -> Result<(), GameError> {
+                Ok(())
+            }
+
+            fn scale_factor_changed(
+                &mut self,
+                _ctx: &mut Context,
+                new_scale: f64,
+            ) -> Result<(), GameError> {
+                self.scales.push(new_scale);
+                Ok(())
+            }
+
+            fn resize_event(
+                &mut self,
+                _ctx: &mut Context,
+                width: f32,
+                height: f32,
+            ) -> Result<(), GameError> {
+                self.sizes.push((width, height));
+                Ok(())
+            }
+        }
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let mut state = State::default();
+        assert_eq!(ctx.gfx.scale_factor(), 1.);
+        assert_eq!(ctx.gfx.coordinate_mode(), CoordinateMode::Physical);
+
+        // SAFETY: the ids are only compared against those of existing windows and devices,
+        // of which there are none.
+        #[allow(unsafe_code)]
+        let (window_id, device_id) = unsafe {
+            (
+                winit::window::WindowId::dummy(),
+                winit::event::DeviceId::dummy(),
+            )
+        };
+        let send = |ctx: &mut Context, state: &mut State, event: WindowEvent<'_>| {
+            let mut event = winit::event::Event::WindowEvent { window_id, event };
+            let _ = event::handle_event(ctx, state, &mut event);
+        };
+        #[allow(deprecated)]
+        let cursor_moved = |x, y| WindowEvent::CursorMoved {
+            device_id,
+            position: winit::dpi::PhysicalPosition::new(x, y),
+            modifiers: Default::default(),
+        };
+
+        ctx.gfx.set_coordinate_mode(CoordinateMode::Logical);
+        send(&mut ctx, &mut state, cursor_moved(12., 6.));
+        assert_eq!(ctx.mouse.position(), [12., 6.].into());
+
+        let mut new_size = PhysicalSize::new(16, 8);
+        send(
+            &mut ctx,
+            &mut state,
+            WindowEvent::ScaleFactorChanged {
+                scale_factor: 2.,
+                new_inner_size: &mut new_size,
+            },
+        );
+        assert_eq!(state.scales, [2.]);
+        assert_eq!(ctx.gfx.scale_factor(), 2.);
+        assert_eq!(ctx.mouse.position(), [6., 3.].into());
+
+        send(&mut ctx, &mut state, cursor_moved(10., 4.));
+        assert_eq!(ctx.mouse.position(), [5., 2.].into());
+        send(&mut ctx, &mut state, WindowEvent::Resized(new_size));
+        assert_eq!(state.sizes, [(8., 4.)]);
+        assert_eq!(ctx.gfx.size(), (8., 4.));
+        assert_eq!(ctx.gfx.drawable_size(), (16., 8.));
+        let canvas = Canvas::from_frame(&ctx, None);
+        assert_eq!(canvas.screen_coordinates(), Some(Rect::new(0., 0., 8., 4.)));
+
+        // Physical mode ignores the scale factor.
+        ctx.gfx.set_coordinate_mode(CoordinateMode::Physical);
+        send(&mut ctx, &mut state, cursor_moved(10., 4.));
+        assert_eq!(ctx.mouse.position(), [10., 4.].into());
+        send(&mut ctx, &mut state, WindowEvent::Resized(new_size));
+        assert_eq!(state.sizes[1], (16., 8.));
+        assert_eq!(ctx.gfx.size(), (16., 8.));
+        let canvas = Canvas::from_frame(&ctx, None);
+        assert_eq!(
+            canvas.screen_coordinates(),
+            Some(Rect::new(0., 0., 16., 8.))
+        );
+    }
+
+    #[test]
+    fn headless_test_error_overlay() {
+        use crate::{
+            event::{self, ErrorPolicy},
+            graphics::Canvas,
+            input::keyboard::{KeyCode, KeyInput},
+        };
+        use winit::event::{ElementState, WindowEvent};
+
+        #[derive(Default)]
+        struct State {
+            fail: bool,
+            updates: usize,
+            keys: usize,
+        }
+
+        impl event::EventHandler for State {
+            fn update(&mut self, _ctx: &mut Context) -> Result<(), GameError> {
+                self.updates += 1;
+                Ok(())
+            }
+
+            fn draw(&mut self, ctx: &mut Context) -> Result<(), GameError> {
+                // The error comes after some drawing, leaving the frame half done.
+                let canvas = Canvas::from_frame(ctx, graphics::Color::RED);
+                if self.fail {
+                    return Err(GameError::CustomError(String::from("broken")));
+                }
+                canvas.finish(ctx)
+            }
+
+            fn key_down_event(
+                &mut self,
+                _ctx: &mut Context,
+                _input: KeyInput,
+                _repeated: bool,
+            ) -> Result<(), GameError> {
+                self.keys += 1;
+                Ok(())
+            }
+        }
+
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let mut state = State::default();
+        // SAFETY: the ids are only compared against those of existing windows and devices,
+        // of which there are none.
+        #[allow(unsafe_code)]
+        let (window_id, device_id) = unsafe {
+            (
+                winit::window::WindowId::dummy(),
+                winit::event::DeviceId::dummy(),
+            )
+        };
+        let press = |ctx: &mut Context, state: &mut State, key: KeyCode| {
+            #[allow(deprecated)]
+            let input = winit::event::KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                modifiers: Default::default(),
+            };
+            let mut event = winit::event::Event::WindowEvent {
+                window_id,
+                event: WindowEvent::KeyboardInput {
+                    device_id,
+                    input,
+                    is_synthetic: false,
+                },
+            };
+            let _ = event::handle_event(ctx, state, &mut event);
+        };
+        let pixel = |ctx: &Context| ctx.gfx.frame().to_pixels(ctx).unwrap()[..4].to_vec();
+
+        ctx.set_error_policy(ErrorPolicy::Overlay);
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert_eq!(pixel(&ctx), [255, 0, 0, 255]);
+
+        // The failed frame shows the dimmed last good one, and keeps the game going.
+        state.fail = true;
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert!(ctx.error_overlay.is_shown());
+        let dimmed = pixel(&ctx);
+        assert!(dimmed[0] > 0 && dimmed[0] < 255, "{dimmed:?}");
+        assert_eq!(dimmed[1..], [0, 0, 255]);
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert_eq!((state.updates, pixel(&ctx)), (2, dimmed));
+
+        // Keys go to the overlay, and R retries.
+        state.fail = false;
+        press(&mut ctx, &mut state, KeyCode::R);
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert_eq!((state.updates, state.keys), (3, 0));
+        assert!(!ctx.error_overlay.is_shown());
+        assert_eq!(pixel(&ctx), [255, 0, 0, 255]);
+
+        // Escape asks to quit.
+        state.fail = true;
+        assert!(event::run_frame(&mut ctx, &mut state));
+        press(&mut ctx, &mut state, KeyCode::Escape);
+        assert!(event::run_frame(&mut ctx, &mut state));
+        assert!(!event::run_frame(&mut ctx, &mut state));
+        assert!(!ctx.continuing);
+
+        // Without the overlay, a failed frame ends the game.
+        ctx.continuing = true;
+        ctx.set_error_policy(ErrorPolicy::Exit);
+        assert!(!event::run_frame(&mut ctx, &mut state));
+    }
+
+    #[test]
+    fn headless_test_gpu_profiler_scopes() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        for _ in 0..8 {
+            ctx.gfx.begin_frame().unwrap();
+            let canvas = graphics::Canvas::from_frame(&ctx, graphics::Color::BLACK);
+            {
+                let mut gfx = ctx.gfx.profiler().scope("scene");
+                canvas.finish(&mut *gfx).unwrap();
+            }
+            ctx.gfx.end_frame().unwrap();
+        }
+        let _ = ctx.gfx.wgpu().device.poll(wgpu::Maintain::Wait);
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let timings = ctx.gfx.frame_gpu_timings();
+        if ctx
+            .gfx
+            .wgpu()
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            let names: Vec<_> = timings.iter().map(|t| (t.name.as_str(), t.depth)).collect();
+            assert_eq!(names, [("scene", 0), ("canvas", 1)]);
+            assert!(timings.iter().all(|t| t.millis >= 0.));
+        } else {
+            assert!(timings.is_empty());
+        }
+    }
+
+    #[test]
+    fn headless_test_max_frame_latency() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        assert_eq!(ctx.gfx.max_frame_latency(), None);
+        ctx.gfx.set_max_frame_latency(0);
+        assert_eq!(ctx.gfx.max_frame_latency(), Some(1));
+        ctx.gfx.set_max_frame_latency(100);
+        assert_eq!(ctx.gfx.max_frame_latency(), Some(16));
+
+        ctx.gfx.set_max_frame_latency(2);
+        for _ in 0..6 {
+            ctx.gfx.begin_frame().unwrap();
+            assert!(ctx.gfx.frames_in_flight.len() < 2);
+            graphics::Canvas::from_frame(&ctx, graphics::Color::BLACK)
+                .finish(&mut ctx)
+                .unwrap();
+            ctx.gfx.end_frame().unwrap();
+        }
+        assert_eq!(ctx.gfx.frames_in_flight.len(), 2);
+
+        ctx.gfx.set_max_frame_latency(None);
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert!(ctx.gfx.frames_in_flight.is_empty());
+    }
 }
@@ -1,4 +1,5 @@
 //! Error types and conversion functions.
+use std::backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
 use std::string::FromUtf8Error;
@@ -57,6 +58,74 @@ pub enum GameError {
     ///
     /// Please include an informative message with the error.
     CustomError(String),
+    /// Another error, annotated with a message describing what was being
+    /// attempted when it happened. Created with [`GameError::context`] or
+    /// [`GameError::wrap`]; the original error is returned by [`Error::source`].
+    WithContext {
+        /// What was being attempted, e.g. `"loading image /player.png"`.
+        message: String,
+        /// The error that caused this one.
+        source: Box<dyn Error + Send + Sync + 'static>,
+        /// Where the error was first wrapped. Only captured when the `backtrace`
+        /// feature is enabled, and subject to the usual `RUST_BACKTRACE` rules.
+        backtrace: Option<Arc<Backtrace>>,
+    },
+}
+
+impl GameError {
+    /// Wraps this error with a message describing what was being attempted,
+    /// keeping the original error available through [`Error::source`].
+    ///
+    /// ```rust
+    /// # use ggez::GameError;
+    /// # use std::error::Error;
+    /// let err = GameError::ResourceLoadError("bad header".into()).context("loading level 1");
+    /// assert!(err.to_string().starts_with("loading level 1: "));
+    /// assert!(err.source().is_some());
+    /// ```
+    pub fn context(self, message: impl Into<String>) -> GameError {
+        // Keep the backtrace from the innermost wrap, that's where the error came from.
+        let backtrace = match self {
+            GameError::WithContext { ref backtrace, .. } => backtrace.clone(),
+            _ => capture_backtrace(),
+        };
+        GameError::WithContext {
+            message: message.into(),
+            source: Box::new(self),
+            backtrace,
+        }
+    }
+
+    /// Wraps any error type with a message, keeping it available through
+    /// [`Error::source`] instead of flattening it into a string.
+    pub fn wrap(
+        message: impl Into<String>,
+        source: impl Error + Send + Sync + 'static,
+    ) -> GameError {
+        GameError::WithContext {
+            message: message.into(),
+            source: Box::new(source),
+            backtrace: capture_backtrace(),
+        }
+    }
+
+    /// Returns the backtrace captured when this error was wrapped, if any.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match *self {
+            GameError::WithContext { ref backtrace, .. } => backtrace.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "backtrace")]
+fn capture_backtrace() -> Option<Arc<Backtrace>> {
+    Some(Arc::new(Backtrace::capture()))
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn capture_backtrace() -> Option<Arc<Backtrace>> {
+    None
 }
 
 impl fmt::Display for GameError {
@@ -75,20 +144,27 @@ impl fmt::Display for GameError {
             GameError::GlyphBrushError(ref e) => write!(f, "Text rendering error: {e}"),
             GameError::FontSelectError(ref e) => write!(f, "No such font '{e}'"),
             GameError::BufferAsyncError(ref e) => write!(f, "Async buffer map error: {e}"),
+            GameError::WithContext {
+                ref message,
+                ref source,
+                ..
+            } => write!(f, "{message}: {source}"),
             _ => write!(f, "GameError {self:?}"),
         }
     }
 }
 
 impl Error for GameError {
-    fn cause(&self) -> Option<&dyn Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
             GameError::RequestDeviceError(ref e) => Some(e),
             GameError::WindowCreationError(ref e) => Some(&**e),
             GameError::IOError(ref e) => Some(&**e),
             GameError::FontError(ref e) => Some(e),
+            GameError::ShaderEncodingError(ref e) => Some(e),
             GameError::GlyphBrushError(ref e) => Some(e),
             GameError::BufferAsyncError(ref e) => Some(e),
+            GameError::WithContext { ref source, .. } => Some(&**source),
             _ => None,
         }
     }
@@ -161,6 +237,14 @@ impl From<gilrs::Error> for GameError {
     }
 }
 
+#[cfg(feature = "gamepad")]
+impl From<gilrs::ff::Error> for GameError {
+    fn from(s: gilrs::ff::Error) -> GameError {
+        let errstr = format!("Gamepad force feedback error: {s}");
+        GameError::GamepadError(errstr)
+    }
+}
+
 impl From<lyon::lyon_tessellation::TessellationError> for GameError {
     fn from(s: lyon::lyon_tessellation::TessellationError) -> GameError {
         let errstr =
@@ -212,3 +296,38 @@ impl From<wgpu::BufferAsyncError> for GameError {
         GameError::BufferAsyncError(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_context_source_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let err = GameError::from(io)
+            .context("reading /mesh.png")
+            .context("loading model /ship.obj");
+
+        assert!(err
+            .to_string()
+            .starts_with("loading model /ship.obj: reading /mesh.png: "));
+
+        let mut chain = Vec::new();
+        let mut cur: Option<&dyn Error> = Some(&err);
+        while let Some(e) = cur {
+            chain.push(e);
+            cur = e.source();
+        }
+        // context, context, IOError, io::Error
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[3].to_string(), "no such file");
+    }
+
+    #[test]
+    fn headless_test_wrap_keeps_source() {
+        let utf8 = String::from_utf8(vec![0xff]).unwrap_err();
+        let err = GameError::wrap("decoding shader", utf8);
+        assert!(err.source().unwrap().is::<FromUtf8Error>());
+        assert_eq!(err.backtrace().is_some(), cfg!(feature = "backtrace"));
+    }
+}
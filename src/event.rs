@@ -9,6 +9,9 @@
 //! little.  For examples of how to write your own main loop, see the
 //! source code for this module, or the [`eventloop`
 //! example](https://github.com/ggez/ggez/blob/master/examples/eventloop.rs).
+//!
+//! To embed a game in an event loop owned by another application, like an editor,
+//! see [`prepare()`], [`handle_event()`] and [`frame()`].
 
 use winit::{self, dpi};
 
@@ -29,8 +32,9 @@ pub mod winit_event {
         TouchPhase, WindowEvent,
     };
 }
+use crate::graphics::WindowId;
 #[cfg(feature = "gamepad")]
-pub use crate::input::gamepad::GamepadId;
+pub use crate::input::gamepad::{GamepadId, GamepadInfo};
 use crate::input::keyboard::{KeyCode, KeyInput, KeyMods};
 use crate::GameError;
 
@@ -40,7 +44,7 @@ use self::winit_event::{
 /// `winit` event loop.
 pub use winit::event_loop::{ControlFlow, EventLoop};
 
-use crate::context::Context;
+use crate::context::{Context, QuitSource};
 
 /// Used in [`EventHandler::on_error()`](trait.EventHandler.html#method.on_error)
 /// to specify where an error originated
@@ -74,12 +78,44 @@ pub enum ErrorOrigin {
     GamepadButtonUpEvent,
     /// error originated in `gamepad_axis_event()`
     GamepadAxisEvent,
+    /// error originated in `gamepad_connected_event()`
+    GamepadConnectedEvent,
+    /// error originated in `gamepad_disconnected_event()`
+    GamepadDisconnectedEvent,
+    /// error originated in `gamepad_slot_connected_event()`
+    GamepadSlotConnectedEvent,
+    /// error originated in `gamepad_slot_disconnected_event()`
+    GamepadSlotDisconnectedEvent,
     /// error originated in `focus_event()`
     FocusEvent,
     /// error originated in `quit_event()`
     QuitEvent,
     /// error originated in `resize_event()`
     ResizeEvent,
+    /// error originated in `scale_factor_changed()`
+    ScaleFactorChanged,
+    /// error originated in `secondary_window_event()`
+    SecondaryWindowEvent,
+}
+
+/// What the event loop does when [`update()`](EventHandler::update) or
+/// [`draw()`](EventHandler::draw) return an error that
+/// [`on_error()`](EventHandler::on_error) considers fatal, see [`run_with()`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum ErrorPolicy {
+    /// End the event loop, like for errors from any other callback.
+    ///
+    /// This is the default.
+    #[default]
+    Exit,
+    /// Keep the game running, and show the error over a dimmed copy of the last frame that
+    /// was drawn without one. Pressing R retries the frame, calling `update()` and `draw()`
+    /// again, while Escape requests to quit. Key presses aren't passed to the game while the
+    /// error is shown.
+    ///
+    /// This keeps the window and the game's state around while developing, so a fix that
+    /// is hot-reloaded, or a state tweaked from a debugger, can be tried right away.
+    Overlay,
 }
 
 /// A trait defining event callbacks.  This is your primary interface with
@@ -198,6 +234,7 @@ where
         match phase {
             TouchPhase::Started => {
                 ctx.mouse.set_button(MouseButton::Left, true);
+                ctx.actions.set_mouse_button(MouseButton::Left, true);
                 self.mouse_button_down_event(ctx, MouseButton::Left, x as f32, y as f32)?;
             }
             TouchPhase::Moved => {
@@ -206,6 +243,7 @@ where
             }
             TouchPhase::Ended | TouchPhase::Cancelled => {
                 ctx.mouse.set_button(MouseButton::Left, false);
+                ctx.actions.set_mouse_button(MouseButton::Left, false);
                 self.mouse_button_up_event(ctx, MouseButton::Left, x as f32, y as f32)?;
             }
         }
@@ -247,6 +285,53 @@ where
         Ok(())
     }
 
+    /// A gamepad was connected; `info` describes it.
+    ///
+    /// Gamepads that were already connected when the game started can be found with
+    /// [`GamepadContext::gamepads`](crate::input::gamepad::GamepadContext::gamepads) and described with
+    /// [`GamepadContext::gamepad_info`](crate::input::gamepad::GamepadContext::gamepad_info).
+    #[cfg(feature = "gamepad")]
+    fn gamepad_connected_event(
+        &mut self,
+        _ctx: &mut Context,
+        _id: GamepadId,
+        _info: &GamepadInfo,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
+    /// A gamepad was disconnected.
+    #[cfg(feature = "gamepad")]
+    fn gamepad_disconnected_event(&mut self, _ctx: &mut Context, _id: GamepadId) -> Result<(), E> {
+        Ok(())
+    }
+
+    /// The gamepad of a player slot connected again, under the new `id`; called after
+    /// [`gamepad_connected_event`](Self::gamepad_connected_event).
+    ///
+    /// See [`GamepadContext::assign_slot`](crate::input::gamepad::GamepadContext::assign_slot).
+    #[cfg(feature = "gamepad")]
+    fn gamepad_slot_connected_event(
+        &mut self,
+        _ctx: &mut Context,
+        _slot: usize,
+        _id: GamepadId,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
+    /// The gamepad of a player slot was disconnected; called after
+    /// [`gamepad_disconnected_event`](Self::gamepad_disconnected_event). The slot is kept
+    /// for when the gamepad comes back.
+    #[cfg(feature = "gamepad")]
+    fn gamepad_slot_disconnected_event(
+        &mut self,
+        _ctx: &mut Context,
+        _slot: usize,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
     /// Called when the window is shown or hidden.
     fn focus_event(&mut self, _ctx: &mut Context, _gained: bool) -> Result<(), E> {
         Ok(())
@@ -265,6 +350,31 @@ where
         Ok(())
     }
 
+    /// Called when the window's scale factor changes, for example because it was moved to
+    /// a monitor with a different DPI. See [`CoordinateMode`](crate::graphics::CoordinateMode)
+    /// for how this affects sizes and positions.
+    ///
+    /// If the window is resized as a result, [`resize_event()`](#method.resize_event)
+    /// is called afterwards.
+    fn scale_factor_changed(&mut self, _ctx: &mut Context, _new_scale: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    /// Called for every event of a secondary window opened with
+    /// [`GraphicsContext::create_window()`](../graphics/struct.GraphicsContext.html#method.create_window).
+    ///
+    /// Secondary windows don't feed into the regular callbacks or the keyboard and mouse state.
+    /// When their close button is pressed, this is called with `WindowEvent::CloseRequested`
+    /// and the window is closed afterwards; the game keeps running.
+    fn secondary_window_event(
+        &mut self,
+        _ctx: &mut Context,
+        _window: WindowId,
+        _event: &WindowEvent,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
     /// Something went wrong, causing a `GameError` (or some other kind of error, depending on what you specified).
     /// If this returns true, the error was fatal, so the event loop ends, aborting the game.
     fn on_error(&mut self, _ctx: &mut Context, _origin: ErrorOrigin, _e: E) -> bool {
@@ -277,304 +387,706 @@ where
 ///
 /// It does not try to do any type of framerate limiting.  See the
 /// documentation for the [`timer`](../timer/index.html) module for more info.
-#[allow(clippy::needless_return)] // necessary as the returns used here are actually necessary to break early from the event loop
-pub fn run<S: 'static, E>(mut ctx: Context, event_loop: EventLoop<()>, mut state: S) -> !
+///
+/// This is a thin wrapper around [`prepare()`], [`handle_event()`] and [`frame()`]; use
+/// those instead if you need to run `ggez` inside an event loop you own.
+pub fn run<S: 'static, E>(ctx: Context, event_loop: EventLoop<()>, state: S) -> !
 where
     S: EventHandler<E>,
     E: std::fmt::Debug,
 {
-    event_loop.run(move |mut event, _, control_flow| {
-        let ctx = &mut ctx;
-        let state = &mut state;
-
-        if ctx.quit_requested {
-            let res = state.quit_event(ctx);
-            ctx.quit_requested = false;
-            if let Ok(false) = res {
-                ctx.continuing = false;
-            } else if catch_error(ctx, res, state, control_flow, ErrorOrigin::QuitEvent) {
-                return;
-            }
+    run_with(ctx, event_loop, state, ErrorPolicy::Exit)
+}
+
+/// Runs the game's main loop like [`run()`], handling fatal errors from
+/// [`update()`](EventHandler::update) and [`draw()`](EventHandler::draw) as `policy` says:
+///
+/// ```rust,no_run
+/// # use ggez::{event::{self, EventHandler, ErrorPolicy}, Context, GameResult};
+/// # struct State;
+/// # impl EventHandler for State {
+/// #     fn update(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// #     fn draw(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// # }
+/// # let (ctx, event_loop) = ggez::ContextBuilder::new("dev", "ggez").build().unwrap();
+/// let policy = if cfg!(debug_assertions) {
+///     ErrorPolicy::Overlay
+/// } else {
+///     ErrorPolicy::Exit
+/// };
+/// event::run_with(ctx, event_loop, State, policy);
+/// ```
+///
+/// When driving `ggez` from your own event loop, set the policy with
+/// [`Context::set_error_policy`] instead.
+pub fn run_with<S, E>(
+    mut ctx: Context,
+    event_loop: EventLoop<()>,
+    mut state: S,
+    policy: ErrorPolicy,
+) -> !
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    ctx.set_error_policy(policy);
+    prepare(&mut ctx);
+    event_loop.run(move |mut event, target, control_flow| {
+        *control_flow = handle_event(&mut ctx, &mut state, &mut event);
+        if let (Event::MainEventsCleared, ControlFlow::Poll) = (event, *control_flow) {
+            *control_flow = frame_inner(&mut ctx, &mut state, Some(target));
         }
-        if !ctx.continuing {
-            *control_flow = ControlFlow::Exit;
+    })
+}
+
+/// Gets the context ready to be driven by an event loop owned by someone else, like an
+/// editor that embeds a `ggez` game. Call this once right before the loop starts, then
+/// pass every event to [`handle_event()`] and call [`frame()`] on `MainEventsCleared`:
+///
+/// ```rust,no_run
+/// # use ggez::{event::{self, EventHandler, ControlFlow}, Context, GameResult};
+/// # struct State;
+/// # impl EventHandler for State {
+/// #     fn update(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// #     fn draw(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// # }
+/// # let (mut ctx, event_loop) = ggez::ContextBuilder::new("host", "ggez").build().unwrap();
+/// # let mut state = State;
+/// event::prepare(&mut ctx);
+/// event_loop.run(move |mut event, target, control_flow| {
+///     // The host handles its own events here, and then hands them to ggez.
+///     *control_flow = event::handle_event(&mut ctx, &mut state, &mut event);
+///     if let (event::winit_event::Event::MainEventsCleared, ControlFlow::Poll) =
+///         (event, *control_flow)
+///     {
+///         ctx.gfx.create_pending_windows(target);
+///         *control_flow = event::frame(&mut ctx, &mut state);
+///     }
+/// });
+/// ```
+///
+/// The time between building the context and calling this isn't counted as part of
+/// the first frame.
+pub fn prepare(ctx: &mut Context) {
+    ctx.time.restart_frame_clock();
+}
+
+/// Feeds an event to `ggez` and the game: updates the context's internal state like
+/// [`process_event()`] does, then calls the matching [`EventHandler`] callback. A pending
+/// quit request is handled first.
+///
+/// Frames are not run here; call [`frame()`] once the events of a frame have been handled.
+///
+/// Returns [`ControlFlow::Exit`] once the game should stop, either because a quit request
+/// wasn't cancelled or because of a fatal error, and [`ControlFlow::Poll`] otherwise.
+pub fn handle_event<S, E>(ctx: &mut Context, state: &mut S, event: &mut Event<()>) -> ControlFlow
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    let mut control_flow = ControlFlow::Poll;
+    dispatch_event(ctx, state, event, &mut control_flow);
+    control_flow
+}
+
+#[allow(clippy::needless_return)] // necessary as the returns used here are actually necessary to break early
+fn dispatch_event<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    event: &mut Event<()>,
+    control_flow: &mut ControlFlow,
+) where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    if process_quit_request(ctx, state, control_flow) {
+        return;
+    }
+    if !ctx.continuing {
+        *control_flow = ControlFlow::Exit;
+        return;
+    }
+
+    process_event(ctx, event);
+
+    if let Event::WindowEvent {
+        event: WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_),
+        ..
+    } = event
+    {
+        // Key presses answer the error overlay instead, see `run_frame_inner`.
+        if ctx.error_overlay.is_shown() {
             return;
         }
+    }
 
-        *control_flow = ControlFlow::Poll;
+    if let Event::WindowEvent { window_id, event } = event {
+        if let Some(id) = ctx.gfx.secondary_window_id(*window_id) {
+            let res = state.secondary_window_event(ctx, id, event);
+            if catch_error(
+                ctx,
+                res,
+                state,
+                control_flow,
+                ErrorOrigin::SecondaryWindowEvent,
+            ) {
+                return;
+            }
+            if let WindowEvent::CloseRequested = event {
+                let _ = ctx.gfx.close_window(id);
+            }
+            return;
+        }
+    }
 
-        process_event(ctx, &mut event);
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(logical_size) => {
-                    // let actual_size = logical_size;
-                    let res = state.resize_event(
-                        ctx,
-                        logical_size.width as f32,
-                        logical_size.height as f32,
-                    );
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::ResizeEvent) {
-                        return;
-                    };
+    if let Event::WindowEvent { event, .. } = event {
+        match *event {
+            WindowEvent::Resized(physical_size) => {
+                let scale = ctx.gfx.coordinate_scale();
+                let res = state.resize_event(
+                    ctx,
+                    physical_size.width as f32 / scale,
+                    physical_size.height as f32 / scale,
+                );
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::ResizeEvent) {
+                    return;
+                };
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                let res = state.scale_factor_changed(ctx, scale_factor);
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::ScaleFactorChanged,
+                ) {
+                    return;
+                };
+            }
+            WindowEvent::CloseRequested => {
+                // `process_event` turned this into a quit request; handle it right away.
+                let failed = process_quit_request(ctx, state, control_flow);
+                if failed {
+                    return;
                 }
-                WindowEvent::CloseRequested => {
-                    let res = state.quit_event(ctx);
-                    if let Ok(false) = res {
-                        ctx.continuing = false;
-                    } else if catch_error(ctx, res, state, control_flow, ErrorOrigin::QuitEvent) {
-                        return;
+            }
+            WindowEvent::Focused(gained) => {
+                let res = state.focus_event(ctx, gained);
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::FocusEvent) {
+                    return;
+                };
+            }
+            WindowEvent::ReceivedCharacter(ch) => {
+                let res = state.text_input_event(ctx, ch);
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::TextInputEvent) {
+                    return;
+                };
+            }
+            WindowEvent::ModifiersChanged(mods) => ctx.keyboard.set_modifiers(KeyMods::from(mods)),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: keycode,
+                        scancode,
+                        ..
+                    },
+                ..
+            } => {
+                let repeat = ctx.keyboard.is_key_repeated();
+                let res = state.key_down_event(
+                    ctx,
+                    KeyInput {
+                        scancode,
+                        keycode,
+                        mods: ctx.keyboard.active_mods(),
+                    },
+                    repeat,
+                );
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyDownEvent) {
+                    return;
+                };
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Released,
+                        virtual_keycode: keycode,
+                        scancode,
+                        ..
+                    },
+                ..
+            } => {
+                let res = state.key_up_event(
+                    ctx,
+                    KeyInput {
+                        scancode,
+                        keycode,
+                        mods: ctx.keyboard.active_mods(),
+                    },
+                );
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyUpEvent) {
+                    return;
+                };
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        let scale_factor = ctx.gfx.window().scale_factor();
+                        let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
+                        (x, y)
+                    }
+                };
+                let res = state.mouse_wheel_event(ctx, x, y);
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseWheelEvent) {
+                    return;
+                };
+            }
+            WindowEvent::MouseInput {
+                state: element_state,
+                button,
+                ..
+            } => {
+                let position = ctx.mouse.position();
+                match element_state {
+                    ElementState::Pressed => {
+                        let res =
+                            state.mouse_button_down_event(ctx, button, position.x, position.y);
+                        if catch_error(
+                            ctx,
+                            res,
+                            state,
+                            control_flow,
+                            ErrorOrigin::MouseButtonDownEvent,
+                        ) {
+                            return;
+                        };
+                    }
+                    ElementState::Released => {
+                        let res = state.mouse_button_up_event(ctx, button, position.x, position.y);
+                        if catch_error(
+                            ctx,
+                            res,
+                            state,
+                            control_flow,
+                            ErrorOrigin::MouseButtonUpEvent,
+                        ) {
+                            return;
+                        };
                     }
                 }
-                WindowEvent::Focused(gained) => {
-                    let res = state.focus_event(ctx, gained);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::FocusEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::ReceivedCharacter(ch) => {
-                    let res = state.text_input_event(ctx, ch);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::TextInputEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::ModifiersChanged(mods) => {
-                    ctx.keyboard.set_modifiers(KeyMods::from(mods))
-                }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: keycode,
-                            scancode,
-                            ..
-                        },
-                    ..
-                } => {
-                    let repeat = ctx.keyboard.is_key_repeated();
-                    let res = state.key_down_event(
-                        ctx,
-                        KeyInput {
-                            scancode,
-                            keycode,
-                            mods: ctx.keyboard.active_mods(),
-                        },
-                        repeat,
-                    );
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyDownEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Released,
-                            virtual_keycode: keycode,
-                            scancode,
-                            ..
-                        },
-                    ..
-                } => {
-                    let res = state.key_up_event(
-                        ctx,
-                        KeyInput {
-                            scancode,
-                            keycode,
-                            mods: ctx.keyboard.active_mods(),
-                        },
-                    );
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyUpEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    let (x, y) = match delta {
-                        MouseScrollDelta::LineDelta(x, y) => (x, y),
-                        MouseScrollDelta::PixelDelta(pos) => {
-                            let scale_factor = ctx.gfx.window.scale_factor();
-                            let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
-                            (x, y)
-                        }
-                    };
-                    let res = state.mouse_wheel_event(ctx, x, y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseWheelEvent) {
-                        return;
-                    };
+            }
+            WindowEvent::CursorMoved { .. } => {
+                let position = ctx.mouse.position();
+                let delta = ctx.mouse.last_delta();
+                let res = state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseMotionEvent) {
+                    return;
+                };
+            }
+            WindowEvent::Touch(touch) => {
+                let scale = f64::from(ctx.gfx.coordinate_scale());
+                let (x, y) = (touch.location.x / scale, touch.location.y / scale);
+                let res = state.touch_event(ctx, touch.phase, x, y);
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::TouchEvent) {
+                    return;
+                };
+            }
+            WindowEvent::CursorEntered { device_id: _ } => {
+                let res = state.mouse_enter_or_leave(ctx, true);
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::MouseEnterOrLeave,
+                ) {
+                    return;
                 }
-                WindowEvent::MouseInput {
-                    state: element_state,
-                    button,
-                    ..
-                } => {
-                    let position = ctx.mouse.position();
-                    match element_state {
-                        ElementState::Pressed => {
-                            let res =
-                                state.mouse_button_down_event(ctx, button, position.x, position.y);
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::MouseButtonDownEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                        ElementState::Released => {
-                            let res =
-                                state.mouse_button_up_event(ctx, button, position.x, position.y);
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::MouseButtonUpEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                    }
+            }
+            WindowEvent::CursorLeft { device_id: _ } => {
+                let res = state.mouse_enter_or_leave(ctx, false);
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::MouseEnterOrLeave,
+                ) {
+                    return;
                 }
-                WindowEvent::CursorMoved { .. } => {
-                    let position = ctx.mouse.position();
-                    let delta = ctx.mouse.last_delta();
-                    let res =
-                        state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseMotionEvent) {
-                        return;
-                    };
+            }
+            _ => {
+                // trace!("ignoring window event {:?}", x);
+            }
+        }
+    }
+}
+
+/// Runs a single frame of the game: handles a pending quit request, then calls
+/// [`update`](EventHandler::update) and [`draw`](EventHandler::draw) like
+/// [`run()`](fn.run.html) does once all events of a frame have been processed.
+///
+/// When driving `ggez` from your own event loop (see [`prepare()`]), call this on
+/// `MainEventsCleared`, after opening the windows requested with
+/// [`GraphicsContext::create_window`](crate::graphics::GraphicsContext::create_window)
+/// through [`create_pending_windows`](crate::graphics::GraphicsContext::create_pending_windows).
+///
+/// Returns [`ControlFlow::Exit`] once the game should stop, either because a quit request
+/// wasn't cancelled or because of a fatal error, and [`ControlFlow::Poll`] otherwise.
+pub fn frame<S, E>(ctx: &mut Context, state: &mut S) -> ControlFlow
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    frame_inner(ctx, state, None)
+}
+
+/// Runs a single frame of the game without an event loop, like [`frame()`].
+///
+/// This is mainly useful with a context created by
+/// [`ContextBuilder::build_headless()`](crate::ContextBuilder::build_headless), for example
+/// to test a game by feeding it synthetic events with [`process_event()`](fn.process_event.html)
+/// in between frames. Errors are passed to [`on_error`](EventHandler::on_error) as usual.
+///
+/// Returns `false` once the game should stop, either because a quit request wasn't
+/// cancelled or because of a fatal error.
+pub fn run_frame<S, E>(ctx: &mut Context, state: &mut S) -> bool
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    frame(ctx, state) != ControlFlow::Exit
+}
+
+fn frame_inner<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    target: Option<&winit::event_loop::EventLoopWindowTarget<()>>,
+) -> ControlFlow
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    let mut control_flow = ControlFlow::Poll;
+    if process_quit_request(ctx, state, &mut control_flow) || !ctx.continuing {
+        return ControlFlow::Exit;
+    }
+    run_frame_inner(ctx, state, &mut control_flow, target);
+    if ctx.continuing {
+        control_flow
+    } else {
+        ControlFlow::Exit
+    }
+}
+
+fn run_frame_inner<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+    target: Option<&winit::event_loop::EventLoopWindowTarget<()>>,
+) where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    // If you are writing your own event loop, make sure
+    // you include `timer_context.tick()` and
+    // `ctx.process_event()` calls.  These update ggez's
+    // internal state however necessary.
+    ctx.time.tick();
+
+    // Handle gamepad events if necessary.
+    #[cfg(feature = "gamepad")]
+    while let Some(gilrs::Event { id, event, time }) = ctx.gamepad.next_event() {
+        match event {
+            gilrs::EventType::ButtonPressed(button, _) => {
+                let time = ctx.time.since_start_at(time);
+                ctx.gamepad
+                    .set_button_event_time(GamepadId(id), button, time);
+                ctx.actions.set_gamepad_button(id.into(), button, true);
+                let res = state.gamepad_button_down_event(ctx, button, GamepadId(id));
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::GamepadButtonDownEvent,
+                ) {
+                    return;
+                };
+                if emulate_mouse_button(ctx, state, control_flow, button, true) {
+                    return;
                 }
-                WindowEvent::Touch(touch) => {
-                    let res =
-                        state.touch_event(ctx, touch.phase, touch.location.x, touch.location.y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::TouchEvent) {
-                        return;
-                    };
+            }
+            gilrs::EventType::ButtonReleased(button, _) => {
+                let time = ctx.time.since_start_at(time);
+                ctx.gamepad
+                    .set_button_event_time(GamepadId(id), button, time);
+                ctx.actions.set_gamepad_button(id.into(), button, false);
+                let res = state.gamepad_button_up_event(ctx, button, GamepadId(id));
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::GamepadButtonUpEvent,
+                ) {
+                    return;
+                };
+                if emulate_mouse_button(ctx, state, control_flow, button, false) {
+                    return;
                 }
-                WindowEvent::CursorEntered { device_id: _ } => {
-                    let res = state.mouse_enter_or_leave(ctx, true);
+            }
+            gilrs::EventType::AxisChanged(axis, value, _) => {
+                ctx.actions.set_gamepad_axis(id.into(), axis, value);
+                let res = state.gamepad_axis_event(ctx, axis, value, GamepadId(id));
+                if catch_error(ctx, res, state, control_flow, ErrorOrigin::GamepadAxisEvent) {
+                    return;
+                };
+            }
+            gilrs::EventType::Connected => {
+                let info = ctx.gamepad.gamepad_info(GamepadId(id));
+                let res = state.gamepad_connected_event(ctx, GamepadId(id), &info);
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::GamepadConnectedEvent,
+                ) {
+                    return;
+                };
+
+                let uuid = ctx.gamepad.gamepad(GamepadId(id)).uuid();
+                if let Some(slot) = ctx.gamepad.slots.connected(GamepadId(id), uuid) {
+                    let res = state.gamepad_slot_connected_event(ctx, slot, GamepadId(id));
                     if catch_error(
                         ctx,
                         res,
                         state,
                         control_flow,
-                        ErrorOrigin::MouseEnterOrLeave,
+                        ErrorOrigin::GamepadSlotConnectedEvent,
                     ) {
                         return;
-                    }
+                    };
                 }
-                WindowEvent::CursorLeft { device_id: _ } => {
-                    let res = state.mouse_enter_or_leave(ctx, false);
+            }
+            gilrs::EventType::Disconnected => {
+                ctx.actions.remove_gamepad(id.into());
+                let res = state.gamepad_disconnected_event(ctx, GamepadId(id));
+                if catch_error(
+                    ctx,
+                    res,
+                    state,
+                    control_flow,
+                    ErrorOrigin::GamepadDisconnectedEvent,
+                ) {
+                    return;
+                };
+
+                if let Some(slot) = ctx.gamepad.slots.disconnected(GamepadId(id)) {
+                    let res = state.gamepad_slot_disconnected_event(ctx, slot);
                     if catch_error(
                         ctx,
                         res,
                         state,
                         control_flow,
-                        ErrorOrigin::MouseEnterOrLeave,
+                        ErrorOrigin::GamepadSlotDisconnectedEvent,
                     ) {
                         return;
-                    }
-                }
-                _x => {
-                    // trace!("ignoring window event {:?}", x);
-                }
-            },
-            Event::DeviceEvent { .. } => (),
-            Event::Resumed => (),
-            Event::Suspended => (),
-            Event::NewEvents(_) => (),
-            Event::UserEvent(_) => (),
-            Event::MainEventsCleared => {
-                // If you are writing your own event loop, make sure
-                // you include `timer_context.tick()` and
-                // `ctx.process_event()` calls.  These update ggez's
-                // internal state however necessary.
-                ctx.time.tick();
-
-                // Handle gamepad events if necessary.
-                #[cfg(feature = "gamepad")]
-                while let Some(gilrs::Event { id, event, .. }) = ctx.gamepad.next_event() {
-                    match event {
-                        gilrs::EventType::ButtonPressed(button, _) => {
-                            let res = state.gamepad_button_down_event(ctx, button, GamepadId(id));
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::GamepadButtonDownEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                        gilrs::EventType::ButtonReleased(button, _) => {
-                            let res = state.gamepad_button_up_event(ctx, button, GamepadId(id));
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::GamepadButtonUpEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                        gilrs::EventType::AxisChanged(axis, value, _) => {
-                            let res = state.gamepad_axis_event(ctx, axis, value, GamepadId(id));
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::GamepadAxisEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                        _ => {}
-                    }
+                    };
                 }
+            }
+            _ => {}
+        }
+    }
+    #[cfg(feature = "gamepad")]
+    if emulate_mouse_motion(ctx, state, control_flow) {
+        return;
+    }
 
-                let res = state.update(ctx);
-                if catch_error(ctx, res, state, control_flow, ErrorOrigin::Update) {
-                    return;
-                };
-
-                if let Err(e) = ctx.gfx.begin_frame() {
-                    error!("Error on GraphicsContext::begin_frame(): {e:?}");
-                    eprintln!("Error on GraphicsContext::begin_frame(): {e:?}");
-                    *control_flow = ControlFlow::Exit;
-                }
+    if ctx.error_overlay.is_shown() {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::R) {
+            ctx.error_overlay.dismiss();
+        } else if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) {
+            ctx.request_quit();
+        }
+    }
 
-                if let Err(e) = state.draw(ctx) {
-                    error!("Error on EventHandler::draw(): {e:?}");
-                    eprintln!("Error on EventHandler::draw(): {e:?}");
-                    if state.on_error(ctx, ErrorOrigin::Draw, e) {
-                        *control_flow = ControlFlow::Exit;
-                        return;
-                    }
-                }
+    if !ctx.error_overlay.is_shown() {
+        let res = state.update(ctx);
+        if catch_frame_error(ctx, res, state, control_flow, ErrorOrigin::Update) {
+            return;
+        };
+    }
 
-                if let Err(e) = ctx.gfx.end_frame() {
-                    error!("Error on GraphicsContext::end_frame(): {e:?}");
-                    eprintln!("Error on GraphicsContext::end_frame(): {e:?}");
-                    *control_flow = ControlFlow::Exit;
-                }
+    if let Some(target) = target {
+        ctx.gfx.create_pending_windows(target);
+    }
 
-                // reset the mouse delta for the next frame
-                // necessary because it's calculated cumulatively each cycle
-                ctx.mouse.reset_delta();
+    if let Err(e) = ctx.gfx.begin_frame() {
+        error!("Error on GraphicsContext::begin_frame(): {e:?}");
+        eprintln!("Error on GraphicsContext::begin_frame(): {e:?}");
+        *control_flow = ControlFlow::Exit;
+    }
 
-                // Copy the state of the keyboard into the KeyboardContext
-                // and the mouse into the MouseContext
-                ctx.keyboard.save_keyboard_state();
-                ctx.mouse.save_mouse_state();
-            }
-            Event::RedrawRequested(_) => (),
-            Event::RedrawEventsCleared => (),
-            Event::LoopDestroyed => (),
+    if !ctx.error_overlay.is_shown() {
+        let res = state.draw(ctx);
+        if catch_frame_error(ctx, res, state, control_flow, ErrorOrigin::Draw) {
+            return;
         }
-    })
+    }
+
+    let res = if ctx.error_overlay.is_shown() {
+        ctx.error_overlay.draw(&mut ctx.gfx)
+    } else if ctx.error_policy == ErrorPolicy::Overlay {
+        ctx.error_overlay.save_frame(&mut ctx.gfx)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = res {
+        error!("Error on the error overlay: {e:?}");
+        eprintln!("Error on the error overlay: {e:?}");
+        *control_flow = ControlFlow::Exit;
+    }
+
+    if let Err(e) = ctx.mouse.draw_custom_cursor(&mut ctx.gfx) {
+        error!("Error drawing the custom cursor: {e:?}");
+        eprintln!("Error drawing the custom cursor: {e:?}");
+        *control_flow = ControlFlow::Exit;
+    }
+
+    if let Err(e) = ctx.gfx.end_frame() {
+        error!("Error on GraphicsContext::end_frame(): {e:?}");
+        eprintln!("Error on GraphicsContext::end_frame(): {e:?}");
+        *control_flow = ControlFlow::Exit;
+    }
+
+    // Presenting with vsync may already be enough to hit the target fps.
+    match ctx.time.target_fps() {
+        Some(fps) if ctx.gfx.vsync_caps_frame_rate(fps) => (),
+        _ => ctx.time.wait_for_next_frame(),
+    }
+
+    ctx.mouse.update_window_cursor(ctx.gfx.window.as_ref());
+
+    // reset the mouse delta for the next frame
+    // necessary because it's calculated cumulatively each cycle
+    ctx.mouse.reset_delta();
+
+    // Copy the state of the keyboard into the KeyboardContext
+    // and the mouse into the MouseContext
+    ctx.keyboard.save_keyboard_state();
+    ctx.mouse.save_mouse_state();
+    ctx.actions.save_action_state();
+}
+
+/// Presses a mouse button for a gamepad button while the mouse is emulated, see
+/// [`GamepadContext::set_mouse_emulation`](crate::input::gamepad::GamepadContext::set_mouse_emulation).
+/// Returns `true` if the event loop should stop.
+#[cfg(feature = "gamepad")]
+fn emulate_mouse_button<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+    button: Button,
+    pressed: bool,
+) -> bool
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    let Some(button) = ctx.gamepad.emulated_mouse_button(button) else {
+        return false;
+    };
+    ctx.mouse.set_button(button, pressed);
+    ctx.mouse
+        .set_button_event_time(button, ctx.time.time_since_start());
+    ctx.actions.set_mouse_button(button, pressed);
+
+    let position = ctx.mouse.position();
+    if pressed {
+        let res = state.mouse_button_down_event(ctx, button, position.x, position.y);
+        catch_error(
+            ctx,
+            res,
+            state,
+            control_flow,
+            ErrorOrigin::MouseButtonDownEvent,
+        )
+    } else {
+        let res = state.mouse_button_up_event(ctx, button, position.x, position.y);
+        catch_error(
+            ctx,
+            res,
+            state,
+            control_flow,
+            ErrorOrigin::MouseButtonUpEvent,
+        )
+    }
+}
+
+/// Moves the mouse with the gamepads' sticks while the mouse is emulated. Returns `true` if
+/// the event loop should stop.
+#[cfg(feature = "gamepad")]
+fn emulate_mouse_motion<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+) -> bool
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    let motion = ctx.gamepad.emulated_mouse_motion(ctx.time.delta());
+    if motion == glam::Vec2::ZERO {
+        return false;
+    }
+
+    let (width, height) = ctx.gfx.drawable_size();
+    let scale = ctx.gfx.coordinate_scale();
+    let screen = glam::Vec2::new(width, height) / scale;
+    let position =
+        (glam::Vec2::from(ctx.mouse.position()) + motion).clamp(glam::Vec2::ZERO, screen);
+    ctx.mouse.handle_move(position.x, position.y);
+    if let Some(window) = &ctx.gfx.window {
+        // Not every platform lets the cursor be moved, in which case only the game sees it move.
+        let _ = window.set_cursor_position(winit::dpi::PhysicalPosition::new(
+            position.x * scale,
+            position.y * scale,
+        ));
+    }
+
+    let delta = ctx.mouse.last_delta();
+    let res = state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+    catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseMotionEvent)
+}
+
+/// Calls `quit_event` for a pending quit request, ending the game unless it's cancelled.
+/// Returns `true` if an error ended the event loop.
+fn process_quit_request<S, E>(
+    ctx: &mut Context,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+) -> bool
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    if !ctx.quit_requested {
+        return false;
+    }
+
+    let res = state.quit_event(ctx);
+    ctx.quit_requested = false;
+    if let Ok(false) = res {
+        ctx.continuing = false;
+        false
+    } else {
+        catch_error(ctx, res, state, control_flow, ErrorOrigin::QuitEvent)
+    }
 }
 
 fn catch_error<T, E, S: 'static>(
@@ -599,22 +1111,64 @@ where
     false
 }
 
+/// Like [`catch_error`], but with [`ErrorPolicy::Overlay`] a fatal error shows the error
+/// overlay instead of ending the event loop.
+fn catch_frame_error<T, E, S>(
+    ctx: &mut Context,
+    event_result: Result<T, E>,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+    origin: ErrorOrigin,
+) -> bool
+where
+    S: EventHandler<E> + 'static,
+    E: std::fmt::Debug,
+{
+    if let Err(e) = event_result {
+        error!("Error on EventHandler {origin:?}: {e:?}");
+        eprintln!("Error on EventHandler {origin:?}: {e:?}");
+        let message = format!("Error in {origin:?}: {e:?}");
+        if state.on_error(ctx, origin, e) {
+            if ctx.error_policy == ErrorPolicy::Overlay {
+                ctx.error_overlay.show(message);
+                return false;
+            }
+            *control_flow = ControlFlow::Exit;
+            return true;
+        }
+    }
+    false
+}
+
 /// Feeds an `Event` into the `Context` so it can update any internal
 /// state it needs to, such as detecting window resizes.  If you are
 /// rolling your own event loop, you should call this on the events
 /// you receive before processing them yourself.
 pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
-    if let winit_event::Event::WindowEvent { event, .. } = event {
+    if let winit_event::Event::WindowEvent { window_id, event } = event {
+        if let Some(id) = ctx.gfx.secondary_window_id(*window_id) {
+            if let winit_event::WindowEvent::Resized(_) = event {
+                ctx.gfx.resize_window(id);
+            }
+            return;
+        }
+
         match event {
             winit_event::WindowEvent::Resized(physical_size) => {
                 ctx.gfx.resize(*physical_size);
             }
+            winit_event::WindowEvent::CloseRequested => {
+                ctx.request_quit_from(QuitSource::WindowClose);
+            }
             winit_event::WindowEvent::CursorMoved {
                 position: physical_position,
                 ..
             } => {
-                ctx.mouse
-                    .handle_move(physical_position.x as f32, physical_position.y as f32);
+                let scale = ctx.gfx.coordinate_scale();
+                ctx.mouse.handle_move(
+                    physical_position.x as f32 / scale,
+                    physical_position.y as f32 / scale,
+                );
             }
             winit_event::WindowEvent::MouseInput { button, state, .. } => {
                 let pressed = match state {
@@ -622,9 +1176,13 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
                     winit_event::ElementState::Released => false,
                 };
                 ctx.mouse.set_button(*button, pressed);
+                ctx.mouse
+                    .set_button_event_time(*button, ctx.time.time_since_start());
+                ctx.actions.set_mouse_button(*button, pressed);
             }
             winit_event::WindowEvent::ModifiersChanged(mods) => {
-                ctx.keyboard.set_modifiers(KeyMods::from(*mods))
+                ctx.keyboard.set_modifiers(KeyMods::from(*mods));
+                ctx.actions.set_modifiers(KeyMods::from(*mods));
             }
             winit_event::WindowEvent::KeyboardInput {
                 input:
@@ -643,9 +1201,32 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
                 ctx.keyboard.set_scancode(*scancode, pressed);
                 if let Some(key) = keycode {
                     ctx.keyboard.set_key(*key, pressed);
+                    ctx.keyboard
+                        .set_key_event_time(*key, ctx.time.time_since_start());
+                }
+                ctx.actions.set_key(*scancode, *keycode, pressed);
+
+                // The default menu that would handle this is disabled, see `Context::from_conf`.
+                #[cfg(target_os = "macos")]
+                if ctx.conf.window_setup.quit_shortcut_event
+                    && pressed
+                    && *keycode == Some(KeyCode::Q)
+                    && ctx.keyboard.active_mods().contains(KeyMods::LOGO)
+                {
+                    ctx.request_quit_from(QuitSource::Shortcut);
                 }
             }
-            winit_event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            winit_event::WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                // Keep the last mouse position at the same place on screen.
+                let old_scale = ctx.gfx.coordinate_scale();
+                ctx.gfx.set_scale_factor(*scale_factor);
+                let ratio = old_scale / ctx.gfx.coordinate_scale();
+                let position = glam::Vec2::from(ctx.mouse.position());
+                ctx.mouse.set_last_position(position * ratio);
+
                 if !ctx.conf.window_mode.resize_on_scale_factor_change {
                     // actively set the new_inner_size to be the desired size
                     // to stop winit from resizing our window
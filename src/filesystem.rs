@@ -20,6 +20,19 @@
 //! altered copies of them in the game's `resources/` directory.  It
 //! is loosely based off of the `PhysicsFS` library.
 //!
+//! More places can be added with
+//! [`ContextBuilder::add_resource_source`](crate::ContextBuilder::add_resource_source) or
+//! [`Filesystem::add_resource_source`], each with a priority. Sources with a higher priority
+//! are searched first, and sources with the same priority are searched in the order they
+//! were added. The places listed above all have a priority of 0, so a source added with a
+//! priority of 1 overrides them all. [`Filesystem::resolve`] shows which sources contain a
+//! file, which helps to find out why the wrong one is loaded.
+//!
+//! Save games are best written with [`Filesystem::write_user_file_atomic`], which never
+//! leaves a half-written file behind, and read back with [`Filesystem::read_user_file`].
+//! [`Filesystem::list_user_files`] finds existing saves and [`Filesystem::backup_rotate`]
+//! keeps a few older versions of them around.
+//!
 //! See the source of the [`files` example](https://github.com/ggez/ggez/blob/master/examples/files.rs) for more details.
 //!
 //! The names of `resources/` and `resources.zip` can be changed with the methods
@@ -39,6 +52,7 @@ use crate::{
 };
 use directories::ProjectDirs;
 use std::{
+    borrow::Cow,
     env, io,
     io::SeekFrom,
     ops::DerefMut,
@@ -49,6 +63,8 @@ use std::{
 pub use crate::vfs::OpenOptions;
 
 const CONFIG_NAME: &str = "/conf.toml";
+/// Appended to the name of a file being written by [`Filesystem::write_user_file_atomic`].
+const TEMP_SUFFIX: &str = ".ggez-tmp";
 
 /// A structure that contains the filesystem state and cache.
 #[derive(Debug)]
@@ -81,6 +97,23 @@ impl InternalClone for Filesystem {
     }
 }
 
+/// A place to search for resources, see [`Filesystem::add_resource_source`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceSource {
+    /// A directory on disk, which is only read from.
+    Dir(path::PathBuf),
+    /// A zip file on disk.
+    Zip(path::PathBuf),
+    /// A zip file in memory, such as one included with `include_bytes!`.
+    Memory(Cow<'static, [u8]>),
+}
+
+/// Identifies one of the places the filesystem searches for resources.
+///
+/// Returned by [`Filesystem::add_resource_source`] and [`Filesystem::resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceId(usize);
+
 /// Represents a file, either in the filesystem, or in the resources zip file,
 /// or whatever.
 #[derive(Debug)]
@@ -175,7 +208,7 @@ impl Filesystem {
             resources_path.push(resources_dir_name);
             trace!("Resources path: {:?}", resources_path);
             let physfs = vfs::PhysicalFS::new(&resources_path, true);
-            overlay.push_back(Box::new(physfs));
+            let _ = overlay.insert(Box::new(physfs), 0);
         }
 
         // <root>/resources.zip
@@ -185,7 +218,7 @@ impl Filesystem {
             if resources_zip_path.exists() {
                 trace!("Resources zip file: {:?}", resources_zip_path);
                 let zipfs = vfs::ZipFS::new(&resources_zip_path)?;
-                overlay.push_back(Box::new(zipfs));
+                let _ = overlay.insert(Box::new(zipfs), 0);
             } else {
                 trace!("No resources zip file found");
             }
@@ -197,7 +230,7 @@ impl Filesystem {
             user_data_path = project_dirs.data_local_dir();
             trace!("User-local data path: {:?}", user_data_path);
             let physfs = vfs::PhysicalFS::new(user_data_path, true);
-            overlay.push_back(Box::new(physfs));
+            let _ = overlay.insert(Box::new(physfs), 0);
         }
 
         // Writeable local dir, ~/.config/whatever/
@@ -206,7 +239,7 @@ impl Filesystem {
             user_config_path = project_dirs.config_dir();
             trace!("User-local configuration path: {:?}", user_config_path);
             let physfs = vfs::PhysicalFS::new(user_config_path, false);
-            overlay.push_back(Box::new(physfs));
+            let _ = overlay.insert(Box::new(physfs), 0);
         }
 
         let fs = Filesystem {
@@ -299,6 +332,8 @@ impl Filesystem {
     /// Returns a list of all files and directories in the resource directory,
     /// in no particular order.
     ///
+    /// The contents of all sources are merged, listing entries found in several sources once.
+    ///
     /// Lists the base directory if an empty path is given.
     pub fn read_dir<P: AsRef<path::Path>>(
         &self,
@@ -313,7 +348,8 @@ impl Filesystem {
     fn write_to_string(&self) -> String {
         use std::fmt::Write;
         let mut s = String::new();
-        for vfs in self.vfs().roots() {
+        let vfs = self.vfs();
+        for vfs in vfs.roots() {
             write!(s, "Source {vfs:?}").expect("Could not write to string; should never happen?");
             match vfs.read_dir(path::Path::new("/")) {
                 Ok(files) => {
@@ -349,10 +385,12 @@ impl Filesystem {
     /// harder than it looks to make it bulletproof across platforms.
     /// But it can be very nice for debugging and dev purposes, such as
     /// by pushing `$CARGO_MANIFEST_DIR/resources` to it
+    ///
+    /// The path is searched after all sources added before it with a priority of 0 or more.
     pub fn mount(&self, path: &path::Path, readonly: bool) {
         let physfs = vfs::PhysicalFS::new(path, readonly);
         trace!("Mounting new path: {:?}", physfs);
-        self.vfs().push_back(Box::new(physfs));
+        let _ = self.vfs().insert(Box::new(physfs), 0);
     }
 
     /// Adds any object that implements Read + Seek as a zip file.
@@ -364,10 +402,164 @@ impl Filesystem {
     pub fn add_zip_file<R: io::Read + io::Seek + 'static>(&self, reader: R) -> GameResult {
         let zipfs = vfs::ZipFS::from_read(reader)?;
         trace!("Adding zip file from reader");
-        self.vfs().push_back(Box::new(zipfs));
+        let _ = self.vfs().insert(Box::new(zipfs), 0);
         Ok(())
     }
 
+    /// Adds a place to search for resources.
+    ///
+    /// Sources with a higher `priority` are searched first, and sources with the same
+    /// priority in the order they were added. The resource directory, zip file and user
+    /// directories set up by ggez have a priority of 0.
+    pub fn add_resource_source(
+        &self,
+        source: ResourceSource,
+        priority: i32,
+    ) -> GameResult<SourceId> {
+        let fs: Box<dyn VFS> = match source {
+            ResourceSource::Dir(path) => Box::new(vfs::PhysicalFS::new(&path, true)),
+            ResourceSource::Zip(path) => Box::new(vfs::ZipFS::new(&path)?),
+            ResourceSource::Memory(bytes) => {
+                Box::new(vfs::ZipFS::from_read(io::Cursor::new(bytes))?)
+            }
+        };
+        trace!("Adding resource source {:?} with priority {}", fs, priority);
+        Ok(SourceId(self.vfs().insert(fs, priority)))
+    }
+
+    /// Returns all sources that contain `path`, in the order they are searched.
+    ///
+    /// The first one is the one [`Filesystem::open`] reads from.
+    pub fn resolve<P: AsRef<path::Path>>(&self, path: P) -> Vec<SourceId> {
+        self.vfs()
+            .resolve(path.as_ref())
+            .into_iter()
+            .map(SourceId)
+            .collect()
+    }
+
+    /// Returns a description of a source for debugging, such as its location on disk.
+    pub fn describe_source(&self, id: SourceId) -> Option<String> {
+        self.vfs().get(id.0).map(|vfs| format!("{vfs:?}"))
+    }
+
+    /// Turns a path like `/saves/slot1.sav` into its location in the user directory.
+    fn user_path(&self, path: &path::Path) -> GameResult<path::PathBuf> {
+        let full = vfs::PhysicalFS::new(&self.user_config_dir, false).to_absolute(path)?;
+        if full == self.user_config_dir {
+            return Err(GameError::FilesystemError(format!(
+                "Path {path:?} does not name a file"
+            )));
+        }
+        Ok(full)
+    }
+
+    /// Writes `bytes` to a file in the user directory, replacing it as a whole.
+    ///
+    /// The data is written to a temporary file next to `path`, flushed to disk and then
+    /// renamed over the old file, so if the game crashes or the power goes out halfway
+    /// through, the file holds either the old or the new contents, never a mix of both.
+    /// Missing parent directories are created.
+    ///
+    /// Meant for save games and anything else that would be painful to lose. Use
+    /// [`Filesystem::backup_rotate`] before writing to also keep older versions around.
+    pub fn write_user_file_atomic<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        bytes: &[u8],
+    ) -> GameResult {
+        let target = self.user_path(path.as_ref())?;
+        write_atomic(&target, bytes)
+    }
+
+    /// Reads a whole file from the user directory.
+    ///
+    /// Unlike [`Filesystem::open`], this only looks in the user directory, so a file with
+    /// the same name in the resources can't shadow a save.
+    pub fn read_user_file<P: AsRef<path::Path>>(&self, path: P) -> GameResult<Vec<u8>> {
+        let path = path.as_ref();
+        let full = self.user_path(path)?;
+        std::fs::read(&full).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                GameError::ResourceNotFound(path.to_string_lossy().into_owned(), Vec::new())
+            }
+            _ => e.into(),
+        })
+    }
+
+    /// Lists the files in the user directory whose path starts with `prefix`, sorted by name.
+    ///
+    /// Paths are returned in the same form the other functions take, such as
+    /// `/saves/slot1.sav` for a prefix of `/saves/slot`. Subdirectories are searched as
+    /// well, and leftovers of interrupted [`Filesystem::write_user_file_atomic`] calls are
+    /// skipped.
+    pub fn list_user_files(&self, prefix: &str) -> GameResult<Vec<path::PathBuf>> {
+        fn walk(dir: &path::Path, rel: &str, out: &mut Vec<String>) -> io::Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = match name.to_str() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let rel = format!("{rel}/{name}");
+                if entry.file_type()?.is_dir() {
+                    walk(&entry.path(), &rel, out)?;
+                } else if !name.ends_with(TEMP_SUFFIX) {
+                    out.push(rel);
+                }
+            }
+            Ok(())
+        }
+
+        let mut files = Vec::new();
+        if self.user_config_dir.is_dir() {
+            walk(&self.user_config_dir, "", &mut files)?;
+        }
+        files.retain(|f| f.starts_with(prefix));
+        files.sort();
+        Ok(files.into_iter().map(path::PathBuf::from).collect())
+    }
+
+    /// Keeps up to `keep_n` older copies of a file in the user directory.
+    ///
+    /// Copies `path` to `path.1`, after moving `path.1` to `path.2` and so on, and deletes
+    /// the copies past `keep_n`. Call it right before overwriting the file:
+    ///
+    /// ```rust,no_run
+    /// # fn t(ctx: &ggez::Context, save: &[u8]) -> ggez::GameResult {
+    /// ctx.fs.backup_rotate("/saves/slot1.sav", 3)?;
+    /// ctx.fs.write_user_file_atomic("/saves/slot1.sav", save)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Does nothing but delete old copies if `path` doesn't exist yet.
+    pub fn backup_rotate<P: AsRef<path::Path>>(&self, path: P, keep_n: usize) -> GameResult {
+        let target = self.user_path(path.as_ref())?;
+        let backup = |n: usize| {
+            let mut name = target.clone().into_os_string();
+            name.push(format!(".{n}"));
+            path::PathBuf::from(name)
+        };
+
+        let mut n = keep_n + 1;
+        while backup(n).is_file() {
+            std::fs::remove_file(backup(n))?;
+            n += 1;
+        }
+        if keep_n == 0 || !target.is_file() {
+            return Ok(());
+        }
+
+        for n in (1..keep_n).rev() {
+            if backup(n).is_file() {
+                std::fs::rename(backup(n), backup(n + 1))?;
+            }
+        }
+        write_atomic(&backup(1), &std::fs::read(&target)?)
+    }
+
     /// Looks for a file named `/conf.toml` in any resource directory and
     /// loads it if it finds it.
     /// If it can't read it for some reason, returns an error.
@@ -422,6 +614,42 @@ impl Filesystem {
     }
 }
 
+/// Replaces the file at `target` with `bytes` by writing to a temporary file and renaming it.
+fn write_atomic(target: &path::Path, bytes: &[u8]) -> GameResult {
+    use std::io::Write;
+
+    let (dir, name) = match (target.parent(), target.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => {
+            return Err(GameError::FilesystemError(format!(
+                "Path {target:?} does not name a file"
+            )))
+        }
+    };
+    std::fs::create_dir_all(dir)?;
+
+    let mut temp_name = name.to_os_string();
+    temp_name.push(TEMP_SUFFIX);
+    let temp = dir.join(temp_name);
+
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| std::fs::rename(&temp, target)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+
+    // The rename itself only survives a crash once the directory is flushed too.
+    // Windows doesn't allow opening directories like this, and flushes renames on its own.
+    #[cfg(unix)]
+    {
+        let _ = std::fs::File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
 /// Opens the given path and returns the resulting `File`
 /// in read-only mode.
 #[deprecated(since = "0.8.0", note = "Use `ctx.fs.open` instead")]
@@ -573,7 +801,7 @@ pub fn write_config(ctx: &Context, conf: &conf::Conf) -> GameResult {
 mod tests {
     use crate::conf;
     use crate::error::GameError;
-    use crate::filesystem::{env, vfs, Arc, Filesystem, Mutex, CONFIG_NAME};
+    use crate::filesystem::{env, vfs, Arc, Filesystem, Mutex, ResourceSource, CONFIG_NAME};
     use std::io::{Read, Write};
     use std::path;
 
@@ -671,4 +899,95 @@ mod tests {
         // Remove the config file!
         f.delete(CONFIG_NAME).unwrap();
     }
+
+    #[test]
+    fn headless_test_resource_source_priorities() {
+        let f = dummy_fs_for_tests();
+        let root = env::temp_dir().join(format!("ggez-sources-{}", std::process::id()));
+        let dir = |name: &str| {
+            let dir = root.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("layer.txt"), name).unwrap();
+            dir
+        };
+        let read = |f: &Filesystem| {
+            let mut s = String::new();
+            let _ = f
+                .open("/layer.txt")
+                .unwrap()
+                .read_to_string(&mut s)
+                .unwrap();
+            s
+        };
+
+        let low = f
+            .add_resource_source(ResourceSource::Dir(dir("low")), 0)
+            .unwrap();
+        assert_eq!(read(&f), "low");
+
+        let high = f
+            .add_resource_source(ResourceSource::Dir(dir("high")), 5)
+            .unwrap();
+        let high2 = f
+            .add_resource_source(ResourceSource::Dir(dir("high2")), 5)
+            .unwrap();
+        assert_eq!(read(&f), "high");
+        assert_eq!(f.resolve("/layer.txt"), [high, high2, low]);
+        assert!(f.describe_source(high).unwrap().contains("high"));
+
+        let entries = f.read_dir("/").unwrap().collect::<Vec<_>>();
+        let layers = entries.iter().filter(|p| p.ends_with("layer.txt")).count();
+        assert_eq!(layers, 1);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn headless_test_user_files() {
+        let mut f = dummy_fs_for_tests();
+        let root = env::temp_dir().join(format!("ggez-saves-{}", std::process::id()));
+        f.user_config_dir = root.clone();
+
+        f.write_user_file_atomic("/saves/slot1.sav", b"first")
+            .unwrap();
+        f.write_user_file_atomic("/saves/slot2.sav", b"other")
+            .unwrap();
+        f.write_user_file_atomic("/options.toml", b"").unwrap();
+        assert_eq!(f.read_user_file("/saves/slot1.sav").unwrap(), b"first");
+        assert!(f.read_user_file("/../escape.sav").is_err());
+        assert!(matches!(
+            f.read_user_file("/saves/missing.sav"),
+            Err(GameError::ResourceNotFound(_, _))
+        ));
+
+        // A crash between writing the temporary file and renaming it leaves the save intact.
+        std::fs::write(root.join("saves/slot1.sav.ggez-tmp"), b"fir").unwrap();
+        assert_eq!(f.read_user_file("/saves/slot1.sav").unwrap(), b"first");
+        assert_eq!(
+            f.list_user_files("/saves/slot").unwrap(),
+            [
+                path::Path::new("/saves/slot1.sav"),
+                path::Path::new("/saves/slot2.sav")
+            ]
+        );
+        f.write_user_file_atomic("/saves/slot1.sav", b"second")
+            .unwrap();
+        assert_eq!(f.read_user_file("/saves/slot1.sav").unwrap(), b"second");
+        assert!(!root.join("saves/slot1.sav.ggez-tmp").exists());
+
+        for save in ["third", "fourth", "fifth"] {
+            f.backup_rotate("/saves/slot1.sav", 2).unwrap();
+            f.write_user_file_atomic("/saves/slot1.sav", save.as_bytes())
+                .unwrap();
+        }
+        assert_eq!(f.read_user_file("/saves/slot1.sav").unwrap(), b"fifth");
+        assert_eq!(f.read_user_file("/saves/slot1.sav.1").unwrap(), b"fourth");
+        assert_eq!(f.read_user_file("/saves/slot1.sav.2").unwrap(), b"third");
+        assert!(!root.join("saves/slot1.sav.3").exists());
+
+        f.backup_rotate("/saves/slot1.sav", 0).unwrap();
+        assert_eq!(f.list_user_files("/saves/slot1").unwrap().len(), 1);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
 }
@@ -0,0 +1,219 @@
+use super::{
+    context::GraphicsContext,
+    gpu::{
+        arc::{ArcBindGroup, ArcBindGroupLayout},
+        bind_group::BindGroupBuilder,
+        pipeline::RenderPipelineInfo,
+    },
+    image::Image,
+    mesh::Vertex,
+    shader::{Shader, ShaderParams},
+};
+use crate::{GameError, GameResult};
+use crevice::std140::AsStd140;
+
+impl GraphicsContext {
+    /// Draws `src` over the whole of `dst` with a full-screen triangle, for example to
+    /// apply a post-processing effect.
+    ///
+    /// Without a `shader`, the pixels are copied as they are, scaled to the size of `dst`.
+    /// Where only one of the images is sRGB, they are converted so they keep their values.
+    ///
+    /// A custom shader only needs a fragment module, written like one for
+    /// [`Canvas::set_shader`](super::Canvas::set_shader): `src` is bound to group 1 and
+    /// the vertex color is white. The UVs go from (0, 0) at the top left of `dst` to
+    /// (1, 1) at the bottom right. The shader's vertex module isn't used. To pass it
+    /// uniforms, use [`blit_with_params`](Self::blit_with_params).
+    ///
+    /// The blit happens in order with the canvases of the current frame, or right away
+    /// outside of a frame. `src` and `dst` must be different images, `dst` must be drawable
+    /// (like an image made with [`Image::new_canvas_image`]) and can't be multisampled.
+    pub fn blit(&mut self, src: &Image, dst: &Image, shader: Option<&Shader>) -> GameResult {
+        if let Some(shader) = shader {
+            shader.check_params(None, wgpu::ShaderStages::FRAGMENT)?;
+        }
+        blit(self, src, dst, shader, None)
+    }
+
+    /// Like [`blit`](Self::blit), with uniforms for the custom shader.
+    ///
+    /// **Bound to bind group 3**, like with [`Canvas::set_shader_params`](super::Canvas::set_shader_params).
+    pub fn blit_with_params<Uniforms: AsStd140>(
+        &mut self,
+        src: &Image,
+        dst: &Image,
+        shader: &Shader,
+        params: &ShaderParams<Uniforms>,
+    ) -> GameResult {
+        shader.check_params(Some(&params.bindings), wgpu::ShaderStages::FRAGMENT)?;
+        let params = (
+            params.bind_group.clone().unwrap(/* always Some */),
+            params.layout.clone().unwrap(/* always Some */),
+            params.buffer_offset,
+        );
+        blit(self, src, dst, Some(shader), Some(params))
+    }
+}
+
+fn blit(
+    gfx: &mut GraphicsContext,
+    src: &Image,
+    dst: &Image,
+    shader: Option<&Shader>,
+    params: Option<(ArcBindGroup, ArcBindGroupLayout, u32)>,
+) -> GameResult {
+    let error = |msg: &str| Err(GameError::RenderError(format!("cannot blit image: {msg}")));
+    if std::ptr::eq(src.texture.as_ref(), dst.texture.as_ref()) {
+        return error("an image can't be drawn onto itself; copy it first");
+    }
+    if src.dimension != wgpu::TextureViewDimension::D2 || src.samples > 1 {
+        return error("array, 3D and multisampled images can't be drawn from");
+    }
+    if dst.samples > 1
+        || !dst
+            .texture
+            .usage()
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    {
+        return error("the destination must be a canvas image that isn't multisampled");
+    }
+
+    let device = &gfx.wgpu.device;
+    let sampler = gfx
+        .sampler_cache
+        .get(device, src.sampler.unwrap_or_default());
+    let (image_group, image_layout) = BindGroupBuilder::new()
+        .image(&src.view, wgpu::ShaderStages::FRAGMENT)
+        .sampler(&sampler, wgpu::ShaderStages::FRAGMENT)
+        .create(device, &mut gfx.bind_group_cache);
+    // Groups 0 and 2 hold the draw uniforms and instances on a canvas, which blits don't have.
+    let (empty_group, empty_layout) =
+        BindGroupBuilder::new().create(device, &mut gfx.bind_group_cache);
+    let mut layouts = vec![empty_layout.clone(), image_layout, empty_layout];
+    if let Some((_, ref layout, _)) = params {
+        layouts.push(layout.clone());
+    }
+    let pipeline_layout = gfx.pipeline_cache.layout(device, &layouts);
+
+    let fs_entry = match (src.format.is_srgb(), dst.format.is_srgb()) {
+        _ if shader.is_some() => "fs_main",
+        (true, false) => "fs_encode",
+        (false, true) => "fs_decode",
+        _ => "fs_main",
+    };
+    let pipeline = gfx.pipeline_cache.render_pipeline(
+        device,
+        &pipeline_layout,
+        RenderPipelineInfo {
+            vs: gfx.blit_shader.clone(),
+            fs: shader
+                .and_then(|shader| shader.fs_module.clone())
+                .unwrap_or_else(|| gfx.blit_shader.clone()),
+            vs_entry: "vs_main".into(),
+            fs_entry: fs_entry.into(),
+            samples: 1,
+            format: dst.format,
+            blend: None,
+            depth: false,
+            vertices: false,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            vertex_layout: Vertex::layout(),
+        },
+    );
+
+    let mut encoder = match gfx.fcx.as_mut() {
+        Some(_) => None,
+        None => {
+            Some(device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }))
+        }
+    };
+    {
+        let cmd = match encoder {
+            Some(ref mut encoder) => encoder,
+            None => &mut gfx.fcx.as_mut().unwrap(/* checked above */).cmd,
+        };
+        let mut pass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: dst.view.as_ref(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &empty_group, &[]);
+        pass.set_bind_group(1, &image_group, &[]);
+        pass.set_bind_group(2, &empty_group, &[]);
+        if let Some((ref group, _, offset)) = params {
+            pass.set_bind_group(3, group, &[offset]);
+        }
+        pass.draw(0..3, 0..1);
+    }
+    if let Some(encoder) = encoder {
+        let _ = gfx.wgpu.queue.submit([encoder.finish()]);
+    }
+    gfx.frame_stats.draw(1, &[]);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::tests::headless_context,
+        graphics::{Image, ImageFormat, ShaderBuilder, ShaderParamsBuilder},
+    };
+    use crevice::std140::AsStd140;
+
+    #[derive(AsStd140)]
+    struct Dim {
+        rate: f32,
+    }
+
+    #[test]
+    fn headless_test_blit() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let pixels = [
+            200, 100, 50, 255, 0, 0, 0, 255, 10, 20, 30, 255, 255, 255, 255, 255,
+        ];
+        let src = Image::from_pixels(&ctx, &pixels, ImageFormat::Rgba8Unorm, 2, 2);
+        let dst = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 2, 2, 1);
+        ctx.gfx.blit(&src, &dst, None).unwrap();
+        assert_eq!(dst.to_pixels(&ctx).unwrap(), pixels);
+
+        // Copying between sRGB and linear images keeps the values of the pixels.
+        let srgb = Image::from_pixels(&ctx, &pixels, ImageFormat::Rgba8UnormSrgb, 2, 2);
+        ctx.gfx.blit(&srgb, &dst, None).unwrap();
+        let copied = dst.to_pixels(&ctx).unwrap();
+        assert!(copied.iter().zip(pixels).all(|(a, b)| a.abs_diff(b) <= 1));
+
+        // Canvas shaders work for blits too, in order with the frame.
+        let shader = ShaderBuilder::new()
+            .fragment_code(include_str!("../../resources/dimmer.wgsl"))
+            .build(&ctx.gfx)
+            .unwrap();
+        let dim = Dim { rate: 0.5 };
+        let params = ShaderParamsBuilder::new(&dim).build(&mut ctx);
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.blit(&src, &dst, None).unwrap();
+        ctx.gfx
+            .blit_with_params(&src, &dst, &shader, &params)
+            .unwrap();
+        ctx.gfx.end_frame().unwrap();
+        let dimmed = dst.to_pixels(&ctx).unwrap();
+        assert!(dimmed
+            .iter()
+            .zip(pixels)
+            .all(|(a, b)| a.abs_diff(b / 2) <= 1));
+
+        assert!(ctx.gfx.blit(&dst, &dst, None).is_err());
+        assert!(ctx.gfx.blit(&dst, &src, None).is_err());
+    }
+}
@@ -8,8 +8,10 @@ use crate::{
 use super::{
     gpu::arc::{ArcBindGroup, ArcBindGroupLayout},
     internal_canvas::{screen_to_mat, InstanceArrayView, InternalCanvas},
+    lut,
+    shader::ShaderBinding,
     BlendMode, Color, DrawParam, Drawable, GraphicsContext, Image, InstanceArray, Mesh, Rect,
-    Sampler, ScreenImage, Shader, ShaderParams, Text, WgpuContext, ZIndex,
+    Sampler, ScreenImage, Shader, ShaderParams, Text, TextOnPath, WgpuContext, WindowId, ZIndex,
 };
 use std::{collections::BTreeMap, sync::Arc};
 
@@ -30,11 +32,16 @@ pub struct Canvas {
     state: DrawState,
     original_state: DrawState,
     screen: Option<Rect>,
+    // Units of the points given to `screen_to_canvas` per target pixel.
+    input_scale: f32,
     defaults: DefaultResources,
 
     target: Image,
     resolve: Option<Image>,
     clear: Option<Color>,
+    lut: Option<Image>,
+    /// Keeps the canvas registered as unfinished, see `GraphicsContext::open_canvas`.
+    open: Option<Arc<()>>,
 
     // This will be removed after queue_text and draw_queued_text have been removed.
     pub(crate) queued_texts: Vec<(Text, mint::Point2<f32>, Option<Color>)>,
@@ -95,7 +102,13 @@ impl Canvas {
 
     /// Create a new [Canvas] that renders directly to the window surface.
     ///
+    /// The screen coordinates cover the window in the units of the
+    /// [`CoordinateMode`](super::CoordinateMode).
+    ///
     /// `clear` will set the image initially to the given color, if a color is provided, or keep it as is, if it's `None`.
+    /// For a [transparent](crate::conf::WindowMode::transparent) window, the color is passed
+    /// on to the compositor with premultiplied alpha, so a see-through clear color should be
+    /// premultiplied too, e.g. `Color::new(0., 0., 0., 0.)`.
     pub fn from_frame(gfx: &impl Has<GraphicsContext>, clear: impl Into<Option<Color>>) -> Self {
         let gfx = gfx.retrieve();
         // these unwraps will never fail
@@ -108,7 +121,35 @@ impl Canvas {
         } else {
             (gfx.frame_image.clone().unwrap(), None)
         };
-        Canvas::new(gfx, target, resolve, clear.into())
+        let mut canvas = Canvas::new(gfx, target, resolve, clear.into());
+        let scale = gfx.coordinate_scale();
+        canvas.input_scale = scale;
+        if scale != 1. {
+            let (width, height) = (canvas.target.width(), canvas.target.height());
+            canvas.set_screen_coordinates(Rect::new(
+                0.,
+                0.,
+                width as f32 / scale,
+                height as f32 / scale,
+            ));
+        }
+        canvas
+    }
+
+    /// Create a new [Canvas] that renders to a secondary window opened with
+    /// [`GraphicsContext::create_window`].
+    ///
+    /// `clear` will set the image initially to the given color, if a color is provided, or keep it as is, if it's `None`.
+    ///
+    /// Returns `GameError::WindowError` if the window has been closed, or hasn't been opened yet.
+    pub fn from_window_frame(
+        gfx: &impl Has<GraphicsContext>,
+        window: WindowId,
+        clear: impl Into<Option<Color>>,
+    ) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let target = gfx.window_frame(window)?;
+        Ok(Canvas::new(gfx, target, None, clear.into()))
     }
 
     fn new(
@@ -126,9 +167,10 @@ impl Canvas {
             params: None,
             text_shader: default_text_shader(),
             text_params: None,
-            sampler: Sampler::default(),
+            sampler: None,
             blend_mode: BlendMode::ALPHA,
             premul_text: true,
+            premul_meshes: false,
             projection: glam::Mat4::IDENTITY.into(),
             scissor_rect: (0, 0, target.width(), target.height()),
         };
@@ -140,17 +182,25 @@ impl Canvas {
             h: target.height() as _,
         };
 
+        let open = match &resolve {
+            Some(resolve) => gfx.open_canvas(&[&target, resolve]),
+            None => gfx.open_canvas(&[&target]),
+        };
+
         let mut this = Canvas {
             wgpu: gfx.wgpu.clone(),
             draws: BTreeMap::new(),
             state: state.clone(),
             original_state: state,
             screen: Some(screen),
+            input_scale: 1.,
             defaults,
 
             target,
             resolve,
             clear,
+            lut: None,
+            open: Some(open),
 
             queued_texts: Vec::new(),
         };
@@ -174,13 +224,16 @@ impl Canvas {
 
     /// Sets the shader parameters to use when drawing meshes.
     ///
-    /// **Bound to bind group 3.**
+    /// **Bound to bind group 3.** [`Canvas::finish`] returns an error naming the first binding
+    /// where they don't provide what the shader expects, e.g. a texture where it samples with
+    /// a sampler.
     #[inline]
     pub fn set_shader_params<Uniforms: AsStd140>(&mut self, params: &ShaderParams<Uniforms>) {
         self.state.params = Some((
             params.bind_group.clone().unwrap(/* always Some */),
             params.layout.clone().unwrap(/* always Some */),
             params.buffer_offset,
+            params.bindings.clone(),
         ));
     }
 
@@ -208,6 +261,7 @@ impl Canvas {
             params.bind_group.clone().unwrap(/* always Some */),
             params.layout.clone().unwrap(/* always Some */),
             params.buffer_offset,
+            params.bindings.clone(),
         ));
         Ok(())
     }
@@ -227,23 +281,28 @@ impl Canvas {
     /// Sets the active sampler used to sample images.
     ///
     /// Use `set_sampler(Sampler::nearest_clamp())` for drawing pixel art graphics without blurring them.
+    ///
+    /// This overrides samplers set on images with [`Image::set_sampler`].
     #[inline]
     pub fn set_sampler(&mut self, sampler: impl Into<Sampler>) {
-        self.state.sampler = sampler.into();
+        self.state.sampler = Some(sampler.into());
     }
 
     /// Returns the currently active sampler used to sample images.
+    ///
+    /// Images with their own sampler are drawn with that instead, unless [`Canvas::set_sampler`] was called.
     #[inline]
     pub fn sampler(&self) -> Sampler {
-        self.state.sampler
+        self.state.sampler.unwrap_or_default()
     }
 
     /// Resets the active sampler to the default.
     ///
-    /// This is equivalent to `set_sampler(Sampler::linear_clamp())`.
+    /// Images are then drawn with the sampler set with [`Image::set_sampler`], or
+    /// `Sampler::linear_clamp()` if they have none.
     #[inline]
     pub fn set_default_sampler(&mut self) {
-        self.set_sampler(Sampler::default());
+        self.state.sampler = None;
     }
 
     /// Sets the active blend mode used when drawing images.
@@ -265,6 +324,43 @@ impl Canvas {
         self.state.premul_text = premultiplied_text;
     }
 
+    /// Selects whether meshes and images will be drawn with [`BlendMode::PREMULTIPLIED`] when the
+    /// current blend mode is [`BlendMode::ALPHA`]. This is `false` by default.
+    ///
+    /// By default, vertex colors and image pixels are treated as straight (non-premultiplied)
+    /// alpha. Enable this when drawing geometry whose vertex colors are already multiplied by
+    /// their alpha, or images holding premultiplied content, such as canvas images that
+    /// transparent content was drawn onto.
+    #[inline]
+    pub fn set_premultiplied_meshes(&mut self, premultiplied_meshes: bool) {
+        self.state.premul_meshes = premultiplied_meshes;
+    }
+
+    /// Sets a color lookup table that everything on the target is run through when the
+    /// canvas is finished, for color grading.
+    ///
+    /// The table is either a strip of N squares of NxN pixels, as made by
+    /// [`identity_lut`](crate::graphics::identity_lut), or an NxNxN image made with
+    /// [`Image::new_3d`]. It maps gamma-encoded colors to gamma-encoded colors, and is
+    /// sampled with linear filtering, so small tables like 16 or 32 work fine.
+    ///
+    /// This applies to the whole target, including what was on it before the canvas started
+    /// if it wasn't cleared.
+    ///
+    /// A strip is converted to a 3D image the first time it's used, and the result is reused
+    /// for as long as the strip is alive. Changes to the strip's pixels after that aren't
+    /// picked up, so use a new image or a 3D image for a table that changes.
+    #[inline]
+    pub fn set_color_lut(&mut self, lut: impl Into<Option<Image>>) {
+        self.lut = lut.into();
+    }
+
+    /// Returns the color lookup table set with [`Canvas::set_color_lut`], if any.
+    #[inline]
+    pub fn color_lut(&self) -> Option<&Image> {
+        self.lut.as_ref()
+    }
+
     /// Sets the raw projection matrix to the given homogeneous
     /// transformation matrix.  For an introduction to graphics matrices,
     /// a good source is this: <http://ncase.me/matrix/>
@@ -313,6 +409,34 @@ impl Canvas {
         self.screen
     }
 
+    /// Converts a point on the canvas's target into the coordinates drawing happens in,
+    /// undoing the [screen coordinates](Self::set_screen_coordinates) or any other
+    /// projection.
+    ///
+    /// For a canvas created with [`from_frame`](Self::from_frame), `point` is in the
+    /// units of the [`CoordinateMode`](super::CoordinateMode), so the mouse position from
+    /// [`MouseContext::position`](crate::input::mouse::MouseContext::position) can be
+    /// passed in as is. For any other canvas, it is in pixels of the target image.
+    ///
+    /// Projections that can't be inverted, or that see the canvas's plane exactly edge-on,
+    /// give non-finite coordinates.
+    pub fn screen_to_canvas(&self, point: impl Into<mint::Point2<f32>>) -> glam::Vec2 {
+        let point = glam::Vec2::from(point.into()) * self.input_scale;
+        let size = glam::vec2(self.target.width() as f32, self.target.height() as f32);
+        let ndc = point / size * glam::vec2(2., -2.) + glam::vec2(-1., 1.);
+
+        // Find where the line through this pixel crosses the z = 0 plane drawing happens on.
+        let inverse = glam::Mat4::from(self.state.projection).inverse();
+        let near = inverse.project_point3(ndc.extend(0.));
+        let far = inverse.project_point3(ndc.extend(1.));
+        let t = if far.z == near.z {
+            0.
+        } else {
+            near.z / (near.z - far.z)
+        };
+        near.lerp(far, t).truncate()
+    }
+
     /// Sets the scissor rectangle used when drawing. Nothing will be drawn to the canvas
     /// that falls outside of this region.
     ///
@@ -401,10 +525,48 @@ impl Canvas {
     }
 
     /// Finish drawing with this canvas and submit all the draw calls.
+    ///
+    /// Fails if another unfinished canvas draws to the same image, since the output of the two
+    /// would depend on the order they happen to be finished in.
     #[inline]
     pub fn finish(mut self, gfx: &mut impl HasMut<GraphicsContext>) -> GameResult {
         let gfx = gfx.retrieve_mut();
-        self.finalize(gfx)
+        self.check_targets(gfx)?;
+        let scope = gfx.begin_gpu_scope("canvas");
+        let result = self.finalize(gfx);
+        gfx.end_gpu_scope(scope);
+        result
+    }
+
+    /// Finishes drawing with this canvas, but only submits its draw calls at the end of the frame,
+    /// sorted by `order` together with other canvases finished this way.
+    ///
+    /// Canvases with a lower `order` draw first; ones with the same `order` draw in the order they
+    /// were finished in. All of them draw after canvases finished with [`Canvas::finish`].
+    /// [`GraphicsContext::flush_ordered_canvases`] submits them earlier.
+    ///
+    /// This allows several canvases to draw to the same image in a fixed order, regardless of
+    /// which part of the game finishes them first.
+    pub fn finish_ordered(
+        mut self,
+        gfx: &mut impl HasMut<GraphicsContext>,
+        order: i32,
+    ) -> GameResult {
+        let gfx = gfx.retrieve_mut();
+        self.check_targets(gfx)?;
+        // Queued canvases are finished as far as users are concerned.
+        self.open = None;
+        gfx.queue_ordered_canvas(order, self)
+    }
+
+    fn check_targets(&self, gfx: &GraphicsContext) -> GameResult {
+        match &self.open {
+            Some(open) => match &self.resolve {
+                Some(resolve) => gfx.check_canvas_targets(open, &[&self.target, resolve]),
+                None => gfx.check_canvas_targets(open, &[&self.target]),
+            },
+            None => Ok(()),
+        }
     }
 
     #[inline]
@@ -432,45 +594,54 @@ impl Canvas {
 
         // apply initial state
         canvas.set_shader(state.shader.clone());
-        if let Some((bind_group, layout, offset)) = &state.params {
+        if let Some((bind_group, layout, offset, _)) = &state.params {
             canvas.set_shader_params(bind_group.clone(), layout.clone(), *offset);
         }
 
         canvas.set_text_shader(state.text_shader.clone());
-        if let Some((bind_group, layout, offset)) = &state.text_params {
+        if let Some((bind_group, layout, offset, _)) = &state.text_params {
             canvas.set_text_shader_params(bind_group.clone(), layout.clone(), *offset);
         }
 
         canvas.set_sampler(state.sampler);
         canvas.set_blend_mode(state.blend_mode);
+        canvas.set_premultiplied_text(state.premul_text);
+        canvas.set_premultiplied_meshes(state.premul_meshes);
         canvas.set_projection(state.projection);
 
         if state.scissor_rect.2 > 0 && state.scissor_rect.3 > 0 {
             canvas.set_scissor_rect(state.scissor_rect);
         }
 
+        // whether the current shaders have been checked against their params
+        let (mut checked, mut text_checked) = (false, false);
+
         for draws in self.draws.values() {
             for draw in draws {
                 // track state and apply to InternalCanvas if changed
 
                 if draw.state.shader != state.shader {
                     canvas.set_shader(draw.state.shader.clone());
+                    checked = false;
                 }
 
                 if draw.state.params != state.params {
-                    if let Some((bind_group, layout, offset)) = &draw.state.params {
+                    if let Some((bind_group, layout, offset, _)) = &draw.state.params {
                         canvas.set_shader_params(bind_group.clone(), layout.clone(), *offset);
                     }
+                    checked = false;
                 }
 
                 if draw.state.text_shader != state.text_shader {
                     canvas.set_text_shader(draw.state.text_shader.clone());
+                    text_checked = false;
                 }
 
                 if draw.state.text_params != state.text_params {
-                    if let Some((bind_group, layout, offset)) = &draw.state.text_params {
+                    if let Some((bind_group, layout, offset, _)) = &draw.state.text_params {
                         canvas.set_text_shader_params(bind_group.clone(), layout.clone(), *offset);
                     }
+                    text_checked = false;
                 }
 
                 if draw.state.sampler != state.sampler {
@@ -485,6 +656,10 @@ impl Canvas {
                     canvas.set_premultiplied_text(draw.state.premul_text);
                 }
 
+                if draw.state.premul_meshes != state.premul_meshes {
+                    canvas.set_premultiplied_meshes(draw.state.premul_meshes);
+                }
+
                 if draw.state.projection != state.projection {
                     canvas.set_projection(draw.state.projection);
                 }
@@ -495,6 +670,41 @@ impl Canvas {
 
                 state = draw.state.clone();
 
+                // Shaders and their params can be set in either order, so they are only
+                // checked against each other once something is drawn with them.
+                if let Draw::BoundedText { .. } | Draw::TextBatch { .. } | Draw::PathText { .. } =
+                    &draw.draw
+                {
+                    if !text_checked {
+                        let params = state.text_params.as_ref().map(|params| &*params.3);
+                        state
+                            .text_shader
+                            .check_params(params, wgpu::ShaderStages::VERTEX_FRAGMENT)?;
+                        text_checked = true;
+                    }
+                } else if !checked {
+                    let params = state.params.as_ref().map(|params| &*params.3);
+                    state
+                        .shader
+                        .check_params(params, wgpu::ShaderStages::VERTEX_FRAGMENT)?;
+                    checked = true;
+                }
+
+                let image = match &draw.draw {
+                    Draw::Mesh { image, .. } => Some(image),
+                    Draw::MeshInstances { instances, .. } => Some(&instances.image),
+                    Draw::BoundedText { .. } | Draw::TextBatch { .. } | Draw::PathText { .. } => {
+                        None
+                    }
+                };
+                if let Some(image) = image {
+                    if image.dimension != wgpu::TextureViewDimension::D2 {
+                        return Err(GameError::RenderError(String::from(
+                            "array and 3D images can't be drawn directly; bind them to a custom shader instead",
+                        )));
+                    }
+                }
+
                 match &draw.draw {
                     Draw::Mesh { mesh, image, scale } => {
                         canvas.draw_mesh(mesh, image, draw.param, *scale)
@@ -505,12 +715,18 @@ impl Canvas {
                         scale,
                     } => canvas.draw_mesh_instances(mesh, instances, draw.param, *scale)?,
                     Draw::BoundedText { text } => canvas.draw_bounded_text(text, draw.param)?,
+                    Draw::TextBatch { entries } => canvas.draw_text_batch(entries, draw.param)?,
+                    Draw::PathText { text } => canvas.draw_path_text(text, draw.param)?,
                 }
             }
         }
 
         canvas.finish();
 
+        if let Some(lut) = &self.lut {
+            lut::apply_color_lut(gfx, self.resolve.as_ref().unwrap_or(&self.target), lut)?;
+        }
+
         Ok(())
     }
 }
@@ -518,12 +734,13 @@ impl Canvas {
 #[derive(Debug, Clone)]
 struct DrawState {
     shader: Shader,
-    params: Option<(ArcBindGroup, ArcBindGroupLayout, u32)>,
+    params: Option<(ArcBindGroup, ArcBindGroupLayout, u32, Arc<[ShaderBinding]>)>,
     text_shader: Shader,
-    text_params: Option<(ArcBindGroup, ArcBindGroupLayout, u32)>,
-    sampler: Sampler,
+    text_params: Option<(ArcBindGroup, ArcBindGroupLayout, u32, Arc<[ShaderBinding]>)>,
+    sampler: Option<Sampler>,
     blend_mode: BlendMode,
     premul_text: bool,
+    premul_meshes: bool,
     projection: mint::ColumnMatrix4<f32>,
     scissor_rect: (u32, u32, u32, u32),
 }
@@ -543,6 +760,12 @@ pub(crate) enum Draw {
     BoundedText {
         text: Text,
     },
+    TextBatch {
+        entries: Arc<Vec<(Text, DrawParam)>>,
+    },
+    PathText {
+        text: TextOnPath,
+    },
 }
 
 // Stores *everything* you need to know to draw something.
@@ -570,16 +793,302 @@ impl DefaultResources {
 
 /// The default shader.
 pub fn default_shader() -> Shader {
-    Shader {
-        fs_module: None,
-        vs_module: None,
-    }
+    Shader::default()
 }
 
 /// The default text shader.
 pub fn default_text_shader() -> Shader {
-    Shader {
-        fs_module: None,
-        vs_module: None,
+    Shader::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conf::WindowSetup,
+        context::tests::headless_context,
+        graphics::{
+            Canvas, ClampMode, Color, ColorSpaceMode, DrawParam, Image, ImageFormat, Mesh,
+            MeshData, PipelineCompilation, PipelineDesc, Quad, Rect, Sampler, Shader,
+            ShaderBuilder, Vertex,
+        },
+        ContextBuilder, GameError,
+    };
+
+    fn quad(gfx: &crate::Context, color: [f32; 4]) -> Mesh {
+        let vertex = |x, y| Vertex {
+            position: [x, y],
+            uv: [x, y],
+            color,
+        };
+        Mesh::from_data(
+            gfx,
+            MeshData {
+                vertices: &[
+                    vertex(0., 0.),
+                    vertex(1., 0.),
+                    vertex(1., 1.),
+                    vertex(0., 1.),
+                ],
+                indices: &[0, 1, 2, 0, 2, 3],
+            },
+        )
+    }
+
+    /// Draws `mesh` over opaque green and returns the resulting pixel.
+    fn composite(ctx: &mut crate::Context, target: &Image, mesh: &Mesh, premul: bool) -> Vec<u8> {
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_image(ctx, target.clone(), Color::new(0., 1., 0., 1.));
+        canvas.set_screen_coordinates(Rect::new(0., 0., 1., 1.));
+        canvas.set_premultiplied_meshes(premul);
+        canvas.draw(mesh, DrawParam::new());
+        canvas.finish(ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        target.to_pixels(ctx).unwrap()[..4].to_vec()
+    }
+
+    #[test]
+    fn headless_test_premultiplied_meshes() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let straight = quad(&ctx, [1., 0., 0., 0.5]);
+        let premultiplied = quad(&ctx, [0.5, 0., 0., 0.5]);
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 2, 2, 1);
+        // 50% red over opaque green
+        let expected = [0.5 * 255., 0.5 * 255., 0., 255.];
+
+        for (mesh, premul) in [(&straight, false), (&premultiplied, true)] {
+            let pixel = composite(&mut ctx, &target, mesh, premul);
+            for (&actual, expected) in pixel.iter().zip(expected) {
+                assert!(
+                    (f32::from(actual) - expected).abs() <= 1.,
+                    "premultiplied: {premul}, got {pixel:?}"
+                );
+            }
+        }
+
+        // drawing the premultiplied mesh as straight alpha darkens the red
+        let pixel = composite(&mut ctx, &target, &premultiplied, false);
+        assert!(pixel[0] < 100, "got {pixel:?}");
+    }
+
+    #[test]
+    fn headless_test_canvas_ordering() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 1, 1, 1);
+        ctx.gfx.begin_frame().unwrap();
+
+        // Two unfinished canvases drawing to the same image are an error.
+        let first = Canvas::from_image(&ctx, target.clone(), Color::RED);
+        let second = Canvas::from_image(&ctx, target.clone(), Color::BLUE);
+        assert!(first.finish(&mut ctx).is_err());
+        second.finish(&mut ctx).unwrap();
+
+        // Ordered canvases draw by order, after the ones finished directly.
+        Canvas::from_image(&ctx, target.clone(), Color::GREEN)
+            .finish_ordered(&mut ctx, 1)
+            .unwrap();
+        Canvas::from_image(&ctx, target.clone(), Color::RED)
+            .finish_ordered(&mut ctx, 0)
+            .unwrap();
+        Canvas::from_image(&ctx, target.clone(), Color::BLUE)
+            .finish(&mut ctx)
+            .unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert_eq!(target.to_pixels(&ctx).unwrap(), [0, 255, 0, 255]);
+
+        let canvas = Canvas::from_image(&ctx, target, Color::RED);
+        assert!(canvas.finish_ordered(&mut ctx, 0).is_err());
+    }
+
+    #[test]
+    fn headless_test_atlas_src_clamp() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // 4x4 atlas of 2x2 sprites: red, green / blue, white
+        let mut pixels = Vec::new();
+        for y in 0..4 {
+            for x in 0..4 {
+                pixels.extend_from_slice(match (x < 2, y < 2) {
+                    (true, true) => &[255, 0, 0, 255],
+                    (false, true) => &[0, 255, 0, 255],
+                    (true, false) => &[0, 0, 255, 255],
+                    (false, false) => &[255, 255, 255, 255],
+                });
+            }
+        }
+        let mut atlas = Image::from_pixels(&ctx, &pixels, ImageFormat::Rgba8Unorm, 4, 4);
+        atlas.set_sampler(Sampler {
+            clamp_u: ClampMode::Repeat,
+            clamp_v: ClampMode::Repeat,
+            ..Sampler::linear_clamp()
+        });
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 32, 32, 1);
+
+        let draw_red = |ctx: &mut crate::Context, src_clamp| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_image(ctx, target.clone(), Color::BLACK);
+            let param = DrawParam::new()
+                .src(atlas.uv_rect(0, 0, 2, 2))
+                .scale([16., 16.])
+                .src_clamp(src_clamp);
+            canvas.draw(&atlas, param);
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            target.to_pixels(ctx).unwrap()
+        };
+
+        let clamped = draw_red(&mut ctx, true);
+        assert!(clamped.chunks(4).all(|p| p == [255, 0, 0, 255]));
+
+        // Without clamping, the image's repeating linear sampler blends in the neighbors.
+        let unclamped = draw_red(&mut ctx, false);
+        assert!(unclamped.chunks(4).any(|p| p[1] > 0 || p[2] > 0));
+    }
+
+    #[test]
+    fn headless_test_color_space_modes() {
+        // 50% gray, then 50% transparent white over black.
+        for (mode, blended) in [
+            (ColorSpaceMode::SrgbCorrect, 188),
+            (ColorSpaceMode::Legacy, 128),
+        ] {
+            let mut ctx = match ContextBuilder::new("test", "ggez")
+                .window_setup(WindowSetup::default().srgb(mode == ColorSpaceMode::SrgbCorrect))
+                .with_conf_file(false)
+                .build_headless()
+            {
+                Ok(ctx) => ctx,
+                Err(GameError::GraphicsInitializationError) => return,
+                Err(e) => panic!("{e}"),
+            };
+            assert_eq!(ctx.gfx.color_space(), mode);
+            assert_eq!(ctx.gfx.surface_format(), mode.image_format());
+
+            let target = Image::new_canvas_image(&ctx, ctx.gfx.surface_format(), 2, 1, 1);
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_image(&ctx, target.clone(), Color::BLACK);
+            canvas.draw(
+                &Quad,
+                DrawParam::new()
+                    .dest_rect(Rect::new(0., 0., 1., 1.))
+                    .color(Color::new(0.5, 0.5, 0.5, 1.)),
+            );
+            canvas.draw(
+                &Quad,
+                DrawParam::new()
+                    .dest_rect(Rect::new(1., 0., 1., 1.))
+                    .color(Color::new(1., 1., 1., 0.5)),
+            );
+            canvas.finish(&mut ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+
+            let pixels = target.to_pixels(&ctx).unwrap();
+            assert!(pixels[0].abs_diff(128) <= 1, "{mode:?}: {pixels:?}");
+            assert!(pixels[4].abs_diff(blended) <= 1, "{mode:?}: {pixels:?}");
+        }
+    }
+
+    #[test]
+    fn headless_test_screen_to_canvas() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 8, 4, 1);
+        let mut canvas = Canvas::from_image(&ctx, target, None);
+        let close = |a: glam::Vec2, b: [f32; 2]| a.distance(b.into()) < 1e-4;
+
+        assert!(close(canvas.screen_to_canvas([2., 3.]), [2., 3.]));
+
+        // Letterboxed: a 2:1 target showing a square area with bars either side.
+        canvas.set_screen_coordinates(Rect::new(-50., 0., 200., 100.));
+        assert!(close(canvas.screen_to_canvas([0., 0.]), [-50., 0.]));
+        assert!(close(canvas.screen_to_canvas([4., 2.]), [50., 50.]));
+        assert!(close(canvas.screen_to_canvas([8., 4.]), [150., 100.]));
+
+        // A view transform on top of the screen coordinates, zooming in twice around the origin.
+        canvas.set_screen_coordinates(Rect::new(0., 0., 8., 4.));
+        let zoom = glam::Mat4::from_scale(glam::vec3(2., 2., 1.));
+        canvas.set_projection(glam::Mat4::from(canvas.projection()) * zoom);
+        assert!(close(canvas.screen_to_canvas([6., 2.]), [3., 1.]));
+    }
+
+    #[test]
+    fn headless_test_background_pipelines() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let green = |name: &str| {
+            let code = format!(
+                "
+                struct VertexOutput {{
+                    @builtin(position) position: vec4<f32>,
+                    @location(0) uv: vec2<f32>,
+                    @location(1) color: vec4<f32>,
+                }}
+
+                // {name}
+                @fragment
+                fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {{
+                    return vec4<f32>(0., 1., 0., 1.);
+                }}
+                "
+            );
+            ShaderBuilder::new()
+                .fragment_code(&code)
+                .build(&ctx.gfx)
+                .unwrap()
+        };
+        let draw = |ctx: &mut crate::Context, shader: &Shader| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+            canvas.set_shader(shader);
+            canvas.draw(&Quad, DrawParam::new().scale([16., 8.]));
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            ctx.gfx.frame().to_pixels(ctx).unwrap()[..4].to_vec()
+        };
+        let wait = |ctx: &crate::Context| {
+            let start = std::time::Instant::now();
+            while ctx.gfx.pipelines_pending() > 0 {
+                assert!(start.elapsed().as_secs() < 10, "pipelines never finished");
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        };
+
+        // Pipelines for the default shader are never deferred.
+        let (skipped, fallback, precompiled) = (green("skip"), green("fallback"), green("pre"));
+        ctx.gfx
+            .set_pipeline_compilation(PipelineCompilation::BackgroundSkip);
+        assert_eq!(draw(&mut ctx, &Shader::default()), [255, 255, 255, 255]);
+        assert_eq!(ctx.gfx.pipelines_pending(), 0);
+
+        // The first draw with a custom shader starts compiling its pipeline and is skipped.
+        assert_eq!(draw(&mut ctx, &skipped), [0, 0, 0, 255]);
+        wait(&ctx);
+        assert_eq!(draw(&mut ctx, &skipped), [0, 255, 0, 255]);
+
+        // Or drawn with the default shader, which draws the white quad as is.
+        ctx.gfx
+            .set_pipeline_compilation(PipelineCompilation::BackgroundFallback);
+        assert_eq!(draw(&mut ctx, &fallback), [255, 255, 255, 255]);
+        wait(&ctx);
+        assert_eq!(draw(&mut ctx, &fallback), [0, 255, 0, 255]);
+
+        // Precompiled pipelines are ready by the first draw.
+        ctx.gfx
+            .set_pipeline_compilation(PipelineCompilation::BackgroundSkip);
+        ctx.gfx
+            .precompile_pipelines(&[PipelineDesc::new(&precompiled)]);
+        wait(&ctx);
+        assert_eq!(draw(&mut ctx, &precompiled), [0, 255, 0, 255]);
+        assert_eq!(ctx.gfx.frame_stats().pipelines_pending, 0);
     }
 }
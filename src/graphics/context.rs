@@ -9,15 +9,20 @@ use super::{
         growing::GrowingBufferArena,
         pipeline::PipelineCache,
         text::TextRenderer,
+        upload::{UploadBelt, UploadSlice},
     },
-    image::{Image, ImageFormat},
+    image::{DecodePool, Image, ImageFormat},
+    internal_canvas::{pipeline_layouts, BuiltinShaders, ShaderType},
     mesh::{Mesh, Vertex},
+    profiler::{FrameStats, FrameStatsCounter, GpuProfiler, GpuTiming, Profiler},
     sampler::{Sampler, SamplerCache},
     text::FontData,
-    MeshData, ScreenImage,
+    window::{surface_alpha_mode, surface_format, SecondaryWindow, WindowId, WindowSurface},
+    BlendMode, Canvas, ColorSpaceMode, CoordinateMode, MemoryWarningCallback, MeshData,
+    PipelineCompilation, PipelineDesc, ResourceInfo, ResourceTracker, ScreenImage,
 };
 use crate::{
-    conf::{self, Backend, Conf, FullscreenType, WindowMode},
+    conf::{self, Backend, Conf, FullscreenType, WindowMode, WindowSetup},
     context::Has,
     error::GameResult,
     filesystem::{Filesystem, InternalClone},
@@ -27,19 +32,25 @@ use crate::{
 use ::image as imgcrate;
 use crevice::std140::AsStd140;
 use glyph_brush::FontId;
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::{Arc, Mutex, OnceLock, Weak},
+};
 use typed_arena::Arena as TypedArena;
 use winit::{
     self,
     dpi::{self, PhysicalPosition},
+    window::WindowLevel,
 };
 
 pub(crate) struct FrameContext {
     pub cmd: wgpu::CommandEncoder,
     pub present: Image,
     pub arenas: FrameArenas,
-    pub frame: wgpu::SurfaceTexture,
-    pub frame_view: wgpu::TextureView,
+    /// The swapchain image and its view; `None` for headless contexts.
+    pub frame: Option<(wgpu::SurfaceTexture, wgpu::TextureView)>,
 }
 
 #[derive(Default)]
@@ -49,14 +60,30 @@ pub(crate) struct FrameArenas {
     pub bind_groups: TypedArena<ArcBindGroup>,
 }
 
+type ResizeCallback = Box<dyn FnMut(&mut GraphicsContext, f32, f32)>;
+type PresentCallback = Box<dyn FnMut(&mut GraphicsContext, &wgpu::TextureView)>;
+
+/// Identifies a callback registered with [`GraphicsContext::register_resize_callback`]
+/// or [`GraphicsContext::register_present_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
 /// WGPU graphics context objects.
 #[derive(Debug)]
 #[allow(missing_docs)]
 pub struct WgpuContext {
     pub instance: wgpu::Instance,
-    pub surface: wgpu::Surface,
+    pub adapter: wgpu::Adapter,
+    /// The window surface; `None` for headless contexts.
+    pub surface: Option<wgpu::Surface>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    pub(crate) resources: ResourceTracker,
+    /// Shared memory for data uploaded every frame, see [`GraphicsContext::upload`].
+    pub(crate) uploads: Mutex<UploadBelt>,
+    /// Whether images in 32-bit float formats can be sampled with filtering, which
+    /// the default shaders need.
+    pub(crate) float32_filterable: bool,
 }
 
 /// A concrete graphics context for WGPU rendering.
@@ -64,8 +91,15 @@ pub struct WgpuContext {
 pub struct GraphicsContext {
     pub(crate) wgpu: Arc<WgpuContext>,
 
-    pub(crate) window: winit::window::Window,
+    /// Connected on first use. Declared before `window` since it must be dropped first.
+    #[cfg(feature = "clipboard")]
+    clipboard: Option<window_clipboard::Clipboard>,
+    /// `None` for headless contexts.
+    pub(crate) window: Option<winit::window::Window>,
     pub(crate) surface_config: wgpu::SurfaceConfiguration,
+    pub(crate) color_space: ColorSpaceMode,
+    coordinate_mode: CoordinateMode,
+    scale_factor: f64,
 
     pub(crate) bind_group_cache: BindGroupCache,
     pub(crate) pipeline_cache: PipelineCache,
@@ -78,33 +112,90 @@ pub struct GraphicsContext {
     pub(crate) frame_msaa_image: Option<Image>,
 
     pub(crate) fcx: Option<FrameContext>,
+    pub(crate) frame_count: u64,
     pub(crate) text: TextRenderer,
     pub(crate) fonts: HashMap<String, FontId>,
-    pub(crate) staging_belt: wgpu::util::StagingBelt,
     pub(crate) uniform_arena: GrowingBufferArena,
+    pub(crate) profiler: GpuProfiler,
+    pub(crate) frame_stats: FrameStatsCounter,
+    last_frame_stats: FrameStats,
+    /// See [`set_max_frame_latency`](Self::set_max_frame_latency).
+    max_frame_latency: Option<u32>,
+    /// Submissions of the frames the GPU may still be working on, oldest first.
+    pub(crate) frames_in_flight: VecDeque<wgpu::SubmissionIndex>,
 
     pub(crate) draw_shader: ArcShaderModule,
     pub(crate) instance_shader: ArcShaderModule,
     pub(crate) instance_unordered_shader: ArcShaderModule,
     pub(crate) text_shader: ArcShaderModule,
     pub(crate) copy_shader: ArcShaderModule,
+    pub(crate) lut_shader: ArcShaderModule,
+    pub(crate) blit_shader: ArcShaderModule,
+    /// Reused by `lut::apply_color_lut` while the target's size and format stay the same.
+    pub(crate) lut_scratch: Option<Image>,
+    /// 3D images made from color LUT strips by `lut::apply_color_lut`, by the id of the strip's
+    /// texture, kept until the strip is dropped.
+    pub(crate) lut_volumes: HashMap<u64, (Weak<wgpu::Texture>, Image)>,
     pub(crate) rect_mesh: Mesh,
     pub(crate) white_image: Image,
     pub(crate) instance_bind_layout: ArcBindGroupLayout,
 
     pub(crate) fs: Filesystem,
+    pub(crate) decode_pool: OnceLock<DecodePool>,
+
+    /// The threshold in bytes, the callback, and whether the total is above the threshold.
+    memory_warning: Option<(u64, MemoryWarningCallback, bool)>,
+
+    resize_callbacks: Vec<(CallbackId, ResizeCallback)>,
+    present_callbacks: Vec<(CallbackId, PresentCallback)>,
+    unregistered_callbacks: Vec<CallbackId>,
+    running_callbacks: bool,
+    /// Ids of the resize callbacks being run, which are taken out of `resize_callbacks` meanwhile.
+    running_resize_callbacks: Vec<CallbackId>,
+    /// Ids of the present callbacks being run, like `running_resize_callbacks`.
+    running_present_callbacks: Vec<CallbackId>,
+    next_callback_id: u64,
+
+    windows: Vec<SecondaryWindow>,
+    next_window_id: u64,
+
+    /// Target images of canvases that haven't been finished yet, with a handle telling
+    /// whether the canvas is still alive.
+    open_canvases: RefCell<Vec<(u64, Weak<()>)>>,
+    /// Canvases queued by `Canvas::finish_ordered`, encoded by order at the end of the frame.
+    ordered_canvases: Vec<(i32, Canvas)>,
 
     bind_group: Option<(Vec<BindGroupEntryKey>, ArcBindGroup)>,
 }
 
 impl GraphicsContext {
-    #[allow(unsafe_code)]
     /// Create a new graphics context
     pub fn new(
         game_id: &str,
         event_loop: &winit::event_loop::EventLoop<()>,
         conf: &Conf,
         filesystem: &Filesystem,
+    ) -> GameResult<Self> {
+        Self::with_backends(conf, |instance| {
+            Self::new_from_instance(game_id, instance, event_loop, conf, filesystem)
+        })
+    }
+
+    /// Create a new graphics context without a window or surface.
+    ///
+    /// Everything is rendered to off-screen images; [`frame()`](Self::frame) is sized
+    /// according to `conf.window_mode` and can be read back with [`Image::to_pixels`].
+    /// Window related functions are no-ops or return a [`GameError::WindowError`].
+    pub fn new_headless(conf: &Conf, filesystem: &Filesystem) -> GameResult<Self> {
+        Self::with_backends(conf, |instance| {
+            let size = conf.window_mode.actual_size()?.to_physical(1.0);
+            Self::new_from_parts(instance, None, size, conf, filesystem)
+        })
+    }
+
+    fn with_backends(
+        conf: &Conf,
+        mut new_from_instance: impl FnMut(wgpu::Instance) -> GameResult<Self>,
     ) -> GameResult<Self> {
         let new_instance = |backends| {
             wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -114,13 +205,7 @@ impl GraphicsContext {
         };
 
         if conf.backend == Backend::All {
-            match Self::new_from_instance(
-                game_id,
-                new_instance(wgpu::Backends::PRIMARY),
-                event_loop,
-                conf,
-                filesystem,
-            ) {
+            match new_from_instance(new_instance(wgpu::Backends::PRIMARY)) {
                 Ok(o) => Ok(o),
                 Err(GameError::GraphicsInitializationError) => {
                     println!(
@@ -130,13 +215,7 @@ impl GraphicsContext {
                         "Failed to initialize graphics, trying secondary backends.. Please mention this if you encounter any bugs!"
                     );
 
-                    Self::new_from_instance(
-                        game_id,
-                        new_instance(wgpu::Backends::SECONDARY),
-                        event_loop,
-                        conf,
-                        filesystem,
-                    )
+                    new_from_instance(new_instance(wgpu::Backends::SECONDARY))
                 }
                 Err(e) => Err(e),
             }
@@ -152,7 +231,7 @@ impl GraphicsContext {
                 Backend::BrowserWebGpu => wgpu::Backends::BROWSER_WEBGPU,
             });
 
-            Self::new_from_instance(game_id, instance, event_loop, conf, filesystem)
+            new_from_instance(instance)
         }
     }
 
@@ -235,12 +314,8 @@ impl GraphicsContext {
         conf: &Conf,
         filesystem: &Filesystem,
     ) -> GameResult<Self> {
-        let mut window_builder = winit::window::WindowBuilder::new()
-            .with_title(conf.window_setup.title.clone())
-            .with_inner_size(conf.window_mode.actual_size().unwrap()) // Unwrap since actual_size only fails if one of the window dimensions is less than 1
-            .with_resizable(conf.window_mode.resizable)
-            .with_visible(conf.window_mode.visible)
-            .with_transparent(conf.window_mode.transparent);
+        #[allow(unused_mut)]
+        let mut window_builder = window_builder(&conf.window_setup, &conf.window_mode, filesystem)?;
 
         #[cfg(any(
             target_os = "linux",
@@ -260,27 +335,27 @@ impl GraphicsContext {
             }
         }
 
-        #[cfg(target_os = "windows")]
-        {
-            use winit::platform::windows::WindowBuilderExtWindows;
-            window_builder = window_builder.with_drag_and_drop(false);
-        }
-
-        window_builder = if !conf.window_setup.icon.is_empty() {
-            let icon = load_icon(conf.window_setup.icon.as_ref(), filesystem)?;
-            window_builder.with_window_icon(Some(icon))
-        } else {
-            window_builder
-        };
-
         let window = window_builder.build(event_loop)?;
         let surface = unsafe { instance.create_surface(&window) }
             .map_err(|_| GameError::GraphicsInitializationError)?;
 
+        let size = window.inner_size();
+        Self::new_from_parts(instance, Some((window, surface)), size, conf, filesystem)
+    }
+
+    fn new_from_parts(
+        instance: wgpu::Instance,
+        window: Option<(winit::window::Window, wgpu::Surface)>,
+        size: dpi::PhysicalSize<u32>,
+        conf: &Conf,
+        filesystem: &Filesystem,
+    ) -> GameResult<Self> {
+        let (window, surface) = window.unzip();
+
         let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::HighPerformance,
             force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            compatible_surface: surface.as_ref(),
         }))
         .ok_or(GameError::GraphicsInitializationError)?;
 
@@ -291,7 +366,13 @@ impl GraphicsContext {
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                features: wgpu::Features::default(),
+                // Timestamps are only used for profiling, BC textures for KTX2 images and
+                // adapter specific format features for filtering 32-bit float images,
+                // so enable them when available but don't require them.
+                features: adapter.features()
+                    & (wgpu::Features::TIMESTAMP_QUERY
+                        | wgpu::Features::TEXTURE_COMPRESSION_BC
+                        | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES),
                 limits: wgpu::Limits {
                     // 1st: DrawParams
                     // 2nd: Texture + Sampler
@@ -309,31 +390,60 @@ impl GraphicsContext {
             None,
         ))?;
 
+        let float32_filterable = device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            && adapter
+                .get_texture_format_features(wgpu::TextureFormat::R32Float)
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE);
+
+        let resources = ResourceTracker::default();
+        let uploads = Mutex::new(UploadBelt::new(&device, resources.clone()));
         let wgpu = Arc::new(WgpuContext {
             instance,
+            adapter,
             surface,
             device,
             queue,
+            resources,
+            uploads,
+            float32_filterable,
         });
 
-        let capabilities = wgpu.surface.get_capabilities(&adapter);
+        let color_space = if conf.window_setup.srgb {
+            ColorSpaceMode::SrgbCorrect
+        } else {
+            ColorSpaceMode::Legacy
+        };
+
+        // Headless contexts never configure a surface, but still keep the
+        // configuration around to track the frame size and format.
+        let (format, alpha_modes) = match wgpu.surface {
+            Some(ref surface) => {
+                let caps = surface.get_capabilities(&wgpu.adapter);
+                (surface_format(&caps.formats, color_space), caps.alpha_modes)
+            }
+            None => (color_space.image_format(), Vec::new()),
+        };
 
-        let size = window.inner_size();
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: capabilities.formats[0],
-            width: size.width,
-            height: size.height,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
             present_mode: if conf.window_setup.vsync {
                 wgpu::PresentMode::AutoVsync
             } else {
                 wgpu::PresentMode::AutoNoVsync
             },
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            alpha_mode: surface_alpha_mode(&alpha_modes, conf.window_mode.transparent),
             view_formats: vec![],
         };
 
-        wgpu.surface.configure(&wgpu.device, &surface_config);
+        if let Some(ref surface) = wgpu.surface {
+            surface.configure(&wgpu.device, &surface_config);
+        }
 
         let mut bind_group_cache = BindGroupCache::new();
         let pipeline_cache = PipelineCache::new();
@@ -343,11 +453,10 @@ impl GraphicsContext {
             .image(wgpu::ShaderStages::FRAGMENT)
             .create(&wgpu.device, &mut bind_group_cache);
 
-        let text = TextRenderer::new(&wgpu.device, image_bind_layout);
+        let text = TextRenderer::new(&wgpu, image_bind_layout, color_space);
 
-        let staging_belt = wgpu::util::StagingBelt::new(1024);
         let uniform_arena = GrowingBufferArena::new(
-            &wgpu.device,
+            &wgpu,
             u64::from(wgpu.device.limits().min_uniform_buffer_offset_alignment),
             wgpu::BufferDescriptor {
                 label: None,
@@ -355,8 +464,11 @@ impl GraphicsContext {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             },
+            "draw uniforms",
         );
 
+        let profiler = GpuProfiler::new(&wgpu);
+
         let draw_shader = ArcShaderModule::new(wgpu.device.create_shader_module(
             wgpu::ShaderModuleDescriptor {
                 label: None,
@@ -394,6 +506,20 @@ impl GraphicsContext {
             },
         ));
 
+        let lut_shader = ArcShaderModule::new(wgpu.device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(include_str!("shader/lut.wgsl").into()),
+            },
+        ));
+
+        let blit_shader = ArcShaderModule::new(wgpu.device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(include_str!("shader/blit.wgsl").into()),
+            },
+        ));
+
         let rect_mesh = Mesh::from_data_wgpu(
             &wgpu,
             MeshData {
@@ -439,11 +565,17 @@ impl GraphicsContext {
         let white_image =
             Image::from_pixels_wgpu(&wgpu, &[255, 255, 255, 255], ImageFormat::Rgba8Unorm, 1, 1);
 
+        let scale_factor = window.as_ref().map_or(1., |window| window.scale_factor());
         let mut this = GraphicsContext {
             wgpu,
 
+            #[cfg(feature = "clipboard")]
+            clipboard: None,
             window,
             surface_config,
+            color_space,
+            coordinate_mode: CoordinateMode::default(),
+            scale_factor,
 
             bind_group_cache,
             pipeline_cache,
@@ -456,20 +588,46 @@ impl GraphicsContext {
             frame_msaa_image: None,
 
             fcx: None,
+            frame_count: 0,
             text,
             fonts: HashMap::new(),
-            staging_belt,
             uniform_arena,
+            profiler,
+            frame_stats: FrameStatsCounter::default(),
+            last_frame_stats: FrameStats::default(),
+            max_frame_latency: None,
+            frames_in_flight: VecDeque::new(),
             draw_shader,
             instance_shader,
             instance_unordered_shader,
             text_shader,
             copy_shader,
+            lut_shader,
+            blit_shader,
+            lut_scratch: None,
+            lut_volumes: HashMap::new(),
             rect_mesh,
             white_image,
             instance_bind_layout,
 
             fs: InternalClone::clone(filesystem),
+            decode_pool: OnceLock::new(),
+
+            memory_warning: None,
+
+            resize_callbacks: Vec::new(),
+            present_callbacks: Vec::new(),
+            unregistered_callbacks: Vec::new(),
+            running_callbacks: false,
+            running_resize_callbacks: Vec::new(),
+            running_present_callbacks: Vec::new(),
+            next_callback_id: 0,
+
+            windows: Vec::new(),
+            next_window_id: 0,
+
+            open_canvases: RefCell::new(Vec::new()),
+            ordered_canvases: Vec::new(),
 
             bind_group: None,
         };
@@ -495,11 +653,27 @@ impl GraphicsContext {
     }
 
     /// Returns a reference to the underlying WGPU context.
+    ///
+    /// This is meant for integrating other wgpu based renderers, such as GUI overlays, and
+    /// changes only with ggez's wgpu version. See also
+    /// [`register_present_callback`](Self::register_present_callback).
     #[inline]
     pub fn wgpu(&self) -> &WgpuContext {
         &self.wgpu
     }
 
+    /// Uploads `data` into memory shared with the other uploads of the frame, so that it can be
+    /// used by wgpu work recorded this frame, such as in a
+    /// [present callback](Self::register_present_callback).
+    ///
+    /// This is cheaper than creating a buffer for data that changes every frame. The data
+    /// stays valid until the GPU has finished the current frame, after which its memory is
+    /// reused; data that outlives the frame needs a buffer of its own.
+    pub fn upload(&self, data: &[u8]) -> UploadSlice {
+        let mut uploads = self.wgpu.uploads.lock().unwrap(); // Unwrap since the lock is never held across a panic.
+        uploads.upload(&self.wgpu.device, &self.wgpu.queue, data)
+    }
+
     /// Sets the image that will be presented to the screen at the end of the frame.
     pub fn present(&mut self, image: &Image) -> GameResult {
         if let Some(fcx) = &mut self.fcx {
@@ -519,12 +693,66 @@ impl GraphicsContext {
         self.fonts.insert(name.to_string(), id);
     }
 
+    /// Adds a new `font` with a given `name` and appends it to the fallback chain.
+    ///
+    /// Whenever a character is missing from the font a piece of text is drawn with, the fallback
+    /// fonts are searched in the order they were added and the first one with a glyph for it is
+    /// used instead. This makes it possible to draw, for example, CJK text or emoji mixed with
+    /// latin text without bundling a single font covering everything.
+    pub fn add_font_fallback(&mut self, name: &str, font: FontData) {
+        let id = self.text.glyph_brush.borrow_mut().add_font(font.font);
+        let _ = self.fonts.insert(name.to_string(), id);
+        self.text.fallbacks.push(id);
+    }
+
+    /// Sets the most memory the glyph cache used for drawing text may take up, in bytes.
+    /// Defaults to 64 MiB, an 8192 by 8192 cache.
+    ///
+    /// The cache grows as more and larger glyphs are drawn in a frame, and shrinks again once
+    /// it hasn't needed to grow for a while. Text that would need a larger cache than this is
+    /// skipped with a warning, so keep the budget large enough for the text of a single frame.
+    pub fn set_glyph_cache_size(&mut self, bytes: u64) {
+        self.text.budget = bytes;
+    }
+
+    /// Returns the most memory the glyph cache may take up, in bytes,
+    /// see [`set_glyph_cache_size`](Self::set_glyph_cache_size).
+    pub fn glyph_cache_size(&self) -> u64 {
+        self.text.budget
+    }
+
     /// Returns the size of the window’s underlying drawable in physical pixels as (width, height).
     pub fn drawable_size(&self) -> (f32, f32) {
-        let size = self.window.inner_size();
+        let size = self.inner_size();
         (size.width as f32, size.height as f32)
     }
 
+    /// Returns `true` if this context was created without a window,
+    /// see [`ContextBuilder::build_headless`](crate::ContextBuilder::build_headless).
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
+    /// Whether presenting with vsync already keeps the frame rate at or below `fps`.
+    pub(crate) fn vsync_caps_frame_rate(&self, fps: u32) -> bool {
+        if self.surface_config.present_mode != wgpu::PresentMode::AutoVsync {
+            return false;
+        }
+        self.window
+            .as_ref()
+            .and_then(|window| window.current_monitor())
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .is_some_and(|millihertz| u64::from(fps) * 1000 >= u64::from(millihertz))
+    }
+
+    pub(crate) fn inner_size(&self) -> dpi::PhysicalSize<u32> {
+        match self.window {
+            Some(ref window) => window.inner_size(),
+            None => dpi::PhysicalSize::new(self.surface_config.width, self.surface_config.height),
+        }
+    }
+
     /// Sets the window size (in physical pixels) / resolution to the specified width and height.
     ///
     /// Note:   These dimensions are only interpreted as resolutions in true fullscreen mode.
@@ -535,43 +763,103 @@ impl GraphicsContext {
 
     /// Sets the window title.
     pub fn set_window_title(&self, title: &str) {
-        self.window.set_title(title);
+        if let Some(ref window) = self.window {
+            window.set_title(title);
+        }
+    }
+
+    /// Sets whether the window is kept above or below all other windows, for example to
+    /// keep a desktop pet or an overlay always on top.
+    pub fn set_window_level(&self, level: WindowLevel) {
+        if let Some(ref window) = self.window {
+            window.set_window_level(level);
+        }
     }
 
     /// Returns the position of the system window, including the outer frame.
     pub fn window_position(&self) -> GameResult<PhysicalPosition<i32>> {
         self.window
+            .as_ref()
+            .ok_or_else(|| GameError::WindowError(String::from("headless context has no window")))?
             .outer_position()
             .map_err(|e| GameError::WindowError(e.to_string()))
     }
 
     /// Sets the window position.
     pub fn set_window_position(&self, position: impl Into<winit::dpi::Position>) -> GameResult {
-        self.window.set_outer_position(position);
+        if let Some(ref window) = self.window {
+            window.set_outer_position(position);
+        }
         Ok(())
     }
 
-    /// Returns the size of the window in pixels as (width, height),
-    /// including borders, titlebar, etc.
-    /// Returns zeros if the window doesn't exist.
+    /// Returns the size of the window as (width, height), in the units of the
+    /// [`CoordinateMode`], including borders, titlebar, etc.
+    /// Returns the frame size if the window doesn't exist.
     pub fn size(&self) -> (f32, f32) {
-        let size = self.window.outer_size();
-        (size.width as f32, size.height as f32)
+        let size = match self.window {
+            Some(ref window) => window.outer_size(),
+            None => self.inner_size(),
+        };
+        let scale = self.coordinate_scale();
+        (size.width as f32 / scale, size.height as f32 / scale)
+    }
+
+    /// Returns the scale factor of the window, which is how many physical pixels make up
+    /// one logical pixel. Headless contexts have a scale factor of 1.
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Returns the units window sizes and positions are given in.
+    #[inline]
+    pub fn coordinate_mode(&self) -> CoordinateMode {
+        self.coordinate_mode
+    }
+
+    /// Sets the units window sizes and positions are given in, see [`CoordinateMode`].
+    ///
+    /// The last mouse position stays in the old units until the mouse moves again.
+    pub fn set_coordinate_mode(&mut self, mode: CoordinateMode) {
+        self.coordinate_mode = mode;
+    }
+
+    /// How many physical pixels make up one unit of the coordinate mode.
+    pub(crate) fn coordinate_scale(&self) -> f32 {
+        match self.coordinate_mode {
+            CoordinateMode::Physical => 1.,
+            CoordinateMode::Logical => self.scale_factor as f32,
+        }
+    }
+
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
     }
 
     /// Returns an iterator providing all resolutions supported by the current monitor.
     pub fn supported_resolutions(&self) -> impl Iterator<Item = winit::dpi::PhysicalSize<u32>> {
         self.window
-            .current_monitor()
-            .unwrap() // Unwrap is fine current monitor should always exist
-            .video_modes()
+            .as_ref()
+            .and_then(|window| window.current_monitor())
+            .into_iter()
+            .flat_map(|monitor| monitor.video_modes())
             .map(|vm| vm.size())
     }
 
     /// Returns a reference to the Winit window.
+    ///
+    /// The window implements `HasRawWindowHandle` and `HasRawDisplayHandle` of the
+    /// `raw-window-handle` 0.5 crate, for libraries that need the native handles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is headless, see [`is_headless`](Self::is_headless).
     #[inline]
     pub fn window(&self) -> &winit::window::Window {
-        &self.window
+        self.window
+            .as_ref()
+            .expect("headless graphics context has no window")
     }
 
     /// Sets the window icon. `None` for path removes the icon.
@@ -585,10 +873,89 @@ impl GraphicsContext {
             Some(p) => Some(load_icon(p.as_ref(), filesystem)?),
             None => None,
         };
-        self.window.set_window_icon(icon);
+        if let Some(ref window) = self.window {
+            window.set_window_icon(icon);
+        }
+        Ok(())
+    }
+
+    /// Sets the window icon to the given RGBA8 pixels, `width` by `height` in size.
+    /// `None` removes the icon.
+    pub fn set_window_icon_from_rgba(&self, icon: Option<(Vec<u8>, u32, u32)>) -> GameResult {
+        let icon = match icon {
+            Some((rgba, width, height)) => Some(icon_from_rgba(rgba, width, height)?),
+            None => None,
+        };
+        if let Some(ref window) = self.window {
+            window.set_window_icon(icon);
+        }
         Ok(())
     }
 
+    /// Sets the window icon to the contents of an image, for example one drawn to at runtime.
+    /// `None` removes the icon.
+    ///
+    /// The image must be in the [`ImageFormat::Rgba8Unorm`] or [`ImageFormat::Rgba8UnormSrgb`] format.
+    pub fn set_window_icon_from_image(&self, image: Option<&Image>) -> GameResult {
+        let icon = match image {
+            Some(image) => match image.format() {
+                ImageFormat::Rgba8Unorm | ImageFormat::Rgba8UnormSrgb => {
+                    Some((image.to_pixels(self)?, image.width(), image.height()))
+                }
+                format => {
+                    return Err(GameError::WindowError(format!(
+                        "cannot use an image with the {format:?} format as window icon"
+                    )))
+                }
+            },
+            None => None,
+        };
+        self.set_window_icon_from_rgba(icon)
+    }
+
+    /// Returns the text currently on the system clipboard.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GameError::WindowError` if the clipboard can't be read, doesn't contain text,
+    /// or if the context is headless.
+    #[cfg(feature = "clipboard")]
+    pub fn clipboard_text(&mut self) -> GameResult<String> {
+        self.clipboard()?
+            .read()
+            .map_err(|e| GameError::WindowError(format!("couldn't read the clipboard: {e}")))
+    }
+
+    /// Puts the given text on the system clipboard.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GameError::WindowError` if the clipboard can't be written to,
+    /// or if the context is headless.
+    #[cfg(feature = "clipboard")]
+    pub fn set_clipboard_text(&mut self, text: &str) -> GameResult {
+        self.clipboard()?
+            .write(text.to_owned())
+            .map_err(|e| GameError::WindowError(format!("couldn't write to the clipboard: {e}")))
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn clipboard(&mut self) -> GameResult<&mut window_clipboard::Clipboard> {
+        if self.clipboard.is_none() {
+            let window = self.window.as_ref().ok_or_else(|| {
+                GameError::WindowError("headless contexts have no clipboard".to_owned())
+            })?;
+            // SAFETY: the clipboard is stored next to the window and dropped before it.
+            #[allow(unsafe_code)]
+            let clipboard =
+                unsafe { window_clipboard::Clipboard::connect(window) }.map_err(|e| {
+                    GameError::WindowError(format!("couldn't connect to the clipboard: {e}"))
+                })?;
+            self.clipboard = Some(clipboard);
+        }
+        Ok(self.clipboard.as_mut().unwrap()) // Unwrap since it was just set.
+    }
+
     /// Sets the window to fullscreen or back.
     pub fn set_fullscreen(&mut self, fullscreen: conf::FullscreenType) -> GameResult {
         let window_mode = self.window_mode.fullscreen_type(fullscreen);
@@ -620,6 +987,185 @@ impl GraphicsContext {
         result
     }
 
+    /// Applies every setting of a window mode at once: the size, min and max size,
+    /// resizability, maximization, fullscreen type, decorations and visibility.
+    ///
+    /// All of the mode is checked before the window is touched, so if it is invalid,
+    /// e.g. a true fullscreen resolution the monitor doesn't support, an error is returned
+    /// and the window is left exactly as it was. Unlike [`set_mode`](Self::set_mode), the
+    /// current mode is only replaced if applying the new one succeeded.
+    ///
+    /// Whether the window is transparent can't be changed after it has been created.
+    pub fn apply_window_mode(&mut self, mode: &WindowMode) -> GameResult {
+        self.set_window_mode(mode)?;
+        self.window_mode = *mode;
+        Ok(())
+    }
+
+    /// Returns the current window mode.
+    ///
+    /// For a window that isn't fullscreen or maximized, the size is the one the window
+    /// has right now, which may differ from the one that was set if the user resized it.
+    pub fn window_mode(&self) -> WindowMode {
+        let mut mode = self.window_mode;
+        if let Some(ref window) = self.window {
+            if mode.fullscreen_type == FullscreenType::Windowed && !window.is_maximized() {
+                let size = window.inner_size();
+                match mode.logical_size {
+                    Some(ref mut logical) => *logical = size.to_logical(window.scale_factor()),
+                    None => (mode.width, mode.height) = (size.width as f32, size.height as f32),
+                }
+            }
+        }
+        mode
+    }
+
+    /// Returns whether presenting waits for the display's vertical sync.
+    pub fn vsync(&self) -> bool {
+        self.surface_config.present_mode == wgpu::PresentMode::AutoVsync
+    }
+
+    /// Sets whether presenting waits for the display's vertical sync, see
+    /// [`WindowSetup::vsync`](crate::conf::WindowSetup::vsync).
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.surface_config.present_mode = if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        };
+        if let Some(ref surface) = self.wgpu.surface {
+            surface.configure(&self.wgpu.device, &self.surface_config);
+        }
+    }
+
+    const MAX_FRAME_LATENCY: u32 = 16;
+
+    /// Limits how many frames the CPU may run ahead of the GPU, so that input is at most
+    /// this many frames old when it shows up on screen. `None`, the default, leaves it to
+    /// the driver and presentation engine, which tend to allow two or three frames.
+    ///
+    /// A latency of 1 makes [`begin_frame`](Self::begin_frame) wait until the GPU has
+    /// finished the previous frame. This gives the lowest latency, at the cost of CPU and
+    /// GPU no longer working at the same time, which may lower the frame rate. The effect
+    /// shows in [`TimeContext::frame_time_histogram`](crate::timer::TimeContext::frame_time_histogram)
+    /// as the time spent waiting moves into the frame time.
+    ///
+    /// Values outside of `1..=16` are clamped with a warning. The swapchain isn't
+    /// reconfigured, so changing this never causes a visible glitch.
+    pub fn set_max_frame_latency(&mut self, frames: impl Into<Option<u32>>) {
+        self.max_frame_latency = frames.into().map(|frames| {
+            let clamped = frames.clamp(1, Self::MAX_FRAME_LATENCY);
+            if clamped != frames {
+                warn!(
+                    "max frame latency of {frames} is outside of 1..={}, using {clamped}",
+                    Self::MAX_FRAME_LATENCY
+                );
+            }
+            clamped
+        });
+    }
+
+    /// Returns the limit set with [`set_max_frame_latency`](Self::set_max_frame_latency).
+    pub fn max_frame_latency(&self) -> Option<u32> {
+        self.max_frame_latency
+    }
+
+    /// Sets how render pipelines are compiled for shaders and drawing state that haven't been
+    /// drawn with before. By default, they are compiled when first needed, which can make that
+    /// frame noticeably late.
+    ///
+    /// With one of the background modes, the frame goes on while the pipeline compiles, and
+    /// [`pipelines_pending`](Self::pipelines_pending) counts the pipelines not ready yet.
+    pub fn set_pipeline_compilation(&mut self, compilation: PipelineCompilation) {
+        self.pipeline_cache.compilation = compilation;
+    }
+
+    /// Returns the mode set with [`set_pipeline_compilation`](Self::set_pipeline_compilation).
+    pub fn pipeline_compilation(&self) -> PipelineCompilation {
+        self.pipeline_cache.compilation
+    }
+
+    /// Starts compiling the render pipelines for the given shaders and drawing state on
+    /// a background thread, so they are ready by the time they are drawn with.
+    ///
+    /// This returns right away, whatever the [compilation mode](Self::set_pipeline_compilation)
+    /// is. A loading screen can wait until [`pipelines_pending`](Self::pipelines_pending) is zero:
+    ///
+    /// ```rust,no_run
+    /// # use ggez::{graphics::{PipelineDesc, Shader}, Context};
+    /// # fn load(ctx: &mut Context, shader: &Shader) {
+    /// ctx.gfx.precompile_pipelines(&[
+    ///     PipelineDesc::new(shader),
+    ///     PipelineDesc::new(shader).instanced(false),
+    /// ]);
+    /// # }
+    /// # fn update(ctx: &mut Context) -> bool {
+    /// // later, every frame:
+    /// let loaded = ctx.gfx.pipelines_pending() == 0;
+    /// # loaded }
+    /// ```
+    ///
+    /// Pipelines for the default shaders are compiled for images with four color channels.
+    pub fn precompile_pipelines(&mut self, pipelines: &[PipelineDesc]) {
+        let builtin = BuiltinShaders::new(self);
+        for desc in pipelines {
+            let blend_mode = match desc.ty {
+                // text is premultiplied by default, see `Canvas::set_premultiplied_text`
+                ShaderType::Text if desc.blend_mode == BlendMode::ALPHA => BlendMode::PREMULTIPLIED,
+                _ => desc.blend_mode,
+            };
+            let (format, samples) = desc.target.unwrap_or_else(|| {
+                let frame = self
+                    .frame_msaa_image
+                    .as_ref()
+                    .unwrap_or_else(|| self.frame());
+                (frame.format(), frame.samples())
+            });
+
+            let groups = pipeline_layouts(
+                &self.wgpu.device,
+                &mut self.bind_group_cache,
+                desc.ty,
+                desc.params.as_ref(),
+            );
+            let layout = self.pipeline_cache.layout(&self.wgpu.device, &groups);
+            let info = builtin.pipeline_info(
+                &desc.shader,
+                desc.ty,
+                blend_mode,
+                (format, samples),
+                4,
+                false,
+            );
+            let _ = self
+                .pipeline_cache
+                .render_pipeline_async(&self.wgpu, layout, info);
+        }
+    }
+
+    /// Returns how many render pipelines are still being compiled in the background, see
+    /// [`set_pipeline_compilation`](Self::set_pipeline_compilation) and
+    /// [`precompile_pipelines`](Self::precompile_pipelines).
+    pub fn pipelines_pending(&self) -> usize {
+        self.pipeline_cache.pending()
+    }
+
+    /// Waits until fewer frames than the [maximum latency](Self::set_max_frame_latency)
+    /// are still being worked on by the GPU.
+    fn wait_for_frame_latency(&mut self) {
+        let Some(max) = self.max_frame_latency else {
+            self.frames_in_flight.clear();
+            return;
+        };
+        while self.frames_in_flight.len() >= max as usize {
+            let index = self.frames_in_flight.pop_front().unwrap(); // Unwrap since it isn't empty.
+            let _ = self
+                .wgpu
+                .device
+                .poll(wgpu::Maintain::WaitForSubmissionIndex(index));
+        }
+    }
+
     /// Returns the default frame image.
     ///
     /// This is the image that is rendered to when `Canvas::from_frame` is used.
@@ -629,9 +1175,103 @@ impl GraphicsContext {
     }
 
     /// Returns the image format of the window surface.
+    ///
+    /// This is also the format of the frame image, and the one to use for canvas images
+    /// that are drawn to the frame. It always matches the [color space](Self::color_space).
     #[inline]
     pub fn surface_format(&self) -> ImageFormat {
-        self.surface_config.format
+        self.color_space.surface_format(self.surface_config.format)
+    }
+
+    /// Returns how colors are handled, which is set with
+    /// [`WindowSetup::srgb`](crate::conf::WindowSetup::srgb).
+    ///
+    /// See [`ColorSpaceMode`] for the differences.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpaceMode {
+        self.color_space
+    }
+
+    /// Returns a handle for measuring how long the GPU spends on parts of the frame.
+    ///
+    /// Every [`Canvas::finish`](crate::graphics::Canvas::finish) is measured automatically
+    /// as a `"canvas"` scope; use [`Profiler::scope`] to group work under your own names.
+    /// The results show up in [`frame_gpu_timings`](Self::frame_gpu_timings) a few frames later.
+    pub fn profiler(&mut self) -> Profiler<'_> {
+        Profiler::new(self)
+    }
+
+    /// Returns the GPU time spent in each profiling scope of the most recently finished frame,
+    /// in the order the scopes were opened.
+    ///
+    /// Timings are read back without stalling the CPU, so they lag a few frames behind.
+    /// Always empty if the device doesn't support timestamp queries.
+    pub fn frame_gpu_timings(&self) -> &[GpuTiming] {
+        self.profiler.timings()
+    }
+
+    /// Returns how many draw calls, instances, buffer bytes and glyph uploads the most
+    /// recently finished frame used. Unlike [`frame_gpu_timings`](Self::frame_gpu_timings), these are counted
+    /// on the CPU and available right away.
+    ///
+    /// Draw calls, instances and buffer bytes are only counted after this was first called,
+    /// or a [`DebugOverlay`](super::DebugOverlay) was created, and are zero before.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats.enable();
+        self.last_frame_stats
+    }
+
+    /// Returns the GPU resources that are currently allocated, largest first.
+    ///
+    /// This covers the textures of images and canvas images, including the frame images,
+    /// the buffers of meshes and instance arrays, and the buffers and textures `ggez` uses
+    /// internally. A resource is listed until the last clone of whatever owns it is dropped.
+    pub fn resource_report(&self) -> Vec<ResourceInfo> {
+        self.wgpu.resources.report()
+    }
+
+    /// Returns the total size of the resources listed by
+    /// [`resource_report`](Self::resource_report), in bytes.
+    pub fn resource_bytes(&self) -> u64 {
+        self.wgpu.resources.total()
+    }
+
+    /// Sets a callback that is run at the end of a frame when the
+    /// [`resource_bytes`](Self::resource_bytes) have gone above `bytes`, with the total.
+    ///
+    /// The callback runs once each time the total goes above the threshold, and again only
+    /// after it has dropped below it in between. It replaces the previous callback, if any.
+    pub fn set_memory_warning_threshold(
+        &mut self,
+        bytes: u64,
+        callback: impl FnMut(&mut GraphicsContext, u64) + 'static,
+    ) {
+        self.memory_warning = Some((bytes, Box::new(callback), false));
+    }
+
+    fn check_memory_warning(&mut self) {
+        let total = self.resource_bytes();
+        let Some((threshold, mut callback, above)) = self.memory_warning.take() else {
+            return;
+        };
+        if total > threshold && !above {
+            callback(self, total);
+        }
+        // Keep a callback set by the callback itself.
+        if self.memory_warning.is_none() {
+            self.memory_warning = Some((threshold, callback, total > threshold));
+        }
+    }
+
+    pub(crate) fn begin_gpu_scope(&mut self, name: impl Into<String>) -> Option<usize> {
+        let fcx = self.fcx.as_mut()?;
+        self.profiler.begin_scope(&mut fcx.cmd, name)
+    }
+
+    pub(crate) fn end_gpu_scope(&mut self, index: Option<usize>) {
+        if let (Some(fcx), Some(index)) = (self.fcx.as_mut(), index) {
+            self.profiler.end_scope(&mut fcx.cmd, index);
+        }
     }
 
     /// Returns the current [`wgpu::CommandEncoder`] if there is a frame in progress.
@@ -639,6 +1279,70 @@ impl GraphicsContext {
         self.fcx.as_mut().map(|fcx| &mut fcx.cmd)
     }
 
+    /// Records that a new canvas draws to `targets`.
+    ///
+    /// The canvas counts as unfinished for as long as the returned handle is alive.
+    pub(crate) fn open_canvas(&self, targets: &[&Image]) -> Arc<()> {
+        let handle = Arc::new(());
+        let mut open = self.open_canvases.borrow_mut();
+        open.retain(|(_, canvas)| canvas.strong_count() > 0);
+        open.extend(
+            targets
+                .iter()
+                .map(|image| (image.texture.id(), Arc::downgrade(&handle))),
+        );
+        handle
+    }
+
+    /// Fails if any of `targets` is also the target of an unfinished canvas other than `canvas`.
+    pub(crate) fn check_canvas_targets(&self, canvas: &Arc<()>, targets: &[&Image]) -> GameResult {
+        let canvas = Arc::downgrade(canvas);
+        let shared = self.open_canvases.borrow().iter().any(|(id, other)| {
+            other.strong_count() > 0
+                && !other.ptr_eq(&canvas)
+                && targets.iter().any(|image| image.texture.id() == *id)
+        });
+
+        if shared {
+            Err(GameError::RenderError(String::from(
+                "another unfinished Canvas draws to the same image; \
+                 finish (or drop) it before creating a new Canvas for the image, \
+                 or use Canvas::finish_ordered to control which one draws first",
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn queue_ordered_canvas(&mut self, order: i32, canvas: Canvas) -> GameResult {
+        if self.fcx.is_none() {
+            return Err(GameError::RenderError(String::from(
+                "finishing Canvas outside of a frame",
+            )));
+        }
+        self.ordered_canvases.push((order, canvas));
+        Ok(())
+    }
+
+    /// Submits the canvases queued with [`Canvas::finish_ordered`], lowest `order` first.
+    ///
+    /// This happens automatically at the end of the frame; calling it is only needed to use the
+    /// results earlier, for example to draw an image that ordered canvases render to.
+    pub fn flush_ordered_canvases(&mut self) -> GameResult {
+        let mut canvases = std::mem::take(&mut self.ordered_canvases);
+        // Stable, so canvases with the same order keep the order they were finished in.
+        canvases.sort_by_key(|(order, _)| *order);
+        for (_, canvas) in canvases {
+            canvas.finish(self)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the canvases queued by `Canvas::finish_ordered` without drawing them.
+    pub(crate) fn discard_ordered_canvases(&mut self) {
+        self.ordered_canvases.clear();
+    }
+
     /// Begins a new frame.
     ///
     /// The only situation you need to call this in is when you are rolling your own event loop.
@@ -649,24 +1353,30 @@ impl GraphicsContext {
             )));
         }
 
-        let size = self.window.inner_size();
-        let frame = match self.wgpu.surface.get_current_texture() {
-            Ok(frame) => Ok(frame),
-            Err(_) => {
-                self.surface_config.width = size.width.max(1);
-                self.surface_config.height = size.height.max(1);
-                self.wgpu
-                    .surface
-                    .configure(&self.wgpu.device, &self.surface_config);
-                self.wgpu.surface.get_current_texture().map_err(|_| {
-                    GameError::RenderError(String::from("failed to get next swapchain image"))
-                })
-            }
-        }?;
+        self.wait_for_frame_latency();
+
+        let frame = if let Some(ref surface) = self.wgpu.surface {
+            let size = self.inner_size();
+            let frame = match surface.get_current_texture() {
+                Ok(frame) => Ok(frame),
+                Err(_) => {
+                    self.surface_config.width = size.width.max(1);
+                    self.surface_config.height = size.height.max(1);
+                    surface.configure(&self.wgpu.device, &self.surface_config);
+                    surface.get_current_texture().map_err(|_| {
+                        GameError::RenderError(String::from("failed to get next swapchain image"))
+                    })
+                }
+            }?;
 
-        let frame_view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+            let frame_view = frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            Some((frame, frame_view))
+        } else {
+            None
+        };
 
         self.fcx = Some(FrameContext {
             cmd: self
@@ -676,91 +1386,500 @@ impl GraphicsContext {
             present: self.frame().clone(),
             arenas: FrameArenas::default(),
             frame,
-            frame_view,
         });
 
+        self.frame_count += 1;
         self.uniform_arena.free();
+        self.profiler.begin_frame(&self.wgpu.device);
+        self.frame_stats.reset();
 
         self.text.verts.free();
+        self.text.begin_frame(&self.wgpu.device);
 
         Ok(())
     }
 
+    /// Submits the work of a frame, after any pending uploads.
+    fn submit(&mut self, mut cmd: wgpu::CommandEncoder) {
+        let mut uploads = self.wgpu.uploads.lock().unwrap(); // Unwrap since the lock is never held across a panic.
+        uploads.record_copies(&mut cmd);
+        let index = self.wgpu.queue.submit([cmd.finish()]);
+        uploads.submitted(&self.wgpu.queue);
+        if self.max_frame_latency.is_some() {
+            self.frames_in_flight.push_back(index);
+        }
+    }
+
     /// Ends the current frame.
     ///
     /// The only situation you need to call this in is when you are rolling your own event loop.
     pub fn end_frame(&mut self) -> GameResult {
+        self.flush_ordered_canvases()?;
+        self.last_frame_stats = FrameStats {
+            glyph_uploads: self.text.uploads,
+            glyph_cache_bytes: self.text.cache_len(),
+            pipelines_pending: self.pipeline_cache.pending() as u32,
+            ..self.frame_stats.stats
+        };
+        self.check_memory_warning();
+
         if let Some(mut fcx) = self.fcx.take() {
-            let mut present_pass = fcx.cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &fcx.frame_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+            self.profiler.resolve(&self.wgpu.device, &mut fcx.cmd);
+
+            let (frame, frame_view) = match fcx.frame.take() {
+                Some(frame) => frame,
+                None => {
+                    // Headless; nothing to present, just submit the frame's work.
+                    self.submit(fcx.cmd);
+                    self.profiler.map();
+                    self.run_present_callbacks(fcx.present.view.as_ref());
+                    return Ok(());
+                }
+            };
+
+            let present = fcx.present.view.clone();
+            let config = &self.surface_config;
+            let (format, alpha_mode) = (config.format, config.alpha_mode);
+            self.copy_to_surface(&mut fcx, present, &frame_view, format, alpha_mode);
+
+            let mut window_frames = Vec::new();
+            for i in 0..self.windows.len() {
+                if let Some(frame) = self.acquire_window_frame(i) {
+                    let view = frame
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    let surface = self.windows[i].surface.as_ref().unwrap(); // Unwrap since a frame was acquired.
+                    let source = surface.frame.view.clone();
+                    let (format, alpha_mode) = (surface.config.format, surface.config.alpha_mode);
+                    self.copy_to_surface(&mut fcx, source, &view, format, alpha_mode);
+                    window_frames.push(frame);
+                }
+            }
 
-            let sampler = &mut self
-                .sampler_cache
-                .get(&self.wgpu.device, Sampler::default());
+            self.submit(fcx.cmd);
+            self.profiler.map();
+            self.run_present_callbacks(&frame_view);
+            frame.present();
+            for frame in window_frames {
+                frame.present();
+            }
 
-            let (bind, layout) = self.bind_group(fcx.present.view, sampler.clone());
+            Ok(())
+        } else {
+            Err(GameError::RenderError(String::from(
+                "cannot end a frame as there was never one in progress; call begin_frame first",
+            )))
+        }
+    }
 
-            let layout = self.pipeline_cache.layout(&self.wgpu.device, &[layout]);
-            let copy = self.pipeline_cache.render_pipeline(
-                &self.wgpu.device,
-                &layout,
-                RenderPipelineInfo {
-                    vs: self.copy_shader.clone(),
-                    fs: self.copy_shader.clone(),
-                    vs_entry: "vs_main".into(),
-                    fs_entry: "fs_main".into(),
-                    samples: 1,
-                    format: self.surface_config.format,
-                    blend: None,
-                    depth: false,
-                    vertices: false,
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    vertex_layout: Vertex::layout(),
+    /// Draws `source` onto a surface texture.
+    fn copy_to_surface(
+        &mut self,
+        fcx: &mut FrameContext,
+        source: ArcTextureView,
+        target: &wgpu::TextureView,
+        format: wgpu::TextureFormat,
+        alpha_mode: wgpu::CompositeAlphaMode,
+    ) {
+        let mut present_pass = fcx.cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
                 },
-            );
+            })],
+            depth_stencil_attachment: None,
+        });
 
-            let copy = fcx.arenas.render_pipelines.alloc(copy);
-            let bind = fcx.arenas.bind_groups.alloc(bind);
+        let sampler = &mut self
+            .sampler_cache
+            .get(&self.wgpu.device, Sampler::default());
 
-            present_pass.set_pipeline(copy);
-            present_pass.set_bind_group(0, bind, &[]);
-            present_pass.draw(0..3, 0..1);
+        let (bind, layout) = self.bind_group(source, sampler.clone());
 
-            std::mem::drop(present_pass);
+        let layout = self.pipeline_cache.layout(&self.wgpu.device, &[layout]);
+        // The frame is in the format the color space asks for, which the surface may not be.
+        let fs_entry = match (self.color_space, format.is_srgb()) {
+            (ColorSpaceMode::SrgbCorrect, false) => "fs_encode",
+            (ColorSpaceMode::Legacy, true) => "fs_decode",
+            _ => "fs_main",
+        };
+        // Frames hold premultiplied colors, which postmultiplied surfaces don't expect.
+        let fs_entry = if alpha_mode == wgpu::CompositeAlphaMode::PostMultiplied {
+            format!("{fs_entry}_unpremultiply")
+        } else {
+            fs_entry.to_owned()
+        };
+        let copy = self.pipeline_cache.render_pipeline(
+            &self.wgpu.device,
+            &layout,
+            RenderPipelineInfo {
+                vs: self.copy_shader.clone(),
+                fs: self.copy_shader.clone(),
+                vs_entry: "vs_main".into(),
+                fs_entry,
+                samples: 1,
+                format,
+                blend: None,
+                depth: false,
+                vertices: false,
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                vertex_layout: Vertex::layout(),
+            },
+        );
 
-            self.staging_belt.finish();
-            let _ = self.wgpu.queue.submit([fcx.cmd.finish()]);
-            fcx.frame.present();
+        let copy = fcx.arenas.render_pipelines.alloc(copy);
+        let bind = fcx.arenas.bind_groups.alloc(bind);
 
-            self.staging_belt.recall();
+        present_pass.set_pipeline(copy);
+        present_pass.set_bind_group(0, bind, &[]);
+        present_pass.draw(0..3, 0..1);
+    }
 
-            Ok(())
-        } else {
-            Err(GameError::RenderError(String::from(
-                "cannot end a frame as there was never one in progress; call begin_frame first",
-            )))
+    /// Gets the next surface texture of the `i`th secondary window, if it has been created.
+    fn acquire_window_frame(&mut self, i: usize) -> Option<wgpu::SurfaceTexture> {
+        let surface = self.windows[i].surface.as_mut()?;
+        match surface.surface.get_current_texture() {
+            Ok(frame) => Some(frame),
+            Err(_) => {
+                surface
+                    .surface
+                    .configure(&self.wgpu.device, &surface.config);
+                match surface.surface.get_current_texture() {
+                    Ok(frame) => Some(frame),
+                    Err(e) => {
+                        warn!(
+                            "failed to get next swapchain image of {:?}: {e}",
+                            self.windows[i].id
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Opens a secondary window, for example for a tool palette or a preview in an editor.
+    ///
+    /// The window shares the device (and so all images, meshes, shaders...) of the main window,
+    /// and is drawn to with [`Canvas::from_window_frame`](crate::graphics::Canvas::from_window_frame).
+    /// Its events are passed to [`EventHandler::secondary_window_event`](crate::event::EventHandler::secondary_window_event)
+    /// instead of the regular event callbacks.
+    ///
+    /// Windows can only be created while the event loop is running, so [`event::run()`](crate::event::run)
+    /// opens the window after the current [`update`](crate::event::EventHandler::update), before
+    /// [`draw`](crate::event::EventHandler::draw) is called. Of `mode`, only the size, `resizable`,
+    /// `visible` and `transparent` are used; of `setup`, MSAA samples and sRGB are taken from the main window.
+    ///
+    /// Returns `GameError::WindowError` for headless contexts.
+    pub fn create_window(&mut self, setup: WindowSetup, mode: WindowMode) -> GameResult<WindowId> {
+        if self.is_headless() {
+            return Err(GameError::WindowError(
+                "headless contexts can't open windows".to_owned(),
+            ));
+        }
+        let _ = mode.actual_size()?;
+
+        let id = WindowId(self.next_window_id);
+        self.next_window_id += 1;
+        self.windows.push(SecondaryWindow {
+            id,
+            setup,
+            mode,
+            surface: None,
+        });
+        Ok(id)
+    }
+
+    /// Closes a window opened with [`create_window`](Self::create_window).
+    ///
+    /// Returns `false` if the window was already closed.
+    pub fn close_window(&mut self, id: WindowId) -> bool {
+        let len = self.windows.len();
+        self.windows.retain(|window| window.id != id);
+        self.windows.len() != len
+    }
+
+    /// Returns the winit window of a secondary window, or `None` if it has been closed or
+    /// hasn't been opened by the event loop yet.
+    pub fn secondary_window(&self, id: WindowId) -> Option<&winit::window::Window> {
+        self.windows
+            .iter()
+            .find(|window| window.id == id)
+            .and_then(|window| window.surface.as_ref())
+            .map(|surface| &surface.window)
+    }
+
+    /// Opens the windows requested with [`create_window`](Self::create_window) since this was last called.
+    ///
+    /// [`event::run()`](crate::event::run) calls this for you. When running `ggez` inside your
+    /// own event loop, call it before [`event::frame()`](crate::event::frame).
+    pub fn create_pending_windows(
+        &mut self,
+        target: &winit::event_loop::EventLoopWindowTarget<()>,
+    ) {
+        for i in 0..self.windows.len() {
+            if self.windows[i].surface.is_some() {
+                continue;
+            }
+
+            match self.open_window(&self.windows[i], target) {
+                Ok(surface) => self.windows[i].surface = Some(surface),
+                Err(e) => {
+                    error!("Couldn't open {:?}: {e}", self.windows[i].id);
+                    eprintln!("Couldn't open {:?}: {e}", self.windows[i].id);
+                }
+            }
         }
+        // Windows that failed to open are dropped, rather than retried every frame.
+        self.windows.retain(|window| window.surface.is_some());
+    }
+
+    fn open_window(
+        &self,
+        window: &SecondaryWindow,
+        target: &winit::event_loop::EventLoopWindowTarget<()>,
+    ) -> GameResult<WindowSurface> {
+        let window_builder = window_builder(&window.setup, &window.mode, &self.fs)?;
+        let window_handle = window_builder.build(target)?;
+        #[allow(unsafe_code)]
+        let surface = unsafe { self.wgpu.instance.create_surface(&window_handle) }
+            .map_err(|e| GameError::WindowError(e.to_string()))?;
+
+        // The new surface may support other formats and alpha modes than the main one.
+        let caps = surface.get_capabilities(&self.wgpu.adapter);
+        if caps.formats.is_empty() {
+            return Err(GameError::WindowError(
+                "the graphics adapter can't present to the window".to_owned(),
+            ));
+        }
+
+        let size = window_handle.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            format: surface_format(&caps.formats, self.color_space),
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: if window.setup.vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            },
+            alpha_mode: surface_alpha_mode(&caps.alpha_modes, window.mode.transparent),
+            ..self.surface_config.clone()
+        };
+        surface.configure(&self.wgpu.device, &config);
+
+        Ok(WindowSurface {
+            frame: Image::new_canvas_image(
+                self,
+                self.color_space.surface_format(config.format),
+                config.width,
+                config.height,
+                1,
+            ),
+            surface,
+            window: window_handle,
+            config,
+        })
+    }
+
+    /// Maps a winit window id to the secondary window it belongs to, if any.
+    pub(crate) fn secondary_window_id(&self, id: winit::window::WindowId) -> Option<WindowId> {
+        self.windows.iter().find_map(|window| {
+            window
+                .surface
+                .as_ref()
+                .filter(|surface| surface.window.id() == id)
+                .map(|_| window.id)
+        })
+    }
+
+    pub(crate) fn resize_window(&mut self, id: WindowId) {
+        let device = &self.wgpu.device;
+        let surface = match self
+            .windows
+            .iter_mut()
+            .find(|window| window.id == id)
+            .and_then(|window| window.surface.as_mut())
+        {
+            Some(surface) => surface,
+            None => return,
+        };
+
+        let size = surface.window.inner_size();
+        surface.config.width = size.width.max(1);
+        surface.config.height = size.height.max(1);
+        surface.surface.configure(device, &surface.config);
+
+        let (format, width, height) = (
+            self.color_space.surface_format(surface.config.format),
+            surface.config.width,
+            surface.config.height,
+        );
+        let frame = Image::new_canvas_image(self, format, width, height, 1);
+        if let Some(surface) = self
+            .windows
+            .iter_mut()
+            .find(|window| window.id == id)
+            .and_then(|window| window.surface.as_mut())
+        {
+            surface.frame = frame;
+        }
+    }
+
+    /// Returns the off-screen image that is shown in a secondary window at the end of the frame.
+    pub(crate) fn window_frame(&self, id: WindowId) -> GameResult<Image> {
+        let window = self
+            .windows
+            .iter()
+            .find(|window| window.id == id)
+            .ok_or_else(|| GameError::WindowError(format!("{id:?} has been closed")))?;
+        window
+            .surface
+            .as_ref()
+            .map(|surface| surface.frame.clone())
+            .ok_or_else(|| {
+                GameError::WindowError(format!("{id:?} hasn't been opened by the event loop yet"))
+            })
     }
 
     pub(crate) fn resize(&mut self, _new_size: dpi::PhysicalSize<u32>) {
-        let size = self.window.inner_size();
+        let size = self.inner_size();
         let _ = self.wgpu.device.poll(wgpu::Maintain::Wait);
         self.surface_config.width = size.width.max(1);
         self.surface_config.height = size.height.max(1);
-        self.wgpu
-            .surface
-            .configure(&self.wgpu.device, &self.surface_config);
+        if let Some(ref surface) = self.wgpu.surface {
+            surface.configure(&self.wgpu.device, &self.surface_config);
+        }
         self.update_frame_image();
+        self.run_resize_callbacks();
+    }
+
+    /// Registers a callback that is run whenever the drawable size changes, with the new
+    /// width and height in physical pixels.
+    ///
+    /// In the regular event loop, callbacks run before [`EventHandler::resize_event`](crate::event::EventHandler::resize_event),
+    /// in the order they were registered. This makes it possible for code holding
+    /// size-dependent resources to keep them up to date without being notified by hand.
+    /// For headless contexts they run when the size is changed through
+    /// [`set_mode`](Self::set_mode) or [`set_drawable_size`](Self::set_drawable_size).
+    pub fn register_resize_callback(
+        &mut self,
+        callback: impl FnMut(&mut GraphicsContext, f32, f32) + 'static,
+    ) -> CallbackId {
+        let id = CallbackId(self.next_callback_id);
+        self.next_callback_id += 1;
+        self.resize_callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Removes a callback added with [`register_resize_callback`](Self::register_resize_callback).
+    ///
+    /// Returns `false` if there was no such callback.
+    pub fn unregister_resize_callback(&mut self, id: CallbackId) -> bool {
+        let len = self.resize_callbacks.len();
+        self.resize_callbacks.retain(|(i, _)| *i != id);
+        if self.resize_callbacks.len() != len {
+            true
+        } else if self.running_resize_callbacks.contains(&id)
+            && !self.unregistered_callbacks.contains(&id)
+        {
+            // The callback is one of those being run right now, see `run_resize_callbacks`.
+            self.unregistered_callbacks.push(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn run_resize_callbacks(&mut self) {
+        if self.running_callbacks || self.resize_callbacks.is_empty() {
+            return;
+        }
+
+        let (width, height) = self.drawable_size();
+        let mut callbacks = std::mem::take(&mut self.resize_callbacks);
+        self.running_callbacks = true;
+        self.running_resize_callbacks = callbacks.iter().map(|(id, _)| *id).collect();
+        for (_, callback) in &mut callbacks {
+            callback(self, width, height);
+        }
+        self.running_callbacks = false;
+        self.running_resize_callbacks.clear();
+
+        // Keep the callbacks registered while running, and drop those unregistered while running.
+        callbacks.append(&mut self.resize_callbacks);
+        let unregistered = std::mem::take(&mut self.unregistered_callbacks);
+        callbacks.retain(|(id, _)| !unregistered.contains(id));
+        self.resize_callbacks = callbacks;
+    }
+
+    /// Registers a callback that is run at the end of every frame, with the view of the
+    /// texture about to be presented, after everything the game drew has been submitted.
+    ///
+    /// This lets libraries such as GUI overlays draw on top of the game with their own
+    /// wgpu renderer, using the [`wgpu`](Self::wgpu) device and queue. The view has the
+    /// format returned by [`present_format`](Self::present_format) and already holds the
+    /// frame, so passes drawing to it should load rather than clear it. For headless
+    /// contexts it is the view of the image set with [`present`](Self::present), which is
+    /// the [`frame`](Self::frame) image by default.
+    ///
+    /// Callbacks run in the order they were registered.
+    pub fn register_present_callback(
+        &mut self,
+        callback: impl FnMut(&mut GraphicsContext, &wgpu::TextureView) + 'static,
+    ) -> CallbackId {
+        let id = CallbackId(self.next_callback_id);
+        self.next_callback_id += 1;
+        self.present_callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Removes a callback added with [`register_present_callback`](Self::register_present_callback).
+    ///
+    /// Returns `false` if there was no such callback.
+    pub fn unregister_present_callback(&mut self, id: CallbackId) -> bool {
+        let len = self.present_callbacks.len();
+        self.present_callbacks.retain(|(i, _)| *i != id);
+        if self.present_callbacks.len() != len {
+            true
+        } else if self.running_present_callbacks.contains(&id)
+            && !self.unregistered_callbacks.contains(&id)
+        {
+            self.unregistered_callbacks.push(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the format of the texture view given to present callbacks.
+    #[inline]
+    pub fn present_format(&self) -> wgpu::TextureFormat {
+        self.surface_config.format
+    }
+
+    fn run_present_callbacks(&mut self, view: &wgpu::TextureView) {
+        if self.running_callbacks || self.present_callbacks.is_empty() {
+            return;
+        }
+
+        let mut callbacks = std::mem::take(&mut self.present_callbacks);
+        self.running_callbacks = true;
+        self.running_present_callbacks = callbacks.iter().map(|(id, _)| *id).collect();
+        for (_, callback) in &mut callbacks {
+            callback(self, view);
+        }
+        self.running_callbacks = false;
+        self.running_present_callbacks.clear();
+
+        callbacks.append(&mut self.present_callbacks);
+        let unregistered = std::mem::take(&mut self.unregistered_callbacks);
+        callbacks.retain(|(id, _)| !unregistered.contains(id));
+        self.present_callbacks = callbacks;
     }
 
     pub(crate) fn update_frame_image(&mut self) {
@@ -778,8 +1897,6 @@ impl GraphicsContext {
     }
 
     pub(crate) fn set_window_mode(&mut self, mode: &WindowMode) -> GameResult {
-        let window = &mut self.window;
-
         // TODO LATER: find out if single-dimension constraints are possible?
         let min_dimensions = if mode.min_width >= 1.0 && mode.min_height >= 1.0 {
             Some(dpi::PhysicalSize {
@@ -792,9 +1909,14 @@ impl GraphicsContext {
                 mode.min_width, mode.min_height
             )));
         };
-        window.set_min_inner_size(min_dimensions);
 
         let max_dimensions = if mode.max_width > 0.0 && mode.max_height > 0.0 {
+            if mode.max_width < mode.min_width || mode.max_height < mode.min_height {
+                return Err(GameError::WindowError(format!(
+                    "window max size {}x{} is smaller than the min size {}x{}",
+                    mode.max_width, mode.max_height, mode.min_width, mode.min_height
+                )));
+            }
             Some(dpi::PhysicalSize {
                 width: f64::from(mode.max_width),
                 height: f64::from(mode.max_height),
@@ -802,6 +1924,43 @@ impl GraphicsContext {
         } else {
             None
         };
+        let actual_size = mode.actual_size()?;
+
+        let window = match self.window {
+            Some(ref window) => window,
+            None => {
+                let size: dpi::PhysicalSize<u32> = actual_size.to_physical(1.0);
+                self.surface_config.width = size.width.max(1);
+                self.surface_config.height = size.height.max(1);
+                if self.frame.is_some() {
+                    self.update_frame_image();
+                    self.run_resize_callbacks();
+                }
+                return Ok(());
+            }
+        };
+
+        // Look for the video mode before changing anything, so a resolution the monitor
+        // doesn't support leaves the window as it was.
+        let video_mode = match (mode.fullscreen_type, window.current_monitor()) {
+            (FullscreenType::True, Some(monitor)) => Some(
+                monitor
+                    .video_modes()
+                    .find(|v_mode| {
+                        let size = v_mode.size();
+                        (size.width, size.height) == (mode.width as u32, mode.height as u32)
+                    })
+                    .ok_or_else(|| {
+                        GameError::WindowError(format!(
+                            "resolution {}x{} is not supported by this monitor",
+                            mode.width, mode.height
+                        ))
+                    })?,
+            ),
+            _ => None,
+        };
+
+        window.set_min_inner_size(min_dimensions);
         window.set_max_inner_size(max_dimensions);
         window.set_visible(mode.visible);
 
@@ -809,30 +1968,13 @@ impl GraphicsContext {
             FullscreenType::Windowed => {
                 window.set_fullscreen(None);
                 window.set_decorations(!mode.borderless);
-                window.set_inner_size(mode.actual_size()?);
+                window.set_inner_size(actual_size);
                 window.set_resizable(mode.resizable);
                 window.set_maximized(mode.maximized);
             }
             FullscreenType::True => {
-                if let Some(monitor) = window.current_monitor() {
-                    let v_modes = monitor.video_modes();
-                    // try to find a video mode with a matching resolution
-                    let mut match_found = false;
-                    for v_mode in v_modes {
-                        let size = v_mode.size();
-                        if (size.width, size.height) == (mode.width as u32, mode.height as u32) {
-                            window
-                                .set_fullscreen(Some(winit::window::Fullscreen::Exclusive(v_mode)));
-                            match_found = true;
-                            break;
-                        }
-                    }
-                    if !match_found {
-                        return Err(GameError::WindowError(format!(
-                            "resolution {}x{} is not supported by this monitor",
-                            mode.width, mode.height
-                        )));
-                    }
+                if let Some(v_mode) = video_mode {
+                    window.set_fullscreen(Some(winit::window::Fullscreen::Exclusive(v_mode)));
                 }
             }
             FullscreenType::Desktop => {
@@ -850,14 +1992,41 @@ impl GraphicsContext {
         self.surface_config.width = size.width.max(1);
         self.surface_config.height = size.height.max(1);
 
-        self.wgpu
-            .surface
-            .configure(&self.wgpu.device, &self.surface_config);
+        if let Some(ref surface) = self.wgpu.surface {
+            surface.configure(&self.wgpu.device, &self.surface_config);
+        }
 
         Ok(())
     }
 }
 
+fn window_builder(
+    setup: &WindowSetup,
+    mode: &WindowMode,
+    filesystem: &Filesystem,
+) -> GameResult<winit::window::WindowBuilder> {
+    #[allow(unused_mut)]
+    let mut window_builder = winit::window::WindowBuilder::new()
+        .with_title(setup.title.clone())
+        .with_inner_size(mode.actual_size()?)
+        .with_resizable(mode.resizable)
+        .with_visible(mode.visible)
+        .with_transparent(mode.transparent);
+
+    #[cfg(target_os = "windows")]
+    {
+        use winit::platform::windows::WindowBuilderExtWindows;
+        window_builder = window_builder.with_drag_and_drop(false);
+    }
+
+    Ok(if !setup.icon.is_empty() {
+        let icon = load_icon(setup.icon.as_ref(), filesystem)?;
+        window_builder.with_window_icon(Some(icon))
+    } else {
+        window_builder
+    })
+}
+
 // This is kinda awful 'cause it copies a couple times,
 // but still better than
 // having `winit` try to do the image loading for us.
@@ -867,14 +2036,17 @@ pub(crate) fn load_icon(
     filesystem: &Filesystem,
 ) -> GameResult<winit::window::Icon> {
     use std::io::Read;
-    use winit::window::Icon;
 
     let mut buf = Vec::new();
     let mut reader = filesystem.open(icon_file)?;
     let _ = reader.read_to_end(&mut buf)?;
     let i = imgcrate::load_from_memory(&buf)?;
     let image_data = i.to_rgba8();
-    Icon::from_rgba(image_data.to_vec(), i.width(), i.height()).map_err(|e| {
+    icon_from_rgba(image_data.to_vec(), i.width(), i.height())
+}
+
+fn icon_from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> GameResult<winit::window::Icon> {
+    winit::window::Icon::from_rgba(rgba, width, height).map_err(|e| {
         let msg = format!("Could not load icon: {e:?}");
         GameError::ResourceLoadError(msg)
     })
@@ -0,0 +1,325 @@
+//! Minimal reader for DDS texture files, as used by [`Image::from_dds`](super::Image::from_dds).
+//!
+//! Like the KTX2 reader, only plain 2D textures are supported: no cubemaps, arrays or
+//! volume textures.
+
+use std::borrow::Cow;
+
+use super::image::ImageFormat;
+use crate::{GameError, GameResult};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_LEN: usize = 128;
+const DX10_HEADER_LEN: usize = 20;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const DX10_MISC_TEXTURECUBE: u32 = 0x4;
+const DX10_DIMENSION_TEXTURE3D: u32 = 4;
+
+/// A parsed DDS file, borrowing its level data from the input where it can be used as is.
+#[derive(Debug)]
+pub(crate) struct Dds<'a> {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Mip levels, largest first.
+    pub levels: Vec<Cow<'a, [u8]>>,
+}
+
+fn error(msg: impl std::fmt::Display) -> GameError {
+    GameError::ResourceLoadError(format!("invalid DDS file: {msg}"))
+}
+
+fn unsupported(msg: impl std::fmt::Display) -> GameError {
+    GameError::ResourceLoadError(format!("unsupported DDS file: {msg}"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) // Unwrap since the slice is always 4 bytes.
+}
+
+/// Maps a legacy four character code to the matching image format.
+fn format_from_four_cc(four_cc: &[u8]) -> Option<ImageFormat> {
+    Some(match four_cc {
+        b"DXT1" => ImageFormat::Bc1RgbaUnorm,
+        b"DXT2" | b"DXT3" => ImageFormat::Bc2RgbaUnorm,
+        b"DXT4" | b"DXT5" => ImageFormat::Bc3RgbaUnorm,
+        b"ATI1" | b"BC4U" => ImageFormat::Bc4RUnorm,
+        b"BC4S" => ImageFormat::Bc4RSnorm,
+        b"ATI2" | b"BC5U" => ImageFormat::Bc5RgUnorm,
+        b"BC5S" => ImageFormat::Bc5RgSnorm,
+        _ => return None,
+    })
+}
+
+/// Maps a DXGI format, as stored in the DX10 header, to the matching image format.
+fn format_from_dxgi(dxgi_format: u32) -> Option<ImageFormat> {
+    Some(match dxgi_format {
+        2 => ImageFormat::Rgba32Float,
+        10 => ImageFormat::Rgba16Float,
+        28 => ImageFormat::Rgba8Unorm,
+        29 => ImageFormat::Rgba8UnormSrgb,
+        49 => ImageFormat::Rg8Unorm,
+        61 => ImageFormat::R8Unorm,
+        71 => ImageFormat::Bc1RgbaUnorm,
+        72 => ImageFormat::Bc1RgbaUnormSrgb,
+        74 => ImageFormat::Bc2RgbaUnorm,
+        75 => ImageFormat::Bc2RgbaUnormSrgb,
+        77 => ImageFormat::Bc3RgbaUnorm,
+        78 => ImageFormat::Bc3RgbaUnormSrgb,
+        80 => ImageFormat::Bc4RUnorm,
+        81 => ImageFormat::Bc4RSnorm,
+        83 => ImageFormat::Bc5RgUnorm,
+        84 => ImageFormat::Bc5RgSnorm,
+        87 => ImageFormat::Bgra8Unorm,
+        91 => ImageFormat::Bgra8UnormSrgb,
+        95 => ImageFormat::Bc6hRgbUfloat,
+        96 => ImageFormat::Bc6hRgbFloat,
+        98 => ImageFormat::Bc7RgbaUnorm,
+        99 => ImageFormat::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+pub(crate) fn parse(data: &[u8]) -> GameResult<Dds<'_>> {
+    if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+        return Err(error("missing DDS magic number"));
+    }
+    if read_u32(data, 4) != 124 {
+        return Err(error("wrong header size"));
+    }
+
+    let flags = read_u32(data, 8);
+    let height = read_u32(data, 12);
+    let width = read_u32(data, 16);
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(data, 28).max(1)
+    } else {
+        1
+    };
+    let pf_flags = read_u32(data, 80);
+    let four_cc = &data[84..88];
+    let caps2 = read_u32(data, 112);
+
+    if caps2 & DDSCAPS2_CUBEMAP != 0 {
+        return Err(unsupported("cubemaps are not supported"));
+    }
+    if caps2 & DDSCAPS2_VOLUME != 0 {
+        return Err(unsupported("volume textures are not supported"));
+    }
+
+    let (format, data_start) = if pf_flags & DDPF_FOURCC != 0 && four_cc == b"DX10" {
+        if data.len() < HEADER_LEN + DX10_HEADER_LEN {
+            return Err(error("DX10 header is truncated"));
+        }
+        let dxgi_format = read_u32(data, 128);
+        let dimension = read_u32(data, 132);
+        let misc_flags = read_u32(data, 136);
+        let array_size = read_u32(data, 140);
+        if misc_flags & DX10_MISC_TEXTURECUBE != 0 {
+            return Err(unsupported("cubemaps are not supported"));
+        }
+        if dimension == DX10_DIMENSION_TEXTURE3D {
+            return Err(unsupported("volume textures are not supported"));
+        }
+        if array_size > 1 {
+            return Err(unsupported("texture arrays are not supported"));
+        }
+        let format = format_from_dxgi(dxgi_format)
+            .ok_or_else(|| unsupported(format!("DXGI format {dxgi_format} is not supported")))?;
+        (format, HEADER_LEN + DX10_HEADER_LEN)
+    } else if pf_flags & DDPF_FOURCC != 0 {
+        let format = format_from_four_cc(four_cc).ok_or_else(|| {
+            unsupported(format!(
+                "format {:?} is not supported",
+                String::from_utf8_lossy(four_cc)
+            ))
+        })?;
+        (format, HEADER_LEN)
+    } else if pf_flags & DDPF_RGB != 0 {
+        // Without alpha the fourth byte is padding, which is made opaque below.
+        let bits = read_u32(data, 88);
+        let masks = [92, 96, 100, 104].map(|offset| read_u32(data, offset));
+        let alpha = if pf_flags & DDPF_ALPHAPIXELS != 0 {
+            masks[3]
+        } else {
+            0xff00_0000
+        };
+        let format = match (bits, [masks[0], masks[1], masks[2], alpha]) {
+            (32, [0xff, 0xff00, 0xff_0000, 0xff00_0000]) => ImageFormat::Rgba8Unorm,
+            (32, [0xff_0000, 0xff00, 0xff, 0xff00_0000]) => ImageFormat::Bgra8Unorm,
+            _ => {
+                return Err(unsupported(format!(
+                    "{bits} bit uncompressed pixels with masks {masks:08x?} are not supported"
+                )))
+            }
+        };
+        (format, HEADER_LEN)
+    } else {
+        return Err(unsupported(
+            "only RGB and block compressed pixels are supported",
+        ));
+    };
+
+    if width == 0 || height == 0 {
+        return Err(error("texture has no size"));
+    }
+    let (block_w, block_h) = format.block_dimensions();
+    if !width.is_multiple_of(block_w) || !height.is_multiple_of(block_h) {
+        return Err(unsupported(format!(
+            "{width}x{height} is not a multiple of the {block_w}x{block_h} block size"
+        )));
+    }
+    if level_count > 32 || (width.max(height) >> (level_count - 1)) == 0 {
+        return Err(error(format!(
+            "{level_count} mip levels is too many for {width}x{height}"
+        )));
+    }
+
+    // Uncompressed pixels without an alpha mask have an unused fourth byte.
+    let opaque = pf_flags & DDPF_FOURCC == 0 && pf_flags & DDPF_ALPHAPIXELS == 0;
+
+    // The levels follow the header back to back, largest first.
+    let block_size = format.block_size(None).unwrap() as usize; // Unwrap since it only fails with depth formats.
+    let mut offset = data_start;
+    let levels = (0..level_count)
+        .map(|level| {
+            let blocks_w = (width >> level).max(1).div_ceil(block_w) as usize;
+            let blocks_h = (height >> level).max(1).div_ceil(block_h) as usize;
+            let truncated = || error(format!("level {level} is truncated"));
+            let len = blocks_w
+                .checked_mul(blocks_h)
+                .and_then(|blocks| blocks.checked_mul(block_size))
+                .ok_or_else(truncated)?;
+            let end = offset.checked_add(len).ok_or_else(truncated)?;
+            let pixels = data.get(offset..end).ok_or_else(truncated)?;
+            offset = end;
+            if !opaque {
+                return Ok(Cow::Borrowed(pixels));
+            }
+            let mut pixels = pixels.to_vec();
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = 0xff;
+            }
+            Ok(Cow::Owned(pixels))
+        })
+        .collect::<GameResult<Vec<_>>>()?;
+
+    Ok(Dds {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a DDS file with a four character code or, for `DX10`, a DXGI format.
+    pub(crate) fn encode(
+        four_cc: &[u8; 4],
+        dxgi_format: u32,
+        width: u32,
+        height: u32,
+        levels: &[&[u8]],
+    ) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        let flags = 0x1007 | DDSD_MIPMAPCOUNT;
+        for v in [124, flags, height, width, 0, 0, levels.len() as u32] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&[0; 11 * 4]);
+        for v in [32, DDPF_FOURCC] {
+            out.extend_from_slice(&u32::to_le_bytes(v));
+        }
+        out.extend_from_slice(four_cc);
+        out.extend_from_slice(&[0; 5 * 4]);
+        out.extend_from_slice(&[0; 5 * 4]); // caps and reserved
+        if four_cc == b"DX10" {
+            for v in [dxgi_format, 3, 0, 1, 0] {
+                out.extend_from_slice(&u32::to_le_bytes(v));
+            }
+        }
+        for level in levels {
+            out.extend_from_slice(level);
+        }
+        out
+    }
+
+    #[test]
+    fn headless_test_dds_parse() {
+        let level0 = [7; 16 * 2];
+        let level1 = [9; 16];
+        let file = encode(b"DXT5", 0, 8, 4, &[&level0, &level1]);
+
+        let dds = parse(&file).unwrap();
+        assert_eq!(dds.format, ImageFormat::Bc3RgbaUnorm);
+        assert_eq!((dds.width, dds.height), (8, 4));
+        assert_eq!(dds.levels, [&level0[..], &level1[..]]);
+
+        let dx10 = encode(b"DX10", 29, 2, 1, &[&[1; 8]]);
+        let dds = parse(&dx10).unwrap();
+        assert_eq!(dds.format, ImageFormat::Rgba8UnormSrgb);
+        assert_eq!(dds.levels, [&[1; 8][..]]);
+    }
+
+    #[test]
+    fn headless_test_dds_parse_errors() {
+        let message = |file: &[u8]| match parse(file) {
+            Err(GameError::ResourceLoadError(msg)) => msg,
+            other => panic!("expected an error, got {other:?}"),
+        };
+
+        assert!(parse(&encode(b"DXT1", 0, 4, 4, &[&[0; 7]])).is_err());
+        assert!(message(&encode(b"DXT1", 0, 6, 4, &[&[0; 16]])).contains("block size"));
+        assert!(message(&encode(b"ABCD", 0, 4, 4, &[&[0; 8]])).contains("\"ABCD\""));
+        assert!(message(&encode(b"DX10", 1234, 4, 4, &[&[0; 8]])).contains("DXGI format 1234"));
+        assert!(parse(b"not a dds file").is_err());
+
+        let mut cube = encode(b"DXT1", 0, 4, 4, &[&[0; 8]]);
+        cube[112..116].copy_from_slice(&(DDSCAPS2_CUBEMAP | 0xfc00).to_le_bytes());
+        assert!(message(&cube).contains("cubemaps"));
+        let mut volume = encode(b"DXT1", 0, 4, 4, &[&[0; 8]]);
+        volume[112..116].copy_from_slice(&DDSCAPS2_VOLUME.to_le_bytes());
+        assert!(message(&volume).contains("volume"));
+        let mut array = encode(b"DX10", 71, 4, 4, &[&[0; 8]]);
+        array[140..144].copy_from_slice(&6u32.to_le_bytes());
+        assert!(message(&array).contains("arrays"));
+
+        // Sizes that overflow are reported like any other truncated level.
+        let huge = encode(b"DX10", 2, 1 << 31, 1 << 31, &[&[0; 16]]);
+        assert!(message(&huge).contains("truncated"));
+    }
+
+    #[test]
+    fn headless_test_dds_rgb_without_alpha() {
+        let pixels = [10, 20, 30, 0, 40, 50, 60, 7];
+        let mut file = encode(b"\0\0\0\0", 0, 2, 1, &[&pixels]);
+        for (offset, v) in [
+            (80, DDPF_RGB),
+            (88, 32),
+            (92, 0xff_0000),
+            (96, 0xff00),
+            (100, 0xff),
+            (104, 0),
+        ] {
+            file[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(v));
+        }
+        let dds = parse(&file).unwrap();
+        assert_eq!(dds.format, ImageFormat::Bgra8Unorm);
+        assert_eq!(dds.levels, [&[10, 20, 30, 255, 40, 50, 60, 255][..]]);
+
+        // With an alpha mask the fourth byte is kept.
+        let flags = DDPF_RGB | DDPF_ALPHAPIXELS;
+        file[80..84].copy_from_slice(&flags.to_le_bytes());
+        file[104..108].copy_from_slice(&0xff00_0000u32.to_le_bytes());
+        assert_eq!(parse(&file).unwrap().levels, [&pixels[..]]);
+    }
+}
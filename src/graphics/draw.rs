@@ -1,4 +1,4 @@
-use super::{Canvas, Color, GraphicsContext, LinearColor, Rect};
+use super::{Canvas, Color, ColorLerpSpace, ColorSpaceMode, GraphicsContext, Rect};
 use crate::context::Has;
 
 /// A struct that represents where to put a drawable object.
@@ -128,6 +128,10 @@ pub struct DrawParam {
     /// A portion of the drawable to clip, as a fraction of the whole image.
     /// Defaults to the whole image (\[0.0, 0.0\] to \[1.0, 1.0\]) if omitted.
     pub src: Rect,
+    /// Whether sampling is kept inside `src`, so filtering doesn't blend in neighboring
+    /// sprites of an atlas. Only applies when `src` is a part of the image and the canvas
+    /// uses the default shader. Default: true.
+    pub src_clamp: bool,
     /// Default: white.
     pub color: Color,
     /// Where to put the object.
@@ -140,6 +144,7 @@ impl Default for DrawParam {
     fn default() -> Self {
         DrawParam {
             src: Rect::one(),
+            src_clamp: true,
             color: Color::WHITE,
             transform: Transform::default(),
             z: 0,
@@ -160,6 +165,13 @@ impl DrawParam {
         self
     }
 
+    /// Set whether sampling is kept inside the source rect.
+    #[must_use]
+    pub fn src_clamp(mut self, src_clamp: bool) -> Self {
+        self.src_clamp = src_clamp;
+        self
+    }
+
     pub(crate) fn get_dest_mut(&mut self) -> &mut mint::Point2<f32> {
         if let Transform::Values { dest, .. } = &mut self.transform {
             dest
@@ -266,6 +278,117 @@ impl DrawParam {
         self.z = z;
         self
     }
+
+    /// Blends from `self` at `t = 0.0` to `other` at `t = 1.0`, e.g. to draw between the
+    /// last two states of a fixed timestep update:
+    ///
+    /// ```rust
+    /// # use ggez::{graphics::{Canvas, DrawParam, Image}, Context};
+    /// # fn t(ctx: &Context, canvas: &mut Canvas, sprite: &Image, prev: DrawParam, curr: DrawParam) {
+    /// canvas.draw(sprite, prev.lerp(&curr, ctx.time.interpolation_alpha()));
+    /// # }
+    /// ```
+    ///
+    /// Positions, offsets and scales are blended linearly and rotations the short way
+    /// around, as is the source rect. Colors are blended like with
+    /// [`ColorLerpSpace::Srgb`]. `src_clamp` and `z` can't be blended and come from
+    /// whichever param `t` is closer to.
+    ///
+    /// If either transform is a [`Transform::Matrix`], both are split into translation,
+    /// rotation and scale, which are blended in the same way. Matrices with skew don't
+    /// split cleanly and only blend approximately. A matrix can't hold an offset, which is
+    /// a fraction of the size of whatever is drawn, so in this case the offset of the
+    /// [`Transform::Values`] side is ignored.
+    #[must_use]
+    pub fn lerp(&self, other: &DrawParam, t: f32) -> DrawParam {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let mix2 = |a: glam::Vec2, b: glam::Vec2| a.lerp(b, t);
+        let nearest = if t < 0.5 { self } else { other };
+
+        let transform = match (self.transform, other.transform) {
+            (
+                Transform::Values {
+                    dest: d1,
+                    rotation: r1,
+                    scale: s1,
+                    offset: o1,
+                },
+                Transform::Values {
+                    dest: d2,
+                    rotation: r2,
+                    scale: s2,
+                    offset: o2,
+                },
+            ) => {
+                let turn = (r2 - r1 + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                    - std::f32::consts::PI;
+                Transform::Values {
+                    dest: mix2(d1.into(), d2.into()).into(),
+                    rotation: r1 + turn * t,
+                    scale: mix2(s1.into(), s2.into()).into(),
+                    offset: mix2(o1.into(), o2.into()).into(),
+                }
+            }
+            (a, b) => Transform::Matrix(lerp_matrices(a, b, t).into()),
+        };
+
+        DrawParam {
+            src: Rect::new(
+                mix(self.src.x, other.src.x),
+                mix(self.src.y, other.src.y),
+                mix(self.src.w, other.src.w),
+                mix(self.src.h, other.src.h),
+            ),
+            src_clamp: nearest.src_clamp,
+            color: self.color.lerp(other.color, t, ColorLerpSpace::Srgb),
+            transform,
+            z: nearest.z,
+        }
+    }
+}
+
+/// Blends two transforms as matrices, by splitting them into translation, rotation and scale.
+///
+/// Offsets are dropped, since `to_bare_matrix` would turn them into pixels.
+fn lerp_matrices(a: Transform, b: Transform, t: f32) -> glam::Mat4 {
+    let split = |transform: Transform| {
+        let transform = match transform {
+            Transform::Values {
+                dest,
+                rotation,
+                scale,
+                ..
+            } => Transform::Values {
+                dest,
+                rotation,
+                scale,
+                offset: mint::Point2 { x: 0., y: 0. },
+            },
+            matrix => matrix,
+        };
+        let matrix = glam::Mat4::from(transform.to_bare_matrix());
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        // A zero scale leaves no rotation to recover.
+        let rotation = if rotation.is_finite() {
+            Some(rotation)
+        } else {
+            None
+        };
+        (scale, rotation, translation)
+    };
+    let (s1, r1, t1) = split(a);
+    let (s2, r2, t2) = split(b);
+    let rotation = match (r1, r2) {
+        (Some(r1), Some(r2)) => {
+            // Quaternions in opposite hemispheres are the same rotation; flip one so the
+            // blend takes the short way around.
+            let r2 = if r1.dot(r2) < 0. { -r2 } else { r2 };
+            r1.slerp(r2, t).normalize()
+        }
+        (Some(r), None) | (None, Some(r)) => r,
+        (None, None) => glam::Quat::IDENTITY,
+    };
+    glam::Mat4::from_scale_rotation_translation(s1.lerp(s2, t), rotation, t1.lerp(t2, t))
 }
 
 /// Create a `DrawParam` from a location, like this:
@@ -312,7 +435,11 @@ unsafe impl bytemuck::Zeroable for DrawUniforms {}
 unsafe impl bytemuck::Pod for DrawUniforms {}
 
 impl DrawUniforms {
-    pub fn from_param(param: &DrawParam, image_scale: Option<mint::Vector2<f32>>) -> Self {
+    pub fn from_param(
+        param: &DrawParam,
+        image_scale: Option<mint::Vector2<f32>>,
+        color_space: ColorSpaceMode,
+    ) -> Self {
         let (scale_x, scale_y) = if let Some(image_scale) = image_scale {
             (image_scale.x * param.src.w, image_scale.y * param.src.h)
         } else {
@@ -329,7 +456,7 @@ impl DrawUniforms {
             ),
         };
 
-        let color = LinearColor::from(param.color);
+        let color = color_space.shader_color(param.color);
 
         DrawUniforms {
             color: <[f32; 4]>::from(color).into(),
@@ -343,3 +470,82 @@ impl DrawUniforms {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn assert_finite(param: &DrawParam) {
+        let matrix = glam::Mat4::from(param.transform.to_bare_matrix());
+        assert!(matrix.is_finite(), "{param:?}");
+    }
+
+    #[test]
+    fn headless_test_draw_param_lerp() {
+        let a = DrawParam::new()
+            .dest([0., 10.])
+            .rotation(PI - 0.1)
+            .scale([1., 2.])
+            .color(Color::BLACK)
+            .z(1);
+        let b = DrawParam::new()
+            .dest([10., 20.])
+            .rotation(-PI + 0.1)
+            .scale([3., 2.])
+            .color(Color::WHITE)
+            .z(5);
+        let mid = a.lerp(&b, 0.5);
+        let Transform::Values {
+            dest,
+            rotation,
+            scale,
+            ..
+        } = mid.transform
+        else {
+            panic!("values should blend into values");
+        };
+        assert_eq!((dest.x, dest.y), (5., 15.));
+        assert_eq!((scale.x, scale.y), (2., 2.));
+        // Across the ±π seam, the short way around.
+        assert!(
+            (rotation.rem_euclid(2. * PI) - PI).abs() < 1e-5,
+            "{rotation}"
+        );
+        assert_eq!(mid.color, Color::new(0.5, 0.5, 0.5, 1.));
+        assert_eq!(mid.z, 5);
+        assert_eq!(a.lerp(&b, 0.), a);
+
+        // Matrices are blended by their parts; rotations of almost a full turn apart are
+        // quaternions in opposite hemispheres.
+        let turned = |angle: f32| {
+            DrawParam::new().transform(glam::Mat4::from_scale_rotation_translation(
+                glam::Vec3::new(2., 2., 1.),
+                glam::Quat::from_rotation_z(angle),
+                glam::Vec3::new(4., 0., 0.),
+            ))
+        };
+        let mid = turned(0.1).lerp(&turned(2. * PI - 0.1), 0.5);
+        assert_finite(&mid);
+        let expected = glam::Mat4::from(turned(0.).transform.to_bare_matrix());
+        let actual = glam::Mat4::from(mid.transform.to_bare_matrix());
+        assert!(actual.abs_diff_eq(expected, 1e-4), "{actual:?}");
+
+        // Zero scales have no rotation, and must not spread NaNs.
+        let flat = DrawParam::new().scale([0., 0.]);
+        assert_finite(&flat.lerp(&turned(1.), 0.3));
+        assert_finite(&turned(1.).lerp(&flat.transform(glam::Mat4::ZERO), 0.3));
+        assert_finite(&flat.lerp(&flat, 0.5));
+
+        // Blending values with a matrix ignores the offset, which only makes sense relative
+        // to the size of what's drawn.
+        let centered = DrawParam::new().dest([10., 0.]).offset([0.5, 0.5]);
+        let moved =
+            DrawParam::new().transform(glam::Mat4::from_translation(glam::vec3(20., 0., 0.)));
+        for (t, x) in [(0., 10.), (0.5, 15.), (1., 20.)] {
+            let matrix = glam::Mat4::from(centered.lerp(&moved, t).transform.to_bare_matrix());
+            let expected = glam::Mat4::from_translation(glam::vec3(x, 0., 0.));
+            assert!(matrix.abs_diff_eq(expected, 1e-5), "{t}: {matrix:?}");
+        }
+    }
+}
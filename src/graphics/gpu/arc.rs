@@ -1,3 +1,4 @@
+use crate::graphics::memory::{Allocation, ResourceKind, ResourceTracker};
 use std::sync::{
     atomic::{AtomicU64, Ordering::SeqCst},
     Arc,
@@ -9,10 +10,14 @@ static NEXT_ID: AtomicU64 = AtomicU64::new(0);
 ///
 /// Beyond allowing for Clone, they also allow different GPU resources to be
 /// unique identified via `id` - primarily used when caching (see the other `gpu` modules).
+///
+/// Buffers and textures are also listed in the [`ResourceTracker`] until the last clone is
+/// dropped, see [`track`](Self::track).
 #[derive(Debug)]
 pub struct ArcHandle<T: 'static> {
     pub handle: Arc<T>,
     id: u64,
+    allocation: Option<Arc<Allocation>>,
 }
 
 impl<T: 'static> ArcHandle<T> {
@@ -20,9 +25,22 @@ impl<T: 'static> ArcHandle<T> {
         ArcHandle {
             handle: Arc::new(handle),
             id: NEXT_ID.fetch_add(1, SeqCst),
+            allocation: None,
         }
     }
 
+    /// Lists the resource in `tracker` for as long as this handle or a clone of it is alive.
+    pub fn track(
+        mut self,
+        tracker: &ResourceTracker,
+        kind: ResourceKind,
+        label: &str,
+        bytes: u64,
+    ) -> Self {
+        self.allocation = Some(Arc::new(tracker.track(kind, label, bytes)));
+        self
+    }
+
     #[inline]
     pub fn id(&self) -> u64 {
         self.id
@@ -34,6 +52,7 @@ impl<T: 'static> Clone for ArcHandle<T> {
         ArcHandle {
             handle: Arc::clone(&self.handle),
             id: self.id,
+            allocation: self.allocation.clone(),
         }
     }
 }
@@ -45,13 +45,21 @@ impl BindGroupLayoutBuilder {
         self
     }
 
-    pub fn image(mut self, visibility: wgpu::ShaderStages) -> Self {
+    pub fn image(self, visibility: wgpu::ShaderStages) -> Self {
+        self.image_with_dimension(visibility, wgpu::TextureViewDimension::D2)
+    }
+
+    pub fn image_with_dimension(
+        mut self,
+        visibility: wgpu::ShaderStages,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> Self {
         self.entries.push(wgpu::BindGroupLayoutEntry {
             binding: self.entries.len() as _,
             visibility,
             ty: wgpu::BindingType::Texture {
                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
+                view_dimension,
                 multisampled: false,
             },
             count: None,
@@ -149,7 +157,16 @@ impl<'a> BindGroupBuilder<'a> {
         }
     }
 
-    pub fn image(mut self, view: &'a ArcTextureView, visibility: wgpu::ShaderStages) -> Self {
+    pub fn image(self, view: &'a ArcTextureView, visibility: wgpu::ShaderStages) -> Self {
+        self.image_with_dimension(view, visibility, wgpu::TextureViewDimension::D2)
+    }
+
+    pub fn image_with_dimension(
+        mut self,
+        view: &'a ArcTextureView,
+        visibility: wgpu::ShaderStages,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> Self {
         self.entries.push(wgpu::BindGroupEntry {
             binding: self.entries.len() as _,
             resource: wgpu::BindingResource::TextureView(view.as_ref()),
@@ -158,7 +175,7 @@ impl<'a> BindGroupBuilder<'a> {
         self.key.push(BindGroupEntryKey::Image { id: view.id() });
 
         BindGroupBuilder {
-            layout: self.layout.image(visibility),
+            layout: self.layout.image_with_dimension(visibility, view_dimension),
             entries: self.entries,
             key: self.key,
         }
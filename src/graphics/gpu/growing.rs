@@ -1,4 +1,5 @@
 use super::arc::ArcBuffer;
+use crate::graphics::{context::WgpuContext, memory::ResourceTracker, ResourceKind};
 
 /// Simple buffer sub-allocation helper.
 ///
@@ -11,19 +12,27 @@ pub struct GrowingBufferArena {
     buffers: Vec<(ArcBuffer, u64)>,
     alignment: u64,
     desc: wgpu::BufferDescriptor<'static>,
+    resources: ResourceTracker,
+    label: &'static str,
 }
 
 impl GrowingBufferArena {
+    /// Creates an arena whose buffers are listed in the resource report as `label`.
     pub fn new(
-        device: &wgpu::Device,
+        wgpu: &WgpuContext,
         alignment: u64,
         desc: wgpu::BufferDescriptor<'static>,
+        label: &'static str,
     ) -> Self {
-        GrowingBufferArena {
-            buffers: vec![(ArcBuffer::new(device.create_buffer(&desc)), 0)],
+        let mut arena = GrowingBufferArena {
+            buffers: Vec::new(),
             alignment,
             desc,
-        }
+            resources: wgpu.resources.clone(),
+            label,
+        };
+        arena.grow(&wgpu.device);
+        arena
     }
 
     pub fn allocate(&mut self, device: &wgpu::Device, size: u64) -> ArenaAllocation {
@@ -53,8 +62,13 @@ impl GrowingBufferArena {
     }
 
     fn grow(&mut self, device: &wgpu::Device) {
-        self.buffers
-            .push((ArcBuffer::new(device.create_buffer(&self.desc)), 0));
+        let buffer = ArcBuffer::new(device.create_buffer(&self.desc)).track(
+            &self.resources,
+            ResourceKind::Internal,
+            self.label,
+            self.desc.size,
+        );
+        self.buffers.push((buffer, 0));
     }
 }
 
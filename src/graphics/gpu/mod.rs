@@ -3,3 +3,4 @@ pub mod bind_group;
 pub mod growing;
 pub mod pipeline;
 pub mod text;
+pub mod upload;
@@ -1,5 +1,17 @@
 use super::arc::{ArcBindGroupLayout, ArcPipelineLayout, ArcRenderPipeline, ArcShaderModule};
-use std::collections::{hash_map::DefaultHasher, HashMap};
+use crate::graphics::{PipelineCompilation, WgpuContext};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    panic::AssertUnwindSafe,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+/// Pipelines compiled on the background thread, waiting to be added to the cache.
+type Finished = Arc<Mutex<Vec<(RenderPipelineInfo, thread::Result<ArcRenderPipeline>)>>>;
+
+/// A pipeline for the background thread to compile.
+type Job = (ArcPipelineLayout, RenderPipelineInfo);
 
 /// Hashable representation of a render pipeline, used as a key in the HashMap cache.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -22,6 +34,13 @@ pub struct RenderPipelineInfo {
 pub struct PipelineCache {
     pipelines: HashMap<RenderPipelineInfo, ArcRenderPipeline>,
     layouts: HashMap<u64, ArcPipelineLayout>,
+    /// Pipelines being compiled in the background.
+    pending: HashSet<RenderPipelineInfo>,
+    finished: Finished,
+    /// Queue of the background thread, which is started for the first pipeline that needs it
+    /// and stops once the cache is dropped.
+    jobs: Option<mpsc::Sender<Job>>,
+    pub compilation: PipelineCompilation,
 }
 
 impl PipelineCache {
@@ -29,6 +48,10 @@ impl PipelineCache {
         PipelineCache {
             pipelines: HashMap::new(),
             layouts: HashMap::new(),
+            pending: HashSet::new(),
+            finished: Arc::default(),
+            jobs: None,
+            compilation: PipelineCompilation::default(),
         }
     }
 
@@ -38,61 +61,56 @@ impl PipelineCache {
         layout: &wgpu::PipelineLayout,
         info: RenderPipelineInfo,
     ) -> ArcRenderPipeline {
-        let vertex_buffers = [info.vertex_layout.clone()];
-
+        self.collect();
         self.pipelines
             .entry(info.clone())
-            .or_insert_with(|| {
-                ArcRenderPipeline::new(device.create_render_pipeline(
-                    &wgpu::RenderPipelineDescriptor {
-                        label: None,
-                        layout: Some(layout),
-                        vertex: wgpu::VertexState {
-                            module: &info.vs,
-                            entry_point: &info.vs_entry,
-                            buffers: if info.vertices { &vertex_buffers } else { &[] },
-                        },
-                        primitive: wgpu::PrimitiveState {
-                            topology: info.topology,
-                            strip_index_format: None,
-                            front_face: wgpu::FrontFace::Ccw,
-                            cull_mode: None,
-                            unclipped_depth: false,
-                            polygon_mode: wgpu::PolygonMode::Fill,
-                            conservative: false,
-                        },
-                        depth_stencil: if info.depth {
-                            Some(wgpu::DepthStencilState {
-                                format: wgpu::TextureFormat::Depth32Float,
-                                depth_write_enabled: true,
-                                depth_compare: wgpu::CompareFunction::Always,
-                                stencil: Default::default(),
-                                bias: Default::default(),
-                            })
-                        } else {
-                            None
-                        },
-                        multisample: wgpu::MultisampleState {
-                            count: info.samples,
-                            mask: !0,
-                            alpha_to_coverage_enabled: false,
-                        },
-                        fragment: Some(wgpu::FragmentState {
-                            module: &info.fs,
-                            entry_point: &info.fs_entry,
-                            targets: &[Some(wgpu::ColorTargetState {
-                                format: info.format,
-                                blend: info.blend,
-                                write_mask: wgpu::ColorWrites::ALL,
-                            })],
-                        }),
-                        multiview: None,
-                    },
-                ))
-            })
+            .or_insert_with(|| create_render_pipeline(device, layout, &info))
             .clone()
     }
 
+    /// Returns the pipeline if it's ready, and otherwise queues it to be compiled on the
+    /// background thread unless that has already happened.
+    pub fn render_pipeline_async(
+        &mut self,
+        wgpu: &Arc<WgpuContext>,
+        layout: ArcPipelineLayout,
+        info: RenderPipelineInfo,
+    ) -> Option<ArcRenderPipeline> {
+        self.collect();
+        if let Some(pipeline) = self.pipelines.get(&info) {
+            return Some(pipeline.clone());
+        }
+
+        if self.pending.insert(info.clone()) {
+            let jobs = self
+                .jobs
+                .get_or_insert_with(|| spawn_compiler(wgpu.clone(), self.finished.clone()));
+            jobs.send((layout, info))
+                .expect("the pipeline compiler thread stopped");
+        }
+        None
+    }
+
+    /// Returns how many pipelines are still being compiled in the background.
+    pub fn pending(&self) -> usize {
+        self.pending.len() - self.finished.lock().unwrap().len()
+    }
+
+    /// Moves the pipelines finished in the background into the cache.
+    fn collect(&mut self) {
+        let finished = std::mem::take(&mut *self.finished.lock().unwrap());
+        for (info, pipeline) in finished {
+            let _ = self.pending.remove(&info);
+            match pipeline {
+                Ok(pipeline) => {
+                    let _ = self.pipelines.insert(info, pipeline);
+                }
+                // Report errors like compiling on this thread would have.
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+    }
+
     pub fn layout(
         &mut self,
         device: &wgpu::Device,
@@ -123,3 +141,75 @@ impl PipelineCache {
             .clone()
     }
 }
+
+/// Starts the thread that compiles the pipelines sent to it, one at a time.
+fn spawn_compiler(wgpu: Arc<WgpuContext>, finished: Finished) -> mpsc::Sender<Job> {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    let _ = thread::Builder::new()
+        .name(String::from("ggez pipeline compiler"))
+        .spawn(move || {
+            for (layout, info) in receiver {
+                // The panic is raised again on the main thread, see `collect`.
+                let pipeline = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    create_render_pipeline(&wgpu.device, &layout, &info)
+                }));
+                finished.lock().unwrap().push((info, pipeline));
+            }
+        })
+        .expect("failed to spawn the pipeline compiler thread");
+    sender
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    info: &RenderPipelineInfo,
+) -> ArcRenderPipeline {
+    let vertex_buffers = [info.vertex_layout.clone()];
+    ArcRenderPipeline::new(
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &info.vs,
+                entry_point: &info.vs_entry,
+                buffers: if info.vertices { &vertex_buffers } else { &[] },
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: info.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: if info.depth {
+                Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: Default::default(),
+                    bias: Default::default(),
+                })
+            } else {
+                None
+            },
+            multisample: wgpu::MultisampleState {
+                count: info.samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &info.fs,
+                entry_point: &info.fs_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: info.format,
+                    blend: info.blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        }),
+    )
+}
@@ -3,9 +3,13 @@ use super::{
     bind_group::BindGroupBuilder,
     growing::GrowingBufferArena,
 };
-use crate::graphics::{context::FrameArenas, LinearColor};
+use crate::graphics::{
+    context::{FrameArenas, WgpuContext},
+    memory::{texture_bytes, ResourceTracker},
+    ColorSpaceMode, LinearColor, ResourceKind,
+};
 use crevice::std140::AsStd140;
-use glyph_brush::{GlyphBrush, GlyphBrushBuilder};
+use glyph_brush::{ab_glyph::Font, FontId, GlyphBrush, GlyphBrushBuilder, GlyphCruncher};
 use ordered_float::OrderedFloat;
 use std::cell::RefCell;
 
@@ -17,27 +21,106 @@ pub(crate) struct TextRenderer {
     pub cache_bind: ArcBindGroup,
     pub cache_bind_layout: ArcBindGroupLayout,
     pub cache_size: (u32, u32),
+    /// Fonts consulted, in order, for characters missing from a fragment's font.
+    pub fallbacks: Vec<FontId>,
+    /// Decides how text colors are passed to the shader.
+    pub color_space: ColorSpaceMode,
 
     pub verts: GrowingBufferArena,
+    resources: ResourceTracker,
+
+    /// The most bytes the glyph cache texture may take up.
+    pub budget: u64,
+    /// Glyphs rasterized and uploaded to the cache this frame.
+    pub uploads: u32,
+    /// How many frames the cache has to go without growing before it shrinks.
+    pub shrink_after: u32,
+    idle_frames: u32,
+    shrunk: bool,
+    /// Whether text was skipped for not fitting the cache this frame.
+    over_budget: bool,
+    /// Whether skipped text was warned about, which is only done once until text fits again.
+    pub warned_over_budget: bool,
 }
 
 impl TextRenderer {
     // if the number of chars goes over this, a dedicated buffer is allocated for the text
     const MAX_TEXT_VERTEX_ARENA: u64 = 2048;
 
-    pub fn new(device: &wgpu::Device, cache_bind_layout: ArcBindGroupLayout) -> Self {
-        let cache_size = (1024, 1024);
+    const INITIAL_CACHE_SIZE: (u32, u32) = (1024, 1024);
+    const MIN_CACHE_SIZE: (u32, u32) = (256, 256);
+    /// The default budget, enough for an 8192x8192 cache.
+    pub const DEFAULT_BUDGET: u64 = 64 * 1024 * 1024;
+    const SHRINK_AFTER_FRAMES: u32 = 600;
+    const MAX_SHRINK_AFTER_FRAMES: u32 = 600 * 16;
+    /// If the cache has to grow again this soon after shrinking, it shrank too early.
+    const SHRINK_GRACE_FRAMES: u32 = 60;
+
+    pub fn new(
+        wgpu: &WgpuContext,
+        cache_bind_layout: ArcBindGroupLayout,
+        color_space: ColorSpaceMode,
+    ) -> Self {
+        let cache_size = Self::INITIAL_CACHE_SIZE;
 
         let glyph_brush = GlyphBrushBuilder::using_fonts(vec![])
             .cache_redraws(false)
             .initial_cache_size(cache_size)
             .build();
 
+        let (cache, cache_view, cache_bind) = Self::create_cache(
+            &wgpu.device,
+            &wgpu.resources,
+            &cache_bind_layout,
+            cache_size,
+        );
+
+        let verts = GrowingBufferArena::new(
+            wgpu,
+            1,
+            wgpu::BufferDescriptor {
+                label: None,
+                size: Self::MAX_TEXT_VERTEX_ARENA * std::mem::size_of::<TextVertex>() as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            },
+            "text vertices",
+        );
+
+        TextRenderer {
+            glyph_brush: RefCell::new(glyph_brush),
+            cache,
+            cache_view,
+            cache_bind,
+            cache_bind_layout,
+            cache_size,
+            fallbacks: Vec::new(),
+            color_space,
+
+            verts,
+            resources: wgpu.resources.clone(),
+
+            budget: Self::DEFAULT_BUDGET,
+            uploads: 0,
+            shrink_after: Self::SHRINK_AFTER_FRAMES,
+            idle_frames: 0,
+            shrunk: false,
+            over_budget: false,
+            warned_over_budget: false,
+        }
+    }
+
+    fn create_cache(
+        device: &wgpu::Device,
+        resources: &ResourceTracker,
+        cache_bind_layout: &ArcBindGroupLayout,
+        (width, height): (u32, u32),
+    ) -> (ArcTexture, ArcTextureView, ArcBindGroup) {
         let cache = ArcTexture::new(device.create_texture(&wgpu::TextureDescriptor {
             label: None,
             size: wgpu::Extent3d {
-                width: cache_size.0,
-                height: cache_size.1,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
@@ -46,7 +129,13 @@ impl TextRenderer {
             format: wgpu::TextureFormat::R8Unorm,
             usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
-        }));
+        }))
+        .track(
+            resources,
+            ResourceKind::Internal,
+            "glyph cache",
+            Self::cache_bytes((width, height)),
+        );
 
         let cache_view =
             ArcTextureView::new(cache.create_view(&wgpu::TextureViewDescriptor::default()));
@@ -54,46 +143,148 @@ impl TextRenderer {
         let cache_bind = BindGroupBuilder::new().image(&cache_view, wgpu::ShaderStages::FRAGMENT);
         let cache_bind = ArcBindGroup::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
-            layout: &cache_bind_layout,
+            layout: cache_bind_layout,
             entries: cache_bind.entries(),
         }));
 
-        let verts = GrowingBufferArena::new(
-            device,
-            1,
-            wgpu::BufferDescriptor {
-                label: None,
-                size: Self::MAX_TEXT_VERTEX_ARENA * std::mem::size_of::<TextVertex>() as u64,
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-                mapped_at_creation: false,
-            },
-        );
+        (cache, cache_view, cache_bind)
+    }
 
-        TextRenderer {
-            glyph_brush: RefCell::new(glyph_brush),
-            cache,
-            cache_view,
-            cache_bind,
-            cache_bind_layout,
-            cache_size,
+    /// Replaces the cache texture with an empty one of a different size.
+    fn resize_cache(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        self.cache_size = size;
+        self.glyph_brush.borrow_mut().resize_texture(size.0, size.1);
+        (self.cache, self.cache_view, self.cache_bind) =
+            Self::create_cache(device, &self.resources, &self.cache_bind_layout, size);
+    }
 
-            verts,
+    /// Returns how many bytes the glyph cache texture takes up.
+    pub fn cache_len(&self) -> u64 {
+        Self::cache_bytes(self.cache_size)
+    }
+
+    /// Shrinks the cache if it's over budget, or if it hasn't needed to grow for a while.
+    ///
+    /// The draw cache of `glyph_brush` already makes room by dropping glyphs the current frame
+    /// doesn't use, but never gives memory back; a burst of large text would otherwise keep
+    /// the cache at its peak size for good.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        self.uploads = 0;
+        if !self.over_budget {
+            self.warned_over_budget = false;
+        }
+        self.over_budget = false;
+
+        let half = |(w, h): (u32, u32)| {
+            (
+                (w / 2).max(Self::MIN_CACHE_SIZE.0),
+                (h / 2).max(Self::MIN_CACHE_SIZE.1),
+            )
+        };
+        let mut size = self.cache_size;
+        while Self::cache_bytes(size) > self.budget && half(size) != size {
+            size = half(size);
+        }
+        if size != self.cache_size {
+            self.resize_cache(device, size);
+            return;
+        }
+
+        if self.cache_size.0 <= Self::INITIAL_CACHE_SIZE.0
+            && self.cache_size.1 <= Self::INITIAL_CACHE_SIZE.1
+        {
+            self.idle_frames = 0;
+            self.shrunk = false;
+            return;
+        }
+        self.idle_frames += 1;
+        if self.idle_frames >= self.shrink_after {
+            self.idle_frames = 0;
+            self.shrunk = true;
+            self.resize_cache(device, half(self.cache_size));
         }
     }
 
-    fn create_verts_buffer(device: &wgpu::Device, num_verts: usize) -> ArcBuffer {
+    /// Grows the cache to `suggested`, returning `false` if that would go over the budget
+    /// or the device's texture size limit.
+    fn grow_cache(&mut self, device: &wgpu::Device, suggested: (u32, u32)) -> bool {
+        let max = device.limits().max_texture_dimension_2d;
+        if suggested.0 > max || suggested.1 > max || Self::cache_bytes(suggested) > self.budget {
+            return false;
+        }
+
+        // Having to grow right after shrinking means the cache was still needed, so wait
+        // longer before trying again.
+        if self.shrunk && self.idle_frames < Self::SHRINK_GRACE_FRAMES {
+            self.shrink_after = (self.shrink_after * 2).min(Self::MAX_SHRINK_AFTER_FRAMES);
+        }
+        self.idle_frames = 0;
+        self.shrunk = false;
+        self.resize_cache(device, suggested);
+        true
+    }
+
+    fn create_verts_buffer(
+        device: &wgpu::Device,
+        resources: &ResourceTracker,
+        num_verts: usize,
+    ) -> ArcBuffer {
+        let size = (num_verts * std::mem::size_of::<TextVertex>()) as u64;
         ArcBuffer::new(device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: (num_verts * std::mem::size_of::<TextVertex>()) as u64,
+            size,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
             mapped_at_creation: false,
         }))
+        .track(resources, ResourceKind::Internal, "text vertices", size)
+    }
+
+    fn cache_bytes((width, height): (u32, u32)) -> u64 {
+        texture_bytes(wgpu::TextureFormat::R8Unorm, (width, height, 1), 1)
+    }
+
+    /// Splits `text` into runs that each use the first font of `font` and the fallback chain
+    /// that has glyphs for them.
+    pub fn font_runs<'a>(&self, text: &'a str, font: FontId) -> Vec<(&'a str, FontId)> {
+        if self.fallbacks.is_empty() {
+            return vec![(text, font)];
+        }
+
+        let glyph_brush = self.glyph_brush.borrow();
+        let fonts = glyph_brush.fonts();
+        let font_for = |c: char| {
+            std::iter::once(font)
+                .chain(self.fallbacks.iter().copied())
+                .find(|id| fonts[id.0].glyph_id(c).0 != 0)
+                .unwrap_or(font)
+        };
+
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut current = font;
+        for (i, c) in text.char_indices() {
+            // Whitespace stays with the surrounding run rather than switching back and forth.
+            if c.is_whitespace() {
+                continue;
+            }
+            let id = font_for(c);
+            if id != current {
+                if i > start {
+                    runs.push((&text[start..i], current));
+                }
+                start = i;
+                current = id;
+            }
+        }
+        runs.push((&text[start..], current));
+        runs
     }
 
     pub fn queue(&self, section: glyph_brush::Section<'_, Extra>) {
         self.glyph_brush.borrow_mut().queue(section);
     }
 
+    /// Draws the queued text, returning how many glyphs were drawn.
     #[allow(unsafe_code)]
     pub(crate) fn draw_queued<'a>(
         &mut self,
@@ -101,9 +292,11 @@ impl TextRenderer {
         queue: &wgpu::Queue,
         arenas: &'a FrameArenas,
         pass: &mut wgpu::RenderPass<'a>,
-    ) {
+    ) -> u32 {
+        let mut uploads = 0;
         let res = self.glyph_brush.borrow_mut().process_queued(
             |rect, pixels| {
+                uploads += 1;
                 queue.write_texture(
                     wgpu::ImageCopyTexture {
                         texture: &self.cache,
@@ -149,11 +342,16 @@ impl TextRenderer {
             },
         );
 
+        self.uploads += uploads;
+
         match res {
             Ok(glyph_brush::BrushAction::Draw(verts)) => {
                 let verts_size = verts.len() * std::mem::size_of::<TextVertex>();
                 let (buffer, offset) = if verts.len() as u64 > Self::MAX_TEXT_VERTEX_ARENA {
-                    (Self::create_verts_buffer(device, verts.len()), 0)
+                    (
+                        Self::create_verts_buffer(device, &self.resources, verts.len()),
+                        0,
+                    )
                 } else {
                     let verts_alloc = self.verts.allocate(device, verts_size as u64);
                     (verts_alloc.buffer, verts_alloc.offset)
@@ -168,45 +366,26 @@ impl TextRenderer {
                 // Also note that vertex data is stepped PER INSTANCE.
                 // Therefore we only store ONE VERTEX for ONE GLYPH (and in the vertex shader we generate the quad vertices on the fly).
                 pass.draw(0..4, 0..verts.len() as u32);
+                verts.len() as u32
             }
             Err(glyph_brush::BrushError::TextureTooSmall { suggested }) => {
                 // increase texture size as recommended by glyph_brush
+                if self.grow_cache(device, suggested) {
+                    return self.draw_queued(device, queue, arenas, pass);
+                }
 
-                self.cache_size = suggested;
-                self.glyph_brush
-                    .borrow_mut()
-                    .resize_texture(self.cache_size.0, self.cache_size.1);
-
-                self.cache = ArcTexture::new(device.create_texture(&wgpu::TextureDescriptor {
-                    label: None,
-                    size: wgpu::Extent3d {
-                        width: self.cache_size.0,
-                        height: self.cache_size.1,
-                        depth_or_array_layers: 1,
-                    },
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::R8Unorm,
-                    usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                    view_formats: &[],
-                }));
-
-                self.cache_view = ArcTextureView::new(
-                    self.cache
-                        .create_view(&wgpu::TextureViewDescriptor::default()),
-                );
-
-                let cache_bind =
-                    BindGroupBuilder::new().image(&self.cache_view, wgpu::ShaderStages::FRAGMENT);
-                self.cache_bind =
-                    ArcBindGroup::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: None,
-                        layout: &self.cache_bind_layout,
-                        entries: cache_bind.entries(),
-                    }));
-
-                self.draw_queued(device, queue, arenas, pass)
+                self.over_budget = true;
+                if !self.warned_over_budget {
+                    self.warned_over_budget = true;
+                    log::warn!(
+                        "the text drawn needs a glyph cache larger than its budget of {} bytes or the device's texture size limit; it will be skipped",
+                        self.budget
+                    );
+                }
+                // glyph_brush keeps the queue when it fails, so rebuild it to drop the text.
+                let mut glyph_brush = self.glyph_brush.borrow_mut();
+                glyph_brush.to_builder().rebuild(&mut glyph_brush);
+                0
             }
             _ => unreachable!(),
         }
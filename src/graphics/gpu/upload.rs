@@ -0,0 +1,358 @@
+use super::arc::ArcBuffer;
+use crate::graphics::{memory::ResourceTracker, ResourceKind};
+use std::sync::{
+    atomic::{AtomicBool, Ordering::SeqCst},
+    Arc,
+};
+
+/// Data uploaded with [`GraphicsContext::upload`](crate::graphics::GraphicsContext::upload).
+///
+/// The data stays valid for the commands of the frame it was uploaded in; afterwards its memory
+/// is reused for later uploads.
+#[derive(Debug, Clone)]
+pub struct UploadSlice {
+    buffer: ArcBuffer,
+    offset: u64,
+    size: u64,
+}
+
+impl UploadSlice {
+    /// Returns the buffer the data was uploaded into, shared with other uploads.
+    #[inline]
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Returns where in the [`buffer`](Self::buffer) the data starts, in bytes. This is a
+    /// multiple of the device's uniform and storage buffer offset alignments, so it can be
+    /// used as a dynamic offset.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the size of the data, in bytes.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the part of the [`buffer`](Self::buffer) holding the data, e.g. for
+    /// [`RenderPass::set_vertex_buffer`](wgpu::RenderPass::set_vertex_buffer).
+    #[inline]
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(self.offset..self.offset + self.size)
+    }
+}
+
+#[derive(Debug)]
+enum ChunkState {
+    /// Can be allocated from.
+    Open,
+    /// Used by submitted work; the flag is set once the GPU is done with it.
+    InFlight(Arc<AtomicBool>),
+}
+
+#[derive(Debug)]
+struct Chunk {
+    buffer: ArcBuffer,
+    size: u64,
+    cursor: u64,
+    state: ChunkState,
+}
+
+#[derive(Debug)]
+struct PendingCopy {
+    source: ArcBuffer,
+    source_offset: u64,
+    target: ArcBuffer,
+    target_offset: u64,
+    size: u64,
+}
+
+/// Linear allocator for data uploaded every frame, in the spirit of
+/// [`StagingBelt`](wgpu::util::StagingBelt).
+///
+/// Uploads are written into large shared chunks instead of buffers of their own. Each chunk
+/// is recycled once the GPU has finished the frame that used it, which is tracked with
+/// [`Queue::on_submitted_work_done`](wgpu::Queue::on_submitted_work_done).
+///
+/// Data meant for a long lived buffer goes through [`write_buffer`](Self::write_buffer), which
+/// uploads it into a chunk and records a copy into the target before the next render pass.
+#[derive(Debug)]
+pub struct UploadBelt {
+    chunks: Vec<Chunk>,
+    copies: Vec<PendingCopy>,
+    alignment: u64,
+    resources: ResourceTracker,
+}
+
+impl UploadBelt {
+    /// Chunks are this big, unless a single upload needs more.
+    const CHUNK_SIZE: u64 = 1 << 20;
+
+    pub fn new(device: &wgpu::Device, resources: ResourceTracker) -> Self {
+        let limits = device.limits();
+        let alignment = u64::from(
+            limits
+                .min_uniform_buffer_offset_alignment
+                .max(limits.min_storage_buffer_offset_alignment),
+        )
+        .max(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        UploadBelt {
+            chunks: Vec::new(),
+            copies: Vec::new(),
+            alignment,
+            resources,
+        }
+    }
+
+    /// Uploads `data`, which is valid until the GPU has finished the current frame.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+    ) -> UploadSlice {
+        // Copies and buffer writes have to be a multiple of four bytes.
+        let size =
+            align(wgpu::COPY_BUFFER_ALIGNMENT, data.len() as u64).max(wgpu::COPY_BUFFER_ALIGNMENT);
+        let reserved = align(self.alignment, size);
+
+        self.recall(device);
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| {
+                matches!(chunk.state, ChunkState::Open) && chunk.size - chunk.cursor >= reserved
+            })
+            .unwrap_or_else(|| {
+                let size = reserved.max(Self::CHUNK_SIZE);
+                let buffer = ArcBuffer::new(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: None,
+                    size,
+                    usage: wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::COPY_DST
+                        | wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::INDEX
+                        | wgpu::BufferUsages::UNIFORM
+                        | wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                }))
+                .track(&self.resources, ResourceKind::Internal, "uploads", size);
+                self.chunks.push(Chunk {
+                    buffer,
+                    size,
+                    cursor: 0,
+                    state: ChunkState::Open,
+                });
+                self.chunks.len() - 1
+            });
+
+        let chunk = &mut self.chunks[index];
+        let offset = chunk.cursor;
+        chunk.cursor += reserved;
+        if size == data.len() as u64 {
+            queue.write_buffer(&chunk.buffer, offset, data);
+        } else {
+            let mut padded = data.to_vec();
+            padded.resize(size as usize, 0);
+            queue.write_buffer(&chunk.buffer, offset, &padded);
+        }
+
+        UploadSlice {
+            buffer: chunk.buffer.clone(),
+            offset,
+            size: data.len() as u64,
+        }
+    }
+
+    /// Uploads `data` and copies it into `target` at `offset` before the next render pass, or
+    /// at the end of the frame if there is none.
+    ///
+    /// Unlike [`Queue::write_buffer`](wgpu::Queue::write_buffer), writes to the same buffer
+    /// between two render passes are seen by the pass in between.
+    pub fn write_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &ArcBuffer,
+        offset: u64,
+        data: &[u8],
+    ) {
+        if data.is_empty() {
+            return;
+        }
+        let upload = self.upload(device, queue, data);
+        self.copies.push(PendingCopy {
+            source: upload.buffer,
+            source_offset: upload.offset,
+            target: target.clone(),
+            target_offset: offset,
+            size: align(wgpu::COPY_BUFFER_ALIGNMENT, upload.size),
+        });
+    }
+
+    /// Records the copies queued by [`write_buffer`](Self::write_buffer) so far.
+    pub fn record_copies(&mut self, cmd: &mut wgpu::CommandEncoder) {
+        for copy in self.copies.drain(..) {
+            cmd.copy_buffer_to_buffer(
+                &copy.source,
+                copy.source_offset,
+                &copy.target,
+                copy.target_offset,
+                copy.size,
+            );
+        }
+    }
+
+    /// Marks every chunk used so far as in flight, to be recycled once the GPU is done with
+    /// the work just submitted to `queue`.
+    pub fn submitted(&mut self, queue: &wgpu::Queue) {
+        let done = Arc::new(AtomicBool::new(false));
+        let mut used = false;
+        for chunk in &mut self.chunks {
+            if matches!(chunk.state, ChunkState::Open) && chunk.cursor > 0 {
+                chunk.state = ChunkState::InFlight(done.clone());
+                used = true;
+            }
+        }
+        if used {
+            queue.on_submitted_work_done(move || done.store(true, SeqCst));
+        }
+    }
+
+    /// Reopens the chunks the GPU is done with, dropping ones that were made larger than
+    /// usual for a single big upload.
+    fn recall(&mut self, device: &wgpu::Device) {
+        if !self
+            .chunks
+            .iter()
+            .any(|chunk| matches!(chunk.state, ChunkState::InFlight(_)))
+        {
+            return;
+        }
+
+        // Runs the callbacks of any finished submissions.
+        let _ = device.poll(wgpu::Maintain::Poll);
+        self.chunks.retain_mut(|chunk| {
+            let ChunkState::InFlight(done) = &chunk.state else {
+                return true;
+            };
+            if !done.load(SeqCst) {
+                return true;
+            }
+            chunk.state = ChunkState::Open;
+            chunk.cursor = 0;
+            chunk.size <= Self::CHUNK_SIZE
+        });
+    }
+
+    /// Returns how many chunks there are and how many of them are in flight.
+    #[cfg(test)]
+    pub fn chunk_counts(&self) -> (usize, usize) {
+        let in_flight = self
+            .chunks
+            .iter()
+            .filter(|chunk| matches!(chunk.state, ChunkState::InFlight(_)))
+            .count();
+        (self.chunks.len(), in_flight)
+    }
+}
+
+fn align(alignment: u64, size: u64) -> u64 {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::tests::headless_context,
+        graphics::{Canvas, Color, DrawParam, Image, ImageFormat, InstanceArray},
+    };
+
+    #[test]
+    fn headless_test_upload_belt() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        ctx.gfx.begin_frame().unwrap();
+        let first = ctx.gfx.upload(&[1, 2, 3]);
+        let second = ctx.gfx.upload(&[4; 64]);
+        let big = ctx.gfx.upload(&vec![5; 3 << 20]);
+        let limits = ctx.gfx.wgpu().device.limits();
+        assert_eq!(first.size(), 3);
+        assert_eq!(first.buffer.id(), second.buffer.id());
+        assert_eq!(
+            second.offset() % u64::from(limits.min_uniform_buffer_offset_alignment),
+            0
+        );
+        assert_eq!(big.size(), 3 << 20);
+        assert_eq!(ctx.gfx.wgpu.uploads.lock().unwrap().chunk_counts(), (2, 0));
+        ctx.gfx.end_frame().unwrap();
+        assert_eq!(ctx.gfx.wgpu.uploads.lock().unwrap().chunk_counts(), (2, 2));
+
+        // Once the GPU is done, the regular chunk is reused and the oversized one dropped.
+        let _ = ctx.gfx.wgpu().device.poll(wgpu::Maintain::Wait);
+        let again = ctx.gfx.upload(&[6; 16]);
+        assert_eq!(again.offset(), 0);
+        assert_eq!(again.buffer.id(), first.buffer.id());
+        assert_eq!(ctx.gfx.wgpu.uploads.lock().unwrap().chunk_counts(), (1, 0));
+    }
+
+    #[test]
+    fn headless_test_instance_array_uploads() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let targets =
+            [(); 2].map(|_| Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 1, 1, 1));
+        let mut instances = InstanceArray::new(&ctx, None);
+        instances.push(DrawParam::new().color(Color::RED));
+
+        // Each canvas draws the instances as they were when it was finished.
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_image(&ctx, targets[0].clone(), Color::BLACK);
+        canvas.draw(&instances, DrawParam::new());
+        canvas.finish(&mut ctx).unwrap();
+        let buffer = instances.buffer.lock().unwrap().id();
+        instances.update(0, DrawParam::new().color(Color::BLUE));
+        let mut canvas = Canvas::from_image(&ctx, targets[1].clone(), Color::BLACK);
+        canvas.draw(&instances, DrawParam::new());
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        assert_eq!(targets[0].to_pixels(&ctx).unwrap(), [255, 0, 0, 255]);
+        assert_eq!(targets[1].to_pixels(&ctx).unwrap(), [0, 0, 255, 255]);
+        // Updates within the capacity reuse the instance buffer.
+        assert_eq!(instances.buffer.lock().unwrap().id(), buffer);
+    }
+
+    #[test]
+    fn headless_test_instance_array_drawn_twice() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 2, 1, 1);
+        let mut instances = InstanceArray::new(&ctx, None);
+        instances.push(DrawParam::new().color(Color::RED));
+
+        // Each draw keeps the instances as they were when it was made, even in the same canvas.
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_image(&ctx, target.clone(), Color::BLACK);
+        canvas.draw(&instances, DrawParam::new());
+        instances.update(0, DrawParam::new().color(Color::BLUE));
+        canvas.draw(&instances, DrawParam::new().dest([1., 0.]));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        assert_eq!(
+            target.to_pixels(&ctx).unwrap(),
+            [255, 0, 0, 255, 0, 0, 255, 255]
+        );
+    }
+}
@@ -1,18 +1,24 @@
 use super::{
     context::GraphicsContext,
+    dds,
     gpu::{
         arc::{ArcBindGroup, ArcSampler, ArcTexture, ArcTextureView},
         bind_group::BindGroupBuilder,
     },
-    Canvas, Color, Draw, DrawParam, Drawable, Rect, WgpuContext,
+    ktx2,
+    memory::{texture_bytes, ResourceKind},
+    Canvas, Color, Draw, DrawParam, Drawable, Rect, Sampler, WgpuContext,
+};
+use crate::{
+    context::{Has, HasMut},
+    Context, GameError, GameResult,
 };
-use crate::{context::Has, Context, GameError, GameResult};
 use image::ImageEncoder;
 use std::{
     collections::BTreeMap,
     io::Read,
     path::Path,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, Mutex, RwLock, Weak},
 };
 
 // maintaing a massive enum of all possible texture formats?
@@ -31,7 +37,10 @@ pub struct Image {
     pub(crate) format: ImageFormat,
     pub(crate) width: u32,
     pub(crate) height: u32,
+    pub(crate) depth: u32,
+    pub(crate) dimension: wgpu::TextureViewDimension,
     pub(crate) samples: u32,
+    pub(crate) sampler: Option<Sampler>,
     pub(crate) cache: Arc<RwLock<BTreeMap<u64, ArcBindGroup>>>,
 }
 
@@ -53,7 +62,8 @@ impl Image {
             samples,
             wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_SRC,
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
         )
     }
 
@@ -73,16 +83,32 @@ impl Image {
                 [r, g, b, a]
             })
             .collect::<Vec<_>>();
-        Self::from_pixels(
-            gfx,
+        let gfx = gfx.retrieve();
+        Self::from_pixels_wgpu(
+            &gfx.wgpu,
             &pixels,
-            wgpu::TextureFormat::Rgba8UnormSrgb,
+            gfx.color_space.image_format(),
             width,
             height,
         )
     }
 
     /// Creates a new image initialized with given pixel data.
+    ///
+    /// `pixels` holds the rows of the image one after the other, without padding, so it
+    /// has to be exactly `width * height` pixels of `format` long.
+    ///
+    /// Besides RGBA images, this can make single and two channel ones, like
+    /// [`ImageFormat::R8Unorm`] for masks or [`ImageFormat::R32Float`] for heightmaps.
+    /// The default shaders draw single channel images in grayscale, with the channel
+    /// copied to red, green and blue and an alpha of 1, and two channel images in
+    /// grayscale with the second channel as alpha. Custom shaders see them as they are,
+    /// with the missing channels read as 0 and alpha as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` has the wrong length, or if the device doesn't support the format.
+    /// Use [`Image::try_from_pixels`] to get an error instead.
     pub fn from_pixels(
         gfx: &impl Has<GraphicsContext>,
         pixels: &[u8],
@@ -90,8 +116,53 @@ impl Image {
         width: u32,
         height: u32,
     ) -> Self {
+        Self::try_from_pixels(gfx, pixels, format, width, height).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Image::from_pixels`], but returns an error if `pixels` has the wrong length, or
+    /// if the device doesn't support the format. 32-bit float formats like `R32Float` need a
+    /// device that can filter them.
+    pub fn try_from_pixels(
+        gfx: &impl Has<GraphicsContext>,
+        pixels: &[u8],
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+    ) -> GameResult<Self> {
         let gfx = gfx.retrieve();
-        Self::from_pixels_wgpu(&gfx.wgpu, pixels, format, width, height)
+        let error = |msg: String| Err(GameError::RenderError(msg));
+
+        let row_size = match row_size(format, width) {
+            Some(row_size) if !format.is_depth_stencil_format() => row_size,
+            _ => return error(format!("{format:?} images can't be created from pixels")),
+        };
+        if !gfx
+            .wgpu
+            .device
+            .features()
+            .contains(format.required_features())
+        {
+            return error(format!("{format:?} images aren't supported by this device"));
+        }
+        let unfilterable =
+            format.sample_type(None) == Some(wgpu::TextureSampleType::Float { filterable: false });
+        if unfilterable && !gfx.wgpu.float32_filterable {
+            return error(format!(
+                "{format:?} images can't be drawn, as this device can't filter 32-bit floats"
+            ));
+        }
+        let (_, block_height) = format.block_dimensions();
+        let expected = row_size as usize * height.div_ceil(block_height) as usize;
+        if pixels.len() != expected {
+            return error(format!(
+                "expected {expected} bytes of {format:?} pixel data for a {width}x{height} image, got {}",
+                pixels.len()
+            ));
+        }
+
+        Ok(Self::from_pixels_wgpu(
+            &gfx.wgpu, pixels, format, width, height,
+        ))
     }
 
     pub(crate) fn from_pixels_wgpu(
@@ -117,7 +188,7 @@ impl Image {
             pixels,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(format.block_size(None).unwrap() * width), // Unwrap since it only fails with depth formats.
+                bytes_per_row: row_size(format, width),
                 rows_per_image: None,
             },
             wgpu::Extent3d {
@@ -135,39 +206,391 @@ impl Image {
     #[allow(unused_results)]
     pub fn from_path(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> GameResult<Self> {
         let gfx = gfx.retrieve();
+        let path = path.as_ref();
 
         let mut encoded = Vec::new();
-        gfx.fs.open(path)?.read_to_end(&mut encoded)?;
+        gfx.fs
+            .open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+            .map_err(|e| e.context(format!("reading image {}", path.display())))?;
 
         Self::from_bytes(gfx, encoded.as_slice())
+            .map_err(|e| e.context(format!("loading image {}", path.display())))
     }
 
     /// Creates a new image initialized with pixel data from a given encoded image (e.g. PNG or JPEG)
+    ///
+    /// The image's format depends on the [color space](GraphicsContext::color_space).
     pub fn from_bytes(gfx: &impl Has<GraphicsContext>, encoded: &[u8]) -> Result<Image, GameError> {
         let decoded = image::load_from_memory(encoded)
-            .map_err(|_| GameError::ResourceLoadError(String::from("failed to load image")))?;
+            .map_err(|e| GameError::wrap("failed to decode image", e))?;
         let rgba8 = decoded.to_rgba8();
         let (width, height) = (rgba8.width(), rgba8.height());
 
-        Ok(Self::from_pixels(
-            gfx,
+        let gfx = gfx.retrieve();
+        Ok(Self::from_pixels_wgpu(
+            &gfx.wgpu,
             rgba8.as_ref(),
-            ImageFormat::Rgba8UnormSrgb,
+            gfx.color_space.image_format(),
             width,
             height,
         ))
     }
 
-    fn new(
+    /// Creates a new image from a KTX2 file at the given path, including its full mip chain.
+    ///
+    /// See [`Image::from_ktx2_bytes`] for the supported formats.
+    #[allow(unused_results)]
+    pub fn from_ktx2(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let path = path.as_ref();
+
+        let mut encoded = Vec::new();
+        gfx.fs
+            .open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+            .and_then(|_| Self::from_ktx2_bytes(gfx, &encoded))
+            .map_err(|e| e.context(format!("loading KTX2 image {}", path.display())))
+    }
+
+    /// Creates a new image from the contents of a KTX2 file, including its full mip chain.
+    ///
+    /// Supported are uncompressed RGBA8 and the BC1 RGBA, BC3, BC5 and BC7 block compressed
+    /// formats, without supercompression. Block compressed formats need a device supporting
+    /// [`wgpu::Features::TEXTURE_COMPRESSION_BC`] (most desktop GPUs); a `RenderError` is returned otherwise.
+    /// Only plain 2D textures are supported, not arrays or cubemaps.
+    ///
+    /// BC1 RGB files are rejected: their blocks can't be uploaded as BC1 RGBA, which decodes
+    /// some of their black pixels as transparent.
+    pub fn from_ktx2_bytes(gfx: &impl Has<GraphicsContext>, data: &[u8]) -> GameResult<Self> {
+        let ktx = ktx2::parse(data)?;
+        Self::from_levels(
+            gfx.retrieve(),
+            ktx.format,
+            ktx.width,
+            ktx.height,
+            &ktx.levels,
+            "KTX2 image",
+        )
+    }
+
+    /// Creates a new image from a DDS file at the given path, including its full mip chain.
+    ///
+    /// See [`Image::from_dds_bytes`] for the supported formats.
+    #[allow(unused_results)]
+    pub fn from_dds(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let path = path.as_ref();
+
+        let mut encoded = Vec::new();
+        gfx.fs
+            .open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+            .and_then(|_| Self::from_dds_bytes(gfx, &encoded))
+            .map_err(|e| e.context(format!("loading DDS image {}", path.display())))
+    }
+
+    /// Creates a new image from the contents of a DDS file, including its full mip chain.
+    ///
+    /// Supported are the BC1 to BC7 block compressed formats (DXT1 to DXT5 in older files),
+    /// uncompressed 8-bit RGBA and BGRA, and through the DX10 header also R8, RG8 and 16-bit
+    /// and 32-bit float RGBA. As with [`Image::from_ktx2_bytes`], block compressed formats
+    /// need a device supporting [`wgpu::Features::TEXTURE_COMPRESSION_BC`]. Only plain 2D
+    /// textures are supported; the error for anything else says what the file uses, like
+    /// a cubemap or a volume texture.
+    pub fn from_dds_bytes(gfx: &impl Has<GraphicsContext>, data: &[u8]) -> GameResult<Self> {
+        let dds = dds::parse(data)?;
+        let levels: Vec<&[u8]> = dds.levels.iter().map(|level| &**level).collect();
+        Self::from_levels(
+            gfx.retrieve(),
+            dds.format,
+            dds.width,
+            dds.height,
+            &levels,
+            "DDS image",
+        )
+    }
+
+    /// Creates a new image from a Radiance HDR (`.hdr`) file at the given path, such as
+    /// a skybox or an environment map.
+    ///
+    /// See [`Image::from_hdr_bytes`].
+    #[allow(unused_results)]
+    pub fn from_hdr(
+        gfx: &impl Has<GraphicsContext>,
+        path: impl AsRef<Path>,
+        format: ImageFormat,
+    ) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let path = path.as_ref();
+
+        let mut encoded = Vec::new();
+        gfx.fs
+            .open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+            .and_then(|_| Self::from_hdr_bytes(gfx, &encoded, format))
+            .map_err(|e| e.context(format!("loading HDR image {}", path.display())))
+    }
+
+    /// Creates a new image from the contents of a Radiance HDR file, keeping its colors
+    /// above 1.0.
+    ///
+    /// `format` is either [`ImageFormat::Rgba16Float`], which is enough for most uses and
+    /// works everywhere, or [`ImageFormat::Rgba32Float`], which needs a device that can
+    /// filter 32-bit float images. The alpha of every pixel is 1.
+    pub fn from_hdr_bytes(
+        gfx: &impl Has<GraphicsContext>,
+        data: &[u8],
+        format: ImageFormat,
+    ) -> GameResult<Self> {
+        let decoder = image::codecs::hdr::HdrDecoder::new(data)
+            .map_err(|e| GameError::wrap("failed to decode HDR image", e))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()
+            .map_err(|e| GameError::wrap("failed to decode HDR image", e))?;
+        let rgba = pixels.iter().flat_map(|pixel| {
+            let [r, g, b] = pixel.0;
+            [r, g, b, 1.0]
+        });
+
+        let bytes: Vec<u8> = match format {
+            ImageFormat::Rgba16Float => rgba.flat_map(|c| f32_to_f16(c).to_le_bytes()).collect(),
+            ImageFormat::Rgba32Float => rgba.flat_map(f32::to_le_bytes).collect(),
+            _ => {
+                return Err(GameError::RenderError(format!(
+                    "HDR images can't be loaded as {format:?}, only as Rgba16Float or Rgba32Float"
+                )))
+            }
+        };
+        Self::try_from_pixels(gfx, &bytes, format, metadata.width, metadata.height)
+    }
+
+    /// Uploads a texture with a full mip chain, largest level first.
+    fn from_levels(
+        gfx: &GraphicsContext,
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        levels: &[&[u8]],
+        label: &'static str,
+    ) -> GameResult<Self> {
+        let required = format.required_features();
+        if !gfx.wgpu.device.features().contains(required) {
+            return Err(GameError::RenderError(format!(
+                "{format:?} images are not supported by this device (missing {required:?})"
+            )));
+        }
+        let unfilterable =
+            format.sample_type(None) == Some(wgpu::TextureSampleType::Float { filterable: false });
+        if unfilterable && !gfx.wgpu.float32_filterable {
+            return Err(GameError::RenderError(format!(
+                "{format:?} images can't be drawn, as this device can't filter 32-bit floats"
+            )));
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = ArcTexture::new(gfx.wgpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        }))
+        .track(
+            &gfx.wgpu.resources,
+            ResourceKind::Image,
+            label,
+            levels.iter().map(|level| level.len() as u64).sum(),
+        );
+
+        let (block_w, block_h) = format.block_dimensions();
+        let block_size = format.block_size(None).unwrap(); // Unwrap since it only fails with depth formats.
+        for (level, pixels) in levels.iter().enumerate() {
+            let level = level as u32;
+            let extent = size
+                .mip_level_size(level, wgpu::TextureDimension::D2)
+                .physical_size(format);
+            gfx.wgpu.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(extent.width / block_w * block_size),
+                    rows_per_image: Some(extent.height / block_h),
+                },
+                extent,
+            );
+        }
+
+        let view = ArcTextureView::new(
+            texture
+                .as_ref()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+
+        Ok(Image {
+            texture,
+            view,
+            format,
+            width,
+            height,
+            depth: 1,
+            dimension: wgpu::TextureViewDimension::D2,
+            samples: 1,
+            sampler: None,
+            cache: Arc::new(RwLock::new(BTreeMap::default())),
+        })
+    }
+
+    /// Creates a new blank 2D texture array with `layers` layers of the given size.
+    ///
+    /// Array images can't be drawn directly; bind them to a custom shader with
+    /// [`ShaderParamsBuilder::images`](crate::graphics::ShaderParamsBuilder::images),
+    /// where they're declared as `texture_2d_array<f32>`. Fill the layers with
+    /// [`Image::write_layer`].
+    pub fn new_array(
+        gfx: &impl Has<GraphicsContext>,
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        layers: u32,
+    ) -> Self {
+        let gfx = gfx.retrieve();
+        Self::new_layered(
+            &gfx.wgpu,
+            format,
+            (width, height, layers),
+            wgpu::TextureViewDimension::D2Array,
+            1,
+            wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        )
+    }
+
+    /// Creates a new blank 3D texture of the given size.
+    ///
+    /// Like array images, 3D images can only be bound to custom shaders, where they're
+    /// declared as `texture_3d<f32>`. Fill the depth slices with [`Image::write_layer`].
+    pub fn new_3d(
+        gfx: &impl Has<GraphicsContext>,
+        format: ImageFormat,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Self {
+        let gfx = gfx.retrieve();
+        Self::new_layered(
+            &gfx.wgpu,
+            format,
+            (width, height, depth),
+            wgpu::TextureViewDimension::D3,
+            1,
+            wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        )
+    }
+
+    /// Uploads pixel data to a single array layer, or depth slice for 3D images.
+    ///
+    /// `pixels` has to cover the whole layer, in the image's format.
+    pub fn write_layer(
+        &self,
+        gfx: &impl Has<GraphicsContext>,
+        layer: u32,
+        pixels: &[u8],
+    ) -> GameResult {
+        let gfx = gfx.retrieve();
+        if layer >= self.depth {
+            return Err(GameError::RenderError(format!(
+                "layer {layer} is out of range for an image with {} layers",
+                self.depth
+            )));
+        }
+
+        let row_size = row_size(self.format, self.width).unwrap(); // Unwrap since it only fails with depth formats.
+        let (_, block_height) = self.format.block_dimensions();
+        let rows = self.height.div_ceil(block_height);
+        let expected = (row_size * rows) as usize;
+        if pixels.len() != expected {
+            return Err(GameError::RenderError(format!(
+                "expected {expected} bytes of pixel data for one layer, got {}",
+                pixels.len()
+            )));
+        }
+
+        gfx.wgpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(row_size),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub(crate) fn new(
         wgpu: &WgpuContext,
         format: ImageFormat,
         width: u32,
         height: u32,
         samples: u32,
         usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::new_layered(
+            wgpu,
+            format,
+            (width, height, 1),
+            wgpu::TextureViewDimension::D2,
+            samples,
+            usage,
+        )
+    }
+
+    pub(crate) fn new_layered(
+        wgpu: &WgpuContext,
+        format: ImageFormat,
+        (width, height, depth): (u32, u32, u32),
+        dimension: wgpu::TextureViewDimension,
+        samples: u32,
+        usage: wgpu::TextureUsages,
     ) -> Self {
         assert!(width > 0);
         assert!(height > 0);
+        assert!(depth > 0);
         assert!(samples > 0);
 
         let texture = ArcTexture::new(wgpu.device.create_texture(&wgpu::TextureDescriptor {
@@ -175,26 +598,41 @@ impl Image {
             size: wgpu::Extent3d {
                 width,
                 height,
-                depth_or_array_layers: 1,
+                depth_or_array_layers: depth,
             },
             mip_level_count: 1,
             sample_count: samples,
-            dimension: wgpu::TextureDimension::D2,
+            dimension: if dimension == wgpu::TextureViewDimension::D3 {
+                wgpu::TextureDimension::D3
+            } else {
+                wgpu::TextureDimension::D2
+            },
             format,
             usage,
             view_formats: &[],
         }));
+        let (kind, label) = if usage.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+            (ResourceKind::CanvasImage, "canvas image")
+        } else {
+            (ResourceKind::Image, "image")
+        };
+        let texture = texture.track(
+            &wgpu.resources,
+            kind,
+            label,
+            texture_bytes(format, (width, height, depth), samples),
+        );
 
         let view =
             ArcTextureView::new(texture.as_ref().create_view(&wgpu::TextureViewDescriptor {
                 label: None,
                 format: Some(format),
-                dimension: Some(wgpu::TextureViewDimension::D2),
+                dimension: Some(dimension),
                 aspect: wgpu::TextureAspect::All,
                 base_mip_level: 0,
                 mip_level_count: Some(1),
                 base_array_layer: 0,
-                array_layer_count: Some(1),
+                array_layer_count: None,
             }));
 
         Image {
@@ -203,7 +641,10 @@ impl Image {
             format,
             width,
             height,
+            depth,
+            dimension,
             samples,
+            sampler: None,
             cache: Arc::new(RwLock::new(BTreeMap::default())),
         }
     }
@@ -216,6 +657,7 @@ impl Image {
 
     /// Reads the pixels of this `ImageView` and returns as `Vec<u8>`.
     /// The format matches the GPU image format.
+    /// For array and 3D images, only the first layer is read.
     ///
     /// **This is a very expensive operation - call sparingly.**
     pub fn to_pixels(&self, gfx: &impl Has<GraphicsContext>) -> GameResult<Vec<u8>> {
@@ -226,11 +668,15 @@ impl Image {
             )));
         }
 
-        let block_size = u64::from(self.format.block_size(None).unwrap()); // Unwrap since it only fails with depth formats.
+        let block_size = self.format.block_size(None).unwrap(); // Unwrap since it only fails with depth formats.
+
+        // Copies require rows to be aligned, so read into a padded buffer and strip the padding afterwards.
+        let row_size = block_size * self.width;
+        let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
 
         let buffer = gfx.wgpu.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: block_size * u64::from(self.width) * u64::from(self.height),
+            size: u64::from(padded_row_size) * u64::from(self.height),
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
@@ -246,7 +692,7 @@ impl Image {
                     buffer: &buffer,
                     layout: wgpu::ImageDataLayout {
                         offset: 0,
-                        bytes_per_row: Some(block_size as u32 * self.width),
+                        bytes_per_row: Some(padded_row_size),
                         rows_per_image: None,
                     },
                 },
@@ -272,7 +718,12 @@ impl Image {
             .expect("All senders dropped, this should not be possible.");
         map_result?;
 
-        let out = buffer.slice(..).get_mapped_range().to_vec();
+        let mapped = buffer.slice(..).get_mapped_range();
+        let out = mapped
+            .chunks_exact(padded_row_size as usize)
+            .flat_map(|row| &row[..row_size as usize])
+            .copied()
+            .collect();
         Ok(out)
     }
 
@@ -319,12 +770,43 @@ impl Image {
         self.format
     }
 
+    /// Returns the number of array layers, or the depth of a 3D image.
+    ///
+    /// This is 1 for regular images.
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns how this image is viewed by shaders; [`wgpu::TextureViewDimension::D2`] for regular images.
+    #[inline]
+    pub fn view_dimension(&self) -> wgpu::TextureViewDimension {
+        self.dimension
+    }
+
     /// Returns the number of MSAA samples this image has.
     #[inline]
     pub fn samples(&self) -> u32 {
         self.samples
     }
 
+    /// Sets the sampler used to draw this image, unless the canvas it's drawn on has its own set
+    /// with [`Canvas::set_sampler`](crate::graphics::Canvas::set_sampler). `None` goes back to
+    /// using the canvas' default sampler.
+    ///
+    /// This is useful for images that are always drawn the same way, such as a pixel art atlas.
+    /// Only this handle is affected, not other clones of the image.
+    #[inline]
+    pub fn set_sampler(&mut self, sampler: impl Into<Option<Sampler>>) {
+        self.sampler = sampler.into();
+    }
+
+    /// Returns the sampler set with [`Image::set_sampler`], if any.
+    #[inline]
+    pub fn sampler(&self) -> Option<Sampler> {
+        self.sampler
+    }
+
     /// Returns the width (in pixels) of the image.
     #[inline]
     pub fn width(&self) -> u32 {
@@ -337,6 +819,144 @@ impl Image {
         self.height
     }
 
+    /// Copies the pixels inside `src_rect` of `src` into this image, with the top left
+    /// corner at `dst_origin`, without drawing them.
+    ///
+    /// The copy happens in order with the canvases of the current frame, or right away
+    /// outside of a frame. Both images must have the same format and can't be multisampled,
+    /// `src_rect` must be in whole pixels, and both rectangles must be inside their images;
+    /// otherwise `GameError::RenderError` is returned. Only the first layer of array and 3D
+    /// images is copied.
+    pub fn blit_from(
+        &self,
+        gfx: &mut impl HasMut<GraphicsContext>,
+        src: &Image,
+        src_rect: Rect,
+        dst_origin: impl Into<mint::Point2<u32>>,
+    ) -> GameResult {
+        let gfx = gfx.retrieve_mut();
+        let dst_origin = dst_origin.into();
+        let error = |msg: &str| Err(GameError::RenderError(format!("cannot blit image: {msg}")));
+
+        if src.format != self.format {
+            return error(&format!(
+                "the formats {:?} and {:?} differ",
+                src.format, self.format
+            ));
+        }
+        if src.samples > 1 || self.samples > 1 {
+            return error("multisampled images can't be copied; resolve them with a canvas");
+        }
+        if std::ptr::eq(src.texture.as_ref(), self.texture.as_ref()) {
+            return error("an image can't be copied into itself; copy it first");
+        }
+        if !src.texture.usage().contains(wgpu::TextureUsages::COPY_SRC)
+            || !self.texture.usage().contains(wgpu::TextureUsages::COPY_DST)
+        {
+            return error("the images weren't created to be copied between");
+        }
+        let whole = [src_rect.x, src_rect.y, src_rect.w, src_rect.h]
+            .iter()
+            .all(|v| *v >= 0. && v.fract() == 0.);
+        if !whole {
+            return error(&format!("{src_rect:?} isn't in whole pixels"));
+        }
+        let (x, y, w, h) = (
+            src_rect.x as u32,
+            src_rect.y as u32,
+            src_rect.w as u32,
+            src_rect.h as u32,
+        );
+        let fits =
+            |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(x, w, src.width) || !fits(y, h, src.height) {
+            return error(&format!(
+                "{src_rect:?} isn't inside the {}x{} source",
+                src.width, src.height
+            ));
+        }
+        if !fits(dst_origin.x, w, self.width) || !fits(dst_origin.y, h, self.height) {
+            return error(&format!(
+                "a {w}x{h} rectangle at {dst_origin:?} isn't inside the {}x{} destination",
+                self.width, self.height
+            ));
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        let (block_w, block_h) = self.format.block_dimensions();
+        if [x, w, dst_origin.x].iter().any(|v| v % block_w != 0)
+            || [y, h, dst_origin.y].iter().any(|v| v % block_h != 0)
+        {
+            return error("compressed images can only be copied in whole blocks");
+        }
+
+        copy_textures(
+            gfx,
+            wgpu::ImageCopyTexture {
+                texture: src.texture.as_ref(),
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTexture {
+                texture: self.texture.as_ref(),
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: dst_origin.x,
+                    y: dst_origin.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns a new image with a copy of the pixels (and all layers) of this one.
+    ///
+    /// Like [`blit_from`](Self::blit_from), the copy happens in order with the canvases
+    /// of the current frame. Multisampled images can't be copied.
+    pub fn copy(&self, gfx: &mut impl HasMut<GraphicsContext>) -> GameResult<Image> {
+        let gfx = gfx.retrieve_mut();
+        if self.samples > 1 {
+            return Err(GameError::RenderError(String::from(
+                "cannot copy a multisampled image; resolve this image with a canvas",
+            )));
+        }
+        if !self.texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(GameError::RenderError(String::from(
+                "cannot copy an image that wasn't created to be copied from",
+            )));
+        }
+        let mut copy = Image::new_layered(
+            &gfx.wgpu,
+            self.format,
+            (self.width, self.height, self.depth),
+            self.dimension,
+            1,
+            self.texture.usage() | wgpu::TextureUsages::COPY_DST,
+        );
+        copy.sampler = self.sampler;
+        copy_textures(
+            gfx,
+            self.texture.as_image_copy(),
+            copy.texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: self.depth,
+            },
+        );
+        Ok(copy)
+    }
+
     /// Helper function that calculates a sub-rectangle of this image in UV coordinates, given pixel coordinates.
     pub fn uv_rect(&self, x: u32, y: u32, w: u32, h: u32) -> Rect {
         Rect {
@@ -371,6 +991,79 @@ impl Image {
     }
 }
 
+/// The size in bytes of one row of pixels (or blocks, for compressed formats) of an image,
+/// or `None` for depth formats.
+fn row_size(format: ImageFormat, width: u32) -> Option<u32> {
+    let (block_width, _) = format.block_dimensions();
+    Some(format.block_size(None)? * width.div_ceil(block_width))
+}
+
+/// Converts to a half precision float, rounding to the nearest one. Values too large
+/// for half precision become infinite.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinity, and NaN stays NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal, or too small and flushed to zero.
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = u32::from(rest > halfway || (rest == halfway && half & 1 == 1));
+        return sign | (half + round) as u16;
+    }
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    // Rounding up can carry into the exponent, which also correctly gives infinity.
+    let round = u32::from(rest > 0x1000 || (rest == 0x1000 && half & 1 == 1));
+    sign | (half + round) as u16
+}
+
+/// How many color channels the default shaders should read from an image of this format.
+pub(crate) fn color_channels(format: ImageFormat) -> u32 {
+    use wgpu::TextureFormat as F;
+    match format {
+        F::R8Unorm | F::R8Snorm | F::R16Unorm | F::R16Snorm | F::R16Float | F::R32Float => 1,
+        F::Rg8Unorm | F::Rg8Snorm | F::Rg16Unorm | F::Rg16Snorm | F::Rg16Float | F::Rg32Float => 2,
+        _ => 4,
+    }
+}
+
+/// Copies between textures as part of the current frame, or right away outside of one.
+fn copy_textures(
+    gfx: &mut GraphicsContext,
+    src: wgpu::ImageCopyTexture,
+    dst: wgpu::ImageCopyTexture,
+    size: wgpu::Extent3d,
+) {
+    match gfx.fcx {
+        Some(ref mut fcx) => fcx.cmd.copy_texture_to_texture(src, dst, size),
+        None => {
+            let mut encoder = gfx
+                .wgpu
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.copy_texture_to_texture(src, dst, size);
+            let _ = gfx.wgpu.queue.submit([encoder.finish()]);
+        }
+    }
+}
+
 impl Drawable for Image {
     fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
         canvas.push_draw(
@@ -393,6 +1086,197 @@ impl Drawable for Image {
     }
 }
 
+type DecodeSlot = Mutex<Option<GameResult<::image::RgbaImage>>>;
+
+struct DecodeJob {
+    encoded: Vec<u8>,
+    slot: Weak<DecodeSlot>,
+}
+
+/// A few worker threads decoding images for [`Image::from_path_async`].
+///
+/// The threads exit once the pool (and with it the sending half of the job queue) is dropped.
+pub(crate) struct DecodePool {
+    sender: Mutex<mpsc::Sender<DecodeJob>>,
+}
+
+impl DecodePool {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<DecodeJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let threads = std::thread::available_parallelism().map_or(2, |n| n.get().min(4));
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("ggez image decoder {i}"))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap(/* only poisoned if a worker panicked */).recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    // The handle was dropped while the job was queued; don't bother decoding.
+                    if job.slot.strong_count() == 0 {
+                        continue;
+                    }
+                    let result = ::image::load_from_memory(&job.encoded)
+                        .map(|image| image.to_rgba8())
+                        .map_err(|e| GameError::wrap("failed to decode image", e));
+                    if let Some(slot) = job.slot.upgrade() {
+                        *slot.lock().unwrap() = Some(result);
+                    }
+                });
+            if let Err(e) = spawned {
+                warn!("Failed to spawn image decoding thread: {e}");
+            }
+        }
+
+        DecodePool {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    fn decode(&self, encoded: Vec<u8>) -> Arc<DecodeSlot> {
+        let slot = Arc::new(Mutex::new(None));
+        let job = DecodeJob {
+            encoded,
+            slot: Arc::downgrade(&slot),
+        };
+        if self.sender.lock().unwrap().send(job).is_err() {
+            *slot.lock().unwrap() = Some(Err(GameError::ResourceLoadError(String::from(
+                "image decoding threads are not running",
+            ))));
+        }
+        slot
+    }
+}
+
+impl Image {
+    /// Starts loading the image at the given path in the background.
+    ///
+    /// The file is read on the calling thread, but decoding happens on a small pool of worker
+    /// threads. Call [`ImageHandle::poll`] (e.g. once per `update`) to upload the image once
+    /// it's ready. Dropping the handle before then cancels the decode if it hasn't started yet.
+    pub fn from_path_async(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> ImageHandle {
+        let gfx = gfx.retrieve();
+        let path = path.as_ref();
+
+        let mut encoded = Vec::new();
+        match gfx
+            .fs
+            .open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+        {
+            Ok(_) => Self::from_bytes_async(gfx, encoded),
+            Err(e) => ImageHandle {
+                state: ImageHandleState::Failed(Some(
+                    e.context(format!("reading image {}", path.display())),
+                )),
+                placeholder: None,
+            },
+        }
+    }
+
+    /// Starts decoding the given encoded image (e.g. PNG or JPEG) in the background.
+    ///
+    /// See [`Image::from_path_async`].
+    pub fn from_bytes_async(gfx: &impl Has<GraphicsContext>, encoded: Vec<u8>) -> ImageHandle {
+        let gfx = gfx.retrieve();
+        let slot = gfx.decode_pool.get_or_init(DecodePool::new).decode(encoded);
+        ImageHandle {
+            state: ImageHandleState::Pending(slot),
+            placeholder: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ImageHandleState {
+    Pending(Arc<DecodeSlot>),
+    Ready(Image),
+    /// The error is taken by the first `poll` that sees it.
+    Failed(Option<GameError>),
+}
+
+/// An image that is being loaded in the background, see [`Image::from_path_async`].
+///
+/// Drawing the handle draws the image once it has been [polled](ImageHandle::poll) successfully,
+/// and the [placeholder](ImageHandle::with_placeholder) (if any) until then.
+#[derive(Debug)]
+pub struct ImageHandle {
+    state: ImageHandleState,
+    placeholder: Option<Image>,
+}
+
+impl ImageHandle {
+    /// Sets an image to draw in place of this one while it is still loading, or if it failed to load.
+    #[must_use]
+    pub fn with_placeholder(mut self, placeholder: Image) -> Self {
+        self.placeholder = Some(placeholder);
+        self
+    }
+
+    /// Checks whether the image finished decoding, uploading it to the GPU if it did.
+    ///
+    /// Returns `None` while the image is still being decoded. Once it's loaded, every call
+    /// returns the image. If loading failed, the error is returned once, and a generic
+    /// `ResourceLoadError` afterwards.
+    pub fn poll(&mut self, gfx: &impl Has<GraphicsContext>) -> Option<GameResult<Image>> {
+        let result = match self.state {
+            ImageHandleState::Pending(ref slot) => slot.lock().unwrap().take()?,
+            ImageHandleState::Ready(ref image) => return Some(Ok(image.clone())),
+            ImageHandleState::Failed(ref mut error) => {
+                return Some(Err(error.take().unwrap_or_else(|| {
+                    GameError::ResourceLoadError(String::from("image failed to load"))
+                })))
+            }
+        };
+
+        match result {
+            Ok(rgba8) => {
+                let gfx = gfx.retrieve();
+                let image = Image::from_pixels_wgpu(
+                    &gfx.wgpu,
+                    rgba8.as_ref(),
+                    gfx.color_space.image_format(),
+                    rgba8.width(),
+                    rgba8.height(),
+                );
+                self.state = ImageHandleState::Ready(image.clone());
+                Some(Ok(image))
+            }
+            Err(e) => {
+                self.state = ImageHandleState::Failed(None);
+                Some(Err(e))
+            }
+        }
+    }
+
+    /// Returns the image if it has been loaded by [`ImageHandle::poll`].
+    pub fn image(&self) -> Option<&Image> {
+        match self.state {
+            ImageHandleState::Ready(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    fn current(&self) -> Option<&Image> {
+        self.image().or(self.placeholder.as_ref())
+    }
+}
+
+impl Drawable for ImageHandle {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        if let Some(image) = self.current() {
+            image.draw(canvas, param);
+        }
+    }
+
+    fn dimensions(&self, gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        self.current()?.dimensions(gfx)
+    }
+}
+
 /// An image which is sized relative to the screen.
 /// This is primarily for canvas images.
 #[derive(Debug, Clone)]
@@ -442,7 +1326,7 @@ impl ScreenImage {
 
     fn size(gfx: &impl Has<GraphicsContext>, (width, height): (f32, f32)) -> (u32, u32) {
         let gfx = gfx.retrieve();
-        let size = gfx.window.inner_size();
+        let size = gfx.inner_size();
         let width = (size.width as f32 * width) as u32;
         let height = (size.height as f32 * height) as u32;
         (width.max(1), height.max(1))
@@ -458,3 +1342,278 @@ impl ScreenImage {
         Image::new_canvas_image(gfx, format, width, height, samples)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::tests::headless_context,
+        graphics::{
+            dds, ktx2, Canvas, Color, DrawParam, Drawable, Image, ImageFormat, Quad, Rect, Sampler,
+            ShaderBuilder, ShaderParamsBuilder,
+        },
+    };
+    use crevice::std140::AsStd140;
+
+    #[derive(AsStd140)]
+    struct Layer {
+        index: f32,
+    }
+
+    #[test]
+    fn headless_test_sample_array_image() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let array = Image::new_array(&ctx, ImageFormat::Rgba8Unorm, 2, 2, 2);
+        assert_eq!(array.depth(), 2);
+        array
+            .write_layer(&ctx, 0, &[255, 0, 0, 255].repeat(4))
+            .unwrap();
+        array
+            .write_layer(&ctx, 1, &[0, 255, 0, 255].repeat(4))
+            .unwrap();
+        assert!(array.write_layer(&ctx, 2, &[0; 16]).is_err());
+        assert!(array.write_layer(&ctx, 1, &[0; 4]).is_err());
+
+        let shader = ShaderBuilder::new()
+            .fragment_code(
+                "
+                struct VertexOutput {
+                    @builtin(position) position: vec4<f32>,
+                    @location(0) uv: vec2<f32>,
+                    @location(1) color: vec4<f32>,
+                }
+
+                struct Layer {
+                    index: f32,
+                }
+
+                @group(3) @binding(0)
+                var<uniform> layer: Layer;
+                @group(3) @binding(1)
+                var t: texture_2d_array<f32>;
+                @group(3) @binding(2)
+                var s: sampler;
+
+                @fragment
+                fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+                    return textureSample(t, s, in.uv, i32(layer.index));
+                }
+                ",
+            )
+            .build(&ctx.gfx)
+            .unwrap();
+        let params = ShaderParamsBuilder::new(&Layer { index: 1. })
+            .images(&[&array], &[Sampler::nearest_clamp()], false)
+            .build(&mut ctx);
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.set_shader(&shader);
+        canvas.set_shader_params(&params);
+        canvas.draw(&Quad, DrawParam::new().scale([16., 8.]));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        assert_eq!(pixels[..4], [0, 255, 0, 255]);
+
+        // drawing it directly isn't possible with the default pipelines
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.draw(&array, DrawParam::new());
+        assert!(canvas.finish(&mut ctx).is_err());
+        ctx.gfx.end_frame().unwrap();
+    }
+
+    #[test]
+    fn headless_test_image_from_ktx2() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+
+        let level0 = [0, 0, 255, 255].repeat(4 * 2);
+        let level1 = [255, 0, 0, 255].repeat(2);
+        let file = ktx2::tests::encode(37, 4, 2, &[&level0, &level1]);
+
+        let image = Image::from_ktx2_bytes(&ctx, &file).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(image.format(), ImageFormat::Rgba8Unorm);
+        assert_eq!(image.texture.mip_level_count(), 2);
+        assert_eq!(image.to_pixels(&ctx).unwrap(), level0);
+
+        // BC7, 4x4: one 16 byte block
+        let bc7 = ktx2::tests::encode(145, 4, 4, &[&[0; 16]]);
+        let supported = ctx
+            .gfx
+            .wgpu()
+            .device
+            .features()
+            .contains(wgpu::Features::TEXTURE_COMPRESSION_BC);
+        assert_eq!(Image::from_ktx2_bytes(&ctx, &bc7).is_ok(), supported);
+    }
+
+    #[test]
+    fn headless_test_image_from_dds_and_hdr() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+
+        let level0 = [10, 20, 30, 255].repeat(4 * 2);
+        let level1 = [40, 50, 60, 255].repeat(2);
+        let file = dds::tests::encode(b"DX10", 28, 4, 2, &[&level0, &level1]);
+        let image = Image::from_dds_bytes(&ctx, &file).unwrap();
+        assert_eq!(image.format(), ImageFormat::Rgba8Unorm);
+        assert_eq!(image.texture.mip_level_count(), 2);
+        assert_eq!(image.to_pixels(&ctx).unwrap(), level0);
+
+        // Two pixels of 1.0 and 2.0, stored as RGBE without run length encoding.
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        hdr.extend_from_slice(&[128, 128, 128, 129, 128, 128, 128, 130]);
+        let image = Image::from_hdr_bytes(&ctx, &hdr, ImageFormat::Rgba16Float).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        let one = 0x3c00u16.to_le_bytes();
+        let two = 0x4000u16.to_le_bytes();
+        assert_eq!(
+            image.to_pixels(&ctx).unwrap(),
+            [one, one, one, one, two, two, two, one].concat()
+        );
+        assert!(Image::from_hdr_bytes(&ctx, &hdr, ImageFormat::Rgba8Unorm).is_err());
+        assert!(Image::from_hdr_bytes(&ctx, b"not an hdr file", ImageFormat::Rgba16Float).is_err());
+    }
+
+    #[test]
+    fn headless_test_f32_to_f16() {
+        use super::f32_to_f16;
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(0.1), 0x2e66);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f32_to_f16(6e-8), 0x0001);
+        assert_eq!(f32_to_f16(1e-9), 0);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7e00, 0x7e00);
+    }
+
+    #[test]
+    fn headless_test_image_from_bytes_async() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+
+        let mut png = Vec::new();
+        ::image::RgbaImage::from_pixel(3, 2, ::image::Rgba([1, 2, 3, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                ::image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        // dropped right away; must not keep anything alive or block the others
+        drop(Image::from_bytes_async(&ctx, png.clone()));
+
+        let placeholder = Image::from_color(&ctx, 1, 1, None);
+        let mut handle = Image::from_bytes_async(&ctx, png).with_placeholder(placeholder);
+        assert_eq!(handle.dimensions(&ctx).unwrap().w, 1.);
+
+        let start = std::time::Instant::now();
+        let image = loop {
+            if let Some(result) = handle.poll(&ctx) {
+                break result.unwrap();
+            }
+            assert!(start.elapsed().as_secs() < 10, "decoding never finished");
+            std::thread::yield_now();
+        };
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.to_pixels(&ctx).unwrap()[..4], [1, 2, 3, 255]);
+        assert_eq!(handle.dimensions(&ctx).unwrap().w, 3.);
+        assert!(handle.poll(&ctx).unwrap().is_ok());
+
+        let mut missing = Image::from_path_async(&ctx, "/does-not-exist.png");
+        assert!(missing.poll(&ctx).unwrap().is_err());
+        assert!(missing.dimensions(&ctx).is_none());
+    }
+
+    #[test]
+    fn headless_test_blit_image() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // Each pixel of the source holds its own index.
+        let pixels = (0..16u8).flat_map(|i| [i, 0, 0, 255]).collect::<Vec<_>>();
+        let src = Image::from_pixels(&ctx, &pixels, ImageFormat::Rgba8Unorm, 4, 4);
+        let dst = Image::from_pixels(&ctx, &[0; 4 * 3 * 3], ImageFormat::Rgba8Unorm, 3, 3);
+        dst.blit_from(&mut ctx, &src, Rect::new(1., 1., 2., 2.), [1, 0])
+            .unwrap();
+        let red = |image: &Image, ctx: &crate::Context| {
+            let pixels = image.to_pixels(ctx).unwrap();
+            pixels.chunks(4).map(|p| p[0]).collect::<Vec<_>>()
+        };
+        assert_eq!(red(&dst, &ctx), [0, 5, 6, 0, 9, 10, 0, 0, 0]);
+
+        let copy = src.copy(&mut ctx).unwrap();
+        assert_eq!(copy.to_pixels(&ctx).unwrap(), pixels);
+
+        let srgb = Image::from_pixels(&ctx, &pixels, ImageFormat::Rgba8UnormSrgb, 4, 4);
+        let whole = Rect::new(0., 0., 2., 2.);
+        assert!(dst.blit_from(&mut ctx, &srgb, whole, [0, 0]).is_err());
+        assert!(dst.blit_from(&mut ctx, &src, whole, [2, 0]).is_err());
+        assert!(dst
+            .blit_from(&mut ctx, &src, Rect::new(3., 0., 2., 2.), [0, 0])
+            .is_err());
+        assert!(dst
+            .blit_from(&mut ctx, &src, Rect::new(0.5, 0., 2., 2.), [0, 0])
+            .is_err());
+        // Rectangles reaching past u32::MAX are errors too, not overflows.
+        assert!(matches!(
+            dst.blit_from(&mut ctx, &src, whole, [u32::MAX, 0]),
+            Err(crate::GameError::RenderError(_))
+        ));
+        assert!(matches!(
+            dst.blit_from(&mut ctx, &src, Rect::new(1., 0., 1e12, 2.), [0, 0]),
+            Err(crate::GameError::RenderError(_))
+        ));
+        assert!(copy
+            .blit_from(&mut ctx, &copy.clone(), whole, [0, 0])
+            .is_err());
+        assert_eq!(red(&dst, &ctx), [0, 5, 6, 0, 9, 10, 0, 0, 0]);
+    }
+
+    #[test]
+    fn headless_test_gray_images() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 2, 1, 1);
+        let draw = |image: &Image, ctx: &mut crate::Context| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_image(ctx, target.clone(), Color::BLACK);
+            canvas.set_blend_mode(crate::graphics::BlendMode::REPLACE);
+            canvas.draw(image, DrawParam::new());
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            target.to_pixels(ctx).unwrap()
+        };
+
+        let mask = Image::from_pixels(&ctx, &[0, 200], ImageFormat::R8Unorm, 2, 1);
+        assert_eq!(draw(&mask, &mut ctx), [0, 0, 0, 255, 200, 200, 200, 255]);
+        let luma_alpha = Image::from_pixels(&ctx, &[50, 255, 100, 0], ImageFormat::Rg8Unorm, 2, 1);
+        assert_eq!(
+            draw(&luma_alpha, &mut ctx),
+            [50, 50, 50, 255, 100, 100, 100, 0]
+        );
+        let heights = [0.25f32, 1.0].map(f32::to_ne_bytes).concat();
+        if let Ok(heights) = Image::try_from_pixels(&ctx, &heights, ImageFormat::R32Float, 2, 1) {
+            let pixels = draw(&heights, &mut ctx);
+            assert!(pixels[0].abs_diff(64) <= 1);
+            assert_eq!(pixels[4..], [255, 255, 255, 255]);
+        }
+
+        assert!(Image::try_from_pixels(&ctx, &[0; 3], ImageFormat::R8Unorm, 2, 1).is_err());
+        assert!(Image::try_from_pixels(&ctx, &[0; 8], ImageFormat::Rg8Unorm, 2, 1).is_err());
+        assert!(Image::try_from_pixels(&ctx, &[0; 8], ImageFormat::Depth32Float, 2, 1).is_err());
+    }
+}
@@ -5,14 +5,15 @@ use super::{
     draw::{DrawParam, DrawUniforms, Std140DrawUniforms},
     gpu::arc::{ArcBindGroup, ArcBindGroupLayout, ArcBuffer},
     internal_canvas::InstanceArrayView,
-    transform_rect, Canvas, Draw, Drawable, Image, Mesh, Rect, WgpuContext,
+    transform_rect, Canvas, ColorSpaceMode, Draw, Drawable, Image, Mesh, Rect, ResourceKind,
+    WgpuContext,
 };
 use crevice::std140::AsStd140;
 use std::{
     collections::BTreeMap,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst},
-        Mutex,
+        Arc, Mutex,
     },
 };
 
@@ -29,6 +30,7 @@ pub struct InstanceArray {
     pub(crate) bind_layout: ArcBindGroupLayout,
     pub(crate) image: Image,
     pub(crate) ordered: bool,
+    color_space: ColorSpaceMode,
     dirty: AtomicBool,
     capacity: AtomicUsize,
     uniforms: Vec<Std140DrawUniforms>,
@@ -50,6 +52,7 @@ impl InstanceArray {
             image.into().unwrap_or_else(|| gfx.white_image.clone()),
             DEFAULT_CAPACITY,
             false,
+            gfx.color_space,
         )
     }
 
@@ -64,6 +67,7 @@ impl InstanceArray {
             image.into().unwrap_or_else(|| gfx.white_image.clone()),
             DEFAULT_CAPACITY,
             true,
+            gfx.color_space,
         )
     }
 
@@ -73,30 +77,45 @@ impl InstanceArray {
         image: Image,
         capacity: usize,
         ordered: bool,
+        color_space: ColorSpaceMode,
     ) -> Self {
         assert!(capacity > 0);
 
+        let size = DrawUniforms::std140_size_static() as u64 * capacity as u64;
         let buffer = ArcBuffer::new(wgpu.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: DrawUniforms::std140_size_static() as u64 * capacity as u64,
+            size,
             usage: wgpu::BufferUsages::STORAGE
                 | wgpu::BufferUsages::COPY_DST
                 | wgpu::BufferUsages::COPY_SRC,
             mapped_at_creation: false,
-        }));
+        }))
+        .track(
+            &wgpu.resources,
+            ResourceKind::InstanceBuffer,
+            "instances",
+            size,
+        );
 
+        let size = if ordered {
+            std::mem::size_of::<u32>() as u64 * capacity as u64
+        } else {
+            4 // min for layout
+        };
         let indices = ArcBuffer::new(wgpu.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: if ordered {
-                std::mem::size_of::<u32>() as u64 * capacity as u64
-            } else {
-                4 // min for layout
-            },
+            size,
             usage: wgpu::BufferUsages::STORAGE
                 | wgpu::BufferUsages::COPY_SRC
                 | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
-        }));
+        }))
+        .track(
+            &wgpu.resources,
+            ResourceKind::InstanceBuffer,
+            "instance order",
+            size,
+        );
 
         let bind_group = BindGroupBuilder::new()
             .buffer(
@@ -132,6 +151,7 @@ impl InstanceArray {
             bind_layout,
             image,
             ordered,
+            color_space,
             dirty: AtomicBool::new(false),
             capacity: AtomicUsize::new(capacity),
             uniforms,
@@ -145,10 +165,11 @@ impl InstanceArray {
         self.params.clear();
         self.params.extend(instances);
         self.uniforms.clear();
+        let color_space = self.color_space;
         self.uniforms.extend(
             self.params
                 .iter()
-                .map(|x| DrawUniforms::from_param(x, None).as_std140()),
+                .map(|x| DrawUniforms::from_param(x, None, color_space).as_std140()),
         );
     }
 
@@ -156,7 +177,7 @@ impl InstanceArray {
     pub fn push(&mut self, instance: DrawParam) {
         self.dirty.store(true, SeqCst);
         self.uniforms
-            .push(DrawUniforms::from_param(&instance, None).as_std140());
+            .push(DrawUniforms::from_param(&instance, None, self.color_space).as_std140());
         self.params.push(instance);
     }
 
@@ -168,7 +189,7 @@ impl InstanceArray {
             .and_then(|x| Some((x, self.params.get_mut(index as usize)?)))
         {
             self.dirty.store(true, SeqCst);
-            *uniform = DrawUniforms::from_param(&instance, None).as_std140();
+            *uniform = DrawUniforms::from_param(&instance, None, self.color_space).as_std140();
             *param = instance;
         }
     }
@@ -200,25 +221,40 @@ impl InstanceArray {
         }
 
         let len = self.uniforms.len();
-        //if len > self.capacity.load(SeqCst) {
-        let mut resized = InstanceArray::new_wgpu(
-            wgpu,
-            self.bind_layout.clone(),
-            self.image.clone(),
-            len,
-            self.ordered,
-        );
-        *self.buffer.lock().map_err(|_| GameError::LockError)? =
-            resized.buffer.get_mut().unwrap().clone();
-        *self.indices.lock().map_err(|_| GameError::LockError)? =
-            resized.indices.get_mut().unwrap().clone();
-        *self.bind_group.lock().map_err(|_| GameError::LockError)? =
-            resized.bind_group.get_mut().unwrap().clone();
-        self.capacity.store(len, SeqCst);
-        //}
+        // Draws that haven't been recorded yet and copies that haven't been made yet hold on to
+        // the current buffer. Writing into it would change what they draw, so they keep it and
+        // the array moves on to a new one.
+        let shared =
+            Arc::strong_count(&self.buffer.lock().map_err(|_| GameError::LockError)?.handle) > 1;
+        if len > self.capacity.load(SeqCst) || shared {
+            let capacity = len.next_power_of_two().max(self.capacity.load(SeqCst));
+            let mut resized = InstanceArray::new_wgpu(
+                wgpu,
+                self.bind_layout.clone(),
+                self.image.clone(),
+                capacity,
+                self.ordered,
+                self.color_space,
+            );
+            *self.buffer.lock().map_err(|_| GameError::LockError)? =
+                resized.buffer.get_mut().unwrap().clone();
+            *self.indices.lock().map_err(|_| GameError::LockError)? =
+                resized.indices.get_mut().unwrap().clone();
+            *self.bind_group.lock().map_err(|_| GameError::LockError)? =
+                resized.bind_group.get_mut().unwrap().clone();
+            self.capacity.store(capacity, SeqCst);
+        }
 
-        wgpu.queue.write_buffer(
-            &self.buffer.lock().unwrap(),
+        // The new data is copied in before the next render pass.
+        let mut uploads = wgpu.uploads.lock().map_err(|_| GameError::LockError)?;
+        uploads.write_buffer(
+            &wgpu.device,
+            &wgpu.queue,
+            &self
+                .buffer
+                .lock()
+                .map_err(|_| GameError::LockError)?
+                .clone(),
             0,
             bytemuck::cast_slice(self.uniforms.as_slice()),
         );
@@ -229,8 +265,14 @@ impl InstanceArray {
                 layers.entry(param.z).or_default().push(i as u32);
             }
             let indices = layers.into_values().flatten().collect::<Vec<_>>();
-            wgpu.queue.write_buffer(
-                &self.indices.lock().unwrap(),
+            uploads.write_buffer(
+                &wgpu.device,
+                &wgpu.queue,
+                &self
+                    .indices
+                    .lock()
+                    .map_err(|_| GameError::LockError)?
+                    .clone(),
                 0,
                 bytemuck::cast_slice(indices.as_slice()),
             );
@@ -255,6 +297,7 @@ impl InstanceArray {
             self.image.clone(),
             new_capacity,
             self.ordered,
+            self.color_space,
         );
         self.buffer = resized.buffer;
         self.indices = resized.indices;
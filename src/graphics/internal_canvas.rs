@@ -8,24 +8,28 @@ use super::{
         pipeline::{PipelineCache, RenderPipelineInfo},
         text::{TextRenderer, TextVertex},
     },
-    image::Image,
+    image::{color_channels, Image},
     mesh::{Mesh, Vertex},
+    profiler::FrameStatsCounter,
     sampler::{Sampler, SamplerCache},
     shader::Shader,
-    BlendMode, Color, InstanceArray, LinearColor, Rect, Text, Transform, WgpuContext,
+    text::batch_bounds,
+    BlendMode, Color, ColorSpaceMode, InstanceArray, PipelineCompilation, Rect, Text, TextOnPath,
+    Transform, WgpuContext,
 };
 use crate::{GameError, GameResult};
 use crevice::std140::AsStd140;
-use std::{collections::HashMap, hash::Hash};
+use std::{collections::HashMap, hash::Hash, sync::Arc};
 
 /// A canvas represents a render pass and is how you render primitives such as meshes and text onto images.
 #[allow(missing_debug_implementations)]
 pub struct InternalCanvas<'a> {
-    wgpu: &'a WgpuContext,
+    wgpu: &'a Arc<WgpuContext>,
     arenas: &'a FrameArenas,
     bind_group_cache: &'a mut BindGroupCache,
     pipeline_cache: &'a mut PipelineCache,
     sampler_cache: &'a mut SamplerCache,
+    stats: &'a mut FrameStatsCounter,
     text_renderer: &'a mut TextRenderer,
     fonts: &'a HashMap<String, glyph_brush::FontId>,
     uniform_arena: &'a mut GrowingBufferArena,
@@ -43,17 +47,20 @@ pub struct InternalCanvas<'a> {
     samples: u32,
     format: wgpu::TextureFormat,
     text_uniforms: ArenaAllocation,
+    color_space: ColorSpaceMode,
 
-    draw_sm: ArcShaderModule,
-    instance_sm: ArcShaderModule,
-    instance_unordered_sm: ArcShaderModule,
-    text_sm: ArcShaderModule,
+    builtin: BuiltinShaders,
 
     transform: glam::Mat4,
     curr_image: Option<ArcTextureView>,
     curr_sampler: Sampler,
-    next_sampler: Sampler,
+    /// Sampler set on the canvas, which takes priority over the image's own.
+    next_sampler: Option<Sampler>,
+    src_clamp: bool,
+    /// Color channels of the image being drawn, for picking the default fragment shader.
+    image_channels: u32,
     premul_text: bool,
+    premul_meshes: bool,
 }
 
 impl<'a> InternalCanvas<'a> {
@@ -66,6 +73,9 @@ impl<'a> InternalCanvas<'a> {
             return Err(GameError::RenderError(String::from("non-MSAA rendering requires an image with exactly 1 sample, for this image use Canvas::from_msaa instead")));
         }
 
+        let clear = clear
+            .into()
+            .map(|color| gfx.color_space.shader_color(color));
         Self::new(gfx, 1, image.format(), |cmd| {
             cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -73,9 +83,9 @@ impl<'a> InternalCanvas<'a> {
                     view: image.view.as_ref(),
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: match clear.into() {
+                        load: match clear {
                             None => wgpu::LoadOp::Load,
-                            Some(color) => wgpu::LoadOp::Clear(LinearColor::from(color).into()),
+                            Some(color) => wgpu::LoadOp::Clear(color.into()),
                         },
                         store: true,
                     },
@@ -109,6 +119,9 @@ impl<'a> InternalCanvas<'a> {
             )));
         }
 
+        let clear = clear
+            .into()
+            .map(|color| gfx.color_space.shader_color(color));
         Self::new(gfx, msaa_image.samples(), msaa_image.format(), |cmd| {
             cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -116,9 +129,9 @@ impl<'a> InternalCanvas<'a> {
                     view: msaa_image.view.as_ref(),
                     resolve_target: Some(resolve_image.view.as_ref()),
                     ops: wgpu::Operations {
-                        load: match clear.into() {
+                        load: match clear {
                             None => wgpu::LoadOp::Load,
-                            Some(color) => wgpu::LoadOp::Clear(LinearColor::from(color).into()),
+                            Some(color) => wgpu::LoadOp::Clear(color.into()),
                         },
                         store: true,
                     },
@@ -141,11 +154,13 @@ impl<'a> InternalCanvas<'a> {
         }
 
         let drawable_size = gfx.drawable_size();
+        let builtin = BuiltinShaders::new(gfx);
 
         let wgpu = &gfx.wgpu;
         let bind_group_cache = &mut gfx.bind_group_cache;
         let pipeline_cache = &mut gfx.pipeline_cache;
         let sampler_cache = &mut gfx.sampler_cache;
+        let stats = &mut gfx.frame_stats;
         let text_renderer = &mut gfx.text;
         let fonts = &gfx.fonts;
         let uniform_arena = &mut gfx.uniform_arena;
@@ -153,6 +168,8 @@ impl<'a> InternalCanvas<'a> {
         let (arenas, mut pass) = {
             let fcx = gfx.fcx.as_mut().unwrap(/* see above */);
 
+            // Buffers written through the upload belt have to be up to date for this pass.
+            wgpu.uploads.lock().unwrap().record_copies(&mut fcx.cmd);
             let pass = create_pass(&mut fcx.cmd);
             let arenas = &fcx.arenas;
 
@@ -169,15 +186,8 @@ impl<'a> InternalCanvas<'a> {
         };
         let transform = screen_to_mat(screen_coords);
 
-        let shader = Shader {
-            vs_module: None,
-            fs_module: None,
-        };
-
-        let text_shader = Shader {
-            vs_module: None,
-            fs_module: None,
-        };
+        let shader = Shader::default();
+        let text_shader = Shader::default();
 
         let text_uniforms =
             uniform_arena.allocate(&wgpu.device, TextUniforms::std140_size_static() as _);
@@ -198,6 +208,7 @@ impl<'a> InternalCanvas<'a> {
             bind_group_cache,
             pipeline_cache,
             sampler_cache,
+            stats,
             text_renderer,
             fonts,
             uniform_arena,
@@ -215,17 +226,18 @@ impl<'a> InternalCanvas<'a> {
             samples,
             format,
             text_uniforms,
+            color_space: gfx.color_space,
 
-            draw_sm: gfx.draw_shader.clone(),
-            instance_sm: gfx.instance_shader.clone(),
-            instance_unordered_sm: gfx.instance_unordered_shader.clone(),
-            text_sm: gfx.text_shader.clone(),
+            builtin,
 
             transform,
             curr_image: None,
             curr_sampler: Sampler::default(),
-            next_sampler: Sampler::default(),
+            next_sampler: None,
+            src_clamp: false,
+            image_channels: 4,
             premul_text: true,
+            premul_meshes: false,
         })
     }
 
@@ -264,7 +276,7 @@ impl<'a> InternalCanvas<'a> {
         self.text_shader = shader;
     }
 
-    pub fn set_sampler(&mut self, sampler: Sampler) {
+    pub fn set_sampler(&mut self, sampler: Option<Sampler>) {
         self.flush_text();
         self.next_sampler = sampler;
     }
@@ -280,6 +292,12 @@ impl<'a> InternalCanvas<'a> {
         self.premul_text = premultiplied_text;
     }
 
+    pub fn set_premultiplied_meshes(&mut self, premultiplied_meshes: bool) {
+        self.flush_text();
+        self.dirty_pipeline = true;
+        self.premul_meshes = premultiplied_meshes;
+    }
+
     pub fn set_projection(&mut self, proj: impl Into<mint::ColumnMatrix4<f32>>) {
         self.flush_text();
         self.transform = proj.into().into();
@@ -305,7 +323,11 @@ impl<'a> InternalCanvas<'a> {
     #[allow(unsafe_code)]
     pub fn draw_mesh(&mut self, mesh: &'a Mesh, image: &Image, param: DrawParam, scale: bool) {
         self.flush_text();
-        self.update_pipeline(ShaderType::Draw);
+        self.set_src_clamp(param.src_clamp);
+        self.set_image_channels(color_channels(image.format));
+        if !self.update_pipeline(ShaderType::Draw) {
+            return;
+        }
 
         let alloc_size = DrawUniforms::std140_size_static() as u64;
         let uniform_alloc = self.uniform_arena.allocate(&self.wgpu.device, alloc_size);
@@ -330,6 +352,7 @@ impl<'a> InternalCanvas<'a> {
             } else {
                 None
             },
+            self.color_space,
         );
         uniforms.transform = (self.transform * glam::Mat4::from(uniforms.transform)).into();
 
@@ -354,6 +377,7 @@ impl<'a> InternalCanvas<'a> {
             .set_index_buffer(mesh.inds.slice(..), wgpu::IndexFormat::Uint32);
 
         self.pass.draw_indexed(0..mesh.index_count as _, 0, 0..1);
+        self.stats.draw(1, &[&mesh.verts, &mesh.inds]);
     }
 
     pub fn draw_mesh_instances(
@@ -369,9 +393,14 @@ impl<'a> InternalCanvas<'a> {
             return Ok(());
         }
 
-        self.update_pipeline(ShaderType::Instance {
+        // Each instance is clamped to its own source rect.
+        self.set_src_clamp(param.src_clamp);
+        self.set_image_channels(color_channels(instances.image.format));
+        if !self.update_pipeline(ShaderType::Instance {
             ordered: instances.ordered,
-        });
+        }) {
+            return Ok(());
+        }
 
         let alloc_size = u64::from(
             self.wgpu
@@ -394,19 +423,11 @@ impl<'a> InternalCanvas<'a> {
 
         self.set_image(instances.image.clone());
 
+        // image scaling is non-sensical for instance array itself as the image scaling is applied locally (see below)
+        let global = DrawUniforms::from_param(&param, None, self.color_space);
         let uniforms = InstanceUniforms {
-            transform: (self.transform
-                * glam::Mat4::from(
-                    // image scaling is non-sensical for instance array itself as the image scaling is applied locally (see below)
-                    DrawUniforms::from_param(&param, None).transform,
-                ))
-            .into(),
-            color: mint::Vector4::<f32> {
-                x: param.color.r,
-                y: param.color.g,
-                z: param.color.b,
-                w: param.color.a,
-            },
+            transform: (self.transform * glam::Mat4::from(global.transform)).into(),
+            color: global.color,
             // this is the actual image scale that we apply in the vertex shader.
             // we can't apply this when we first convert the instance array drawparams because we don't know the image size at the time the user inserts the drawparams.
             // we also can't apply image scaling in the global instance transform as it *must* be applied in local space.
@@ -440,11 +461,71 @@ impl<'a> InternalCanvas<'a> {
 
         self.pass
             .draw_indexed(0..mesh.index_count as _, 0, 0..instances.len as _);
+        self.stats.draw(
+            instances.len,
+            &[
+                &mesh.verts,
+                &mesh.inds,
+                &instances.buffer,
+                &instances.indices,
+            ],
+        );
 
         Ok(())
     }
 
-    pub fn draw_bounded_text(&mut self, text: &Text, mut param: DrawParam) -> GameResult {
+    pub fn draw_bounded_text(&mut self, text: &Text, param: DrawParam) -> GameResult {
+        self.queue_text(text, param)?;
+        self.begin_text();
+        Ok(())
+    }
+
+    pub fn draw_path_text(&mut self, text: &TextOnPath, param: DrawParam) -> GameResult {
+        let (section, _, _) = text.layout(self.fonts, self.text_renderer, param)?;
+        self.text_renderer.queue(section);
+        self.begin_text();
+        Ok(())
+    }
+
+    /// Queues every text of a batch, so they all end up in the same draw call.
+    ///
+    /// The glyphs are only laid out and uploaded when the text is flushed; if the glyph
+    /// cache has to grow for new characters, the whole queue is processed again.
+    pub fn draw_text_batch(
+        &mut self,
+        entries: &[(Text, DrawParam)],
+        param: DrawParam,
+    ) -> GameResult {
+        // The offset is a fraction of the batch's size, which the matrix needs in pixels.
+        let mut transform = param.transform;
+        if let Transform::Values { offset, .. } = &mut transform {
+            if offset.x != 0. || offset.y != 0. {
+                let (text_renderer, fonts) = (&*self.text_renderer, self.fonts);
+                if let Some(rect) =
+                    batch_bounds(entries, |text| text.measure_raw(text_renderer, fonts).ok())
+                {
+                    offset.x = rect.x + offset.x * rect.w;
+                    offset.y = rect.y + offset.y * rect.h;
+                }
+            }
+        }
+        let batch = glam::Mat4::from(transform.to_bare_matrix());
+        for (text, entry) in entries {
+            let entry = self.resolve_text_offset(text, *entry)?;
+            let matrix = batch * glam::Mat4::from(entry.transform.to_bare_matrix());
+            let color = Color::new(
+                entry.color.r * param.color.r,
+                entry.color.g * param.color.g,
+                entry.color.b * param.color.b,
+                entry.color.a * param.color.a,
+            );
+            self.queue_text(text, entry.color(color).transform(matrix))?;
+        }
+        self.begin_text();
+        Ok(())
+    }
+
+    fn resolve_text_offset(&mut self, text: &Text, mut param: DrawParam) -> GameResult<DrawParam> {
         if let Transform::Values { dest, offset, .. } = &mut param.transform {
             if offset.x > 0. || offset.y > 0. {
                 let bounds = text.measure_raw(self.text_renderer, self.fonts)?;
@@ -453,10 +534,17 @@ impl<'a> InternalCanvas<'a> {
                 *offset = mint::Point2 { x: 0., y: 0. };
             }
         }
+        Ok(param)
+    }
 
-        self.text_renderer
-            .queue(text.as_section(self.fonts, param)?);
+    fn queue_text(&mut self, text: &Text, param: DrawParam) -> GameResult {
+        let param = self.resolve_text_offset(text, param)?;
+        let section = text.as_section(self.fonts, self.text_renderer, param)?;
+        self.text_renderer.queue(section);
+        Ok(())
+    }
 
+    fn begin_text(&mut self) {
         self.set_text_image(self.text_renderer.cache_view.clone());
 
         let (text_uniforms_bind, _) = BindGroupBuilder::new()
@@ -477,8 +565,6 @@ impl<'a> InternalCanvas<'a> {
         );
 
         self.queuing_text = true;
-
-        Ok(())
     }
 
     fn flush_text(&mut self) {
@@ -489,13 +575,15 @@ impl<'a> InternalCanvas<'a> {
                 premul = true;
                 self.set_blend_mode(BlendMode::PREMULTIPLIED);
             }
-            self.update_pipeline(ShaderType::Text);
-            self.text_renderer.draw_queued(
+            // text is never skipped, see `PipelineCompilation::BackgroundSkip`
+            let _ = self.update_pipeline(ShaderType::Text);
+            let glyphs = self.text_renderer.draw_queued(
                 &self.wgpu.device,
                 &self.wgpu.queue,
                 self.arenas,
                 &mut self.pass,
             );
+            self.stats.draw(glyphs, &[]);
             if premul {
                 self.set_blend_mode(BlendMode::ALPHA);
             }
@@ -510,139 +598,120 @@ impl<'a> InternalCanvas<'a> {
         self.flush_text();
     }
 
-    fn update_pipeline(&mut self, ty: ShaderType) {
+    fn set_src_clamp(&mut self, src_clamp: bool) {
+        if self.src_clamp != src_clamp {
+            self.src_clamp = src_clamp;
+            self.dirty_pipeline = true;
+        }
+    }
+
+    fn set_image_channels(&mut self, channels: u32) {
+        if self.image_channels != channels {
+            self.image_channels = channels;
+            self.dirty_pipeline = true;
+        }
+    }
+
+    /// Sets the pipeline for drawing `ty`, returning `false` if the draw should be skipped
+    /// while the pipeline compiles in the background.
+    fn update_pipeline(&mut self, ty: ShaderType) -> bool {
         if self.dirty_pipeline || self.shader_ty != Some(ty) {
             self.dirty_pipeline = false;
             self.shader_ty = Some(ty);
 
-            let texture_layout = BindGroupLayoutBuilder::new()
-                .image(wgpu::ShaderStages::FRAGMENT)
-                .sampler(wgpu::ShaderStages::FRAGMENT)
-                .create(&self.wgpu.device, self.bind_group_cache);
-
-            let instance_layout = BindGroupLayoutBuilder::new()
-                .buffer(
-                    wgpu::ShaderStages::VERTEX,
-                    wgpu::BufferBindingType::Storage { read_only: true },
-                    false,
-                )
-                .buffer(
-                    wgpu::ShaderStages::VERTEX,
-                    wgpu::BufferBindingType::Storage { read_only: true },
-                    false,
-                )
-                .create(&self.wgpu.device, self.bind_group_cache);
-
-            let uniform_layout = BindGroupLayoutBuilder::new()
-                .seed(ty)
-                .buffer(
-                    wgpu::ShaderStages::VERTEX,
-                    wgpu::BufferBindingType::Uniform,
-                    true,
-                )
-                .create(&self.wgpu.device, self.bind_group_cache);
-
-            let (dummy_group, dummy_layout) =
-                BindGroupBuilder::new().create(&self.wgpu.device, self.bind_group_cache);
-
-            let mut groups = vec![uniform_layout, texture_layout];
+            let blend_mode = match ty {
+                ShaderType::Draw | ShaderType::Instance { .. }
+                    if self.premul_meshes && self.blend_mode == BlendMode::ALPHA =>
+                {
+                    BlendMode::PREMULTIPLIED
+                }
+                _ => self.blend_mode,
+            };
 
-            if let ShaderType::Instance { .. } = ty {
-                groups.push(instance_layout);
-            } else {
+            if !matches!(ty, ShaderType::Instance { .. }) {
                 // the dummy group ensures the user's bind group is at index 3
-                groups.push(dummy_layout);
+                let (dummy_group, _) =
+                    BindGroupBuilder::new().create(&self.wgpu.device, self.bind_group_cache);
                 self.pass
                     .set_bind_group(2, self.arenas.bind_groups.alloc(dummy_group), &[]);
             }
 
-            let shader = match ty {
+            let (shader, params) = match ty {
                 ShaderType::Draw | ShaderType::Instance { .. } => {
-                    if let Some((bind_group, bind_group_layout, offset)) = &self.shader_bind_group {
-                        self.pass.set_bind_group(3, bind_group, &[*offset]);
-                        groups.push(bind_group_layout.clone());
-                    }
-
-                    &self.shader
-                }
-                ShaderType::Text => {
-                    if let Some((bind_group, bind_group_layout, offset)) =
-                        &self.text_shader_bind_group
-                    {
-                        self.pass.set_bind_group(3, bind_group, &[*offset]);
-                        groups.push(bind_group_layout.clone());
-                    }
-
-                    &self.text_shader
+                    (&self.shader, &self.shader_bind_group)
                 }
+                ShaderType::Text => (&self.text_shader, &self.text_shader_bind_group),
             };
-
+            if let Some((bind_group, _, offset)) = params {
+                self.pass.set_bind_group(3, bind_group, &[*offset]);
+            }
+            let groups = pipeline_layouts(
+                &self.wgpu.device,
+                self.bind_group_cache,
+                ty,
+                params.as_ref().map(|(_, layout, _)| layout),
+            );
             let layout = self.pipeline_cache.layout(&self.wgpu.device, &groups);
-            let pipeline = self
-                .arenas
-                .render_pipelines
-                .alloc(self.pipeline_cache.render_pipeline(
+
+            let target = (self.format, self.samples);
+            let info = |shader| {
+                self.builtin.pipeline_info(
+                    shader,
+                    ty,
+                    blend_mode,
+                    target,
+                    self.image_channels,
+                    self.src_clamp,
+                )
+            };
+            // Only custom shaders are worth deferring; the default ones are needed right away
+            // anyway, as the fallback and for text.
+            let custom = shader.vs_module.is_some() || shader.fs_module.is_some();
+            let pipeline = if !custom
+                || self.pipeline_cache.compilation == PipelineCompilation::Blocking
+            {
+                Some(self.pipeline_cache.render_pipeline(
                     &self.wgpu.device,
                     layout.as_ref(),
-                    RenderPipelineInfo {
-                        vs: if let Some(vs_module) = &shader.vs_module {
-                            vs_module.clone()
-                        } else {
-                            match ty {
-                                ShaderType::Draw => self.draw_sm.clone(),
-                                ShaderType::Instance { ordered } => {
-                                    if ordered {
-                                        self.instance_sm.clone()
-                                    } else {
-                                        self.instance_unordered_sm.clone()
-                                    }
-                                }
-                                ShaderType::Text => self.text_sm.clone(),
-                            }
-                        },
-                        fs: if let Some(fs_module) = &shader.fs_module {
-                            fs_module.clone()
-                        } else {
-                            match ty {
-                                ShaderType::Draw | ShaderType::Instance { .. } => {
-                                    self.draw_sm.clone()
-                                }
-                                ShaderType::Text => self.text_sm.clone(),
-                            }
-                        },
-                        vs_entry: "vs_main".into(),
-                        fs_entry: "fs_main".into(),
-                        samples: self.samples,
-                        format: self.format,
-                        blend: Some(wgpu::BlendState {
-                            color: self.blend_mode.color,
-                            alpha: self.blend_mode.alpha,
-                        }),
-                        depth: false,
-                        vertices: true,
-                        topology: match ty {
-                            ShaderType::Text => wgpu::PrimitiveTopology::TriangleStrip,
-                            _ => wgpu::PrimitiveTopology::TriangleList,
-                        },
-                        vertex_layout: match ty {
-                            ShaderType::Text => TextVertex::layout(),
-                            _ => Vertex::layout(),
-                        },
-                    },
-                ));
+                    info(shader),
+                ))
+            } else {
+                self.pipeline_cache
+                    .render_pipeline_async(self.wgpu, layout.clone(), info(shader))
+            };
+            let pipeline = match pipeline {
+                Some(pipeline) => pipeline,
+                None => {
+                    // check again on the next draw
+                    self.dirty_pipeline = true;
+                    if self.pipeline_cache.compilation == PipelineCompilation::BackgroundSkip
+                        && ty != ShaderType::Text
+                    {
+                        return false;
+                    }
+                    self.pipeline_cache.render_pipeline(
+                        &self.wgpu.device,
+                        layout.as_ref(),
+                        info(&Shader::default()),
+                    )
+                }
+            };
 
-            self.pass.set_pipeline(pipeline);
+            self.pass
+                .set_pipeline(self.arenas.render_pipelines.alloc(pipeline));
         }
+        true
     }
 
     fn set_image(&mut self, image: Image) {
-        if self.curr_sampler != self.next_sampler
+        let sampler = self.next_sampler.or(image.sampler).unwrap_or_default();
+        if self.curr_sampler != sampler
             || self
                 .curr_image
                 .as_ref()
                 .map_or(true, |curr| curr.id() != image.view.id())
         {
-            self.curr_sampler = self.next_sampler;
+            self.curr_sampler = sampler;
             let sample = self.sampler_cache.get(&self.wgpu.device, self.curr_sampler);
             let image_bind = image.fetch_buffer(sample.id(), sample, &self.wgpu.device);
 
@@ -654,13 +723,14 @@ impl<'a> InternalCanvas<'a> {
     }
 
     fn set_text_image(&mut self, view: ArcTextureView) {
-        if self.curr_sampler != self.next_sampler
+        let sampler = self.next_sampler.unwrap_or_default();
+        if self.curr_sampler != sampler
             || self
                 .curr_image
                 .as_ref()
                 .map_or(true, |curr| curr.id() != view.id())
         {
-            self.curr_sampler = self.next_sampler;
+            self.curr_sampler = sampler;
 
             let (image_bind, _) = BindGroupBuilder::new()
                 .image(&view, wgpu::ShaderStages::FRAGMENT)
@@ -711,8 +781,149 @@ impl InstanceArrayView {
     }
 }
 
+/// The built-in shader modules, used for whatever a [`Shader`] doesn't have a module for.
+#[derive(Debug, Clone)]
+pub(crate) struct BuiltinShaders {
+    draw: ArcShaderModule,
+    instance: ArcShaderModule,
+    instance_unordered: ArcShaderModule,
+    text: ArcShaderModule,
+}
+
+impl BuiltinShaders {
+    pub fn new(gfx: &GraphicsContext) -> Self {
+        BuiltinShaders {
+            draw: gfx.draw_shader.clone(),
+            instance: gfx.instance_shader.clone(),
+            instance_unordered: gfx.instance_unordered_shader.clone(),
+            text: gfx.text_shader.clone(),
+        }
+    }
+
+    /// Describes the pipeline drawing `ty` with `shader` to a target of the given format and
+    /// sample count. The image's color channels and source rect clamping only matter to the
+    /// default shaders.
+    pub fn pipeline_info(
+        &self,
+        shader: &Shader,
+        ty: ShaderType,
+        blend_mode: BlendMode,
+        (format, samples): (wgpu::TextureFormat, u32),
+        image_channels: u32,
+        src_clamp: bool,
+    ) -> RenderPipelineInfo {
+        // Clamping to the source rect needs both default shaders, as it passes the rect
+        // between them.
+        let clamped = src_clamp
+            && ty != ShaderType::Text
+            && shader.vs_module.is_none()
+            && shader.fs_module.is_none();
+        // The default fragment shader also broadcasts single and two channel images
+        // to grayscale.
+        let fs_entry = match (image_channels, clamped) {
+            _ if ty == ShaderType::Text || shader.fs_module.is_some() => "fs_main",
+            (1, false) => "fs_gray",
+            (1, true) => "fs_gray_clamped",
+            (2, false) => "fs_gray_alpha",
+            (2, true) => "fs_gray_alpha_clamped",
+            (_, false) => "fs_main",
+            (_, true) => "fs_clamped",
+        };
+
+        RenderPipelineInfo {
+            vs: if let Some(vs_module) = &shader.vs_module {
+                vs_module.clone()
+            } else {
+                match ty {
+                    ShaderType::Draw => self.draw.clone(),
+                    ShaderType::Instance { ordered } => {
+                        if ordered {
+                            self.instance.clone()
+                        } else {
+                            self.instance_unordered.clone()
+                        }
+                    }
+                    ShaderType::Text => self.text.clone(),
+                }
+            },
+            fs: if let Some(fs_module) = &shader.fs_module {
+                fs_module.clone()
+            } else {
+                match ty {
+                    ShaderType::Draw | ShaderType::Instance { .. } => self.draw.clone(),
+                    ShaderType::Text => self.text.clone(),
+                }
+            },
+            vs_entry: if clamped { "vs_clamped" } else { "vs_main" }.into(),
+            fs_entry: fs_entry.into(),
+            samples,
+            format,
+            blend: Some(wgpu::BlendState {
+                color: blend_mode.color,
+                alpha: blend_mode.alpha,
+            }),
+            depth: false,
+            vertices: true,
+            topology: match ty {
+                ShaderType::Text => wgpu::PrimitiveTopology::TriangleStrip,
+                _ => wgpu::PrimitiveTopology::TriangleList,
+            },
+            vertex_layout: match ty {
+                ShaderType::Text => TextVertex::layout(),
+                _ => Vertex::layout(),
+            },
+        }
+    }
+}
+
+/// Returns the bind group layouts of a pipeline drawing `ty`, with the layout of the custom
+/// shader's params at group 3.
+pub(crate) fn pipeline_layouts(
+    device: &wgpu::Device,
+    bind_group_cache: &mut BindGroupCache,
+    ty: ShaderType,
+    params: Option<&ArcBindGroupLayout>,
+) -> Vec<ArcBindGroupLayout> {
+    let texture_layout = BindGroupLayoutBuilder::new()
+        .image(wgpu::ShaderStages::FRAGMENT)
+        .sampler(wgpu::ShaderStages::FRAGMENT)
+        .create(device, bind_group_cache);
+
+    let uniform_layout = BindGroupLayoutBuilder::new()
+        .seed(ty)
+        .buffer(
+            wgpu::ShaderStages::VERTEX,
+            wgpu::BufferBindingType::Uniform,
+            true,
+        )
+        .create(device, bind_group_cache);
+
+    let mut groups = vec![uniform_layout, texture_layout];
+
+    if let ShaderType::Instance { .. } = ty {
+        let instance_layout = BindGroupLayoutBuilder::new()
+            .buffer(
+                wgpu::ShaderStages::VERTEX,
+                wgpu::BufferBindingType::Storage { read_only: true },
+                false,
+            )
+            .buffer(
+                wgpu::ShaderStages::VERTEX,
+                wgpu::BufferBindingType::Storage { read_only: true },
+                false,
+            )
+            .create(device, bind_group_cache);
+        groups.push(instance_layout);
+    } else {
+        groups.push(BindGroupLayoutBuilder::new().create(device, bind_group_cache));
+    }
+
+    groups.extend(params.cloned());
+    groups
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum ShaderType {
+pub(crate) enum ShaderType {
     Draw,
     Instance { ordered: bool },
     Text,
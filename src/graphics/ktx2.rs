@@ -0,0 +1,187 @@
+//! Minimal reader for KTX2 texture containers, as used by [`Image::from_ktx2`](super::Image::from_ktx2).
+//!
+//! Only what's needed to upload a plain 2D texture is supported: no supercompression,
+//! cubemaps, arrays or 3D textures.
+
+use super::image::ImageFormat;
+use crate::{GameError, GameResult};
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const HEADER_LEN: usize = 80;
+const LEVEL_INDEX_ENTRY_LEN: usize = 24;
+
+/// A parsed KTX2 file, borrowing its level data from the input.
+#[derive(Debug)]
+pub(crate) struct Ktx2<'a> {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Mip levels, largest first.
+    pub levels: Vec<&'a [u8]>,
+}
+
+fn error(msg: impl std::fmt::Display) -> GameError {
+    GameError::ResourceLoadError(format!("invalid KTX2 file: {msg}"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) // Unwrap since the slice is always 4 bytes.
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()) // Unwrap since the slice is always 8 bytes.
+}
+
+/// Maps a Vulkan format, as stored in the KTX2 header, to the matching image format.
+fn format_from_vk(vk_format: u32) -> Option<ImageFormat> {
+    Some(match vk_format {
+        37 => ImageFormat::Rgba8Unorm,
+        43 => ImageFormat::Rgba8UnormSrgb,
+        // BC1 RGB (131 and 132) has no equivalent: its three color blocks decode index 3 as
+        // opaque black, where BC1 RGBA makes it transparent.
+        133 => ImageFormat::Bc1RgbaUnorm,
+        134 => ImageFormat::Bc1RgbaUnormSrgb,
+        137 => ImageFormat::Bc3RgbaUnorm,
+        138 => ImageFormat::Bc3RgbaUnormSrgb,
+        141 => ImageFormat::Bc5RgUnorm,
+        142 => ImageFormat::Bc5RgSnorm,
+        145 => ImageFormat::Bc7RgbaUnorm,
+        146 => ImageFormat::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+pub(crate) fn parse(data: &[u8]) -> GameResult<Ktx2<'_>> {
+    if data.len() < HEADER_LEN || data[..12] != IDENTIFIER {
+        return Err(error("missing KTX2 identifier"));
+    }
+
+    let vk_format = read_u32(data, 12);
+    let width = read_u32(data, 20);
+    let height = read_u32(data, 24);
+    let depth = read_u32(data, 28);
+    let layers = read_u32(data, 32);
+    let faces = read_u32(data, 36);
+    let level_count = read_u32(data, 40).max(1);
+    let supercompression = read_u32(data, 44);
+
+    let format = format_from_vk(vk_format).ok_or_else(|| match vk_format {
+        131 | 132 => error("BC1 RGB is not supported, use BC1 RGBA instead"),
+        _ => error(format!("unsupported format (VkFormat {vk_format})")),
+    })?;
+    if supercompression != 0 {
+        return Err(error("supercompressed files are not supported"));
+    }
+    if depth > 1 || layers > 1 || faces != 1 {
+        return Err(error(
+            "only plain 2D textures are supported, not arrays, cubemaps or 3D textures",
+        ));
+    }
+    if width == 0 || height == 0 {
+        return Err(error("texture has no size"));
+    }
+    let (block_w, block_h) = format.block_dimensions();
+    if !width.is_multiple_of(block_w) || !height.is_multiple_of(block_h) {
+        return Err(error(format!(
+            "{width}x{height} is not a multiple of the {block_w}x{block_h} block size"
+        )));
+    }
+    if level_count > 32 || (width.max(height) >> (level_count - 1)) == 0 {
+        return Err(error(format!(
+            "{level_count} mip levels is too many for {width}x{height}"
+        )));
+    }
+
+    let block_size = u64::from(format.block_size(None).unwrap()); // Unwrap since it only fails with depth formats.
+    let index_end = HEADER_LEN + level_count as usize * LEVEL_INDEX_ENTRY_LEN;
+    if data.len() < index_end {
+        return Err(error("level index is truncated"));
+    }
+
+    let levels = (0..level_count)
+        .map(|level| {
+            let entry = HEADER_LEN + level as usize * LEVEL_INDEX_ENTRY_LEN;
+            let offset = read_u64(data, entry);
+            let len = read_u64(data, entry + 8);
+
+            let blocks_w = u64::from((width >> level).max(1).div_ceil(block_w));
+            let blocks_h = u64::from((height >> level).max(1).div_ceil(block_h));
+            let expected = blocks_w * blocks_h * block_size;
+            if len != expected {
+                return Err(error(format!(
+                    "level {level} has {len} bytes, expected {expected}"
+                )));
+            }
+
+            offset
+                .checked_add(len)
+                .and_then(|end| usize::try_from(offset).ok().zip(usize::try_from(end).ok()))
+                .and_then(|(start, end)| data.get(start..end))
+                .ok_or_else(|| error(format!("level {level} is out of bounds")))
+        })
+        .collect::<GameResult<Vec<_>>>()?;
+
+    Ok(Ktx2 {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a KTX2 file with the given format and mip levels.
+    pub(crate) fn encode(vk_format: u32, width: u32, height: u32, levels: &[&[u8]]) -> Vec<u8> {
+        let mut out = IDENTIFIER.to_vec();
+        for v in [vk_format, 1, width, height, 0, 0, 1, levels.len() as u32, 0] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        // dfd, kvd and sgd offsets/lengths, none of which are read
+        out.extend_from_slice(&[0; 4 * 4 + 8 * 2]);
+
+        let mut offset = (HEADER_LEN + levels.len() * LEVEL_INDEX_ENTRY_LEN) as u64;
+        for level in levels {
+            let len = level.len() as u64;
+            for v in [offset, len, len] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            offset += len;
+        }
+        for level in levels {
+            out.extend_from_slice(level);
+        }
+        out
+    }
+
+    #[test]
+    fn headless_test_ktx2_parse() {
+        let level0 = [7; 4 * 4 * 2];
+        let level1 = [9; 4 * 2];
+        let file = encode(43, 4, 2, &[&level0, &level1]);
+
+        let ktx = parse(&file).unwrap();
+        assert_eq!(ktx.format, ImageFormat::Rgba8UnormSrgb);
+        assert_eq!((ktx.width, ktx.height), (4, 2));
+        assert_eq!(ktx.levels, [&level0[..], &level1[..]]);
+    }
+
+    #[test]
+    fn headless_test_ktx2_parse_errors() {
+        let level0 = [0; 4 * 4 * 2];
+        assert!(parse(&encode(43, 4, 2, &[&level0[1..]])).is_err());
+        assert!(parse(&encode(1234, 4, 2, &[&level0])).is_err());
+        assert!(parse(&encode(131, 4, 4, &[&[0; 8]])).is_err());
+        assert!(parse(&encode(133, 4, 4, &[&[0; 8]])).is_ok());
+        assert!(parse(&encode(133, 6, 4, &[&[0; 16]])).is_err());
+        assert!(parse(b"not a ktx2 file").is_err());
+
+        let mut truncated = encode(43, 4, 2, &[&level0]);
+        truncated.truncate(truncated.len() - 1);
+        assert!(parse(&truncated).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! 2D lighting: visibility polygons of a point light among occluding segments, and meshes
+//! of them to draw into a light map.
+
+use super::{context::GraphicsContext, Color, LinearColor, Mesh, MeshData, Rect, Vertex};
+use crate::context::Has;
+use glam::Vec2;
+use std::f32::consts::TAU;
+
+/// How many sides the polygon approximating the light's circle has.
+const CIRCLE_SIDES: usize = 64;
+/// Angle by which extra rays pass either side of each corner, to see past it.
+const RAY_OFFSET: f32 = 1e-4;
+
+/// A line segment that blocks light, see [`visibility_polygon`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineSegment {
+    /// One end of the segment.
+    pub a: mint::Point2<f32>,
+    /// The other end of the segment.
+    pub b: mint::Point2<f32>,
+}
+
+impl LineSegment {
+    /// Creates a segment between two points.
+    pub fn new(a: impl Into<mint::Point2<f32>>, b: impl Into<mint::Point2<f32>>) -> Self {
+        LineSegment {
+            a: a.into(),
+            b: b.into(),
+        }
+    }
+
+    /// Returns the four sides of a rectangle, for example a wall or crate.
+    pub fn from_rect(rect: Rect) -> [LineSegment; 4] {
+        let [a, b, c, d] = rect.corners();
+        [
+            LineSegment::new(a, b),
+            LineSegment::new(b, c),
+            LineSegment::new(c, d),
+            LineSegment::new(d, a),
+        ]
+    }
+}
+
+/// Computes the area lit by a light at `light`, reaching up to `radius` away and blocked
+/// by the `occluders`.
+///
+/// Returns the outline of the lit area, ordered by angle around the light. Corners of the
+/// occluders the light can see are included, while points in the middle of straight edges
+/// aren't, so a light in an empty box room gives just the four corners.
+pub fn visibility_polygon(
+    light: impl Into<mint::Point2<f32>>,
+    occluders: &[LineSegment],
+    radius: f32,
+) -> Vec<mint::Point2<f32>> {
+    let light = Vec2::from(light.into());
+    let eps = radius.max(1.) * 1e-6;
+
+    // The edge of the light's reach is an occluder too, so every ray hits something.
+    let circle = (0..CIRCLE_SIDES).map(|i| {
+        let angle = i as f32 / CIRCLE_SIDES as f32 * TAU;
+        light + radius * direction(angle)
+    });
+    let mut segments = occluders
+        .iter()
+        .map(|s| (Vec2::from(s.a), Vec2::from(s.b)))
+        .filter(|(a, b)| a.distance(*b) > eps)
+        .collect::<Vec<_>>();
+    let circle = circle.collect::<Vec<_>>();
+    segments.extend((0..CIRCLE_SIDES).map(|i| (circle[i], circle[(i + 1) % CIRCLE_SIDES])));
+
+    // Rays go towards every corner, where the outline can change direction, and past
+    // either side of it, to find what's behind.
+    let mut corners = segments
+        .iter()
+        .flat_map(|&(a, b)| [a, b])
+        .collect::<Vec<_>>();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        for &(c, d) in &segments[i + 1..] {
+            if let Some(p) = segment_intersection(a, b, c, d) {
+                corners.push(p);
+            }
+        }
+    }
+    let mut angles = corners
+        .into_iter()
+        .filter(|p| p.distance(light) > eps)
+        .map(|p| (p.y - light.y).atan2(p.x - light.x))
+        .flat_map(|angle| [angle - RAY_OFFSET, angle, angle + RAY_OFFSET])
+        .collect::<Vec<_>>();
+    angles.sort_by(f32::total_cmp);
+    angles.dedup_by(|a, b| (*a - *b).abs() < RAY_OFFSET * 0.1);
+
+    let mut points = Vec::<Vec2>::with_capacity(angles.len());
+    for angle in angles {
+        let dir = direction(angle);
+        let hit = segments
+            .iter()
+            .filter_map(|&(a, b)| ray_hit(light, dir, a, b, eps))
+            .fold(radius, f32::min);
+        let point = light + dir * hit;
+        if points.last().is_none_or(|last| last.distance(point) > eps) {
+            points.push(point);
+        }
+    }
+    if points.len() > 1 && points[0].distance(points[points.len() - 1]) <= eps {
+        let _ = points.pop();
+    }
+
+    // Drop points in the middle of straight edges, until only corners are left.
+    let mut i = 0;
+    while points.len() > 3 && i < points.len() {
+        let n = points.len();
+        let prev = points[(i + n - 1) % n];
+        let next = points[(i + 1) % n];
+        let (u, v) = (prev - points[i], next - points[i]);
+        let off_line = (next - prev).perp_dot(-u).abs() / prev.distance(next);
+        if off_line <= eps && u.dot(v) < 0. {
+            let _ = points.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+
+    points.into_iter().map(Into::into).collect()
+}
+
+/// Creates a mesh covering the area lit by a light, see [`visibility_polygon`].
+///
+/// The mesh is white at the light and fades to transparent black at `radius`; tint it
+/// with the color of the [`DrawParam`](super::DrawParam). Drawing the meshes of all lights
+/// with [`BlendMode::ADD`](super::BlendMode::ADD) onto a dark canvas gives a light map,
+/// which can then be multiplied onto the scene.
+pub fn visibility_mesh(
+    gfx: &impl Has<GraphicsContext>,
+    light: impl Into<mint::Point2<f32>>,
+    occluders: &[LineSegment],
+    radius: f32,
+) -> Mesh {
+    let light = light.into();
+    let outline = visibility_polygon(light, occluders, radius);
+
+    let color = |point: Vec2| {
+        let falloff = 1. - (point.distance(Vec2::from(light)) / radius).min(1.);
+        LinearColor::from(Color::new(falloff, falloff, falloff, falloff)).into()
+    };
+    let vertices = std::iter::once(Vec2::from(light))
+        .chain(outline.iter().map(|&p| Vec2::from(p)))
+        .map(|p| Vertex {
+            position: p.into(),
+            uv: [0., 0.],
+            color: color(p),
+        })
+        .collect::<Vec<_>>();
+    let n = outline.len() as u32;
+    let indices = (0..n)
+        .flat_map(|i| [0, i + 1, (i + 1) % n + 1])
+        .collect::<Vec<_>>();
+
+    Mesh::from_data(
+        gfx,
+        MeshData {
+            vertices: &vertices,
+            indices: &indices,
+        },
+    )
+}
+
+fn direction(angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(cos, sin)
+}
+
+/// Returns how far along the ray from `origin` in direction `dir` it hits the segment.
+fn ray_hit(origin: Vec2, dir: Vec2, a: Vec2, b: Vec2, eps: f32) -> Option<f32> {
+    let edge = b - a;
+    let to_a = a - origin;
+    let denom = dir.perp_dot(edge);
+    if denom.abs() <= 1e-9 * edge.length() {
+        // Parallel; if the ray runs along the segment, it stops at the nearer end.
+        if to_a.perp_dot(dir).abs() > eps {
+            return None;
+        }
+        let (ta, tb) = (to_a.dot(dir), (b - origin).dot(dir));
+        return match (ta >= 0., tb >= 0.) {
+            (true, true) => Some(ta.min(tb)),
+            (true, false) | (false, true) => Some(0.),
+            (false, false) => None,
+        };
+    }
+
+    let t = to_a.perp_dot(edge) / denom;
+    let u = to_a.perp_dot(dir) / denom;
+    // Just enough slack for rays aimed right at an end to hit it, but not for the rays
+    // passing beside it.
+    (t >= 0. && (-1e-6..=1. + 1e-6).contains(&u)).then_some(t)
+}
+
+/// Returns where two segments cross, if they do.
+fn segment_intersection(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> Option<Vec2> {
+    let (r, s) = (b - a, d - c);
+    let denom = r.perp_dot(s);
+    if denom.abs() <= f32::EPSILON * r.length() * s.length() {
+        return None;
+    }
+    let t = (c - a).perp_dot(s) / denom;
+    let u = (c - a).perp_dot(r) / denom;
+    ((0.0..=1.).contains(&t) && (0.0..=1.).contains(&u)).then(|| a + t * r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::tests::headless_context;
+
+    fn area(points: &[mint::Point2<f32>]) -> f32 {
+        let n = points.len();
+        (0..n)
+            .map(|i| {
+                let (p, q) = (points[i], points[(i + 1) % n]);
+                p.x * q.y - q.x * p.y
+            })
+            .sum::<f32>()
+            .abs()
+            / 2.
+    }
+
+    fn assert_outline(points: &[mint::Point2<f32>], expected: &[[f32; 2]]) {
+        assert_eq!(points.len(), expected.len(), "{points:?}");
+        for e in expected {
+            assert!(
+                points
+                    .iter()
+                    .any(|p| (p.x - e[0]).abs() < 1e-2 && (p.y - e[1]).abs() < 1e-2),
+                "{e:?} missing from {points:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn headless_test_visibility_box_room() {
+        let room = LineSegment::from_rect(Rect::new(0., 0., 100., 100.));
+        let corners = [[0., 0.], [100., 0.], [100., 100.], [0., 100.]];
+        let points = visibility_polygon([30., 60.], &room, 1000.);
+        assert_outline(&points, &corners);
+        assert!((area(&points) - 10_000.).abs() < 1.);
+
+        // Walls split into collinear pieces, and duplicated walls, change nothing.
+        let mut walls = room.to_vec();
+        walls.extend(room);
+        walls.push(LineSegment::new([0., 0.], [50., 0.]));
+        walls.push(LineSegment::new([50., 0.], [100., 0.]));
+        walls.push(LineSegment::new([20., 20.], [20., 20.]));
+        assert_outline(&visibility_polygon([30., 60.], &walls, 1000.), &corners);
+
+        // A bench in front of the light casts a shadow onto the top wall.
+        walls.push(LineSegment::new([40., 30.], [60., 30.]));
+        let points = visibility_polygon([50., 50.], &walls, 1000.);
+        assert_outline(
+            &points,
+            &[
+                [0., 0.],
+                [25., 0.],
+                [40., 30.],
+                [60., 30.],
+                [75., 0.],
+                [100., 0.],
+                [100., 100.],
+                [0., 100.],
+            ],
+        );
+        assert!((area(&points) - 8950.).abs() < 1.);
+
+        // A pillar seen along one of its sides.
+        let mut walls = room.to_vec();
+        walls.extend(LineSegment::from_rect(Rect::new(50., 40., 10., 10.)));
+        let points = visibility_polygon([10., 40.], &walls, 1000.);
+        assert_outline(
+            &points,
+            &[
+                [0., 0.],
+                [100., 0.],
+                [100., 40.],
+                [50., 40.],
+                [50., 50.],
+                [100., 62.5],
+                [100., 100.],
+                [0., 100.],
+            ],
+        );
+    }
+
+    #[test]
+    fn headless_test_visibility_radius() {
+        let points = visibility_polygon([5., 5.], &[], 10.);
+        assert_eq!(points.len(), CIRCLE_SIDES);
+        for p in &points {
+            assert!((Vec2::new(p.x - 5., p.y - 5.).length() - 10.).abs() < 1e-3);
+        }
+
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+        let mesh = visibility_mesh(&ctx, [5., 5.], &[], 10.);
+        assert_eq!(mesh.vertex_count(), CIRCLE_SIDES + 1);
+        assert_eq!(mesh.index_count(), 3 * CIRCLE_SIDES);
+    }
+}
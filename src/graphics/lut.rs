@@ -0,0 +1,344 @@
+use super::{
+    context::GraphicsContext,
+    gpu::{
+        arc::{ArcBindGroup, ArcBuffer},
+        bind_group::{BindGroupBuilder, BindGroupLayoutBuilder},
+        pipeline::RenderPipelineInfo,
+    },
+    image::{Image, ImageFormat},
+    memory::ResourceKind,
+    mesh::Vertex,
+    sampler::Sampler,
+};
+use crate::{context::Has, GameError, GameResult};
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+/// Creates a color lookup table for [`Canvas::set_color_lut`](super::Canvas::set_color_lut)
+/// that leaves colors unchanged.
+///
+/// The table is a strip of `size` squares of `size`x`size` pixels each. Red increases to the
+/// right within each square, green downwards, and blue from one square to the next. This is
+/// the layout most image editors and engines use, so a LUT can be made by color grading a
+/// screenshot with this image pasted into it, and cutting the graded strip back out.
+///
+/// # Panics
+///
+/// Panics if `size` isn't between 2 and 64.
+pub fn identity_lut(gfx: &impl Has<GraphicsContext>, size: u32) -> Image {
+    assert!(
+        (2..=64).contains(&size),
+        "LUT size must be between 2 and 64"
+    );
+
+    let level = |i: u32| (i as f32 / (size - 1) as f32 * 255.).round() as u8;
+    let mut pixels = Vec::with_capacity((size * size * size * 4) as usize);
+    for g in 0..size {
+        for b in 0..size {
+            for r in 0..size {
+                pixels.extend_from_slice(&[level(r), level(g), level(b), 255]);
+            }
+        }
+    }
+    Image::from_pixels_wgpu(
+        &gfx.retrieve().wgpu,
+        &pixels,
+        ImageFormat::Rgba8Unorm,
+        size * size,
+        size,
+    )
+}
+
+/// Runs every pixel of `target` through `lut`, which is either a strip as made by
+/// [`identity_lut`] or a 3D image.
+pub(crate) fn apply_color_lut(
+    gfx: &mut GraphicsContext,
+    target: &Image,
+    lut: &Image,
+) -> GameResult {
+    let size = lut.height;
+    let is_strip = lut.dimension == wgpu::TextureViewDimension::D2 && lut.width == size * size;
+    let is_volume =
+        lut.dimension == wgpu::TextureViewDimension::D3 && lut.width == size && lut.depth == size;
+    if !is_strip && !is_volume {
+        return Err(GameError::RenderError(format!(
+            "a color LUT must be a strip of N squares of NxN pixels or an NxNxN 3D image, not {}x{}x{}",
+            lut.width, lut.height, lut.depth
+        )));
+    }
+    if lut.format.block_dimensions() != (1, 1) {
+        return Err(GameError::RenderError(String::from(
+            "a color LUT can't be compressed",
+        )));
+    }
+
+    // A strip is converted to a 3D image the first time it's used.
+    gfx.lut_volumes
+        .retain(|_, (strip, _)| strip.strong_count() > 0);
+    let (volume, convert) = match gfx.lut_volumes.get(&lut.texture.id()) {
+        _ if !is_strip => (lut.clone(), false),
+        Some((_, volume)) => (volume.clone(), false),
+        None => {
+            let volume = Image::new_layered(
+                &gfx.wgpu,
+                lut.format,
+                (size, size, size),
+                wgpu::TextureViewDimension::D3,
+                1,
+                wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+            let strip = Arc::downgrade(&lut.texture.handle);
+            let _ = gfx
+                .lut_volumes
+                .insert(lut.texture.id(), (strip, volume.clone()));
+            (volume, true)
+        }
+    };
+
+    let device = &gfx.wgpu.device;
+
+    // A pass can't sample the image it renders to, so it reads from a copy.
+    let source = match &gfx.lut_scratch {
+        Some(scratch)
+            if (scratch.format, scratch.width, scratch.height)
+                == (target.format, target.width, target.height) =>
+        {
+            scratch.clone()
+        }
+        _ => {
+            let scratch = Image::new(
+                &gfx.wgpu,
+                target.format,
+                target.width,
+                target.height,
+                1,
+                wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+            gfx.lut_scratch = Some(scratch.clone());
+            scratch
+        }
+    };
+
+    let params = ArcBuffer::new(
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[
+                u32::from(target.format.is_srgb()),
+                u32::from(lut.format.is_srgb()),
+                0,
+                0,
+            ]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        }),
+    )
+    .track(
+        &gfx.wgpu.resources,
+        ResourceKind::Internal,
+        "color LUT parameters",
+        16,
+    );
+    let sampler = gfx.sampler_cache.get(device, Sampler::linear_clamp());
+
+    let layout = BindGroupLayoutBuilder::new()
+        .image(wgpu::ShaderStages::FRAGMENT)
+        .image_with_dimension(wgpu::ShaderStages::FRAGMENT, wgpu::TextureViewDimension::D3)
+        .sampler(wgpu::ShaderStages::FRAGMENT)
+        .buffer(
+            wgpu::ShaderStages::FRAGMENT,
+            wgpu::BufferBindingType::Uniform,
+            false,
+        )
+        .create(device, &mut gfx.bind_group_cache);
+    let entries = BindGroupBuilder::new()
+        .image(&source.view, wgpu::ShaderStages::FRAGMENT)
+        .image_with_dimension(
+            &volume.view,
+            wgpu::ShaderStages::FRAGMENT,
+            wgpu::TextureViewDimension::D3,
+        )
+        .sampler(&sampler, wgpu::ShaderStages::FRAGMENT)
+        .buffer(
+            &params,
+            0,
+            wgpu::ShaderStages::FRAGMENT,
+            wgpu::BufferBindingType::Uniform,
+            false,
+            None,
+        );
+    let bind_group = ArcBindGroup::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: layout.as_ref(),
+        entries: entries.entries(),
+    }));
+
+    let pipeline_layout = gfx.pipeline_cache.layout(device, &[layout]);
+    let pipeline = gfx.pipeline_cache.render_pipeline(
+        device,
+        &pipeline_layout,
+        RenderPipelineInfo {
+            vs: gfx.lut_shader.clone(),
+            fs: gfx.lut_shader.clone(),
+            vs_entry: "vs_main".into(),
+            fs_entry: "fs_main".into(),
+            samples: 1,
+            format: target.format,
+            blend: None,
+            depth: false,
+            vertices: false,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            vertex_layout: Vertex::layout(),
+        },
+    );
+
+    let fcx = gfx.fcx.as_mut().ok_or_else(|| {
+        GameError::RenderError(String::from(
+            "starting Canvas outside of a frame; call begin_frame first",
+        ))
+    })?;
+
+    if convert {
+        // Each square of the strip becomes one depth slice.
+        for slice in 0..size {
+            fcx.cmd.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    texture: lut.texture.as_ref(),
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: slice * size,
+                        y: 0,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyTexture {
+                    texture: volume.texture.as_ref(),
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: slice,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    fcx.cmd.copy_texture_to_texture(
+        target.texture.as_image_copy(),
+        source.texture.as_image_copy(),
+        wgpu::Extent3d {
+            width: target.width,
+            height: target.height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    let mut pass = fcx.cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target.view.as_ref(),
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    pass.set_pipeline(&pipeline);
+    pass.set_bind_group(0, &bind_group, &[]);
+    pass.draw(0..3, 0..1);
+    gfx.frame_stats.draw(1, &[]);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::tests::headless_context,
+        graphics::{Canvas, Color, DrawParam, Quad, Rect},
+    };
+
+    #[test]
+    fn headless_test_color_lut() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let colors = [
+            Color::new(0.1, 0.5, 0.9, 1.),
+            Color::new(0.75, 0.25, 0.0, 1.),
+            Color::new(0.33, 0.66, 1.0, 1.),
+            Color::BLACK,
+        ];
+        let render = |ctx: &mut crate::Context, format, lut: Option<Image>| {
+            let target = Image::new_canvas_image(ctx, format, 4, 1, 1);
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_image(ctx, target.clone(), Color::WHITE);
+            for (i, color) in colors.into_iter().enumerate() {
+                canvas.draw(
+                    &Quad,
+                    DrawParam::new()
+                        .dest_rect(Rect::new(i as f32, 0., 1., 1.))
+                        .color(color),
+                );
+            }
+            canvas.set_color_lut(lut);
+            let result = canvas.finish(ctx);
+            ctx.gfx.end_frame().unwrap();
+            result.map(|_| target.to_pixels(ctx).unwrap())
+        };
+
+        // The identity LUT leaves the picture alone, in linear and sRGB targets.
+        for format in [ImageFormat::Rgba8Unorm, ImageFormat::Rgba8UnormSrgb] {
+            let plain = render(&mut ctx, format, None).unwrap();
+            let lut = identity_lut(&ctx, 16);
+            let graded = render(&mut ctx, format, Some(lut)).unwrap();
+            for (a, b) in plain.iter().zip(&graded) {
+                assert!(a.abs_diff(*b) <= 1, "{format:?}: {plain:?} vs {graded:?}");
+            }
+        }
+
+        // An inverting LUT, as a 3D image.
+        let lut = Image::new_3d(&ctx, ImageFormat::Rgba8Unorm, 2, 2, 2);
+        for b in 0..2u8 {
+            let mut pixels = Vec::new();
+            for g in 0..2u8 {
+                for r in 0..2u8 {
+                    pixels.extend_from_slice(&[255 - r * 255, 255 - g * 255, 255 - b * 255, 255]);
+                }
+            }
+            lut.write_layer(&ctx, u32::from(b), &pixels).unwrap();
+        }
+        let plain = render(&mut ctx, ImageFormat::Rgba8Unorm, None).unwrap();
+        let inverted = render(&mut ctx, ImageFormat::Rgba8Unorm, Some(lut)).unwrap();
+        for (i, (a, b)) in plain.iter().zip(&inverted).enumerate() {
+            if i % 4 != 3 {
+                assert!((255 - a).abs_diff(*b) <= 1, "{plain:?} vs {inverted:?}");
+            }
+        }
+
+        // A strip is only converted to a 3D image once, which is dropped along with the strip.
+        let lut = identity_lut(&ctx, 4);
+        let id = lut.texture.id();
+        let _ = render(&mut ctx, ImageFormat::Rgba8Unorm, Some(lut.clone())).unwrap();
+        let volume = ctx.gfx.lut_volumes[&id].1.clone();
+        let _ = render(&mut ctx, ImageFormat::Rgba8Unorm, Some(lut.clone())).unwrap();
+        assert!(ctx.gfx.lut_volumes[&id].1.texture == volume.texture);
+        drop(lut);
+        let other = identity_lut(&ctx, 4);
+        let _ = render(&mut ctx, ImageFormat::Rgba8Unorm, Some(other)).unwrap();
+        assert!(!ctx.gfx.lut_volumes.contains_key(&id));
+
+        let not_a_lut = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 5, 3, 1);
+        assert!(render(&mut ctx, ImageFormat::Rgba8Unorm, Some(not_a_lut)).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use super::context::GraphicsContext;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+pub(crate) type MemoryWarningCallback = Box<dyn FnMut(&mut GraphicsContext, u64)>;
+
+/// What a GPU resource is used for, see [`ResourceInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// The texture of an [`Image`](super::Image) that can't be drawn to.
+    Image,
+    /// The texture of an image that can be drawn to with a [`Canvas`](super::Canvas),
+    /// including the frame images of the window.
+    CanvasImage,
+    /// The vertex or index buffer of a [`Mesh`](super::Mesh).
+    MeshBuffer,
+    /// The buffers of an [`InstanceArray`](super::InstanceArray).
+    InstanceBuffer,
+    /// Buffers and textures `ggez` uses internally, like the glyph cache of the text
+    /// renderer and the buffers uniforms are written to.
+    Internal,
+}
+
+/// A GPU resource that is currently allocated, see [`GraphicsContext::resource_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceInfo {
+    /// Describes what the resource is, for example `"mesh vertices"`.
+    pub label: String,
+    /// What the resource is used for.
+    pub kind: ResourceKind,
+    /// How much memory the resource takes up, in bytes.
+    ///
+    /// This is worked out from the size and format of the resource, so it doesn't include
+    /// padding or alignment the driver may add.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    next_id: u64,
+    total: u64,
+    resources: BTreeMap<u64, ResourceInfo>,
+}
+
+/// Keeps a list of the GPU resources that are alive, shared by everything that allocates them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResourceTracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl ResourceTracker {
+    /// Adds a resource to the list, until the returned allocation is dropped.
+    pub(crate) fn track(
+        &self,
+        kind: ResourceKind,
+        label: impl Into<String>,
+        bytes: u64,
+    ) -> Allocation {
+        let mut state = self.state.lock().unwrap(/* not poisoned */);
+        let id = state.next_id;
+        state.next_id += 1;
+        state.total += bytes;
+        let info = ResourceInfo {
+            label: label.into(),
+            kind,
+            bytes,
+        };
+        let _ = state.resources.insert(id, info);
+        Allocation {
+            id,
+            tracker: Arc::downgrade(&self.state),
+        }
+    }
+
+    pub(crate) fn report(&self) -> Vec<ResourceInfo> {
+        let state = self.state.lock().unwrap(/* not poisoned */);
+        let mut report = state.resources.values().cloned().collect::<Vec<_>>();
+        // Stable, so resources of the same size stay in the order they were allocated.
+        report.sort_by_key(|info| Reverse(info.bytes));
+        report
+    }
+
+    pub(crate) fn total(&self) -> u64 {
+        self.state.lock().unwrap(/* not poisoned */).total
+    }
+}
+
+/// Removes a resource from its [`ResourceTracker`] when dropped.
+#[derive(Debug)]
+pub(crate) struct Allocation {
+    id: u64,
+    tracker: Weak<Mutex<TrackerState>>,
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        if let Some(state) = self.tracker.upgrade() {
+            let mut state = state.lock().unwrap(/* not poisoned */);
+            if let Some(info) = state.resources.remove(&self.id) {
+                state.total -= info.bytes;
+            }
+        }
+    }
+}
+
+/// Returns how many bytes a texture takes up.
+pub(crate) fn texture_bytes(
+    format: wgpu::TextureFormat,
+    (width, height, depth): (u32, u32, u32),
+    samples: u32,
+) -> u64 {
+    let (block_w, block_h) = format.block_dimensions();
+    let block_size = format.block_size(None).unwrap_or(4);
+    let blocks = u64::from(width.div_ceil(block_w)) * u64::from(height.div_ceil(block_h));
+    blocks * u64::from(block_size) * u64::from(depth) * u64::from(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::tests::headless_context,
+        graphics::{Image, InstanceArray, Mesh, MeshData, Vertex},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn headless_test_resource_report() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let baseline = ctx.gfx.resource_bytes();
+        let report = ctx.gfx.resource_report();
+        assert_eq!(report.iter().map(|r| r.bytes).sum::<u64>(), baseline);
+        // The frame images of the 16x8 window.
+        assert!(report
+            .iter()
+            .any(|r| r.kind == ResourceKind::CanvasImage && r.bytes == 16 * 8 * 4));
+
+        let image = Image::from_color(&ctx, 4, 2, None);
+        let copy = image.clone();
+        let info = ResourceInfo {
+            label: String::from("image"),
+            kind: ResourceKind::Image,
+            bytes: 4 * 2 * 4,
+        };
+        assert!(ctx.gfx.resource_report().contains(&info));
+        assert_eq!(ctx.gfx.resource_bytes(), baseline + 32);
+        drop(image);
+        assert_eq!(ctx.gfx.resource_bytes(), baseline + 32);
+        drop(copy);
+        assert_eq!(ctx.gfx.resource_bytes(), baseline);
+
+        let vertex = Vertex {
+            position: [0., 0.],
+            uv: [0., 0.],
+            color: [1.; 4],
+        };
+        let mesh = Mesh::from_data(
+            &ctx,
+            MeshData {
+                vertices: &[vertex; 3],
+                indices: &[0, 1, 2],
+            },
+        );
+        let instances = InstanceArray::new(&ctx, None);
+        let kinds = |ctx: &crate::Context, kind| {
+            ctx.gfx
+                .resource_report()
+                .iter()
+                .filter(|r| r.kind == kind)
+                .map(|r| r.bytes)
+                .sum::<u64>()
+        };
+        assert_eq!(
+            kinds(&ctx, ResourceKind::MeshBuffer),
+            ctx.gfx.rect_mesh.verts.size()
+                + ctx.gfx.rect_mesh.inds.size()
+                + mesh.verts.size()
+                + mesh.inds.size()
+        );
+        assert!(kinds(&ctx, ResourceKind::InstanceBuffer) > 0);
+        drop((mesh, instances));
+        assert_eq!(ctx.gfx.resource_bytes(), baseline);
+
+        // The warning runs once each time the total goes above the threshold.
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let log = warnings.clone();
+        ctx.gfx
+            .set_memory_warning_threshold(baseline + 100, move |_, total| {
+                log.borrow_mut().push(total)
+            });
+        let frame = |ctx: &mut crate::Context| {
+            ctx.gfx.begin_frame().unwrap();
+            ctx.gfx.end_frame().unwrap();
+        };
+        frame(&mut ctx);
+        let image = Image::from_color(&ctx, 8, 8, None);
+        frame(&mut ctx);
+        frame(&mut ctx);
+        assert_eq!(*warnings.borrow(), [baseline + 256]);
+        drop(image);
+        frame(&mut ctx);
+        let _image = Image::from_color(&ctx, 8, 8, None);
+        frame(&mut ctx);
+        assert_eq!(*warnings.borrow(), [baseline + 256; 2]);
+    }
+}
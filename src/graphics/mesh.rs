@@ -1,8 +1,10 @@
 use super::{
-    context::GraphicsContext, gpu::arc::ArcBuffer, Canvas, Color, Draw, DrawMode, DrawParam,
-    Drawable, LinearColor, Rect, WgpuContext,
+    context::GraphicsContext, gpu::arc::ArcBuffer, outline, Canvas, Color, ColorSpaceMode, Draw,
+    DrawMode, DrawParam, Drawable, FillOptions, FillRule, Image, LinearColor, Rect, ResourceKind,
+    WgpuContext,
 };
 use crate::{context::Has, GameError, GameResult};
+use glam::Vec2;
 use lyon::{math::Point as LPoint, path::Polygon, tessellation as tess};
 use wgpu::util::DeviceExt;
 
@@ -14,7 +16,7 @@ pub struct Vertex {
     pub position: [f32; 2],
     /// `vec2` UV/texture coordinates.
     pub uv: [f32; 2],
-    /// `vec4` color.
+    /// `vec4` color, in linear space like [`LinearColor`].
     pub color: [f32; 4],
 }
 
@@ -60,7 +62,24 @@ impl Mesh {
     /// Create a new mesh from [`MeshData`].
     pub fn from_data(gfx: &impl Has<GraphicsContext>, raw: MeshData) -> Self {
         let gfx = gfx.retrieve();
-        Self::from_data_wgpu(&gfx.wgpu, raw)
+        match gfx.color_space {
+            ColorSpaceMode::SrgbCorrect => Self::from_data_wgpu(&gfx.wgpu, raw),
+            ColorSpaceMode::Legacy => {
+                let vertices = raw
+                    .vertices
+                    .iter()
+                    .map(|vert| Vertex {
+                        color: gfx.color_space.shader_color_linear(vert.color),
+                        ..*vert
+                    })
+                    .collect::<Vec<_>>();
+                let raw = MeshData {
+                    vertices: &vertices,
+                    indices: raw.indices,
+                };
+                Self::from_data_wgpu(&gfx.wgpu, raw)
+            }
+        }
     }
 
     pub(crate) fn from_data_wgpu(wgpu: &WgpuContext, raw: MeshData) -> Self {
@@ -164,6 +183,27 @@ impl Mesh {
         ))
     }
 
+    /// Create a new mesh for a closed polygon with holes cut out of it.
+    ///
+    /// See [`MeshBuilder::polygon_with_holes`].
+    pub fn new_polygon_with_holes<P>(
+        gfx: &impl Has<GraphicsContext>,
+        mode: DrawMode,
+        outer: &[P],
+        holes: &[impl AsRef<[P]>],
+        color: Color,
+    ) -> GameResult<Self>
+    where
+        P: Into<mint::Point2<f32>> + Clone,
+    {
+        Ok(Mesh::from_data(
+            gfx,
+            MeshBuilder::new()
+                .polygon_with_holes(mode, outer, holes, color)?
+                .build(),
+        ))
+    }
+
     /// Create a new mesh for a rectangle.
     pub fn new_rectangle(
         gfx: &impl Has<GraphicsContext>,
@@ -205,6 +245,114 @@ impl Mesh {
         ))
     }
 
+    /// Create a new mesh covering the visible pixels of an image, along with the outlines
+    /// it was built from, for example for collision shapes or click tests.
+    ///
+    /// Pixels whose alpha is above `threshold` count as solid. Their outlines are traced
+    /// with marching squares and then simplified so that no dropped point is further than
+    /// `simplify_epsilon` pixels from the result. There is one outline for every island
+    /// and every hole, in pixel coordinates with the origin at the image's top left, and
+    /// the mesh fills them with [`FillRule::EvenOdd`](super::FillRule::EvenOdd).
+    ///
+    /// The image is read back from the GPU, which is very expensive; if you still have
+    /// the pixels, use [`from_alpha_bytes`](Self::from_alpha_bytes) instead. Only images
+    /// with 8 bit alpha, such as [`ImageFormat::Rgba8UnormSrgb`](super::ImageFormat), are
+    /// supported.
+    pub fn from_image_alpha(
+        gfx: &impl Has<GraphicsContext>,
+        image: &Image,
+        threshold: u8,
+        simplify_epsilon: f32,
+    ) -> GameResult<(Self, Vec<Vec<Vec2>>)> {
+        use wgpu::TextureFormat as F;
+        let (stride, alpha) = match image.format() {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb | F::Bgra8Unorm | F::Bgra8UnormSrgb => (4, 3),
+            F::Rg8Unorm => (2, 1),
+            format => {
+                return Err(GameError::RenderError(format!(
+                    "Mesh::from_image_alpha() does not support {format:?} images"
+                )))
+            }
+        };
+        let pixels = image.to_pixels(gfx)?;
+        Self::from_alpha(
+            gfx,
+            &pixels,
+            (image.width(), image.height()),
+            (stride, alpha),
+            threshold,
+            simplify_epsilon,
+        )
+    }
+
+    /// Like [`from_image_alpha`](Self::from_image_alpha), but takes the image as tightly
+    /// packed RGBA8 `pixels` rather than reading it back from the GPU.
+    pub fn from_alpha_bytes(
+        gfx: &impl Has<GraphicsContext>,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        threshold: u8,
+        simplify_epsilon: f32,
+    ) -> GameResult<(Self, Vec<Vec<Vec2>>)> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(GameError::RenderError(format!(
+                "Mesh::from_alpha_bytes() got {} bytes, expected {} for {width}x{height} RGBA8 pixels",
+                pixels.len(),
+                width as usize * height as usize * 4
+            )));
+        }
+        Self::from_alpha(
+            gfx,
+            pixels,
+            (width, height),
+            (4, 3),
+            threshold,
+            simplify_epsilon,
+        )
+    }
+
+    fn from_alpha(
+        gfx: &impl Has<GraphicsContext>,
+        pixels: &[u8],
+        (width, height): (u32, u32),
+        (stride, alpha): (usize, usize),
+        threshold: u8,
+        simplify_epsilon: f32,
+    ) -> GameResult<(Self, Vec<Vec<Vec2>>)> {
+        let solid = |x: u32, y: u32| {
+            let pixel = (y as usize * width as usize + x as usize) * stride;
+            pixels[pixel + alpha] > threshold
+        };
+        let outlines: Vec<_> = outline::trace(width, height, solid)
+            .iter()
+            .map(|ring| outline::simplify(ring, simplify_epsilon))
+            .filter(|ring| !ring.is_empty())
+            .collect();
+        if outlines.is_empty() {
+            return Err(GameError::LyonError(format!(
+                "no pixels have an alpha above {threshold}"
+            )));
+        }
+
+        let mut path_builder = tess::path::Path::builder();
+        for ring in &outlines {
+            let points: Vec<LPoint> = ring.iter().map(|p| tess::math::point(p.x, p.y)).collect();
+            path_builder.add_polygon(Polygon {
+                points: &points,
+                closed: true,
+            });
+        }
+        let mode = DrawMode::Fill(FillOptions::default().with_fill_rule(FillRule::EvenOdd));
+        let mesh = Mesh::from_data(
+            gfx,
+            MeshBuilder::new()
+                .from_lyon_path(mode, &path_builder.build(), Color::WHITE)?
+                .build(),
+        );
+        Ok((mesh, outlines))
+    }
+
     /// Returns the WGPU vertex and index buffers of this mesh, in that order.
     #[inline]
     pub fn wgpu(&self) -> (&wgpu::Buffer, &wgpu::Buffer) {
@@ -233,6 +381,12 @@ impl Mesh {
                     usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
                 }),
         )
+        .track(
+            &wgpu.resources,
+            ResourceKind::MeshBuffer,
+            "mesh vertices",
+            std::mem::size_of_val(vertices) as u64,
+        )
     }
 
     #[allow(unsafe_code)]
@@ -245,6 +399,12 @@ impl Mesh {
                     usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
                 }),
         )
+        .track(
+            &wgpu.resources,
+            ResourceKind::MeshBuffer,
+            "mesh indices",
+            std::mem::size_of_val(indices) as u64,
+        )
     }
 }
 
@@ -469,6 +629,82 @@ impl MeshBuilder {
         self.polyline_inner(mode, points, true, color)
     }
 
+    /// Create a new mesh for a closed polygon with holes cut out of it,
+    /// such as a ring or the outline of a glyph.
+    ///
+    /// Each of `outer` and `holes` is a closed ring of points. Which areas end up
+    /// filled is decided by the fill rule of the [`FillOptions`](super::FillOptions)
+    /// in `mode`: with the default [`FillRule::EvenOdd`](super::FillRule::EvenOdd)
+    /// the holes are cut out regardless of their winding, while with
+    /// [`FillRule::NonZero`](super::FillRule::NonZero) they must wind the opposite
+    /// way to `outer`.
+    pub fn polygon_with_holes<P>(
+        &mut self,
+        mode: DrawMode,
+        outer: &[P],
+        holes: &[impl AsRef<[P]>],
+        color: Color,
+    ) -> GameResult<&mut Self>
+    where
+        P: Into<mint::Point2<f32>> + Clone,
+    {
+        let rings = std::iter::once(outer).chain(holes.iter().map(AsRef::as_ref));
+        let mut path_builder = tess::path::Path::builder();
+        for ring in rings {
+            if ring.len() < 3 {
+                return Err(GameError::LyonError(
+                    "MeshBuilder::polygon_with_holes() got a ring of < 3 points".to_string(),
+                ));
+            }
+            let points: Vec<LPoint> = ring
+                .iter()
+                .cloned()
+                .map(|p| {
+                    let mint_point: mint::Point2<f32> = p.into();
+                    tess::math::point(mint_point.x, mint_point.y)
+                })
+                .collect();
+            path_builder.add_polygon(Polygon {
+                points: &points,
+                closed: true,
+            });
+        }
+
+        self.from_lyon_path(mode, &path_builder.build(), color)
+    }
+
+    /// Create a new mesh by tessellating an arbitrary [`lyon`] path, which may
+    /// contain curves and several sub-paths.
+    ///
+    /// As with [`polygon_with_holes`](Self::polygon_with_holes), overlapping sub-paths
+    /// are filled according to the fill rule of `mode`.
+    pub fn from_lyon_path(
+        &mut self,
+        mode: DrawMode,
+        path: &tess::path::Path,
+        color: Color,
+    ) -> GameResult<&mut Self> {
+        {
+            let buffers = &mut self.buffer;
+            let vb = VertexBuilder {
+                color: LinearColor::from(color),
+            };
+            match mode {
+                DrawMode::Fill(fill_options) => {
+                    let builder = &mut tess::BuffersBuilder::new(buffers, vb);
+                    let mut tessellator = tess::FillTessellator::new();
+                    tessellator.tessellate_path(path, &fill_options, builder)?;
+                }
+                DrawMode::Stroke(options) => {
+                    let builder = &mut tess::BuffersBuilder::new(buffers, vb);
+                    let mut tessellator = tess::StrokeTessellator::new();
+                    tessellator.tessellate_path(path, &options, builder)?;
+                }
+            };
+        }
+        Ok(self)
+    }
+
     fn polyline_inner<P>(
         &mut self,
         mode: DrawMode,
@@ -691,3 +927,70 @@ impl tess::FillVertexConstructor<Vertex> for VertexBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: f32, max: f32) -> Vec<[f32; 2]> {
+        vec![[min, min], [max, min], [max, max], [min, max]]
+    }
+
+    #[test]
+    fn headless_test_polygon_with_holes() {
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let mut hole = square(3., 7.);
+            if rule == FillRule::NonZero {
+                hole.reverse();
+            }
+
+            let mut builder = MeshBuilder::new();
+            let _ = builder
+                .polygon_with_holes(
+                    DrawMode::Fill(FillOptions::default().with_fill_rule(rule)),
+                    &square(0., 10.),
+                    &[hole],
+                    Color::WHITE,
+                )
+                .unwrap();
+            let data = builder.build();
+            assert_eq!(data.indices.len() / 3, 8);
+            assert_eq!(data.vertices.len(), 8);
+        }
+
+        assert!(MeshBuilder::new()
+            .polygon_with_holes(
+                DrawMode::fill(),
+                &square(0., 10.),
+                &[&[[0., 0.]]],
+                Color::WHITE
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn headless_test_mesh_from_alpha_bytes() {
+        let Some(ctx) = crate::context::tests::headless_context() else {
+            return;
+        };
+        let (width, height) = (6, 4);
+        let mut pixels = vec![0; width * height * 4];
+        for y in 1..3 {
+            for x in 1..5 {
+                pixels[(y * width + x) * 4 + 3] = 200;
+            }
+        }
+
+        let (mesh, outlines) =
+            Mesh::from_alpha_bytes(&ctx, &pixels, width as u32, height as u32, 127, 0.).unwrap();
+        assert_eq!(outlines.len(), 1);
+        assert_eq!(outlines[0].len(), 8);
+        assert!(outlines[0]
+            .iter()
+            .all(|p| (1.0..=5.0).contains(&p.x) && (1.0..=3.0).contains(&p.y)));
+        assert!(mesh.index_count() >= 6 * 3);
+
+        assert!(Mesh::from_alpha_bytes(&ctx, &pixels, 6, 4, 200, 0.).is_err());
+        assert!(Mesh::from_alpha_bytes(&ctx, &pixels, 5, 4, 127, 0.).is_err());
+    }
+}
@@ -21,23 +21,39 @@
 //! [custom shader]:Canvas::set_shader
 //! [blend mode]:Canvas::set_blend_mode
 
+mod blit;
 pub(crate) mod canvas;
 pub(crate) mod context;
+mod dds;
 pub(crate) mod draw;
 pub(crate) mod gpu;
 pub(crate) mod image;
 pub(crate) mod instance;
 pub(crate) mod internal_canvas;
+mod ktx2;
+pub mod light;
+pub(crate) mod lut;
+pub(crate) mod memory;
 pub(crate) mod mesh;
+mod outline;
+pub(crate) mod overlay;
+pub(crate) mod profiler;
 pub(crate) mod sampler;
 pub(crate) mod shader;
+pub(crate) mod sprite;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
 pub(crate) mod text;
 mod types;
+pub(crate) mod window;
 
+pub use gpu::upload::UploadSlice;
 pub use lyon::tessellation::{FillOptions, FillRule, LineCap, LineJoin, StrokeOptions};
+pub use winit::window::WindowLevel;
 pub use {
-    self::image::*, canvas::*, context::*, draw::*, instance::*, mesh::*, sampler::*, shader::*,
-    text::*, types::*,
+    self::image::*, canvas::*, context::*, draw::*, instance::*, light::*, lut::*, memory::*,
+    mesh::*, overlay::*, profiler::*, sampler::*, shader::*, sprite::*, text::*, types::*,
+    window::*,
 };
 
 /// Applies `DrawParam` to `Rect`.
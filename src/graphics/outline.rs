@@ -0,0 +1,204 @@
+//! Outline tracing for [`Mesh::from_image_alpha`](super::Mesh::from_image_alpha).
+
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
+
+/// Which cell edge a marching squares segment ends on.
+#[derive(Clone, Copy)]
+enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/// The segments through a cell, indexed by which of its corners are solid: top left is 8,
+/// top right 4, bottom right 2 and bottom left 1.
+///
+/// In the two saddle cases the solid corners are kept apart, so pixels touching only
+/// diagonally end up in separate outlines.
+const CASES: [&[(Edge, Edge)]; 16] = {
+    use Edge::*;
+    [
+        &[],
+        &[(Bottom, Left)],
+        &[(Right, Bottom)],
+        &[(Right, Left)],
+        &[(Top, Right)],
+        &[(Top, Right), (Bottom, Left)],
+        &[(Top, Bottom)],
+        &[(Top, Left)],
+        &[(Top, Left)],
+        &[(Top, Bottom)],
+        &[(Top, Left), (Right, Bottom)],
+        &[(Top, Right)],
+        &[(Right, Left)],
+        &[(Right, Bottom)],
+        &[(Bottom, Left)],
+        &[],
+    ]
+};
+
+/// Traces the outlines of the solid pixels of a `width` by `height` grid with marching
+/// squares, sampling at pixel centers.
+///
+/// Returns one closed ring per island and per hole, in pixel coordinates. Consecutive
+/// points may be collinear; see [`simplify`].
+pub(crate) fn trace(width: u32, height: u32, solid: impl Fn(u32, u32) -> bool) -> Vec<Vec<Vec2>> {
+    let (w, h) = (width as i32, height as i32);
+    let sample = |x: i32, y: i32| x >= 0 && y >= 0 && x < w && y < h && solid(x as u32, y as u32);
+
+    // Points are kept in doubled coordinates so that edge midpoints stay integers. Every
+    // crossed edge is shared by exactly two cells, so each point joins exactly two segments.
+    let mut order = Vec::new();
+    let mut neighbours: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    for cy in -1..h {
+        for cx in -1..w {
+            let case = usize::from(sample(cx, cy)) << 3
+                | usize::from(sample(cx + 1, cy)) << 2
+                | usize::from(sample(cx + 1, cy + 1)) << 1
+                | usize::from(sample(cx, cy + 1));
+            let midpoint = |edge| match edge {
+                Edge::Top => (2 * cx + 2, 2 * cy + 1),
+                Edge::Right => (2 * cx + 3, 2 * cy + 2),
+                Edge::Bottom => (2 * cx + 2, 2 * cy + 3),
+                Edge::Left => (2 * cx + 1, 2 * cy + 2),
+            };
+            for &(a, b) in CASES[case] {
+                let (a, b) = (midpoint(a), midpoint(b));
+                for (from, to) in [(a, b), (b, a)] {
+                    let entry = neighbours.entry(from).or_default();
+                    if entry.is_empty() {
+                        order.push(from);
+                    }
+                    entry.push(to);
+                }
+            }
+        }
+    }
+
+    let mut rings = Vec::new();
+    let mut visited = HashSet::with_capacity(neighbours.len());
+    for start in order {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut ring = vec![start];
+        let (mut prev, mut current) = (start, neighbours[&start][0]);
+        while current != start {
+            let _ = visited.insert(current);
+            ring.push(current);
+            let next = neighbours[&current]
+                .iter()
+                .copied()
+                .find(|&p| p != prev)
+                .unwrap_or(prev); // A ring of two points only happens in degenerate grids.
+            prev = current;
+            current = next;
+        }
+        rings.push(
+            ring.into_iter()
+                .map(|(x, y)| Vec2::new(x as f32, y as f32) / 2.)
+                .collect(),
+        );
+    }
+    rings
+}
+
+/// Simplifies a closed ring with the Douglas-Peucker algorithm, dropping points that lie
+/// no further than `epsilon` from the simplified outline.
+///
+/// Returns an empty ring if fewer than three points would remain.
+pub(crate) fn simplify(ring: &[Vec2], epsilon: f32) -> Vec<Vec2> {
+    if ring.len() < 3 {
+        return Vec::new();
+    }
+
+    // Split the ring at the point furthest from the first one, then simplify both halves
+    // as open polylines.
+    let far = (1..ring.len())
+        .max_by(|&a, &b| {
+            let da = ring[a].distance_squared(ring[0]);
+            let db = ring[b].distance_squared(ring[0]);
+            da.total_cmp(&db)
+        })
+        .unwrap(); // Unwrap since the ring has at least three points.
+    let mut keep = vec![false; ring.len() + 1];
+    keep[0] = true;
+    keep[far] = true;
+    let closed = |i: usize| ring[i % ring.len()];
+
+    let mut stack = vec![(0, far), (far, ring.len())];
+    while let Some((start, end)) = stack.pop() {
+        let (a, b) = (closed(start), closed(end));
+        let furthest = (start + 1..end)
+            .map(|i| (i, segment_distance(closed(i), a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((i, distance)) = furthest {
+            if distance > epsilon {
+                keep[i] = true;
+                stack.push((start, i));
+                stack.push((i, end));
+            }
+        }
+    }
+
+    let simplified: Vec<_> = (0..ring.len())
+        .filter(|&i| keep[i])
+        .map(|i| ring[i])
+        .collect();
+    if simplified.len() < 3 {
+        Vec::new()
+    } else {
+        simplified
+    }
+}
+
+fn segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab == Vec2::ZERO {
+        0.
+    } else {
+        ((p - a).dot(ab) / ab.length_squared()).clamp(0., 1.)
+    };
+    p.distance(a + t * ab)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(ring: &[Vec2]) -> f32 {
+        let n = ring.len();
+        (0..n)
+            .map(|i| ring[i].perp_dot(ring[(i + 1) % n]))
+            .sum::<f32>()
+            .abs()
+            / 2.
+    }
+
+    #[test]
+    fn headless_test_trace_and_simplify() {
+        // A 4x4 block with a 2x2 hole, plus a lone pixel and a pixel touching it diagonally.
+        let solid = |x: u32, y: u32| {
+            let block = (1..5).contains(&x) && (1..5).contains(&y);
+            let hole = (2..4).contains(&x) && (2..4).contains(&y);
+            (block && !hole) || (x, y) == (6, 1) || (x, y) == (7, 2)
+        };
+        let rings: Vec<_> = trace(8, 6, solid)
+            .iter()
+            .map(|ring| simplify(ring, 0.))
+            .collect();
+        assert_eq!(rings.len(), 4);
+
+        let mut counts: Vec<_> = rings.iter().map(|r| (r.len(), area(r))).collect();
+        counts.sort_by(|a, b| a.1.total_cmp(&b.1));
+        // The lone pixels become diamonds, while the block and its hole have their corners cut.
+        assert_eq!(counts, [(4, 0.5), (4, 0.5), (8, 3.5), (8, 15.5)]);
+
+        let rough = simplify(&rings[0], 1.);
+        assert!(rough.len() >= 3 && rough.len() < 8);
+        assert!(simplify(&rings[0], 100.).is_empty());
+        assert!(trace(3, 3, |_, _| false).is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+use super::{
+    context::GraphicsContext, Canvas, Color, DrawParam, Drawable, Image, Mesh, MeshData, Quad,
+    Rect, Text, Transform, Vertex,
+};
+use crate::{context::Has, Context, GameError, GameResult};
+use std::fmt::Write;
+
+/// How many of the most recent frames the graph shows.
+const GRAPH_FRAMES: usize = 120;
+const BAR_WIDTH: f32 = 2.;
+const GRAPH_HEIGHT: f32 = 40.;
+const TEXT_HEIGHT: f32 = 68.;
+const PADDING: f32 = 6.;
+const WIDTH: f32 = 2. * PADDING + GRAPH_FRAMES as f32 * BAR_WIDTH;
+const HEIGHT: f32 = 3. * PADDING + TEXT_HEIGHT + GRAPH_HEIGHT;
+/// Frame time the graph is scaled to, unless a frame took longer.
+const GRAPH_MILLIS: f32 = 1000. / 30.;
+
+/// A panel showing the frame rate, a graph of the recent frame times and the
+/// [`FrameStats`](super::FrameStats) of the last frame.
+///
+/// Call [`update`](Self::update) once per frame and draw it like any other drawable:
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{Canvas, Color, DebugOverlay}, Context, GameResult};
+/// # fn t(ctx: &mut Context, overlay: &mut DebugOverlay) -> GameResult {
+/// overlay.update(ctx);
+/// let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+/// canvas.draw(overlay, [10., 10.]);
+/// canvas.finish(ctx)
+/// # }
+/// ```
+///
+/// The graph mesh is created once and rewritten in place by `update`. Only the transform
+/// and Z index of the draw parameters are used.
+#[derive(Debug, Clone)]
+pub struct DebugOverlay {
+    graph: Mesh,
+    vertices: Vec<Vertex>,
+    millis: Vec<f32>,
+    text: Text,
+}
+
+impl DebugOverlay {
+    /// Creates an overlay; it shows nothing useful until the first [`update`](Self::update).
+    pub fn new(gfx: &impl Has<GraphicsContext>) -> Self {
+        let vertices = vec![
+            Vertex {
+                position: [0., 0.],
+                uv: [0., 0.],
+                color: [0., 0., 0., 0.],
+            };
+            4 * GRAPH_FRAMES
+        ];
+        let indices = (0..GRAPH_FRAMES as u32)
+            .flat_map(|bar| [0, 1, 2, 2, 1, 3].map(|i| 4 * bar + i))
+            .collect::<Vec<_>>();
+        let graph = Mesh::from_data(
+            gfx,
+            MeshData {
+                vertices: &vertices,
+                indices: &indices,
+            },
+        );
+
+        gfx.retrieve().frame_stats.enable();
+
+        let mut text = Text::new("");
+        let _ = text.set_scale(14.);
+        DebugOverlay {
+            graph,
+            vertices,
+            millis: Vec::with_capacity(GRAPH_FRAMES),
+            text,
+        }
+    }
+
+    /// Updates the overlay with the timings and stats of the frames so far.
+    pub fn update(&mut self, ctx: &Context) {
+        let frames = ctx.time.frame_durations();
+        let skip = frames.len().saturating_sub(GRAPH_FRAMES);
+        self.millis.clear();
+        self.millis
+            .extend(frames.skip(skip).map(|d| d.as_secs_f32() * 1000.));
+
+        let max = self.millis.iter().copied().fold(GRAPH_MILLIS, f32::max);
+        let first = GRAPH_FRAMES - self.millis.len();
+        let bottom = HEIGHT - PADDING;
+        for (bar, quad) in self.vertices.chunks_exact_mut(4).enumerate() {
+            let millis = bar.checked_sub(first).map_or(0., |i| self.millis[i]);
+            let color = if millis <= 1000. / 55. {
+                Color::GREEN
+            } else if millis <= GRAPH_MILLIS {
+                Color::YELLOW
+            } else {
+                Color::RED
+            };
+            let color = ctx.gfx.color_space.shader_color(color).into();
+            let x = PADDING + bar as f32 * BAR_WIDTH;
+            let top = bottom - millis / max * GRAPH_HEIGHT;
+            for (vertex, (x, y)) in quad.iter_mut().zip([
+                (x, top),
+                (x + BAR_WIDTH, top),
+                (x, bottom),
+                (x + BAR_WIDTH, bottom),
+            ]) {
+                vertex.position = [x, y];
+                vertex.color = color;
+            }
+        }
+        ctx.gfx
+            .wgpu
+            .queue
+            .write_buffer(&self.graph.verts, 0, bytemuck::cast_slice(&self.vertices));
+
+        let stats = ctx.gfx.frame_stats();
+        let fragment = &mut self.text.fragments_mut()[0].text;
+        fragment.clear();
+        let _ = write!(
+            fragment,
+            "{:.1} FPS, {:.2} ms\ndraw calls: {}\ninstances: {}\nbuffers: {:.1} KiB",
+            ctx.time.fps(),
+            ctx.time.average_delta().as_secs_f64() * 1000.,
+            stats.draw_calls,
+            stats.instances,
+            stats.buffer_bytes as f64 / 1024.,
+        );
+    }
+}
+
+impl Drawable for DebugOverlay {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        let param = param.into();
+        let transform = glam::Mat4::from(param.transform.to_bare_matrix());
+        let place = |x: f32, y: f32, w: f32, h: f32, color: Color| {
+            let local = glam::Mat4::from_translation(glam::vec3(x, y, 0.))
+                * glam::Mat4::from_scale(glam::vec3(w, h, 1.));
+            DrawParam {
+                transform: Transform::Matrix((transform * local).into()),
+                ..DrawParam::new().color(color).z(param.z)
+            }
+        };
+
+        canvas.draw(
+            &Quad,
+            place(0., 0., WIDTH, HEIGHT, Color::new(0., 0., 0., 0.7)),
+        );
+        canvas.draw(&self.graph, place(0., 0., 1., 1., Color::WHITE));
+        canvas.draw(&self.text, place(PADDING, PADDING, 1., 1., Color::WHITE));
+    }
+
+    fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        Some(Rect::new(0., 0., WIDTH, HEIGHT))
+    }
+}
+
+/// Shows an error over a dimmed copy of the last frame that was drawn without one, see
+/// [`ErrorPolicy::Overlay`](crate::event::ErrorPolicy::Overlay).
+#[derive(Debug, Default)]
+pub(crate) struct ErrorOverlay {
+    last_frame: Option<Image>,
+    message: Option<String>,
+}
+
+impl ErrorOverlay {
+    pub(crate) fn is_shown(&self) -> bool {
+        self.message.is_some()
+    }
+
+    pub(crate) fn show(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub(crate) fn dismiss(&mut self) {
+        self.message = None;
+    }
+
+    /// Copies the frame drawn so far, to show behind the error if a later frame fails.
+    pub(crate) fn save_frame(&mut self, gfx: &mut GraphicsContext) -> GameResult {
+        let frame = gfx.frame_image.clone().unwrap(/* invariant */);
+        let reuse = self.last_frame.as_ref().is_some_and(|last| {
+            (last.width(), last.height(), last.format())
+                == (frame.width(), frame.height(), frame.format())
+        });
+        if !reuse {
+            self.last_frame = Some(Image::new(
+                &gfx.wgpu,
+                frame.format(),
+                frame.width(),
+                frame.height(),
+                1,
+                wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            ));
+        }
+        let last = self.last_frame.as_ref().unwrap(/* just set */);
+
+        let fcx = gfx.fcx.as_mut().ok_or_else(|| {
+            GameError::RenderError(String::from(
+                "cannot save the frame outside of a frame; call begin_frame first",
+            ))
+        })?;
+        fcx.cmd.copy_texture_to_texture(
+            frame.texture.as_image_copy(),
+            last.texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: frame.width(),
+                height: frame.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+
+    /// Draws the overlay over the whole frame, replacing whatever a failed frame drew.
+    pub(crate) fn draw(&self, gfx: &mut GraphicsContext) -> GameResult {
+        gfx.discard_ordered_canvases();
+        let mut canvas = Canvas::from_frame(gfx, Color::BLACK);
+        let screen = canvas.screen_coordinates().unwrap(/* set by from_frame */);
+        if let Some(ref frame) = self.last_frame {
+            canvas.draw(
+                frame,
+                DrawParam::new().scale([
+                    screen.w / frame.width() as f32,
+                    screen.h / frame.height() as f32,
+                ]),
+            );
+        }
+        canvas.draw(
+            &Quad,
+            DrawParam::new()
+                .scale([screen.w, screen.h])
+                .color(Color::new(0., 0., 0., 0.75)),
+        );
+
+        let mut text = Text::new(format!(
+            "{}\n\nPress R to retry, or Escape to quit.",
+            self.message.as_deref().unwrap_or_default()
+        ));
+        let _ = text
+            .set_scale(16.)
+            .set_bounds([screen.w - 4. * PADDING, f32::INFINITY]);
+        canvas.draw(
+            &text,
+            DrawParam::new()
+                .dest([2. * PADDING, 2. * PADDING])
+                .color(Color::WHITE),
+        );
+        canvas.finish(gfx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::tests::headless_context;
+
+    #[test]
+    fn headless_test_debug_overlay() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // Nothing is counted until the stats are asked for.
+        let frame = |ctx: &mut Context| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+            canvas.draw(&Quad, [0., 0.]);
+            canvas.draw(&Quad, [2., 0.]);
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+        };
+        frame(&mut ctx);
+        assert_eq!(ctx.gfx.frame_stats().draw_calls, 0);
+
+        // The same mesh drawn twice counts its buffers once.
+        frame(&mut ctx);
+        let stats = ctx.gfx.frame_stats();
+        assert_eq!((stats.draw_calls, stats.instances), (2, 2));
+        let quad = &ctx.gfx.rect_mesh;
+        assert_eq!(stats.buffer_bytes, quad.verts.size() + quad.inds.size());
+
+        let mut overlay = DebugOverlay::new(&ctx);
+        for _ in 0..3 {
+            ctx.time.tick();
+            overlay.update(&ctx);
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+            canvas.draw(&overlay, [10., 10.]);
+            canvas.finish(&mut ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+        }
+        assert!(overlay.text.contents().contains("draw calls: 3"));
+        assert_eq!(ctx.gfx.frame_stats().draw_calls, 3);
+        assert_eq!(
+            overlay.dimensions(&ctx),
+            Some(Rect::new(0., 0., WIDTH, HEIGHT))
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use super::{
+    context::{GraphicsContext, WgpuContext},
+    gpu::arc::ArcBuffer,
+    memory::{Allocation, ResourceKind, ResourceTracker},
+};
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Timestamps available per frame; each scope uses two.
+const MAX_QUERIES: u32 = 256;
+/// Frames that can wait for their timings to be read back at once.
+/// If the GPU falls further behind than this, frames go unprofiled rather than stalling.
+const MAX_READBACKS: usize = 3;
+
+/// GPU time spent inside a profiling scope during a frame.
+///
+/// See [`GraphicsContext::profiler`] and [`GraphicsContext::frame_gpu_timings`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuTiming {
+    /// Name the scope was opened with.
+    pub name: String,
+    /// How deeply nested the scope was; top-level scopes have a depth of 0.
+    pub depth: usize,
+    /// GPU time between the start and end of the scope, in milliseconds.
+    pub millis: f64,
+}
+
+struct Scope {
+    name: String,
+    depth: usize,
+    begin: u32,
+    end: Option<u32>,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    scopes: Vec<Scope>,
+    mapped: Arc<AtomicBool>,
+}
+
+/// Records timestamp queries around profiling scopes and reads them back without blocking.
+pub(crate) struct GpuProfiler {
+    queries: Option<wgpu::QuerySet>,
+    resolve: Option<wgpu::Buffer>,
+    period: f64,
+    next_query: u32,
+    scopes: Vec<Scope>,
+    open: Vec<usize>,
+    in_flight: Vec<Readback>,
+    pending: Option<Readback>,
+    free: Vec<wgpu::Buffer>,
+    timings: Vec<GpuTiming>,
+    resources: ResourceTracker,
+    /// The profiler's buffers are never freed while it's alive, only reused.
+    allocations: Vec<Allocation>,
+}
+
+impl GpuProfiler {
+    pub fn new(wgpu: &WgpuContext) -> Self {
+        let (device, queue) = (&wgpu.device, &wgpu.queue);
+        let supported = device.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+
+        let queries = supported.then(|| {
+            device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("ggez profiler queries"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_QUERIES,
+            })
+        });
+
+        let resolve = supported.then(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("ggez profiler resolve"),
+                size: Self::buffer_size(),
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+
+        let mut profiler = GpuProfiler {
+            queries,
+            resolve,
+            period: f64::from(queue.get_timestamp_period()),
+            next_query: 0,
+            scopes: Vec::new(),
+            open: Vec::new(),
+            in_flight: Vec::new(),
+            pending: None,
+            free: Vec::new(),
+            timings: Vec::new(),
+            resources: wgpu.resources.clone(),
+            allocations: Vec::new(),
+        };
+        if profiler.resolve.is_some() {
+            let allocation = profiler.resources.track(
+                ResourceKind::Internal,
+                "profiler resolve",
+                Self::buffer_size(),
+            );
+            profiler.allocations.push(allocation);
+        }
+        profiler
+    }
+
+    fn buffer_size() -> u64 {
+        u64::from(MAX_QUERIES) * std::mem::size_of::<u64>() as u64
+    }
+
+    pub fn timings(&self) -> &[GpuTiming] {
+        &self.timings
+    }
+
+    /// Collects the timings of any finished frames and resets the scopes for a new frame.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        self.next_query = 0;
+        self.scopes.clear();
+        self.open.clear();
+
+        if self.in_flight.is_empty() {
+            return;
+        }
+
+        let _ = device.poll(wgpu::Maintain::Poll);
+
+        // Only the latest finished frame is reported, older ones are just recycled.
+        let mut latest = None;
+        let mut i = 0;
+        while i < self.in_flight.len() {
+            if self.in_flight[i].mapped.load(Ordering::Acquire) {
+                let readback = self.in_flight.remove(i);
+                if let Some(old) = latest.replace(readback) {
+                    self.recycle(old);
+                }
+            } else {
+                i += 1;
+            }
+        }
+
+        if let Some(readback) = latest {
+            self.timings = {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                readback
+                    .scopes
+                    .iter()
+                    .filter_map(|scope| {
+                        let end = scope.end?;
+                        let elapsed = ticks[end as usize].wrapping_sub(ticks[scope.begin as usize]);
+                        Some(GpuTiming {
+                            name: scope.name.clone(),
+                            depth: scope.depth,
+                            millis: elapsed as f64 * self.period / 1_000_000.,
+                        })
+                    })
+                    .collect()
+            };
+            self.recycle(readback);
+        }
+    }
+
+    fn recycle(&mut self, readback: Readback) {
+        readback.buffer.unmap();
+        self.free.push(readback.buffer);
+    }
+
+    /// Writes the starting timestamp of a new scope, returning its index.
+    pub fn begin_scope(
+        &mut self,
+        cmd: &mut wgpu::CommandEncoder,
+        name: impl Into<String>,
+    ) -> Option<usize> {
+        let queries = self.queries.as_ref()?;
+        if self.next_query + 2 > MAX_QUERIES {
+            return None;
+        }
+
+        cmd.write_timestamp(queries, self.next_query);
+        self.scopes.push(Scope {
+            name: name.into(),
+            depth: self.open.len(),
+            begin: self.next_query,
+            end: None,
+        });
+        self.next_query += 1;
+
+        let index = self.scopes.len() - 1;
+        self.open.push(index);
+        Some(index)
+    }
+
+    /// Writes the ending timestamp of a scope returned by `begin_scope`.
+    pub fn end_scope(&mut self, cmd: &mut wgpu::CommandEncoder, index: usize) {
+        let queries = match self.queries {
+            Some(ref queries) => queries,
+            None => return,
+        };
+        // The scope may belong to a frame that has already ended.
+        let scope = match self.scopes.get_mut(index) {
+            Some(scope) if scope.end.is_none() => scope,
+            _ => return,
+        };
+
+        cmd.write_timestamp(queries, self.next_query);
+        scope.end = Some(self.next_query);
+        self.next_query += 1;
+        self.open.retain(|&i| i != index);
+    }
+
+    /// Resolves this frame's timestamps into a readback buffer; call before submitting `cmd`.
+    pub fn resolve(&mut self, device: &wgpu::Device, cmd: &mut wgpu::CommandEncoder) {
+        let (queries, resolve) = match (&self.queries, &self.resolve) {
+            (Some(queries), Some(resolve)) => (queries, resolve),
+            _ => return,
+        };
+        if self.next_query == 0 || self.in_flight.len() >= MAX_READBACKS {
+            return;
+        }
+
+        let buffer = match self.free.pop() {
+            Some(buffer) => buffer,
+            None => {
+                let allocation = self.resources.track(
+                    ResourceKind::Internal,
+                    "profiler readback",
+                    Self::buffer_size(),
+                );
+                self.allocations.push(allocation);
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("ggez profiler readback"),
+                    size: Self::buffer_size(),
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            }
+        };
+
+        let size = u64::from(self.next_query) * std::mem::size_of::<u64>() as u64;
+        cmd.resolve_query_set(queries, 0..self.next_query, resolve, 0);
+        cmd.copy_buffer_to_buffer(resolve, 0, &buffer, 0, size);
+
+        self.pending = Some(Readback {
+            buffer,
+            scopes: std::mem::take(&mut self.scopes),
+            mapped: Arc::new(AtomicBool::new(false)),
+        });
+    }
+
+    /// Starts mapping the buffer filled by `resolve`; call after submitting.
+    pub fn map(&mut self) {
+        if let Some(readback) = self.pending.take() {
+            let mapped = readback.mapped.clone();
+            readback
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    mapped.store(result.is_ok(), Ordering::Release)
+                });
+            self.in_flight.push(readback);
+        }
+    }
+}
+
+/// Counts of the rendering work recorded during a frame.
+///
+/// See [`GraphicsContext::frame_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Draw calls recorded by canvases.
+    pub draw_calls: u32,
+    /// Instances drawn; meshes and images count as one each, text as one per glyph.
+    pub instances: u64,
+    /// Size of the vertex, index and instance buffers that were drawn from, in bytes.
+    /// Buffers used several times count once.
+    pub buffer_bytes: u64,
+    /// Glyphs rasterized and uploaded to the glyph cache.
+    pub glyph_uploads: u32,
+    /// Size of the glyph cache at the end of the frame, in bytes,
+    /// see [`GraphicsContext::set_glyph_cache_size`](super::GraphicsContext::set_glyph_cache_size).
+    pub glyph_cache_bytes: u64,
+    /// Render pipelines still being compiled in the background at the end of the frame,
+    /// see [`GraphicsContext::pipelines_pending`](super::GraphicsContext::pipelines_pending).
+    pub pipelines_pending: u32,
+}
+
+/// Collects the [`FrameStats`] of the frame in progress.
+///
+/// Draws are only counted once [`enable`](Self::enable) was called, so games that never look
+/// at the stats don't pay for them.
+#[derive(Debug, Default)]
+pub(crate) struct FrameStatsCounter {
+    pub stats: FrameStats,
+    buffers: HashSet<u64>,
+    enabled: Cell<bool>,
+}
+
+impl FrameStatsCounter {
+    pub fn enable(&self) {
+        self.enabled.set(true);
+    }
+
+    pub fn reset(&mut self) {
+        self.stats = FrameStats::default();
+        self.buffers.clear();
+    }
+
+    pub fn draw(&mut self, instances: u32, buffers: &[&ArcBuffer]) {
+        if !self.enabled.get() {
+            return;
+        }
+        self.stats.draw_calls += 1;
+        self.stats.instances += u64::from(instances);
+        for buffer in buffers {
+            if self.buffers.insert(buffer.id()) {
+                self.stats.buffer_bytes += buffer.size();
+            }
+        }
+    }
+}
+
+/// Handle for opening GPU profiling scopes, returned by [`GraphicsContext::profiler`].
+pub struct Profiler<'a> {
+    gfx: &'a mut GraphicsContext,
+}
+
+impl<'a> Profiler<'a> {
+    pub(crate) fn new(gfx: &'a mut GraphicsContext) -> Self {
+        Profiler { gfx }
+    }
+
+    /// Opens a scope that measures the GPU time of all work recorded until it is dropped.
+    ///
+    /// The scope dereferences to the [`GraphicsContext`], so it can be passed to anything
+    /// taking one:
+    ///
+    /// ```rust,no_run
+    /// # use ggez::{graphics::{Canvas, Color}, GameResult};
+    /// # fn t(ctx: &mut ggez::Context) -> GameResult {
+    /// let canvas = Canvas::from_frame(ctx, Color::BLACK);
+    /// let mut gfx = ctx.gfx.profiler().scope("scene");
+    /// canvas.finish(&mut *gfx)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Scopes only record anything while a frame is in progress.
+    pub fn scope(self, name: impl Into<String>) -> ProfileScope<'a> {
+        let index = self.gfx.begin_gpu_scope(name);
+        ProfileScope {
+            gfx: self.gfx,
+            index,
+        }
+    }
+}
+
+/// An open GPU profiling scope, closed when dropped. See [`Profiler::scope`].
+pub struct ProfileScope<'a> {
+    gfx: &'a mut GraphicsContext,
+    index: Option<usize>,
+}
+
+impl fmt::Debug for Profiler<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Profiler: {self:p}>")
+    }
+}
+
+impl fmt::Debug for ProfileScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<ProfileScope: {self:p}>")
+    }
+}
+
+impl Deref for ProfileScope<'_> {
+    type Target = GraphicsContext;
+
+    fn deref(&self) -> &Self::Target {
+        self.gfx
+    }
+}
+
+impl DerefMut for ProfileScope<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.gfx
+    }
+}
+
+impl Drop for ProfileScope<'_> {
+    fn drop(&mut self) {
+        self.gfx.end_gpu_scope(self.index);
+    }
+}
@@ -1,17 +1,27 @@
+use std::collections::BTreeMap;
 use std::io::Read;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
-use crate::{context::Has, Context, GameError, GameResult};
+use crate::{
+    context::{Has, HasMut},
+    Context, GameError, GameResult,
+};
 
 use super::{
     context::GraphicsContext,
     gpu::{
-        arc::{ArcBindGroup, ArcBindGroupLayout, ArcSampler, ArcShaderModule, ArcTextureView},
+        arc::{
+            ArcBindGroup, ArcBindGroupLayout, ArcBuffer, ArcSampler, ArcShaderModule,
+            ArcTextureView,
+        },
         bind_group::BindGroupBuilder,
         growing::GrowingBufferArena,
     },
     image::Image,
+    internal_canvas::ShaderType,
     sampler::Sampler,
+    ImageFormat,
 };
 use crevice::std140::Std140;
 
@@ -90,45 +100,175 @@ impl<'a> ShaderBuilder<'a> {
     }
 
     /// Create a Shader from the builder.
+    ///
+    /// The shader code is checked here, so that mistakes are reported as errors with the
+    /// offending line rather than as graphics API panics when drawing. The resources the shader
+    /// binds to group 3 are recorded too, to be checked against the [`ShaderParams`] it is
+    /// drawn with. The layout of group 3 still comes from those params, so drawing with this
+    /// shader needs params that match it.
     pub fn build(self, gfx: &impl Has<GraphicsContext>) -> GameResult<Shader> {
         let gfx = gfx.retrieve();
-        let load = |s: &str| {
-            Some(ArcShaderModule::new(gfx.wgpu.device.create_shader_module(
+        let load = |s: &str, name: &str| -> GameResult<Module> {
+            let (reflection, info) = parse_wgsl(s, name)?;
+            let module = ArcShaderModule::new(gfx.wgpu.device.create_shader_module(
                 wgpu::ShaderModuleDescriptor {
                     label: None,
                     source: wgpu::ShaderSource::Wgsl(s.into()),
                 },
-            )))
+            ));
+            Ok((module, reflection, info))
         };
-        let load_resource = |path: &str| -> GameResult<Option<ArcShaderModule>> {
+        let load_resource = |path: &str| -> GameResult<Module> {
             let mut encoded = Vec::new();
-            _ = gfx.fs.open(path)?.read_to_end(&mut encoded)?;
-            Ok(load(
-                &String::from_utf8(encoded).map_err(GameError::ShaderEncodingError)?,
-            ))
+            _ = gfx
+                .fs
+                .open(path)
+                .and_then(|mut f| Ok(f.read_to_end(&mut encoded)?))
+                .map_err(|e| e.context(format!("reading shader {path}")))?;
+            let source = String::from_utf8(encoded).map_err(|e| {
+                GameError::ShaderEncodingError(e).context(format!("loading shader {path}"))
+            })?;
+            load(&source, path).map_err(|e| e.context(format!("loading shader {path}")))
         };
-        let load_any = |source| -> GameResult<Option<ArcShaderModule>> {
+        let load_any = |source| -> GameResult<Option<Module>> {
             Ok(match source {
-                ShaderSource::Code(source) => load(source),
-                ShaderSource::Path(source) => load_resource(source)?,
+                ShaderSource::Code(source) => Some(load(source, "wgsl")?),
+                ShaderSource::Path(source) => Some(load_resource(source)?),
                 ShaderSource::None => None,
             })
         };
-        Ok(if self.vs == self.fs {
-            let module = load_any(self.vs)?;
-            Shader {
-                vs_module: module.clone(),
-                fs_module: module,
+
+        let mut bindings = BTreeMap::new();
+        let mut reflect_any = |module: &Option<Module>, stage| -> GameResult {
+            if let Some((_, module, info)) = module {
+                reflect(module, info, stage, &mut bindings)?;
             }
+            Ok(())
+        };
+        let (vs_module, fs_module) = if self.vs == self.fs {
+            let module = load_any(self.vs)?;
+            reflect_any(&module, wgpu::ShaderStages::VERTEX)?;
+            reflect_any(&module, wgpu::ShaderStages::FRAGMENT)?;
+            let module = module.map(|(module, ..)| module);
+            (module.clone(), module)
         } else {
-            Shader {
-                vs_module: load_any(self.vs)?,
-                fs_module: load_any(self.fs)?,
-            }
+            let (vs, fs) = (load_any(self.vs)?, load_any(self.fs)?);
+            reflect_any(&vs, wgpu::ShaderStages::VERTEX)?;
+            reflect_any(&fs, wgpu::ShaderStages::FRAGMENT)?;
+            (vs.map(|(module, ..)| module), fs.map(|(module, ..)| module))
+        };
+
+        Ok(Shader {
+            vs_module,
+            fs_module,
+            bindings: Arc::new(bindings),
         })
     }
 }
 
+/// A loaded shader module, with its parsed source for reflection.
+type Module = (ArcShaderModule, naga::Module, naga::valid::ModuleInfo);
+
+/// Parses and validates WGSL code, so that errors can be returned with the offending line.
+fn parse_wgsl(source: &str, name: &str) -> GameResult<(naga::Module, naga::valid::ModuleInfo)> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+        GameError::RenderError(format!(
+            "invalid shader:\n{}",
+            e.emit_to_string_with_path(source, name)
+        ))
+    })?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| {
+        GameError::RenderError(format!(
+            "invalid shader:\n{}",
+            e.emit_to_string_with_path(source, name)
+        ))
+    })?;
+    Ok((module, info))
+}
+
+/// Records the group 3 resources used by the entry point a module is drawn with at `stage`.
+fn reflect(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    stage: wgpu::ShaderStages,
+    bindings: &mut BTreeMap<u32, ShaderBinding>,
+) -> GameResult {
+    let (name, entry_point, naga_stage) = if stage == wgpu::ShaderStages::VERTEX {
+        ("vertex", "vs_main", naga::ShaderStage::Vertex)
+    } else {
+        ("fragment", "fs_main", naga::ShaderStage::Fragment)
+    };
+    let index = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.name == entry_point && ep.stage == naga_stage)
+        .ok_or_else(|| {
+            GameError::RenderError(format!(
+                "the {name} shader has no `@{name} fn {entry_point}` entry point"
+            ))
+        })?;
+    let uses = info.get_entry_point(index);
+
+    for (handle, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        if uses[handle].is_empty() || binding.group < 3 {
+            continue;
+        }
+        if binding.group > 3 {
+            return Err(GameError::RenderError(format!(
+                "group {} binding {} is out of range; custom shaders bind their resources to group 3",
+                binding.group, binding.binding
+            )));
+        }
+
+        let ty = &module.types[global.ty].inner;
+        let kind = match (global.space, ty) {
+            (naga::AddressSpace::Uniform, _) => {
+                BindingKind::Uniform(u64::from(ty.size(&module.constants)))
+            }
+            (naga::AddressSpace::Storage { .. }, _) => BindingKind::Storage,
+            (
+                _,
+                naga::TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class:
+                        naga::ImageClass::Sampled {
+                            kind: naga::ScalarKind::Float,
+                            multi: false,
+                        },
+                },
+            ) => BindingKind::Texture(match (dim, arrayed) {
+                (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+                (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+                (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+            }),
+            (_, naga::TypeInner::Image { .. }) => BindingKind::OtherTexture,
+            (_, naga::TypeInner::Sampler { comparison: false }) => BindingKind::Sampler,
+            (_, naga::TypeInner::Sampler { comparison: true }) => BindingKind::ComparisonSampler,
+            _ => BindingKind::Other,
+        };
+        bindings
+            .entry(binding.binding)
+            .or_insert(ShaderBinding {
+                kind,
+                stages: wgpu::ShaderStages::empty(),
+            })
+            .stages |= stage;
+    }
+    Ok(())
+}
+
 impl Default for ShaderBuilder<'_> {
     fn default() -> Self {
         Self::new()
@@ -150,7 +290,7 @@ impl Default for ShaderBuilder<'_> {
 /// ```
 /// if the fragment module is left unspecified (default).
 ///
-/// Produce a Shader using [`ShaderBuilder`].
+/// Produce a Shader using [`ShaderBuilder`]. `Shader::default()` uses the default modules for both.
 ///
 /// Adapted from the `shader.rs` example:
 /// ```rust
@@ -187,10 +327,114 @@ impl Default for ShaderBuilder<'_> {
 ///     /* ... */
 /// }
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct Shader {
     pub(crate) vs_module: Option<ArcShaderModule>,
     pub(crate) fs_module: Option<ArcShaderModule>,
+    // what the shader expects at each binding of group 3, reflected from its code
+    pub(crate) bindings: Arc<BTreeMap<u32, ShaderBinding>>,
+}
+
+impl Shader {
+    /// Checks that shader params, described by what they bind to each binding of group 3,
+    /// provide what the shader's modules for `stages` expect there.
+    pub(crate) fn check_params(
+        &self,
+        params: Option<&[ShaderBinding]>,
+        stages: wgpu::ShaderStages,
+    ) -> GameResult {
+        let error = |message: String| {
+            Err(GameError::RenderError(format!(
+                "the shader params don't match the shader: {message}"
+            )))
+        };
+
+        for (&binding, expected) in self.bindings.iter() {
+            let expected = ShaderBinding {
+                kind: expected.kind,
+                stages: expected.stages & stages,
+            };
+            if expected.stages.is_empty() {
+                continue;
+            }
+            let Some(params) = params else {
+                return error(format!(
+                    "group 3 binding {binding} expects {}, but no shader params were set",
+                    expected.kind
+                ));
+            };
+            let Some(provided) = params.get(binding as usize) else {
+                return error(format!(
+                    "group 3 binding {binding} expects {}, but the shader params only have {} bindings",
+                    expected.kind,
+                    params.len()
+                ));
+            };
+            let matches = match (expected.kind, provided.kind) {
+                // the params' uniforms may be padded past what the shader reads
+                (BindingKind::Uniform(needed), BindingKind::Uniform(size)) => size >= needed,
+                (expected, provided) => expected == provided,
+            };
+            if !matches {
+                return error(format!(
+                    "group 3 binding {binding} expects {}, but the shader params provide {}",
+                    expected.kind, provided.kind
+                ));
+            }
+            if !provided.stages.contains(expected.stages) {
+                return error(format!(
+                    "group 3 binding {binding} is used by the vertex shader, so the shader params' images need to be visible to it (see `ShaderParamsBuilder::images`)"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The kind of resource at a binding of group 3, as expected by a shader or provided by
+/// shader params.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BindingKind {
+    /// A uniform buffer of the given size in bytes.
+    Uniform(u64),
+    Storage,
+    /// A filterable float texture, which is what images are bound as.
+    Texture(wgpu::TextureViewDimension),
+    /// Integer, depth, multisampled and storage textures.
+    OtherTexture,
+    Sampler,
+    ComparisonSampler,
+    /// Binding arrays and anything else shader params can't provide.
+    Other,
+}
+
+impl std::fmt::Display for BindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use wgpu::TextureViewDimension as Dim;
+        match self {
+            BindingKind::Uniform(size) => write!(f, "a uniform buffer of {size} bytes"),
+            BindingKind::Storage => f.write_str("a storage buffer"),
+            BindingKind::Texture(Dim::D1) => f.write_str("a texture_1d"),
+            BindingKind::Texture(Dim::D2) => f.write_str("a texture_2d"),
+            BindingKind::Texture(Dim::D2Array) => f.write_str("a texture_2d_array"),
+            BindingKind::Texture(Dim::Cube) => f.write_str("a texture_cube"),
+            BindingKind::Texture(Dim::CubeArray) => f.write_str("a texture_cube_array"),
+            BindingKind::Texture(Dim::D3) => f.write_str("a texture_3d"),
+            BindingKind::OtherTexture => {
+                f.write_str("an integer, depth, multisampled or storage texture")
+            }
+            BindingKind::Sampler => f.write_str("a sampler"),
+            BindingKind::ComparisonSampler => f.write_str("a comparison sampler"),
+            BindingKind::Other => f.write_str("a binding array or other unsupported resource"),
+        }
+    }
+}
+
+/// What is bound at a binding of group 3, and to which shader stages it is visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ShaderBinding {
+    pub kind: BindingKind,
+    pub stages: wgpu::ShaderStages,
 }
 
 use crevice::std140::AsStd140;
@@ -221,6 +465,9 @@ impl<'a, Uniforms: AsStd140> ShaderParamsBuilder<'a, Uniforms> {
 
     /// Provides images to the shaders.
     ///
+    /// Array and 3D images (see [`Image::new_array`] and [`Image::new_3d`]) are bound as
+    /// `texture_2d_array<f32>` and `texture_3d<f32>` respectively.
+    ///
     /// # Arguments
     ///
     /// * `vs_visible` - If the images should also be visible to the vertex shader, rather
@@ -242,7 +489,30 @@ impl<'a, Uniforms: AsStd140> ShaderParamsBuilder<'a, Uniforms> {
 
     /// Produce a [`ShaderParams`] from the builder.
     pub fn build(self, ctx: &mut Context) -> ShaderParams<Uniforms> {
-        let images = self.images.iter().map(|image| image.view.clone()).collect();
+        let vis = if self.images_vs_visible {
+            wgpu::ShaderStages::VERTEX_FRAGMENT
+        } else {
+            wgpu::ShaderStages::FRAGMENT
+        };
+        let bindings = std::iter::once(ShaderBinding {
+            kind: BindingKind::Uniform(Uniforms::std140_size_static() as u64),
+            stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        })
+        .chain(self.images.iter().map(|image| ShaderBinding {
+            kind: BindingKind::Texture(image.dimension),
+            stages: vis,
+        }))
+        .chain(self.samplers.iter().map(|_| ShaderBinding {
+            kind: BindingKind::Sampler,
+            stages: vis,
+        }))
+        .collect();
+
+        let images = self
+            .images
+            .iter()
+            .map(|image| (image.view.clone(), image.dimension))
+            .collect();
         let samplers = self
             .samplers
             .iter()
@@ -251,7 +521,7 @@ impl<'a, Uniforms: AsStd140> ShaderParamsBuilder<'a, Uniforms> {
 
         let mut params = ShaderParams {
             uniform_arena: GrowingBufferArena::new(
-                &ctx.gfx.wgpu.device,
+                &ctx.gfx.wgpu,
                 u64::from(
                     ctx.gfx
                         .wgpu
@@ -266,17 +536,20 @@ impl<'a, Uniforms: AsStd140> ShaderParamsBuilder<'a, Uniforms> {
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                     mapped_at_creation: false,
                 },
+                "shader uniforms",
             ),
             layout: None,
             bind_group: None,
             buffer_offset: 0,
+            bound_buffer: None,
             images,
             samplers,
             images_vs_visible: self.images_vs_visible,
-            last_tick: 0,
+            bindings,
+            last_frame: None,
             _marker: PhantomData,
         };
-        params.set_uniforms(ctx, self.uniforms);
+        params.set(ctx, self.uniforms);
         params
     }
 }
@@ -309,10 +582,14 @@ pub struct ShaderParams<Uniforms: AsStd140> {
     pub(crate) layout: Option<ArcBindGroupLayout>,
     pub(crate) bind_group: Option<ArcBindGroup>,
     pub(crate) buffer_offset: u32,
-    images: Vec<ArcTextureView>,
+    // what is bound at each binding, to check against the shader
+    pub(crate) bindings: Arc<[ShaderBinding]>,
+    // id of the arena buffer the bind group currently points at
+    bound_buffer: Option<u64>,
+    images: Vec<(ArcTextureView, wgpu::TextureViewDimension)>,
     samplers: Vec<ArcSampler>,
     images_vs_visible: bool,
-    last_tick: usize,
+    last_frame: Option<u64>,
     _marker: PhantomData<Uniforms>,
 }
 
@@ -325,30 +602,64 @@ impl<Uniforms: AsStd140> ShaderParams<Uniforms> {
     /// Updates the uniform data.
     ///
     /// When called, [`Canvas::set_shader_params`] (or [`Canvas::set_text_shader_params`]) **needs to be called again** for the new uniforms to take effect.
+    ///
+    /// This is equivalent to [`ShaderParams::set`].
     pub fn set_uniforms(&mut self, ctx: &mut Context, uniforms: &Uniforms) {
-        if ctx.time.ticks() != self.last_tick {
+        self.set(ctx, uniforms);
+    }
+
+    /// Updates the uniform data, keeping the underlying bind group stable.
+    ///
+    /// Every update within a frame is written to its own slot of a per-frame buffer arena
+    /// and selected through a dynamic offset, so draws which were queued before the update
+    /// keep seeing the old values. This makes it safe to call between draws on the same canvas:
+    ///
+    /// ```rust,ignore
+    /// params.set(ctx, &Dim { rate: 0.25 });
+    /// canvas.set_shader_params(&params);
+    /// canvas.draw(&quad, DrawParam::default());
+    ///
+    /// params.set(ctx, &Dim { rate: 0.75 });
+    /// canvas.set_shader_params(&params);
+    /// canvas.draw(&quad, DrawParam::default().dest([64., 0.]));
+    /// ```
+    ///
+    /// [`Canvas::set_shader_params`] (or [`Canvas::set_text_shader_params`]) still **needs to be
+    /// called again** to pick up the new offset, but no new bind group is created unless the
+    /// arena had to grow.
+    pub fn set(&mut self, gfx: &mut impl HasMut<GraphicsContext>, uniforms: &Uniforms) {
+        let gfx = gfx.retrieve_mut();
+
+        if self.last_frame != Some(gfx.frame_count) {
             self.uniform_arena.free();
-            self.last_tick = ctx.time.ticks();
+            self.last_frame = Some(gfx.frame_count);
         }
+
         let alloc = self
             .uniform_arena
-            .allocate(&ctx.gfx.wgpu.device, Uniforms::std140_size_static() as u64);
-        ctx.gfx.wgpu.queue.write_buffer(
-            &alloc.buffer,
-            alloc.offset,
-            uniforms.as_std140().as_bytes(),
-        );
+            .allocate(&gfx.wgpu.device, Uniforms::std140_size_static() as u64);
+        gfx.wgpu
+            .queue
+            .write_buffer(&alloc.buffer, alloc.offset, uniforms.as_std140().as_bytes());
 
         self.buffer_offset = alloc.offset as u32;
 
+        if self.bound_buffer != Some(alloc.buffer.id()) || self.bind_group.is_none() {
+            self.bound_buffer = Some(alloc.buffer.id());
+            self.rebuild_bind_group(gfx, &alloc.buffer);
+        }
+    }
+
+    fn rebuild_bind_group(&mut self, gfx: &mut GraphicsContext, buffer: &ArcBuffer) {
         let mut builder = BindGroupBuilder::new();
         builder = builder.buffer(
-            &alloc.buffer,
+            buffer,
             0,
             wgpu::ShaderStages::VERTEX_FRAGMENT,
             wgpu::BufferBindingType::Uniform,
             true,
-            None,
+            // bound with an explicit size so any dynamic offset within the arena stays in range
+            Some(Uniforms::std140_size_static() as u64),
         );
 
         let vis = if self.images_vs_visible {
@@ -357,21 +668,106 @@ impl<Uniforms: AsStd140> ShaderParams<Uniforms> {
             wgpu::ShaderStages::FRAGMENT
         };
 
-        for view in &self.images {
-            builder = builder.image(view, vis);
+        for (view, dimension) in &self.images {
+            builder = builder.image_with_dimension(view, vis, *dimension);
         }
 
         for sampler in &self.samplers {
             builder = builder.sampler(sampler, vis);
         }
 
-        let (bind_group, layout) =
-            builder.create(&ctx.gfx.wgpu.device, &mut ctx.gfx.bind_group_cache);
+        let (bind_group, layout) = builder.create(&gfx.wgpu.device, &mut gfx.bind_group_cache);
         self.layout = Some(layout);
         self.bind_group = Some(bind_group);
     }
 }
 
+/// How render pipelines are compiled for combinations of shader and drawing state that
+/// haven't been drawn with before, see
+/// [`GraphicsContext::set_pipeline_compilation`](super::GraphicsContext::set_pipeline_compilation).
+///
+/// Compiling a pipeline can take long enough to cause a visible hitch in the frame that first
+/// needs it. Pipelines for the default shaders are always compiled right away, in every mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PipelineCompilation {
+    /// Compile pipelines when they are first drawn with, stalling the frame until they are
+    /// ready.
+    #[default]
+    Blocking,
+    /// Compile pipelines for custom shaders on a background thread, drawing with the default
+    /// shaders until they are ready.
+    BackgroundFallback,
+    /// Compile pipelines for custom shaders on a background thread, skipping the draws that
+    /// need them until they are ready. Text is drawn with the default text shader instead.
+    BackgroundSkip,
+}
+
+/// A shader together with the drawing state it is used with, for compiling its render
+/// pipeline ahead of time with
+/// [`GraphicsContext::precompile_pipelines`](super::GraphicsContext::precompile_pipelines).
+///
+/// By default it describes drawing meshes and images with alpha blending to the frame.
+#[derive(Debug, Clone)]
+pub struct PipelineDesc {
+    pub(crate) shader: Shader,
+    pub(crate) params: Option<ArcBindGroupLayout>,
+    pub(crate) ty: ShaderType,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) target: Option<(ImageFormat, u32)>,
+}
+
+impl PipelineDesc {
+    /// Describes drawing with `shader`, as set with [`Canvas::set_shader`].
+    pub fn new(shader: &Shader) -> Self {
+        PipelineDesc {
+            shader: shader.clone(),
+            params: None,
+            ty: ShaderType::Draw,
+            blend_mode: BlendMode::ALPHA,
+            target: None,
+        }
+    }
+
+    /// Describes drawing text with `shader`, as set with [`Canvas::set_text_shader`].
+    pub fn text(shader: &Shader) -> Self {
+        PipelineDesc {
+            ty: ShaderType::Text,
+            ..Self::new(shader)
+        }
+    }
+
+    /// Sets the shader params drawn with, as they are part of the pipeline's layout.
+    #[must_use]
+    pub fn params<Uniforms: AsStd140>(mut self, params: &ShaderParams<Uniforms>) -> Self {
+        self.params = params.layout.clone();
+        self
+    }
+
+    /// Describes drawing an [`InstanceArray`](super::InstanceArray) rather than a single mesh
+    /// or image, with `ordered` as passed to [`InstanceArray::new_ordered`](super::InstanceArray::new_ordered).
+    #[must_use]
+    pub fn instanced(mut self, ordered: bool) -> Self {
+        if self.ty != ShaderType::Text {
+            self.ty = ShaderType::Instance { ordered };
+        }
+        self
+    }
+
+    /// Sets the blend mode drawn with.
+    #[must_use]
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets the format and sample count of the canvas image drawn to, instead of the frame's.
+    #[must_use]
+    pub fn target(mut self, format: ImageFormat, samples: u32) -> Self {
+        self.target = Some((format, samples));
+        self
+    }
+}
+
 pub use wgpu::{BlendComponent, BlendFactor, BlendOperation};
 
 /// Describes the blend mode used when drawing images.
@@ -515,3 +911,171 @@ impl BlendMode {
         },
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::tests::headless_context,
+        graphics::{
+            BlendMode, Canvas, Color, DrawParam, Image, ImageFormat, Quad, Sampler, Shader,
+            ShaderBuilder, ShaderParams, ShaderParamsBuilder,
+        },
+        Context,
+    };
+    use crevice::std140::AsStd140;
+
+    #[derive(AsStd140)]
+    struct Dim {
+        rate: f32,
+    }
+
+    const SAMPLED: &str = "
+        struct VertexOutput {
+            @builtin(position) position: vec4<f32>,
+            @location(0) uv: vec2<f32>,
+            @location(1) color: vec4<f32>,
+        }
+
+        struct Dim {
+            rate: f32,
+        }
+
+        @group(3) @binding(0)
+        var<uniform> dim: Dim;
+        @group(3) @binding(1)
+        var s: sampler;
+        @group(3) @binding(2)
+        var t: texture_2d<f32>;
+
+        @fragment
+        fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+            return textureSample(t, s, in.uv) * dim.rate;
+        }
+    ";
+
+    fn draw(
+        ctx: &mut Context,
+        shader: &Shader,
+        params: Option<&ShaderParams<Dim>>,
+    ) -> Result<(), String> {
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        canvas.set_shader(shader);
+        if let Some(params) = params {
+            canvas.set_shader_params(params);
+        }
+        canvas.draw(&Quad, DrawParam::new());
+        let result = canvas.finish(ctx);
+        ctx.gfx.end_frame().unwrap();
+        result.map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn headless_test_shader_reflection() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // Mistakes in the code are errors rather than panics.
+        let error = ShaderBuilder::new()
+            .fragment_code("@fragment fn fs_main() -> @location(0) vec4<f32> { return 1; }")
+            .build(&ctx.gfx)
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid shader"), "{error}");
+        assert!(ShaderBuilder::new()
+            .fragment_code("@fragment fn main() -> @location(0) vec4<f32> { return vec4(1.); }")
+            .build(&ctx.gfx)
+            .is_err());
+
+        let shader = ShaderBuilder::new()
+            .fragment_code(SAMPLED)
+            .build(&ctx.gfx)
+            .unwrap();
+        let image = Image::from_color(&ctx, 1, 1, Some(Color::WHITE));
+        let error = draw(&mut ctx, &shader, None).unwrap_err();
+        assert!(
+            error.contains(
+                "group 3 binding 0 expects a uniform buffer of 4 bytes, but no shader params were set"
+            ),
+            "{error}"
+        );
+
+        // The images come before the samplers.
+        let params = ShaderParamsBuilder::new(&Dim { rate: 1. })
+            .images(&[&image], &[Sampler::default()], false)
+            .build(&mut ctx);
+        let error = draw(&mut ctx, &shader, Some(&params)).unwrap_err();
+        assert!(
+            error.contains(
+                "group 3 binding 1 expects a sampler, but the shader params provide a texture_2d"
+            ),
+            "{error}"
+        );
+
+        let params = ShaderParamsBuilder::new(&Dim { rate: 1. }).build(&mut ctx);
+        let error = draw(&mut ctx, &shader, Some(&params)).unwrap_err();
+        assert!(error.contains("only have 1 bindings"), "{error}");
+    }
+
+    #[test]
+    fn headless_test_shader_reflection_matches() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let source = SAMPLED
+            .replace("@binding(1)\n        var s", "@binding(2)\n        var s")
+            .replace("@binding(2)\n        var t", "@binding(1)\n        var t");
+        let shader = ShaderBuilder::new()
+            .fragment_code(&source)
+            .build(&ctx.gfx)
+            .unwrap();
+        let image = Image::from_color(&ctx, 1, 1, Some(Color::WHITE));
+        let params = ShaderParamsBuilder::new(&Dim { rate: 0.5 })
+            .images(&[&image], &[Sampler::default()], false)
+            .build(&mut ctx);
+
+        draw(&mut ctx, &shader, Some(&params)).unwrap();
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        // the quad is the dimmed image
+        assert!((1..255).contains(&pixels[0]), "{:?}", &pixels[..4]);
+    }
+
+    #[test]
+    fn headless_test_shader_params_set_between_draws() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let shader = ShaderBuilder::new()
+            .fragment_code(include_str!("../../resources/dimmer.wgsl"))
+            .build(&ctx.gfx)
+            .unwrap();
+        let mut params = ShaderParamsBuilder::new(&Dim { rate: 0.25 }).build(&mut ctx);
+        let target = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 2, 1, 1);
+
+        // Each draw keeps the uniforms that were set when it was queued.
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_image(&ctx, target.clone(), Color::BLACK);
+        canvas.set_blend_mode(BlendMode::REPLACE);
+        canvas.set_shader(&shader);
+        params.set(&mut ctx, &Dim { rate: 0.25 });
+        canvas.set_shader_params(&params);
+        canvas.draw(&Quad, DrawParam::new());
+        params.set(&mut ctx, &Dim { rate: 0.75 });
+        canvas.set_shader_params(&params);
+        canvas.draw(&Quad, DrawParam::new().dest([1., 0.]));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = target.to_pixels(&ctx).unwrap();
+        assert!(
+            pixels[..4].iter().all(|c| c.abs_diff(64) <= 1),
+            "{pixels:?}"
+        );
+        assert!(
+            pixels[4..].iter().all(|c| c.abs_diff(191) <= 1),
+            "{pixels:?}"
+        );
+    }
+}
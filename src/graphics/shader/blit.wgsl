@@ -0,0 +1,52 @@
+// A full-screen triangle for `GraphicsContext::blit`, laid out like the draw
+// shader so custom fragment shaders work for both.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = vec2<f32>(0.0);
+    out.uv.x = select(0.0, 2.0, in_vertex_index == 1u);
+    out.uv.y = select(0.0, 2.0, in_vertex_index == 2u);
+    out.position = vec4<f32>(out.uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 1.0, 1.0);
+    out.color = vec4<f32>(1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t, s, in.uv);
+}
+
+// Used when only one of the images is sRGB, so the pixels keep their values.
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_encode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = textureSample(t, s, in.uv);
+    return vec4<f32>(linear_to_srgb(c.rgb), c.a);
+}
+
+@fragment
+fn fs_decode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = textureSample(t, s, in.uv);
+    return vec4<f32>(srgb_to_linear(c.rgb), c.a);
+}
@@ -23,3 +23,50 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return textureSample(t, s, in.uv);
 }
+
+// Used when the frame and the surface disagree on whether they are sRGB,
+// so the surface still shows the frame as it was drawn.
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_encode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = textureSample(t, s, in.uv);
+    return vec4<f32>(linear_to_srgb(c.rgb), c.a);
+}
+
+@fragment
+fn fs_decode(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = textureSample(t, s, in.uv);
+    return vec4<f32>(srgb_to_linear(c.rgb), c.a);
+}
+
+// Used for surfaces that the compositor blends with postmultiplied alpha,
+// since frames are drawn with premultiplied colors.
+
+fn unpremultiply(c: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(select(c.rgb / c.a, vec3<f32>(0.0), c.a <= 0.0), c.a);
+}
+
+@fragment
+fn fs_main_unpremultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    return unpremultiply(textureSample(t, s, in.uv));
+}
+
+@fragment
+fn fs_encode_unpremultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = unpremultiply(textureSample(t, s, in.uv));
+    return vec4<f32>(linear_to_srgb(c.rgb), c.a);
+}
+
+@fragment
+fn fs_decode_unpremultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    let c = unpremultiply(textureSample(t, s, in.uv));
+    return vec4<f32>(srgb_to_linear(c.rgb), c.a);
+}
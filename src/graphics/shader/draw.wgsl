@@ -4,6 +4,13 @@ struct VertexOutput {
     @location(1) color: vec4<f32>,
 }
 
+struct ClampedVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) src_rect: vec4<f32>,
+}
+
 struct DrawUniforms {
     color: vec4<f32>,
     src_rect: vec4<f32>,
@@ -19,11 +26,10 @@ var t: texture_2d<f32>;
 @group(1) @binding(1)
 var s: sampler;
 
-@vertex
-fn vs_main(
-    @location(0) position: vec2<f32>,
-    @location(1) uv: vec2<f32>,
-    @location(2) color: vec4<f32>,
+fn vertex(
+    position: vec2<f32>,
+    uv: vec2<f32>,
+    color: vec4<f32>,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = uniforms.transform * vec4<f32>(position, 0.0, 1.0);
@@ -32,7 +38,77 @@ fn vs_main(
     return out;
 }
 
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    return vertex(position, uv, color);
+}
+
+// Used with `fs_clamped` below.
+@vertex
+fn vs_clamped(
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> ClampedVertexOutput {
+    let v = vertex(position, uv, color);
+    return ClampedVertexOutput(v.position, v.uv, v.color, uniforms.src_rect);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color * textureSample(t, s, in.uv);
-}
\ No newline at end of file
+}
+
+// Keeps linear filtering from picking up texels outside the source rect, such as neighboring
+// sprites in an atlas. Source rects that cover the whole image or reach outside it (for repeating
+// textures) are left alone.
+fn sample_clamped(in: ClampedVertexOutput) -> vec4<f32> {
+    let lo = min(in.src_rect.xy, in.src_rect.zw);
+    let hi = max(in.src_rect.xy, in.src_rect.zw);
+    var uv = in.uv;
+    if all(lo >= vec2<f32>(0.0)) && all(hi <= vec2<f32>(1.0)) && any(hi - lo < vec2<f32>(1.0)) {
+        let half_texel = 0.5 / vec2<f32>(textureDimensions(t));
+        let center = (lo + hi) * 0.5;
+        uv = clamp(uv, min(lo + half_texel, center), max(hi - half_texel, center));
+    }
+    return textureSample(t, s, uv);
+}
+
+@fragment
+fn fs_clamped(in: ClampedVertexOutput) -> @location(0) vec4<f32> {
+    return in.color * sample_clamped(in);
+}
+
+// Single channel images are drawn in grayscale, and two channel images in grayscale with
+// the second channel as alpha.
+fn gray(c: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(c.rrr, 1.0);
+}
+
+fn gray_alpha(c: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(c.rrr, c.g);
+}
+
+@fragment
+fn fs_gray(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * gray(textureSample(t, s, in.uv));
+}
+
+@fragment
+fn fs_gray_clamped(in: ClampedVertexOutput) -> @location(0) vec4<f32> {
+    return in.color * gray(sample_clamped(in));
+}
+
+@fragment
+fn fs_gray_alpha(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * gray_alpha(textureSample(t, s, in.uv));
+}
+
+@fragment
+fn fs_gray_alpha_clamped(in: ClampedVertexOutput) -> @location(0) vec4<f32> {
+    return in.color * gray_alpha(sample_clamped(in));
+}
@@ -4,6 +4,13 @@ struct VertexOutput {
     @location(1) color: vec4<f32>,
 }
 
+struct ClampedVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) src_rect: vec4<f32>,
+}
+
 struct Uniforms {
     transform: mat4x4<f32>,
     color: vec4<f32>,
@@ -39,12 +46,11 @@ var<storage, read> instances: InstanceArray;
 @group(2) @binding(1)
 var<storage, read> indices: InstanceArrayIndices;
 
-@vertex
-fn vs_main(
-    @builtin(instance_index) in_instance_index: u32,
-    @location(0) position: vec2<f32>,
-    @location(1) uv: vec2<f32>,
-    @location(2) color: vec4<f32>,
+fn vertex(
+    in_instance_index: u32,
+    position: vec2<f32>,
+    uv: vec2<f32>,
+    color: vec4<f32>,
 ) -> VertexOutput {
     var index = indices.indices[in_instance_index];
     var instance = instances.instances[index];
@@ -77,6 +83,29 @@ fn vs_main(
     return out;
 }
 
+@vertex
+fn vs_main(
+    @builtin(instance_index) in_instance_index: u32,
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    return vertex(in_instance_index, position, uv, color);
+}
+
+// Used with `fs_clamped` from draw.wgsl, which keeps sampling inside the source rect.
+@vertex
+fn vs_clamped(
+    @builtin(instance_index) in_instance_index: u32,
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> ClampedVertexOutput {
+    let v = vertex(in_instance_index, position, uv, color);
+    let src_rect = instances.instances[indices.indices[in_instance_index]].src_rect;
+    return ClampedVertexOutput(v.position, v.uv, v.color, src_rect);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color * textureSample(t, s, in.uv);
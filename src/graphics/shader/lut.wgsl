@@ -0,0 +1,63 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+struct LutParams {
+    // Whether the target's format is sRGB, so it reads and writes linear colors.
+    target_srgb: u32,
+    // Whether the LUT's format is sRGB, so it returns linear colors.
+    lut_srgb: u32,
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var lut: texture_3d<f32>;
+
+@group(0) @binding(2)
+var lut_sampler: sampler;
+
+@group(0) @binding(3)
+var<uniform> params: LutParams;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var uv = vec2<f32>(0.0);
+    uv.x = select(0.0, 2.0, in_vertex_index == 1u);
+    uv.y = select(0.0, 2.0, in_vertex_index == 2u);
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 1.0, 1.0);
+    return out;
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+// LUTs map gamma-encoded colors to gamma-encoded colors, like the image editors they're made in.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(source, vec2<i32>(in.position.xy), 0);
+
+    var rgb = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if params.target_srgb != 0u {
+        rgb = linear_to_srgb(rgb);
+    }
+
+    // Sample texel centers, so 0 and 1 map exactly onto the first and last entry.
+    let size = f32(textureDimensions(lut).x);
+    var graded = textureSampleLevel(lut, lut_sampler, (rgb * (size - 1.0) + 0.5) / size, 0.0).rgb;
+    if params.lut_srgb != 0u {
+        graded = linear_to_srgb(graded);
+    }
+    if params.target_srgb != 0u {
+        graded = srgb_to_linear(graded);
+    }
+
+    return vec4<f32>(graded, color.a);
+}
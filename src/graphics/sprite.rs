@@ -0,0 +1,524 @@
+use super::{context::GraphicsContext, Canvas, DrawParam, Drawable, Image, Rect};
+use crate::{context::Has, GameError, GameResult};
+use std::{ops::RangeInclusive, sync::Arc, time::Duration};
+
+/// One frame of a [`SpriteAnimation`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteFrame {
+    /// Where the frame is in the atlas, in pixels.
+    pub rect: Rect,
+    /// How long the frame is shown.
+    pub duration: Duration,
+}
+
+impl SpriteFrame {
+    /// Creates a frame from its place in the atlas, in pixels, and how long it's shown.
+    pub fn new(rect: Rect, duration: Duration) -> Self {
+        SpriteFrame { rect, duration }
+    }
+}
+
+/// The order in which the frames of a tag are played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AnimationDirection {
+    /// From the first frame to the last.
+    #[default]
+    Forward,
+    /// From the last frame to the first.
+    Reverse,
+    /// From the first frame to the last and back, without showing either end twice.
+    PingPong,
+}
+
+/// A named run of frames in a [`SpriteAnimation`], such as "walk" or "idle".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationTag {
+    /// The name the tag is played by.
+    pub name: String,
+    /// The frames of the tag, as indices into the animation's frames.
+    pub frames: RangeInclusive<usize>,
+    /// The order the frames are played in.
+    pub direction: AnimationDirection,
+}
+
+/// Something that happened during the last [`SpriteAnimation::update`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// The animation moved from one frame to another.
+    FrameChanged {
+        /// The frame shown before.
+        from: usize,
+        /// The frame shown now.
+        to: usize,
+    },
+    /// The animation reached the end of its last frame; a looping animation then starts over.
+    Ended {
+        /// The tag that was playing, or `None` when playing all frames.
+        tag: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct SpriteSheet {
+    frames: Vec<SpriteFrame>,
+    tags: Vec<AnimationTag>,
+}
+
+/// An animated sprite, drawing frames out of an atlas [`Image`].
+///
+/// The animation plays either all frames in order, or one of its named [tags](AnimationTag).
+/// Advance it with [`update`](Self::update) each frame, then draw it like an image; it sets
+/// the [`src`](DrawParam::src) of the draw parameters to the current frame.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, AnimationDirection, Canvas, Color, SpriteAnimation}, Context, GameResult};
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let atlas = graphics::Image::from_path(ctx, "/hero.png")?;
+/// let mut hero = SpriteAnimation::from_grid(atlas, 8, 2, Duration::from_millis(100))
+///     .with_tag("idle", 0..=3, AnimationDirection::PingPong)
+///     .with_tag("walk", 8..=15, AnimationDirection::Forward);
+/// hero.play("walk")?;
+///
+/// hero.update(ctx.time.delta());
+/// let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+/// canvas.draw(&hero, [100., 100.]);
+/// canvas.finish(ctx)
+/// # }
+/// ```
+///
+/// Cloning an animation is cheap, as the frames and tags are shared. To draw a crowd of
+/// animated sprites in one call, keep an animation per sprite and push their
+/// [`src`](Self::src) into an [`InstanceArray`](super::InstanceArray) of the atlas.
+#[derive(Debug, Clone)]
+pub struct SpriteAnimation {
+    image: Image,
+    sheet: Arc<SpriteSheet>,
+    tag: Option<usize>,
+    sequence: Vec<usize>,
+    step: usize,
+    elapsed: Duration,
+    looping: bool,
+    finished: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl SpriteAnimation {
+    /// Creates an animation playing `frames` of the `image` in order.
+    pub fn new(image: Image, frames: impl IntoIterator<Item = SpriteFrame>) -> Self {
+        let frames = frames.into_iter().collect::<Vec<_>>();
+        SpriteAnimation {
+            image,
+            sequence: (0..frames.len()).collect(),
+            sheet: Arc::new(SpriteSheet {
+                frames,
+                tags: Vec::new(),
+            }),
+            tag: None,
+            step: 0,
+            elapsed: Duration::ZERO,
+            looping: true,
+            finished: false,
+            events: Vec::new(),
+        }
+    }
+
+    /// Creates an animation from an atlas divided into `columns` x `rows` equally sized
+    /// frames, numbered row by row, all shown for `duration`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` or `rows` is zero.
+    pub fn from_grid(image: Image, columns: u32, rows: u32, duration: Duration) -> Self {
+        assert!(
+            columns > 0 && rows > 0,
+            "sprite grid must have at least one column and row, got {columns}x{rows}"
+        );
+        let (w, h) = (image.width() / columns, image.height() / rows);
+        let frames = (0..rows).flat_map(|row| {
+            (0..columns).map(move |column| {
+                SpriteFrame::new(
+                    Rect::new((column * w) as f32, (row * h) as f32, w as f32, h as f32),
+                    duration,
+                )
+            })
+        });
+        SpriteAnimation::new(image, frames)
+    }
+
+    /// Adds a named run of frames, which can then be [played](Self::play).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames` reaches past the last frame.
+    #[must_use]
+    pub fn with_tag(
+        mut self,
+        name: impl Into<String>,
+        frames: RangeInclusive<usize>,
+        direction: AnimationDirection,
+    ) -> Self {
+        assert!(
+            *frames.end() < self.sheet.frames.len(),
+            "animation tag frames {frames:?} out of range"
+        );
+        Arc::make_mut(&mut self.sheet).tags.push(AnimationTag {
+            name: name.into(),
+            frames,
+            direction,
+        });
+        self
+    }
+
+    /// Starts playing the tag with the given name from its beginning.
+    ///
+    /// Does nothing if the tag is already playing; use [`restart`](Self::restart) for that.
+    pub fn play(&mut self, tag: &str) -> GameResult {
+        let index = self
+            .sheet
+            .tags
+            .iter()
+            .position(|t| t.name == tag)
+            .ok_or_else(|| {
+                GameError::ResourceNotFound(format!("animation tag {tag:?}"), Vec::new())
+            })?;
+        if self.tag != Some(index) {
+            self.tag = Some(index);
+            self.restart();
+        }
+        Ok(())
+    }
+
+    /// Starts playing all frames in order from the beginning.
+    pub fn play_all(&mut self) {
+        self.tag = None;
+        self.restart();
+    }
+
+    /// Starts the current tag over from its first frame.
+    pub fn restart(&mut self) {
+        self.sequence.clear();
+        match self.tag.map(|i| &self.sheet.tags[i]) {
+            None => self.sequence.extend(0..self.sheet.frames.len()),
+            Some(tag) => {
+                let (first, last) = (*tag.frames.start(), *tag.frames.end());
+                match tag.direction {
+                    AnimationDirection::Forward => self.sequence.extend(first..=last),
+                    AnimationDirection::Reverse => self.sequence.extend((first..=last).rev()),
+                    AnimationDirection::PingPong => {
+                        self.sequence.extend(first..=last);
+                        self.sequence.extend((first + 1..last).rev());
+                    }
+                }
+            }
+        }
+        self.step = 0;
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+    }
+
+    /// Sets whether the animation starts over after its last frame, which it does by default.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Advances the animation by `dt`, replacing the [`events`](Self::events).
+    pub fn update(&mut self, dt: Duration) {
+        self.events.clear();
+        if self.finished || self.sequence.is_empty() {
+            return;
+        }
+
+        self.elapsed += dt;
+        loop {
+            let from = self.sequence[self.step];
+            let duration = self.sheet.frames[from].duration;
+            // A frame without a duration stays up, rather than spinning here forever.
+            if duration.is_zero() || self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+
+            if self.step + 1 == self.sequence.len() {
+                self.events.push(AnimationEvent::Ended {
+                    tag: self.tag_name().map(String::from),
+                });
+                if !self.looping {
+                    self.finished = true;
+                    self.elapsed = Duration::ZERO;
+                    break;
+                }
+                self.step = 0;
+            } else {
+                self.step += 1;
+            }
+
+            let to = self.sequence[self.step];
+            if to != from {
+                self.events.push(AnimationEvent::FrameChanged { from, to });
+            }
+        }
+    }
+
+    /// Returns what happened during the last [`update`](Self::update).
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// Returns whether a non-looping animation has played its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the name of the playing tag, or `None` when playing all frames.
+    pub fn tag_name(&self) -> Option<&str> {
+        self.tag.map(|i| self.sheet.tags[i].name.as_str())
+    }
+
+    /// Returns the index of the current frame.
+    pub fn frame(&self) -> usize {
+        self.sequence.get(self.step).copied().unwrap_or(0)
+    }
+
+    /// Returns all frames of the animation.
+    pub fn frames(&self) -> &[SpriteFrame] {
+        &self.sheet.frames
+    }
+
+    /// Returns the tags of the animation.
+    pub fn tags(&self) -> &[AnimationTag] {
+        &self.sheet.tags
+    }
+
+    /// Returns the atlas image.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the current frame in UV coordinates, as used by [`DrawParam::src`].
+    pub fn src(&self) -> Rect {
+        match self.sheet.frames.get(self.frame()) {
+            Some(frame) => self.image.uv_rect(
+                frame.rect.x as u32,
+                frame.rect.y as u32,
+                frame.rect.w as u32,
+                frame.rect.h as u32,
+            ),
+            None => Rect::one(),
+        }
+    }
+
+    /// Creates an animation from the JSON data that Aseprite exports alongside a sprite
+    /// sheet, in either its "hash" or "array" layout, including the frame tags.
+    ///
+    /// Tags played "pingpong_reverse" are played [`PingPong`](AnimationDirection::PingPong).
+    #[cfg(feature = "aseprite")]
+    pub fn from_aseprite_json(image: Image, json: &[u8]) -> GameResult<Self> {
+        let data: aseprite::Data = serde_json::from_slice(json)
+            .map_err(|e| GameError::ResourceLoadError(format!("invalid Aseprite JSON: {e}")))?;
+
+        let frames = data.frames.0.into_iter().map(|frame| {
+            let r = frame.frame;
+            SpriteFrame::new(
+                Rect::new(r.x as f32, r.y as f32, r.w as f32, r.h as f32),
+                Duration::from_millis(frame.duration),
+            )
+        });
+        let mut animation = SpriteAnimation::new(image, frames);
+        for tag in data.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= animation.sheet.frames.len() {
+                return Err(GameError::ResourceLoadError(format!(
+                    "Aseprite tag {:?} has frames {}..={} out of range",
+                    tag.name, tag.from, tag.to
+                )));
+            }
+            let direction = match tag.direction.as_str() {
+                "reverse" => AnimationDirection::Reverse,
+                "pingpong" | "pingpong_reverse" => AnimationDirection::PingPong,
+                _ => AnimationDirection::Forward,
+            };
+            animation = animation.with_tag(tag.name, tag.from..=tag.to, direction);
+        }
+        Ok(animation)
+    }
+}
+
+impl Drawable for SpriteAnimation {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        self.image.draw(canvas, param.into().src(self.src()));
+    }
+
+    fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        let frame = self.sheet.frames.get(self.frame())?;
+        Some(Rect::new(0., 0., frame.rect.w, frame.rect.h))
+    }
+}
+
+#[cfg(feature = "aseprite")]
+mod aseprite {
+    use serde::{
+        de::{MapAccess, SeqAccess, Visitor},
+        Deserialize, Deserializer,
+    };
+    use std::fmt;
+
+    #[derive(Deserialize)]
+    pub(super) struct Data {
+        pub frames: Frames,
+        pub meta: Meta,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Frame {
+        pub frame: FrameRect,
+        pub duration: u64,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct FrameRect {
+        pub x: u32,
+        pub y: u32,
+        pub w: u32,
+        pub h: u32,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Meta {
+        #[serde(rename = "frameTags", default)]
+        pub frame_tags: Vec<Tag>,
+    }
+
+    #[derive(Deserialize)]
+    pub(super) struct Tag {
+        pub name: String,
+        pub from: usize,
+        pub to: usize,
+        #[serde(default)]
+        pub direction: String,
+    }
+
+    /// The frames, either as an array or as an object keyed by file name, in file order.
+    pub(super) struct Frames(pub Vec<Frame>);
+
+    impl<'de> Deserialize<'de> for Frames {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct FramesVisitor;
+
+            impl<'de> Visitor<'de> for FramesVisitor {
+                type Value = Frames;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("an array or map of frames")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Frames, A::Error> {
+                    let mut frames = Vec::new();
+                    while let Some(frame) = seq.next_element()? {
+                        frames.push(frame);
+                    }
+                    Ok(Frames(frames))
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Frames, A::Error> {
+                    let mut frames = Vec::new();
+                    while let Some((_, frame)) = map.next_entry::<String, Frame>()? {
+                        frames.push(frame);
+                    }
+                    Ok(Frames(frames))
+                }
+            }
+
+            deserializer.deserialize_any(FramesVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::tests::headless_context, graphics::ImageFormat};
+
+    #[test]
+    fn headless_test_sprite_animation() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+        let image = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 64, 32, 1);
+        let ms = Duration::from_millis;
+        let mut anim = SpriteAnimation::from_grid(image, 4, 2, ms(100))
+            .with_tag("walk", 4..=6, AnimationDirection::PingPong)
+            .with_tag("die", 1..=2, AnimationDirection::Reverse);
+        assert_eq!(anim.frames().len(), 8);
+        assert_eq!(anim.frames()[5].rect, Rect::new(16., 16., 16., 16.));
+        assert_eq!(anim.dimensions(&ctx), Some(Rect::new(0., 0., 16., 16.)));
+
+        anim.update(ms(250));
+        assert_eq!(anim.frame(), 2);
+        assert_eq!(anim.src(), Rect::new(0.5, 0., 0.25, 0.5));
+        assert_eq!(anim.events().len(), 2);
+
+        // 4, 5, 6, 5, then back to 4.
+        anim.play("walk").unwrap();
+        assert_eq!(anim.frame(), 4);
+        anim.update(ms(350));
+        assert_eq!(anim.frame(), 5);
+        assert_eq!(
+            anim.events(),
+            [
+                AnimationEvent::FrameChanged { from: 4, to: 5 },
+                AnimationEvent::FrameChanged { from: 5, to: 6 },
+                AnimationEvent::FrameChanged { from: 6, to: 5 },
+            ]
+        );
+        anim.update(ms(100));
+        assert_eq!(anim.frame(), 4);
+        assert_eq!(
+            anim.events()[0],
+            AnimationEvent::Ended {
+                tag: Some("walk".into())
+            }
+        );
+
+        anim.play("die").unwrap();
+        anim.set_looping(false);
+        anim.update(ms(1000));
+        assert!(anim.is_finished());
+        assert_eq!(anim.frame(), 1);
+        anim.update(ms(1000));
+        assert!(anim.events().is_empty());
+
+        assert!(anim.play("fly").is_err());
+        assert_eq!(anim.tag_name(), Some("die"));
+    }
+
+    #[cfg(feature = "aseprite")]
+    #[test]
+    fn headless_test_sprite_aseprite() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+        let image = Image::new_canvas_image(&ctx, ImageFormat::Rgba8Unorm, 32, 16, 1);
+        let json = br#"{
+            "frames": {
+                "hero 2.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 80 },
+                "hero 10.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 120 }
+            },
+            "meta": {
+                "frameTags": [{ "name": "blink", "from": 0, "to": 1, "direction": "reverse" }]
+            }
+        }"#;
+        let anim = SpriteAnimation::from_aseprite_json(image.clone(), json).unwrap();
+        assert_eq!(
+            anim.frames(),
+            [
+                SpriteFrame::new(Rect::new(0., 0., 16., 16.), Duration::from_millis(80)),
+                SpriteFrame::new(Rect::new(16., 0., 16., 16.), Duration::from_millis(120)),
+            ]
+        );
+        assert_eq!(anim.tags()[0].direction, AnimationDirection::Reverse);
+
+        let bad =
+            br#"{ "frames": [], "meta": { "frameTags": [{ "name": "x", "from": 0, "to": 3 }] } }"#;
+        assert!(SpriteAnimation::from_aseprite_json(image, bad).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Helpers for testing drawing code against golden images, in ggez's own tests and, with the
+//! `test-utils` feature, in games.
+//!
+//! A test renders into a [headless context](headless_context), then checks the result with
+//! [`assert_image_matches`]:
+//!
+//! ```rust,no_run
+//! # use ggez::graphics::{testing, Canvas, Color, DrawParam, Quad};
+//! let Some(mut ctx) = testing::headless_context(64, 64) else {
+//!     return; // No graphics adapter, e.g. on some CI machines.
+//! };
+//! ctx.gfx.begin_frame().unwrap();
+//! let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+//! canvas.draw(&Quad, DrawParam::new().dest([16., 16.]).scale([32., 32.]));
+//! canvas.finish(&mut ctx).unwrap();
+//! ctx.gfx.end_frame().unwrap();
+//!
+//! testing::assert_image_matches(&ctx, ctx.gfx.frame(), "tests/golden/quad.png", 2);
+//! ```
+//!
+//! If the golden image doesn't exist yet, the first run writes it and fails, so it can be
+//! checked by eye before being committed. Setting the `GGEZ_UPDATE_GOLDEN` environment
+//! variable overwrites golden images with the current output instead of comparing.
+
+use std::path::{Path, PathBuf};
+
+use super::{context::GraphicsContext, Image, ImageFormat};
+use crate::{
+    conf::WindowMode,
+    context::{Context, ContextBuilder, Has},
+    GameError,
+};
+
+/// Builds a headless context with a `width` by `height` frame, ignoring any `conf.toml`,
+/// so that tests render the same everywhere.
+///
+/// Returns `None` if there is no graphics adapter to render with, and panics on any other
+/// error.
+pub fn headless_context(width: u32, height: u32) -> Option<Context> {
+    match ContextBuilder::new("test", "ggez")
+        .window_mode(WindowMode::default().dimensions(width as f32, height as f32))
+        .with_conf_file(false)
+        .build_headless()
+    {
+        Ok(ctx) => Some(ctx),
+        Err(GameError::GraphicsInitializationError) => None,
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// How two images differ, see [`compare_pixels`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// How many pixels differ by more than the tolerance in at least one channel.
+    pub mismatched_pixels: usize,
+    /// The largest difference of any channel of any pixel.
+    pub max_difference: u8,
+    /// RGBA8 pixels showing the mismatched pixels in red, brighter the more they differ,
+    /// over a faded grayscale copy of the actual image.
+    pub pixels: Vec<u8>,
+}
+
+/// Compares two equally sized images of RGBA8 pixels channel by channel, allowing each
+/// channel to be off by up to `tolerance`.
+///
+/// # Panics
+///
+/// Panics if the images have different lengths or aren't made of whole pixels.
+pub fn compare_pixels(actual: &[u8], expected: &[u8], tolerance: u8) -> ImageDiff {
+    assert_eq!(actual.len(), expected.len(), "the images differ in size");
+    assert_eq!(actual.len() % 4, 0, "the images aren't RGBA8 pixels");
+
+    let mut diff = ImageDiff {
+        mismatched_pixels: 0,
+        max_difference: 0,
+        pixels: Vec::with_capacity(actual.len()),
+    };
+    for (a, e) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        let difference = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e));
+        let difference = difference.max().unwrap(); // Unwrap since pixels have four channels.
+        diff.max_difference = diff.max_difference.max(difference);
+        if difference > tolerance {
+            diff.mismatched_pixels += 1;
+            diff.pixels
+                .extend_from_slice(&[difference.saturating_mul(2).max(128), 0, 0, 255]);
+        } else {
+            let gray = ((u16::from(a[0]) + u16::from(a[1]) + u16::from(a[2])) / 12) as u8;
+            diff.pixels.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+    diff
+}
+
+/// Reads an image back from the GPU and checks that it matches the PNG at `golden` to
+/// within `tolerance` per channel, see [`compare_pixels`].
+///
+/// On a mismatch, the actual image and a diff image are written next to the golden one,
+/// as `<name>.actual.png` and `<name>.diff.png`, before panicking. Only 8 bit RGBA and
+/// BGRA images can be checked.
+///
+/// # Panics
+///
+/// Panics if the images don't match, or if any of the images can't be read or written.
+pub fn assert_image_matches(
+    gfx: &impl Has<GraphicsContext>,
+    image: &Image,
+    golden: impl AsRef<Path>,
+    tolerance: u8,
+) {
+    let golden = golden.as_ref();
+    let (width, height) = (image.width(), image.height());
+    let mut actual = image
+        .to_pixels(gfx)
+        .unwrap_or_else(|e| panic!("could not read back the image: {e}"));
+    match image.format() {
+        ImageFormat::Rgba8Unorm | ImageFormat::Rgba8UnormSrgb => {}
+        ImageFormat::Bgra8Unorm | ImageFormat::Bgra8UnormSrgb => {
+            actual
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2));
+        }
+        format => panic!("cannot compare {format:?} images"),
+    }
+
+    let save = |path: &Path, pixels: &[u8]| {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        ::image::save_buffer(path, pixels, width, height, ::image::ColorType::Rgba8)
+            .unwrap_or_else(|e| panic!("could not write {}: {e}", path.display()));
+    };
+    if std::env::var_os("GGEZ_UPDATE_GOLDEN").is_some() {
+        save(golden, &actual);
+        return;
+    }
+    if !golden.exists() {
+        save(golden, &actual);
+        panic!(
+            "golden image {} did not exist, so it was written from the current output; check it and run again",
+            golden.display()
+        );
+    }
+
+    let expected = ::image::open(golden)
+        .unwrap_or_else(|e| panic!("could not read {}: {e}", golden.display()))
+        .into_rgba8();
+    if expected.dimensions() != (width, height) {
+        save(&sibling(golden, "actual"), &actual);
+        panic!(
+            "the image is {width}x{height}, but golden image {} is {}x{}",
+            golden.display(),
+            expected.width(),
+            expected.height()
+        );
+    }
+
+    let diff = compare_pixels(&actual, &expected, tolerance);
+    if diff.mismatched_pixels > 0 {
+        let diff_path = sibling(golden, "diff");
+        save(&sibling(golden, "actual"), &actual);
+        save(&diff_path, &diff.pixels);
+        panic!(
+            "{} pixels differ from golden image {} by up to {} (tolerance {tolerance}), see {}",
+            diff.mismatched_pixels,
+            golden.display(),
+            diff.max_difference,
+            diff_path.display()
+        );
+    }
+}
+
+/// Returns `dir/name.<suffix>.png` for a golden image at `dir/name.png`.
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden.file_stem().unwrap_or_default().to_string_lossy();
+    golden.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{Canvas, Color, DrawParam, Quad};
+
+    #[test]
+    fn headless_test_compare_pixels() {
+        let actual = [10, 20, 30, 255, 0, 0, 0, 255];
+        let expected = [12, 20, 30, 255, 0, 0, 100, 255];
+        let diff = compare_pixels(&actual, &expected, 2);
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_difference, 100);
+        assert_eq!(diff.pixels[..4], [5, 5, 5, 255]);
+        assert_eq!(diff.pixels[4..], [200, 0, 0, 255]);
+        assert_eq!(compare_pixels(&actual, &expected, 100).mismatched_pixels, 0);
+    }
+
+    #[test]
+    fn headless_test_assert_image_matches() {
+        let Some(mut ctx) = headless_context(4, 4) else {
+            return;
+        };
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.draw(&Quad, DrawParam::new().scale([2., 2.]).color(Color::RED));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let dir = std::env::temp_dir().join(format!("ggez-golden-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let golden = dir.join("quad.png");
+        let mut expected = ::image::RgbaImage::from_pixel(4, 4, ::image::Rgba([0, 0, 0, 255]));
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            expected.put_pixel(x, y, ::image::Rgba([255, 0, 0, 255]));
+        }
+        expected.save(&golden).unwrap();
+        assert_image_matches(&ctx, ctx.gfx.frame(), &golden, 0);
+
+        // A mismatch panics and leaves the actual and diff images for inspection.
+        expected.put_pixel(3, 3, ::image::Rgba([0, 255, 0, 255]));
+        expected.save(&golden).unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            assert_image_matches(&ctx, ctx.gfx.frame(), &golden, 0)
+        }));
+        assert!(result.is_err());
+        assert!(dir.join("quad.actual.png").exists());
+        let diff = ::image::open(dir.join("quad.diff.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(diff.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
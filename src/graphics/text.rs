@@ -1,10 +1,13 @@
 use super::{
     gpu::text::{Extra, TextRenderer},
-    Canvas, Color, Draw, DrawParam, Drawable, GraphicsContext, Rect,
+    Canvas, Color, Draw, DrawParam, Drawable, GraphicsContext, Rect, Transform,
 };
 use crate::{context::Has, filesystem::Filesystem, GameError, GameResult};
-use glyph_brush::{ab_glyph, FontId, GlyphCruncher};
-use std::{collections::HashMap, io::Read, path::Path};
+use glyph_brush::{
+    ab_glyph::{self, Font, ScaleFont},
+    FontId, GlyphCruncher,
+};
+use std::{collections::HashMap, io::Read, ops::Range, path::Path, sync::Arc};
 
 /// Font data that can be used to create a new font in [`GraphicsContext`].
 #[derive(Debug)]
@@ -17,11 +20,16 @@ impl FontData {
     #[allow(unused_results)]
     pub fn from_path(fs: &impl Has<Filesystem>, path: impl AsRef<Path>) -> GameResult<Self> {
         let fs = fs.retrieve();
+        let path = path.as_ref();
 
         let mut bytes = vec![];
-        fs.open(path)?.read_to_end(&mut bytes)?;
+        fs.open(path)
+            .and_then(|mut f| Ok(f.read_to_end(&mut bytes)?))
+            .map_err(|e| e.context(format!("reading font {}", path.display())))?;
         Ok(FontData {
-            font: ab_glyph::FontArc::try_from_vec(bytes)?,
+            font: ab_glyph::FontArc::try_from_vec(bytes).map_err(|e| {
+                GameError::from(e).context(format!("loading font {}", path.display()))
+            })?,
         })
     }
 
@@ -38,6 +46,35 @@ impl FontData {
             font: ab_glyph::FontArc::try_from_slice(data)?,
         })
     }
+
+    /// Loads an installed system font by its family name, such as `"Noto Sans CJK JP"`.
+    ///
+    /// Returns [`GameError::ResourceNotFound`] if no font of that family is installed.
+    #[cfg(feature = "system_fonts")]
+    pub fn from_system(family_name: &str) -> GameResult<Self> {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+
+        let id = db
+            .query(&fontdb::Query {
+                families: &[fontdb::Family::Name(family_name)],
+                ..Default::default()
+            })
+            .ok_or_else(|| {
+                GameError::ResourceNotFound(format!("system font {family_name}"), Vec::new())
+            })?;
+        let font = db
+            .with_face_data(id, |data, index| {
+                ab_glyph::FontVec::try_from_vec_and_index(data.to_vec(), index)
+            })
+            .ok_or_else(|| {
+                GameError::ResourceLoadError(format!("reading system font {family_name}"))
+            })??;
+
+        Ok(FontData {
+            font: ab_glyph::FontArc::new(font),
+        })
+    }
 }
 
 pub use glyph_brush::ab_glyph::PxScale;
@@ -195,6 +232,44 @@ impl Text {
         self
     }
 
+    /// Lays the text out along a curve through `points`, for things like map labels.
+    ///
+    /// The text is set on a single line, and each glyph is placed with its baseline on the
+    /// curve and rotated to follow it, at the same distance along it as it would be from
+    /// the start of the line. `align` places the text at the start, middle or end of the
+    /// curve. Bounds, wrapping and the text's own layout don't apply.
+    ///
+    /// The points are copied, so the result doesn't borrow them.
+    pub fn layout_on_path(
+        &self,
+        points: &[impl Into<mint::Point2<f32>> + Clone],
+        align: TextAlign,
+    ) -> TextOnPath {
+        let mut text = self.clone();
+        let _ = text
+            .set_bounds([f32::INFINITY, f32::INFINITY])
+            .set_wrap(false)
+            .set_layout(TextLayout::top_left());
+
+        let mut path: Vec<(glam::Vec2, f32)> = Vec::with_capacity(points.len());
+        for point in points {
+            let point: glam::Vec2 = point.clone().into().into();
+            match path.last() {
+                // Points on top of each other have no direction to rotate the glyphs to.
+                Some(&(last, _)) if last == point => (),
+                Some(&(last, distance)) => path.push((point, distance + last.distance(point))),
+                None => path.push((point, 0.)),
+            }
+        }
+
+        TextOnPath {
+            text,
+            path: path.into(),
+            align,
+            overflow: PathOverflow::Clip,
+        }
+    }
+
     /// Returns the string that the text represents.
     pub fn contents(&self) -> String {
         self.fragments.iter().map(|f| f.text.as_str()).collect()
@@ -206,11 +281,12 @@ impl Text {
         gfx: &impl Has<GraphicsContext>,
     ) -> GameResult<Vec<mint::Point2<f32>>> {
         let gfx = gfx.retrieve();
+        let section = self.as_section(&gfx.fonts, &gfx.text, DrawParam::default())?;
         Ok(gfx
             .text
             .glyph_brush
             .borrow_mut()
-            .glyphs(self.as_section(&gfx.fonts, DrawParam::default())?)
+            .glyphs(section)
             .map(|glyph| mint::Point2::<f32> {
                 x: glyph.glyph.position.x,
                 y: glyph.glyph.position.y,
@@ -225,15 +301,125 @@ impl Text {
         self.measure_raw(&gfx.text, &gfx.fonts)
     }
 
+    /// Measures the text line by line, using the same layout as drawing it does.
+    ///
+    /// This takes bounds, wrapping, alignment and the scale and font of every fragment into
+    /// account, which makes it possible to, for example, vertically center text on its baseline
+    /// or draw selection boxes. Empty lines get an entry too, with no width or glyphs and the
+    /// height of their font.
+    pub fn measure_detailed(&self, gfx: &impl Has<GraphicsContext>) -> GameResult<TextMetrics> {
+        let gfx = gfx.retrieve();
+        let total = self.measure_raw(&gfx.text, &gfx.fonts)?;
+
+        let section = self.as_section(&gfx.fonts, &gfx.text, DrawParam::default())?;
+        let mut glyph_brush = gfx.text.glyph_brush.borrow_mut();
+        let glyphs = glyph_brush.glyphs(&section).cloned().collect::<Vec<_>>();
+        let fonts = glyph_brush.fonts();
+
+        let mut lines: Vec<LineMetrics> = Vec::new();
+        let mut gaps = Vec::new();
+        let mut line_left = 0.;
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+            let position = glyph.glyph.position;
+            let right = position.x + font.h_advance(glyph.glyph.id);
+
+            // glyph_brush puts all glyphs of a line on the same baseline.
+            match lines.last_mut() {
+                Some(line) if line.baseline == position.y => {
+                    line.width = right - line_left;
+                    line.ascent = line.ascent.max(font.ascent());
+                    line.descent = line.descent.min(font.descent());
+                    line.glyph_range.end = i + 1;
+                    let gap = gaps.last_mut().expect("one gap per line");
+                    *gap = font.line_gap().max(*gap);
+                }
+                _ => {
+                    line_left = position.x;
+                    lines.push(LineMetrics {
+                        width: right - position.x,
+                        ascent: font.ascent(),
+                        descent: font.descent(),
+                        baseline: position.y,
+                        glyph_range: i..i + 1,
+                    });
+                    gaps.push(font.line_gap());
+                }
+            }
+        }
+
+        // A line break right after another one (or at the start) makes an empty line, which
+        // has no glyphs but is as high as the font of the line break. Every other character
+        // except control characters becomes a glyph, so counting them tells where it goes.
+        let mut empty = Vec::new();
+        let (mut glyph_count, mut after_break) = (0, true);
+        for run in &section.text {
+            let font = fonts[run.font_id.0].as_scaled(run.scale);
+            for c in run.text.chars() {
+                if c == '\n' {
+                    if after_break {
+                        empty.push((glyph_count, font.ascent(), font.descent(), font.line_gap()));
+                    }
+                    after_break = true;
+                } else if !c.is_control() {
+                    glyph_count += 1;
+                    after_break = false;
+                }
+            }
+        }
+        if empty.is_empty() {
+            return Ok(TextMetrics { lines, total });
+        }
+
+        let empty_line = |at: usize, ascent: f32, descent: f32, baseline: f32| LineMetrics {
+            width: 0.,
+            ascent,
+            descent,
+            baseline,
+            glyph_range: at..at,
+        };
+        let mut empty = empty.into_iter().peekable();
+        let mut all_lines = Vec::with_capacity(lines.len() + empty.len());
+        let mut top = 0.;
+        for (line, gap) in lines.into_iter().zip(gaps) {
+            // Empty lines before this one are stacked upwards from its top, which also works
+            // for text that isn't aligned to the top.
+            let before =
+                std::iter::from_fn(|| empty.next_if(|&(at, ..)| at <= line.glyph_range.start))
+                    .collect::<Vec<_>>();
+            let mut bottom = line.baseline - line.ascent;
+            let start = all_lines.len();
+            for &(at, ascent, descent, line_gap) in before.iter().rev() {
+                let baseline = bottom + descent - line_gap;
+                all_lines.push(empty_line(at, ascent, descent, baseline));
+                bottom = baseline - ascent;
+            }
+            all_lines[start..].reverse();
+            top = line.baseline - line.descent + gap;
+            all_lines.push(line);
+        }
+        // Trailing empty lines follow the last line with glyphs.
+        for (at, ascent, descent, line_gap) in empty {
+            all_lines.push(empty_line(at, ascent, descent, top + ascent));
+            top += ascent - descent + line_gap;
+        }
+
+        Ok(TextMetrics {
+            lines: all_lines,
+            total,
+        })
+    }
+
     pub(crate) fn measure_raw(
         &self,
         text: &TextRenderer,
         fonts: &HashMap<String, FontId>,
     ) -> GameResult<mint::Vector2<f32>> {
+        let section = self.as_section(fonts, text, DrawParam::default())?;
         Ok(text
             .glyph_brush
             .borrow_mut()
-            .glyph_bounds(self.as_section(fonts, DrawParam::default())?)
+            .glyph_bounds(section)
             .map(|rect| mint::Vector2::<f32> {
                 x: rect.width(),
                 y: rect.height(),
@@ -244,6 +430,7 @@ impl Text {
     pub(crate) fn as_section<'a>(
         &'a self,
         fonts: &HashMap<String, FontId>,
+        renderer: &TextRenderer,
         param: DrawParam,
     ) -> GameResult<glyph_brush::Section<'a, Extra>> {
         Ok(glyph_brush::Section {
@@ -263,23 +450,58 @@ impl Text {
                 .iter()
                 .map(|text| {
                     let font = text.font.as_ref().unwrap_or(&self.font);
-                    Ok(glyph_brush::Text {
-                        text: &text.text,
-                        scale: text.scale.unwrap_or(self.scale),
-                        font_id: *fonts
-                            .get(font)
-                            .ok_or_else(|| GameError::FontSelectError(font.clone()))?,
-                        extra: Extra {
-                            color: text.color.unwrap_or(param.color).into(),
-                            transform: param.transform.to_bare_matrix().into(),
+                    let font_id = *fonts
+                        .get(font)
+                        .ok_or_else(|| GameError::FontSelectError(font.clone()))?;
+                    let extra = Extra {
+                        color: renderer
+                            .color_space
+                            .shader_color(text.color.unwrap_or(param.color)),
+                        transform: param.transform.to_bare_matrix().into(),
+                    };
+                    // Characters missing from the font are pulled from the fallback chain.
+                    // glyph_brush lays out all runs of a line on a shared baseline.
+                    Ok(renderer.font_runs(&text.text, font_id).into_iter().map(
+                        move |(run, font_id)| glyph_brush::Text {
+                            text: run,
+                            scale: text.scale.unwrap_or(self.scale),
+                            font_id,
+                            extra,
                         },
-                    })
+                    ))
                 })
-                .collect::<GameResult<Vec<_>>>()?,
+                .collect::<GameResult<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
         })
     }
 }
 
+/// Size of a [`Text`] and each of its lines, see [`Text::measure_detailed`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMetrics {
+    /// Metrics of each line, from top to bottom.
+    pub lines: Vec<LineMetrics>,
+    /// Size of the whole text, the same as returned by [`Text::measure`].
+    pub total: mint::Vector2<f32>,
+}
+
+/// Metrics of a single line of a [`Text`], see [`Text::measure_detailed`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMetrics {
+    /// Width of the line, from the start of its first glyph to the end of its last one.
+    pub width: f32,
+    /// Distance from the baseline to the top of the tallest font used in the line.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest font used in the line; usually negative.
+    pub descent: f32,
+    /// Vertical position of the baseline, relative to the text's origin.
+    pub baseline: f32,
+    /// Indices of the glyphs in this line, as returned by [`Text::glyph_positions`].
+    pub glyph_range: Range<usize>,
+}
+
 impl Drawable for Text {
     fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
         canvas.push_draw(Draw::BoundedText { text: self.clone() }, param.into());
@@ -296,6 +518,273 @@ impl Drawable for Text {
     }
 }
 
+/// A list of [`Text`]s drawn together with a single draw call.
+///
+/// Drawing many small texts (damage numbers, nametags) with a [`TextBatch`] queues the
+/// glyphs of all of them at once, so they share one vertex upload and one draw call no
+/// matter what else is drawn in between. Each entry is laid out exactly like the same
+/// [`Text`] drawn on its own, and the [`DrawParam`] the batch is drawn with applies on top
+/// of the entries' own params, like with [`InstanceArray`](super::InstanceArray). Its
+/// offset is relative to the [dimensions](Drawable::dimensions) of the whole batch.
+///
+/// Cloning a batch is cheap; the entries are only copied when a shared batch is changed.
+#[derive(Debug, Clone, Default)]
+pub struct TextBatch {
+    pub(crate) entries: Arc<Vec<(Text, DrawParam)>>,
+}
+
+impl TextBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a text to the batch, drawn with `param` relative to the batch.
+    pub fn push(&mut self, text: Text, param: impl Into<DrawParam>) -> &mut Self {
+        Arc::make_mut(&mut self.entries).push((text, param.into()));
+        self
+    }
+
+    /// Removes all texts from the batch.
+    pub fn clear(&mut self) {
+        Arc::make_mut(&mut self.entries).clear();
+    }
+
+    /// Returns the texts in the batch and their params.
+    pub fn entries(&self) -> &[(Text, DrawParam)] {
+        &self.entries
+    }
+
+    /// Returns how many texts are in the batch.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the batch has no texts.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Drawable for TextBatch {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        if self.is_empty() {
+            return;
+        }
+        canvas.push_draw(
+            Draw::TextBatch {
+                entries: self.entries.clone(),
+            },
+            param.into(),
+        );
+    }
+
+    fn dimensions(&self, gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        batch_bounds(&self.entries, |text| text.measure(gfx).ok())
+    }
+}
+
+/// Returns the rectangle covering all entries of a [`TextBatch`], with `measure` giving the
+/// size of each text.
+pub(crate) fn batch_bounds(
+    entries: &[(Text, DrawParam)],
+    mut measure: impl FnMut(&Text) -> Option<mint::Vector2<f32>>,
+) -> Option<Rect> {
+    entries
+        .iter()
+        .filter_map(|(text, param)| {
+            let size = measure(text)?;
+            let rect = Rect::new(0., 0., size.x, size.y);
+            let mut param = *param;
+            if let Transform::Values { dest, offset, .. } = &mut param.transform {
+                dest.x -= offset.x * rect.w;
+                dest.y -= offset.y * rect.h;
+                *offset = mint::Point2 { x: 0., y: 0. };
+            }
+            let matrix = glam::Mat4::from(param.transform.to_bare_matrix());
+            let corners = rect
+                .corners()
+                .map(|c| matrix.transform_point3(glam::vec3(c.x, c.y, 0.)));
+            let (min, max) = corners.iter().fold(
+                (
+                    glam::Vec3::splat(f32::INFINITY),
+                    glam::Vec3::splat(f32::NEG_INFINITY),
+                ),
+                |(min, max), c| (min.min(*c), max.max(*c)),
+            );
+            Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+        })
+        .reduce(|a, b| a.combine_with(b))
+}
+
+/// What happens to a [`TextOnPath`] that's longer than its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PathOverflow {
+    /// Glyphs that don't fit on the path aren't drawn.
+    #[default]
+    Clip,
+    /// The text is scaled down to fit the path exactly.
+    Scale,
+}
+
+/// A [`Text`] laid out along a curve, made with [`Text::layout_on_path`].
+///
+/// The [`DrawParam`] it's drawn with transforms the whole curve, and its color applies to
+/// fragments without a color of their own, like for a [`Text`].
+#[derive(Debug, Clone)]
+pub struct TextOnPath {
+    text: Text,
+    /// Points of the path, each with its distance along the path from the first one.
+    path: Arc<[(glam::Vec2, f32)]>,
+    align: TextAlign,
+    overflow: PathOverflow,
+}
+
+impl TextOnPath {
+    /// Sets what happens when the text is longer than the path; [`PathOverflow::Clip`]
+    /// by default.
+    #[must_use]
+    pub fn overflow(mut self, overflow: PathOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Returns the text that's laid out.
+    pub fn text(&self) -> &Text {
+        &self.text
+    }
+
+    /// Returns the length of the whole path.
+    pub fn path_length(&self) -> f32 {
+        self.path.last().map_or(0., |&(_, distance)| distance)
+    }
+
+    /// Measures how much of the path the text takes up, the counterpart of
+    /// [`Text::measure`]. This is never more than [`path_length`](Self::path_length).
+    pub fn measure(&self, gfx: &impl Has<GraphicsContext>) -> GameResult<f32> {
+        let gfx = gfx.retrieve();
+        let (_, _, consumed) = self.layout(&gfx.fonts, &gfx.text, DrawParam::default())?;
+        Ok(consumed)
+    }
+
+    /// Lays the glyphs out on the path. Returns a section where every glyph has its own
+    /// transform, the bounds of the visible glyphs and how much of the path is used.
+    pub(crate) fn layout<'a>(
+        &'a self,
+        fonts: &HashMap<String, FontId>,
+        renderer: &TextRenderer,
+        param: DrawParam,
+    ) -> GameResult<(glyph_brush::Section<'a, Extra>, Vec<Rect>, f32)> {
+        let mut section = self.text.as_section(fonts, renderer, param)?;
+        // Every character gets a text of its own, to carry its own transform.
+        section.text = section
+            .text
+            .into_iter()
+            .flat_map(|item| {
+                item.text
+                    .char_indices()
+                    .map(move |(i, c)| glyph_brush::Text {
+                        text: &item.text[i..i + c.len_utf8()],
+                        ..item
+                    })
+            })
+            .collect();
+
+        let mut glyph_brush = renderer.glyph_brush.borrow_mut();
+        let glyphs = glyph_brush.glyphs(&section).cloned().collect::<Vec<_>>();
+        let fonts = glyph_brush.fonts();
+        let start = glyphs.first().map_or(0., |glyph| glyph.glyph.position.x);
+        let end = glyphs.iter().fold(start, |end, glyph| {
+            let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+            end.max(glyph.glyph.position.x + font.h_advance(glyph.glyph.id))
+        });
+
+        let length = self.path_length();
+        let text_length = end - start;
+        let scale = match self.overflow {
+            PathOverflow::Scale if text_length > length => length / text_length,
+            _ => 1.,
+        };
+        let offset = match self.align {
+            TextAlign::Begin => 0.,
+            TextAlign::Middle => (length - text_length * scale) / 2.,
+            TextAlign::End => length - text_length * scale,
+        };
+
+        let base = glam::Mat4::from(param.transform.to_bare_matrix());
+        let mut bounds = Vec::with_capacity(glyphs.len());
+        for glyph in &glyphs {
+            let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+            let position = glyph.glyph.position;
+            let center = position.x + font.h_advance(glyph.glyph.id) / 2.;
+            // Glyphs are placed and rotated by the middle of their advance.
+            let distance = offset + (center - start) * scale;
+            let transform = match self.point_at(distance) {
+                Some((point, angle)) => {
+                    glam::Mat4::from_translation(point.extend(0.))
+                        * glam::Mat4::from_rotation_z(angle)
+                        * glam::Mat4::from_scale(glam::vec3(scale, scale, 1.))
+                        * glam::Mat4::from_translation(glam::vec3(-center, -position.y, 0.))
+                }
+                None => glam::Mat4::ZERO,
+            };
+            section.text[glyph.section_index].extra.transform = base * transform;
+
+            if transform != glam::Mat4::ZERO {
+                let rect = font.glyph_bounds(&glyph.glyph);
+                let corners = [
+                    (rect.min.x, rect.min.y),
+                    (rect.max.x, rect.min.y),
+                    (rect.min.x, rect.max.y),
+                    (rect.max.x, rect.max.y),
+                ]
+                .map(|(x, y)| transform.transform_point3(glam::vec3(x, y, 0.)));
+                let min = corners.iter().fold(corners[0], |a, b| a.min(*b));
+                let max = corners.iter().fold(corners[0], |a, b| a.max(*b));
+                bounds.push(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y));
+            }
+        }
+
+        let consumed = if glyphs.is_empty() {
+            0.
+        } else {
+            (text_length * scale).min(length)
+        };
+        Ok((section, bounds, consumed))
+    }
+
+    /// The point `distance` along the path and the angle of the path there, or `None` if
+    /// that's past either end.
+    fn point_at(&self, distance: f32) -> Option<(glam::Vec2, f32)> {
+        if self.path.len() < 2 || !(0. ..=self.path_length()).contains(&distance) {
+            return None;
+        }
+        let next = self
+            .path
+            .partition_point(|&(_, d)| d <= distance)
+            .clamp(1, self.path.len() - 1);
+        let (from, from_distance) = self.path[next - 1];
+        let (to, to_distance) = self.path[next];
+        let t = (distance - from_distance) / (to_distance - from_distance);
+        let direction = to - from;
+        Some((from.lerp(to, t), direction.y.atan2(direction.x)))
+    }
+}
+
+impl Drawable for TextOnPath {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        canvas.push_draw(Draw::PathText { text: self.clone() }, param.into());
+    }
+
+    fn dimensions(&self, gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        let gfx = gfx.retrieve();
+        let (_, bounds, _) = self
+            .layout(&gfx.fonts, &gfx.text, DrawParam::default())
+            .ok()?;
+        bounds.into_iter().reduce(|a, b| a.combine_with(b))
+    }
+}
+
 /// Describes text alignment along a single axis.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TextAlign {
@@ -353,3 +842,311 @@ impl TextLayout {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::tests::headless_context,
+        graphics::{
+            Canvas, Color, Drawable, FontData, PathOverflow, Quad, Rect, Text, TextAlign,
+            TextBatch, TextFragment,
+        },
+    };
+
+    #[test]
+    fn headless_test_measure_detailed() {
+        let Some(ctx) = headless_context() else {
+            return;
+        };
+
+        let mut text = Text::new("ab cd");
+        let _ = text.set_scale(20.);
+        let single = text.measure_detailed(&ctx).unwrap();
+        assert_eq!(single.lines.len(), 1);
+        assert_eq!(single.lines[0].glyph_range, 0..5);
+        assert_eq!(single.total, text.measure(&ctx).unwrap());
+
+        // Wrap after "ab ", which makes "cd" the second line.
+        let _ = text.set_bounds([single.lines[0].width * 0.7, f32::INFINITY]);
+        let wrapped = text.measure_detailed(&ctx).unwrap();
+        assert_eq!(wrapped.lines.len(), 2);
+        let (first, second) = (&wrapped.lines[0], &wrapped.lines[1]);
+        assert_eq!(first.glyph_range.start, 0);
+        assert_eq!(first.glyph_range.end, second.glyph_range.start);
+        assert_eq!(second.glyph_range.end, 5);
+        assert!(second.baseline > first.baseline);
+        assert!(first.ascent > 0. && first.descent < 0.);
+        assert!(first.baseline >= first.ascent - 0.5);
+        // Monospaced, so "cd" is exactly two fifths of "ab cd".
+        assert!((second.width - single.lines[0].width * 0.4).abs() < 0.01);
+
+        let positions = text.glyph_positions(&ctx).unwrap();
+        assert_eq!(positions[second.glyph_range.start].y, second.baseline);
+
+        // Empty lines are measured too, evenly spaced between the others.
+        let mut text = Text::new("a\n\nb\n\n");
+        let _ = text.set_scale(20.);
+        let blank = text.measure_detailed(&ctx).unwrap();
+        let ranges = blank.lines.iter().map(|line| line.glyph_range.clone());
+        assert_eq!(ranges.collect::<Vec<_>>(), [0..1, 1..1, 1..2, 2..2]);
+        let height = blank.lines[1].baseline - blank.lines[0].baseline;
+        assert!(height > 0.);
+        for pair in blank.lines.windows(2) {
+            assert!((pair[1].baseline - pair[0].baseline - height).abs() < 0.01);
+        }
+        assert_eq!(blank.lines[1].width, 0.);
+        assert_eq!(blank.lines[1].ascent, blank.lines[0].ascent);
+        let positions = text.glyph_positions(&ctx).unwrap();
+        assert_eq!(positions[1].y, blank.lines[2].baseline);
+        assert_eq!(
+            Text::new("\n\n")
+                .measure_detailed(&ctx)
+                .unwrap()
+                .lines
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn headless_test_font_fallbacks() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // Tangerine has no cyrillic glyphs, which LiberationMono does.
+        let tangerine = include_bytes!("../../resources/Tangerine_Regular.ttf");
+        ctx.gfx
+            .add_font("Tangerine", FontData::from_slice(tangerine).unwrap());
+        let id = ctx.gfx.fonts["Tangerine"];
+        assert_eq!(ctx.gfx.text.font_runs("ab Жcd", id).len(), 1);
+
+        let mono = include_bytes!("../../resources/LiberationMono-Regular.ttf");
+        ctx.gfx
+            .add_font_fallback("Fallback", FontData::from_slice(mono).unwrap());
+        let fallback = ctx.gfx.fonts["Fallback"];
+        assert_eq!(
+            ctx.gfx.text.font_runs("ab Жcd", id),
+            [("ab ", id), ("Ж", fallback), ("cd", id)]
+        );
+
+        let text = Text::new(TextFragment::new("ab Жcd").font("Tangerine"));
+        let metrics = text.measure_detailed(&ctx).unwrap();
+        assert_eq!(metrics.lines.len(), 1);
+        assert_eq!(metrics.lines[0].glyph_range, 0..6);
+    }
+
+    #[test]
+    fn headless_test_text_batch() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        // Enough large, different glyphs that the glyph cache has to grow halfway through.
+        let cache_size = ctx.gfx.text.cache_size;
+        let chars = ('!'..='~').collect::<Vec<_>>();
+        let mut batch = TextBatch::new();
+        for (i, chunk) in chars.chunks(8).enumerate() {
+            let mut text = Text::new(chunk.iter().collect::<String>());
+            let _ = text.set_scale(400.);
+            let _ = batch.push(text, [0., i as f32 * 4.]);
+        }
+
+        let frame = |ctx: &mut crate::Context, batch: &TextBatch| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+            canvas.draw(&Quad, [0., 0.]);
+            canvas.draw(batch, [1., 1.]);
+            canvas.draw(&Quad, [2., 0.]);
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            ctx.gfx.frame_stats()
+        };
+        let _ = ctx.gfx.frame_stats();
+        let stats = frame(&mut ctx, &batch);
+        assert!(ctx.gfx.text.cache_size.0 > cache_size.0);
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.instances, 2 + chars.len() as u64);
+        assert!(stats.glyph_uploads > 0);
+        // The glyphs are cached now, so nothing is uploaded again.
+        let stats = crate::graphics::FrameStats {
+            glyph_uploads: 0,
+            ..stats
+        };
+        assert_eq!(frame(&mut ctx, &batch), stats);
+
+        // Entries are laid out like the same text drawn on its own.
+        let mut text = Text::new("nametag");
+        let _ = text.set_scale(12.);
+        let size = text.measure(&ctx).unwrap();
+        batch.clear();
+        assert!(batch.is_empty());
+        assert_eq!(batch.dimensions(&ctx), None);
+        let _ = batch.push(text.clone(), [10., 20.]);
+        assert_eq!(
+            batch.dimensions(&ctx),
+            Some(Rect::new(10., 20., size.x, size.y))
+        );
+        let centered = crate::graphics::DrawParam::new()
+            .dest([10., 20.])
+            .offset([0.5, 0.5]);
+        batch.clear();
+        let _ = batch.push(text, centered);
+        let rect = batch.dimensions(&ctx).unwrap();
+        assert!((rect.x - (10. - size.x / 2.)).abs() < 1e-3);
+        assert!((rect.y - (20. - size.y / 2.)).abs() < 1e-3);
+
+        // The offset the batch is drawn with is relative to the whole batch, like a text's.
+        let render = |ctx: &mut crate::Context, drawable: &dyn Fn(&mut Canvas)| {
+            let image = crate::graphics::Image::new_canvas_image(
+                ctx,
+                crate::graphics::ImageFormat::Rgba8Unorm,
+                64,
+                64,
+                1,
+            );
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_image(ctx, image.clone(), Color::BLACK);
+            drawable(&mut canvas);
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            image.to_pixels(ctx).unwrap()
+        };
+        let mut text = Text::new("ab");
+        let _ = text.set_scale(16.);
+        batch.clear();
+        let _ = batch.push(text.clone(), [0., 0.]);
+        let param = crate::graphics::DrawParam::new()
+            .dest([32., 32.])
+            .offset([0.5, 0.5]);
+        let batched = render(&mut ctx, &|canvas| canvas.draw(&batch, param));
+        assert!(batched.iter().any(|&c| c > 0));
+        assert_eq!(
+            batched,
+            render(&mut ctx, &|canvas| canvas.draw(&text, param))
+        );
+    }
+
+    #[test]
+    fn headless_test_glyph_cache_budget() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+        let budget = 2048 * 2048;
+        ctx.gfx.set_glyph_cache_size(budget);
+        ctx.gfx.text.shrink_after = 3;
+
+        let frame = |ctx: &mut crate::Context, texts: &[Text]| {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+            for text in texts {
+                canvas.draw(text, [0., 0.]);
+            }
+            canvas.finish(ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            ctx.gfx.frame_stats()
+        };
+
+        // Many unique glyphs at growing scales, until a frame needs more than the budget.
+        let mut peak = 0;
+        for scale in [40., 80., 160., 320., 640.] {
+            let texts: Vec<_> = (0..4)
+                .map(|i| {
+                    let mut text = Text::new(('!'..='~').skip(i * 24).take(24).collect::<String>());
+                    let _ = text.set_scale(scale + i as f32);
+                    text
+                })
+                .collect();
+            let stats = frame(&mut ctx, &texts);
+            assert!(stats.glyph_cache_bytes <= budget);
+            peak = peak.max(stats.glyph_cache_bytes);
+        }
+        assert!(peak > 1024 * 1024);
+
+        // Once text stops needing the space, the cache shrinks back down.
+        for _ in 0..8 {
+            let _ = frame(&mut ctx, &[Text::new("idle")]);
+        }
+        let stats = frame(&mut ctx, &[Text::new("idle")]);
+        assert_eq!(stats.glyph_uploads, 0);
+        assert!(stats.glyph_cache_bytes < peak);
+
+        // Lowering the budget shrinks the cache right away.
+        ctx.gfx.set_glyph_cache_size(512 * 512);
+        assert_eq!(frame(&mut ctx, &[]).glyph_cache_bytes, 512 * 512);
+
+        // Text too large for the budget is skipped, warning only once while it keeps coming.
+        let mut huge = Text::new("W");
+        let _ = huge.set_scale(1000.);
+        for _ in 0..3 {
+            let _ = frame(&mut ctx, &[huge.clone()]);
+            assert!(ctx.gfx.text.warned_over_budget);
+        }
+        let _ = frame(&mut ctx, &[]);
+        let _ = frame(&mut ctx, &[]);
+        assert!(!ctx.gfx.text.warned_over_budget);
+
+        // Cycling through 10k unique strings at random scales never goes over the budget,
+        // and the cache stops growing once it's reached its working size.
+        ctx.gfx.set_glyph_cache_size(budget);
+        let mut rng = crate::random::RandContext::new(192);
+        let mut sizes = Vec::new();
+        for chunk in 0..100 {
+            let texts: Vec<_> = (0..100)
+                .map(|i| {
+                    let mut text = Text::new(format!("#{}", chunk * 100 + i));
+                    let _ = text.set_scale(8. + rng.f32() * 56.);
+                    text
+                })
+                .collect();
+            let stats = frame(&mut ctx, &texts);
+            assert!(stats.glyph_cache_bytes <= budget, "{stats:?}");
+            sizes.push(stats.glyph_cache_bytes);
+        }
+        let settled = sizes[50];
+        assert!(sizes[50..].iter().all(|&size| size == settled), "{sizes:?}");
+    }
+
+    #[test]
+    fn headless_test_text_on_path() {
+        let Some(mut ctx) = headless_context() else {
+            return;
+        };
+
+        let mut text = Text::new("label");
+        let _ = text.set_scale(20.);
+        let width = text.measure(&ctx).unwrap().x;
+
+        // On a straight line, the text sits on its baseline like it would on its own.
+        let line = text.layout_on_path(&[[0., 0.], [200., 0.]], TextAlign::Begin);
+        assert_eq!(line.path_length(), 200.);
+        assert!((line.measure(&ctx).unwrap() - width).abs() < 1e-3);
+        let rect = line.dimensions(&ctx).unwrap();
+        assert!(rect.x <= 0. && (rect.right() - width).abs() < 1e-3);
+        assert!(rect.y < 0. && rect.bottom() < 10.);
+
+        // A path going down turns the glyphs with it.
+        let down = text.layout_on_path(&[[0., 0.], [0., 100.], [0., 200.]], TextAlign::End);
+        let rect = down.dimensions(&ctx).unwrap();
+        assert!(rect.h > rect.w);
+        assert!(rect.bottom() <= 200. && rect.bottom() > 190.);
+
+        let short =
+            text.layout_on_path(&[[0., 0.], [5., 0.], [5., 0.], [10., 0.]], TextAlign::Begin);
+        assert_eq!(short.path_length(), 10.);
+        assert_eq!(short.measure(&ctx).unwrap(), 10.);
+        let clipped = short.dimensions(&ctx).unwrap();
+        assert!(clipped.right() < width / 2.);
+        let scaled = short.clone().overflow(PathOverflow::Scale);
+        assert_eq!(scaled.measure(&ctx).unwrap(), 10.);
+        let rect = scaled.dimensions(&ctx).unwrap();
+        assert!(rect.right() <= 10. + 1e-3 && rect.h < clipped.h);
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.draw(&down, [8., 0.]);
+        canvas.draw(&scaled, crate::graphics::DrawParam::new().rotation(0.5));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+    }
+}
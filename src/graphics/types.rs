@@ -1,6 +1,6 @@
 use mint::Point2;
 
-use crate::graphics::{FillOptions, StrokeOptions};
+use crate::graphics::{FillOptions, ImageFormat, StrokeOptions};
 
 /// A simple 2D rectangle.
 ///
@@ -202,6 +202,114 @@ impl Rect {
         }
     }
 
+    /// Returns a copy of the `Rect` with a non-negative width and height, describing the same area.
+    ///
+    /// A `Rect` with a negative extent (e.g. from dragging a selection box up and to the left)
+    /// has its origin moved to the actual top-left corner.
+    #[must_use]
+    pub fn normalized(self) -> Rect {
+        let (x, w) = if self.w < 0.0 {
+            (self.x + self.w, -self.w)
+        } else {
+            (self.x, self.w)
+        };
+        let (y, h) = if self.h < 0.0 {
+            (self.y + self.h, -self.h)
+        } else {
+            (self.y, self.h)
+        };
+        Rect { x, y, w, h }
+    }
+
+    /// Returns the four corners of the `Rect`, in the order
+    /// top-left, top-right, bottom-right, bottom-left.
+    pub fn corners(&self) -> [mint::Point2<f32>; 4] {
+        let r = self.normalized();
+        [
+            mint::Point2 { x: r.x, y: r.y },
+            mint::Point2 {
+                x: r.right(),
+                y: r.y,
+            },
+            mint::Point2 {
+                x: r.right(),
+                y: r.bottom(),
+            },
+            mint::Point2 {
+                x: r.x,
+                y: r.bottom(),
+            },
+        ]
+    }
+
+    /// Returns the four corners of the `Rect` after rotating it by `angle` radians around its center,
+    /// in the same order as [`Rect::corners`].
+    ///
+    /// Unlike [`Rect::rotate`] this keeps the exact rotated shape instead of its bounding box.
+    pub fn rotated_corners(&self, angle: f32) -> [mint::Point2<f32>; 4] {
+        let center = glam::Vec2::from(self.center());
+        let rotation = glam::Mat2::from_angle(angle);
+        self.corners()
+            .map(|p| (rotation * (glam::Vec2::from(p) - center) + center).into())
+    }
+
+    /// Checks whether the `Rect`, rotated by `angle` radians around its center, overlaps `other`
+    /// rotated by `other_angle` radians around its center.
+    ///
+    /// Uses the separating axis theorem. Like [`Rect::overlaps`], touching edges count as overlapping.
+    pub fn overlaps_rotated(&self, angle: f32, other: &Rect, other_angle: f32) -> bool {
+        let a = self.rotated_corners(angle).map(glam::Vec2::from);
+        let b = other.rotated_corners(other_angle).map(glam::Vec2::from);
+
+        // the edge normals of a rectangle only depend on its rotation, which also keeps
+        // zero-sized rects from producing degenerate axes
+        let axes = [
+            glam::Vec2::from_angle(angle),
+            glam::Vec2::from_angle(angle).perp(),
+            glam::Vec2::from_angle(other_angle),
+            glam::Vec2::from_angle(other_angle).perp(),
+        ];
+
+        let project = |points: &[glam::Vec2; 4], axis: glam::Vec2| {
+            points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                let d = p.dot(axis);
+                (min.min(d), max.max(d))
+            })
+        };
+
+        axes.iter().all(|&axis| {
+            let (a_min, a_max) = project(&a, axis);
+            let (b_min, b_max) = project(&b, axis);
+            a_min <= b_max && b_min <= a_max
+        })
+    }
+
+    /// Returns the axis-aligned bounding box of the `Rect` after applying the given transformation matrix.
+    ///
+    /// Only the x/y components of the transformation are taken into account.
+    #[must_use]
+    pub fn transform(self, transform: impl Into<mint::ColumnMatrix4<f32>>) -> Rect {
+        let transform = glam::Mat4::from(transform.into());
+        let points = self.corners().map(|p| {
+            transform
+                .transform_point3(glam::vec3(p.x, p.y, 0.0))
+                .truncate()
+        });
+
+        let (min, max) = points
+            .iter()
+            .fold((points[0], points[0]), |(min, max), &p| {
+                (min.min(p), max.max(p))
+            });
+
+        Rect {
+            x: min.x,
+            y: min.y,
+            w: max.x - min.x,
+            h: max.y - min.y,
+        }
+    }
+
     /// Returns a new `Rect` that includes all points of these two `Rect`s.
     #[must_use]
     pub fn combine_with(self, other: Rect) -> Rect {
@@ -403,6 +511,159 @@ impl Color {
 
         u32::from_be_bytes([0, r, g, b])
     }
+
+    /// Returns the color with its alpha component replaced.
+    #[must_use]
+    pub const fn with_alpha(self, a: f32) -> Color {
+        Color { a, ..self }
+    }
+
+    /// Create an opaque `Color` from a hue in degrees, and saturation and value in the
+    /// range `[0.0-1.0]`. The hue wraps around, so `360.0` and `-120.0` are the same as
+    /// `0.0` and `240.0`.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let c = v * s;
+        let (r, g, b) = hue_to_rgb(h, c);
+        let m = v - c;
+        Color::new(r + m, g + m, b + m, 1.0)
+    }
+
+    /// Return the hue in degrees in the range `[0.0-360.0)`, and the saturation and value
+    /// in the range `[0.0-1.0]`. The alpha component is ignored.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let s = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (h, s, max)
+    }
+
+    /// Create an opaque `Color` from a hue in degrees, and saturation and lightness in the
+    /// range `[0.0-1.0]`. The hue wraps around like in [`from_hsv`](Self::from_hsv).
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Color {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let (r, g, b) = hue_to_rgb(h, c);
+        let m = l - c / 2.0;
+        Color::new(r + m, g + m, b + m, 1.0)
+    }
+
+    /// Return the hue in degrees in the range `[0.0-360.0)`, and the saturation and
+    /// lightness in the range `[0.0-1.0]`. The alpha component is ignored.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue();
+        let l = (max + min) / 2.0;
+        let s = if max > min {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        } else {
+            0.0
+        };
+        (h, s, l)
+    }
+
+    /// Returns the hue in degrees and the largest and smallest of the RGB components.
+    fn hue(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let d = max - min;
+        let h = if d <= 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / d)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / d + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / d + 4.0)
+        };
+        (h.rem_euclid(360.0), max, min)
+    }
+
+    /// Create an opaque `Color` from [OKLab](https://bottosson.github.io/posts/oklab/)
+    /// lightness and `a`/`b` components. Colors outside of the sRGB gamut are clamped.
+    ///
+    /// OKLab is perceptually uniform, so equal steps in it look like equal changes in color.
+    pub fn from_oklab(l: f32, a: f32, b: f32) -> Color {
+        let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+        let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+        let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+        Color::from(LinearColor {
+            r: (4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_).clamp(0.0, 1.0),
+            g: (-1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_).clamp(0.0, 1.0),
+            b: (-0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_).clamp(0.0, 1.0),
+            a: 1.0,
+        })
+    }
+
+    /// Return the OKLab lightness and `a`/`b` components of the color, see
+    /// [`from_oklab`](Self::from_oklab). The alpha component is ignored.
+    pub fn to_oklab(self) -> (f32, f32, f32) {
+        let c = LinearColor::from(self);
+        let l = (0.412_221_46 * c.r + 0.536_332_55 * c.g + 0.051_445_995 * c.b).cbrt();
+        let m = (0.211_903_5 * c.r + 0.680_699_5 * c.g + 0.107_396_96 * c.b).cbrt();
+        let s = (0.088_302_46 * c.r + 0.281_718_85 * c.g + 0.629_978_7 * c.b).cbrt();
+        (
+            0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        )
+    }
+
+    /// Create an opaque `Color` from OKLCh lightness, chroma and hue in degrees, the polar
+    /// form of [OKLab](Self::from_oklab). The hue wraps around.
+    pub fn from_oklch(l: f32, c: f32, h: f32) -> Color {
+        let (sin, cos) = h.to_radians().sin_cos();
+        Color::from_oklab(l, c * cos, c * sin)
+    }
+
+    /// Return the OKLCh lightness, chroma and hue in degrees in the range `[0.0-360.0)`.
+    /// The alpha component is ignored.
+    pub fn to_oklch(self) -> (f32, f32, f32) {
+        let (l, a, b) = self.to_oklab();
+        (l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Blends from `self` at `t = 0.0` to `other` at `t = 1.0`, interpolating in the
+    /// given color space. Alpha is always interpolated linearly.
+    #[must_use]
+    pub fn lerp(self, other: Color, t: f32, space: ColorLerpSpace) -> Color {
+        fn mix(a: f32, b: f32, t: f32) -> f32 {
+            a + (b - a) * t
+        }
+
+        let a = mix(self.a, other.a, t);
+        match space {
+            ColorLerpSpace::Srgb => Color::new(
+                mix(self.r, other.r, t),
+                mix(self.g, other.g, t),
+                mix(self.b, other.b, t),
+                a,
+            ),
+            ColorLerpSpace::Linear => {
+                let (x, y) = (LinearColor::from(self), LinearColor::from(other));
+                Color::from(LinearColor {
+                    r: mix(x.r, y.r, t),
+                    g: mix(x.g, y.g, t),
+                    b: mix(x.b, y.b, t),
+                    a,
+                })
+            }
+            ColorLerpSpace::Oklab => {
+                let (x, y) = (self.to_oklab(), other.to_oklab());
+                Color::from_oklab(mix(x.0, y.0, t), mix(x.1, y.1, t), mix(x.2, y.2, t))
+                    .with_alpha(a)
+            }
+            ColorLerpSpace::Oklch => {
+                let (x, y) = (self.to_oklch(), other.to_oklch());
+                // A gray has no hue, so keep the other color's hue instead of sweeping
+                // through unrelated ones.
+                let (hx, hy) = match (x.1 < 1e-4, y.1 < 1e-4) {
+                    (true, false) => (y.2, y.2),
+                    (false, true) => (x.2, x.2),
+                    _ => (x.2, y.2),
+                };
+                // Go around the shorter way.
+                let dh = (hy - hx + 180.0).rem_euclid(360.0) - 180.0;
+                Color::from_oklch(mix(x.0, y.0, t), mix(x.1, y.1, t), hx + dh * t).with_alpha(a)
+            }
+        }
+    }
 }
 
 impl From<(u8, u8, u8, u8)> for Color {
@@ -482,6 +743,38 @@ impl From<Color> for [f32; 4] {
     }
 }
 
+/// Converts a hue in degrees and chroma into RGB components, still missing the
+/// offset that sets their lightness.
+fn hue_to_rgb(h: f32, c: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    }
+}
+
+/// The color space [`Color::lerp`] interpolates in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorLerpSpace {
+    /// Interpolates the sRGB components directly. Cheap, but gradients between saturated
+    /// colors pass through dark, muddy tones.
+    #[default]
+    Srgb,
+    /// Interpolates in linear light, like blending on the GPU in
+    /// [`ColorSpaceMode::SrgbCorrect`].
+    Linear,
+    /// Interpolates in OKLab, giving perceptually even gradients.
+    Oklab,
+    /// Interpolates lightness, chroma and hue in OKLCh, going around the hue circle the
+    /// shorter way. Keeps gradients saturated, like a rainbow between red and blue.
+    Oklch,
+}
+
 /// A RGBA color in the *linear* color space,
 /// suitable for shoving into a shader.
 #[derive(Copy, Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
@@ -553,6 +846,103 @@ impl From<LinearColor> for wgpu::Color {
     }
 }
 
+/// How colors are handled between [`Color`]s, images and the window, see
+/// [`GraphicsContext::color_space`](crate::graphics::GraphicsContext::color_space).
+///
+/// In both modes an opaque `Color::new(0.5, 0.5, 0.5, 1.0)` shows up as the pixel value
+/// 128, and images look the same as in an image viewer. The modes differ in what happens in
+/// between: how colors blend, how gradients between vertex colors look, and how images are
+/// filtered.
+///
+/// # Migrating
+///
+/// Earlier versions always converted colors to linear space, but only picked an sRGB window
+/// surface if it happened to be the first one the platform offered. On platforms where it
+/// wasn't, everything was drawn too dark. Both modes now pick a matching surface format, so
+/// code that compensated for that by brightening colors should drop the workaround.
+/// Choose [`ColorSpaceMode::Legacy`] to keep blending as it looked on those platforms,
+/// which is also how most older 2D engines behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpaceMode {
+    /// Colors and images are treated as sRGB and converted to linear space, where all
+    /// blending and filtering happens. The window and canvas images use sRGB formats, which
+    /// convert the result back when it's written.
+    ///
+    /// This is the physically correct mode, and the default.
+    #[default]
+    SrgbCorrect,
+    /// Colors and image pixels are passed to shaders as they are, and blended as they are.
+    /// The window and canvas images use non-sRGB formats.
+    ///
+    /// Blending two colors gives a darker result than in [`ColorSpaceMode::SrgbCorrect`],
+    /// like in most image editors' default settings.
+    Legacy,
+}
+
+/// The units that window sizes and positions are given in, see
+/// [`GraphicsContext::set_coordinate_mode`](super::GraphicsContext::set_coordinate_mode).
+///
+/// On a HiDPI monitor with a scale factor of 2, a window that is 800 logical pixels wide
+/// covers 1600 physical pixels. The mode applies to [`GraphicsContext::size`](super::GraphicsContext::size),
+/// mouse and touch positions, the sizes passed to
+/// [`EventHandler::resize_event`](crate::event::EventHandler::resize_event) and the
+/// default screen coordinates of [`Canvas::from_frame`](super::Canvas::from_frame).
+/// [`GraphicsContext::drawable_size`](super::GraphicsContext::drawable_size) and resize
+/// callbacks always use physical pixels, since they describe the frame image itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CoordinateMode {
+    /// Physical pixels of the window surface. One unit is one pixel of the frame image,
+    /// so the same game looks smaller on a HiDPI monitor.
+    ///
+    /// This is the default.
+    #[default]
+    Physical,
+    /// Logical pixels, which are physical pixels divided by the window's scale factor.
+    /// The game keeps the same apparent size when the window moves between monitors.
+    Logical,
+}
+
+impl ColorSpaceMode {
+    /// Returns the format images loaded from files and canvas images are created with.
+    pub fn image_format(self) -> ImageFormat {
+        match self {
+            ColorSpaceMode::SrgbCorrect => ImageFormat::Rgba8UnormSrgb,
+            ColorSpaceMode::Legacy => ImageFormat::Rgba8Unorm,
+        }
+    }
+
+    /// Returns the variant of a surface format that matches this mode.
+    pub(crate) fn surface_format(self, format: ImageFormat) -> ImageFormat {
+        match self {
+            ColorSpaceMode::SrgbCorrect => format.add_srgb_suffix(),
+            ColorSpaceMode::Legacy => format.remove_srgb_suffix(),
+        }
+    }
+
+    /// Converts a color into the values shaders should see.
+    pub(crate) fn shader_color(self, color: Color) -> LinearColor {
+        match self {
+            ColorSpaceMode::SrgbCorrect => LinearColor::from(color),
+            ColorSpaceMode::Legacy => LinearColor {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+                a: color.a,
+            },
+        }
+    }
+
+    /// Like [`ColorSpaceMode::shader_color`], but for a color that is already linear,
+    /// such as the color of a mesh [`Vertex`](crate::graphics::Vertex).
+    pub(crate) fn shader_color_linear(self, color: [f32; 4]) -> [f32; 4] {
+        let [r, g, b, a] = color;
+        match self {
+            ColorSpaceMode::SrgbCorrect => color,
+            ColorSpaceMode::Legacy => Color::from(LinearColor { r, g, b, a }).into(),
+        }
+    }
+}
+
 /// Specifies whether a mesh should be drawn
 /// filled or as an outline.
 #[derive(Debug, Copy, Clone)]
@@ -612,6 +1002,86 @@ mod tests {
         assert_eq!(puce1, puce4);
     }
 
+    #[test]
+    fn headless_test_color_spaces() {
+        let colors = [
+            Color::new(0.8, 0.53333336, 0.6, 1.0),
+            Color::new(0.1, 0.9, 0.4, 1.0),
+            Color::new(0.25, 0.25, 0.25, 1.0),
+            Color::BLACK,
+            Color::WHITE,
+            Color::CYAN,
+        ];
+        let near = |a: Color, b: Color| {
+            (a.r - b.r).abs() < 1e-3 && (a.g - b.g).abs() < 1e-3 && (a.b - b.b).abs() < 1e-3
+        };
+        for c in colors {
+            let (h, s, v) = c.to_hsv();
+            assert!(near(Color::from_hsv(h, s, v), c), "{c:?} via HSV");
+            let (h, s, l) = c.to_hsl();
+            assert!(near(Color::from_hsl(h, s, l), c), "{c:?} via HSL");
+            let (l, a, b) = c.to_oklab();
+            assert!(near(Color::from_oklab(l, a, b), c), "{c:?} via OKLab");
+            let (l, ch, h) = c.to_oklch();
+            assert!(near(Color::from_oklch(l, ch, h), c), "{c:?} via OKLCh");
+        }
+
+        // Hues wrap around.
+        assert!(near(Color::from_hsv(0.0, 1.0, 1.0), Color::RED));
+        assert!(near(Color::from_hsv(360.0, 1.0, 1.0), Color::RED));
+        assert!(near(Color::from_hsv(-120.0, 1.0, 1.0), Color::BLUE));
+        assert!(near(Color::from_hsl(480.0, 1.0, 0.5), Color::GREEN));
+        assert_relative_eq!(Color::BLUE.to_hsv().0, 240.0, epsilon = 1e-3);
+        assert_relative_eq!(
+            Color::from_rgb(255, 0, 1).to_hsv().0,
+            359.76,
+            epsilon = 1e-2
+        );
+
+        let (l, _, _) = Color::WHITE.to_oklab();
+        assert_relative_eq!(l, 1.0, epsilon = 1e-3);
+        assert_eq!(Color::RED.with_alpha(0.25), Color::new(1.0, 0.0, 0.0, 0.25));
+    }
+
+    #[test]
+    fn headless_test_color_lerp() {
+        let a = Color::RED.with_alpha(0.0);
+        let b = Color::BLUE;
+        for space in [
+            ColorLerpSpace::Srgb,
+            ColorLerpSpace::Linear,
+            ColorLerpSpace::Oklab,
+            ColorLerpSpace::Oklch,
+        ] {
+            let start = a.lerp(b, 0.0, space);
+            let end = a.lerp(b, 1.0, space);
+            for (x, y) in [(start, a), (end, b)] {
+                assert_relative_eq!(x.r, y.r, epsilon = 1e-3);
+                assert_relative_eq!(x.g, y.g, epsilon = 1e-3);
+                assert_relative_eq!(x.b, y.b, epsilon = 1e-3);
+                assert_relative_eq!(x.a, y.a, epsilon = 1e-3);
+            }
+            assert_relative_eq!(a.lerp(b, 0.5, space).a, 0.5);
+        }
+
+        assert_eq!(
+            a.lerp(b, 0.5, ColorLerpSpace::Srgb),
+            Color::new(0.5, 0.0, 0.5, 0.5)
+        );
+        // Linear light blends brighter than sRGB components.
+        assert!(a.lerp(b, 0.5, ColorLerpSpace::Linear).r > 0.7);
+        // OKLCh goes through magenta, the short way from red to blue.
+        let (_, _, h) = a.lerp(b, 0.5, ColorLerpSpace::Oklch).to_oklch();
+        let (_, _, red) = Color::RED.to_oklch();
+        let (_, _, blue) = Color::BLUE.to_oklch();
+        assert!(h > blue && h < red + 360.0, "{blue} < {h} < {red}");
+        // Gray has no hue of its own.
+        let (_, _, h) = Color::new(0.5, 0.5, 0.5, 1.0)
+            .lerp(Color::RED, 0.5, ColorLerpSpace::Oklch)
+            .to_oklch();
+        assert_relative_eq!(h, red, epsilon = 1.0);
+    }
+
     #[test]
     fn headless_test_rect_scaling() {
         let r1 = Rect::new(0.0, 0.0, 128.0, 128.0);
@@ -811,4 +1281,98 @@ mod tests {
             assert_relative_eq!(r, expected);
         }
     }
+
+    #[test]
+    fn headless_test_rect_normalized() {
+        let r = Rect::new(10.0, 10.0, -4.0, -6.0).normalized();
+        assert_relative_eq!(r, Rect::new(6.0, 4.0, 4.0, 6.0));
+
+        let r = Rect::new(1.0, 2.0, 3.0, 4.0);
+        assert_relative_eq!(r.normalized(), r);
+
+        // negative extents describe the same corners as the normalized rect
+        let neg = Rect::new(4.0, 6.0, -3.0, -4.0);
+        assert_eq!(neg.corners(), Rect::new(1.0, 2.0, 3.0, 4.0).corners());
+    }
+
+    #[test]
+    fn headless_test_rect_rotated_corners() {
+        let r = Rect::new(-1.0, -1.0, 2.0, 2.0);
+        let corners = r.rotated_corners(PI * 0.25);
+        let half_diag = 2.0f32.sqrt();
+        for c in corners {
+            assert_relative_eq!(glam::Vec2::from(c).length(), half_diag, epsilon = 1e-5);
+        }
+        // rotating a full turn yields the original corners
+        for (a, b) in r.rotated_corners(PI * 2.0).iter().zip(r.corners().iter()) {
+            assert_relative_eq!(a.x, b.x, epsilon = 1e-5);
+            assert_relative_eq!(a.y, b.y, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn headless_test_rect_overlaps_rotated() {
+        let a = Rect::new(0.0, 0.0, 2.0, 2.0);
+
+        // axis-aligned cases agree with `overlaps`
+        let b = Rect::new(1.0, 1.0, 2.0, 2.0);
+        assert!(a.overlaps_rotated(0.0, &b, 0.0));
+        let b = Rect::new(3.0, 0.0, 2.0, 2.0);
+        assert!(!a.overlaps_rotated(0.0, &b, 0.0));
+
+        // edge-touching counts as overlapping
+        let b = Rect::new(2.0, 0.0, 2.0, 2.0);
+        assert!(a.overlaps(&b));
+        assert!(a.overlaps_rotated(0.0, &b, 0.0));
+
+        // the AABBs overlap, but the rotated diamond does not reach the square
+        let b = Rect::new(2.3, 2.3, 2.0, 2.0);
+        let c = glam::Vec3::new(b.center().x, b.center().y, 0.0);
+        let around_center = glam::Mat4::from_translation(c)
+            * glam::Mat4::from_rotation_z(PI * 0.25)
+            * glam::Mat4::from_translation(-c);
+        assert!(a.overlaps(&b.transform(around_center)));
+        assert!(!a.overlaps_rotated(0.0, &b, PI * 0.25));
+
+        // moving the diamond closer makes it overlap
+        let b = Rect::new(1.5, 1.5, 2.0, 2.0);
+        assert!(a.overlaps_rotated(0.0, &b, PI * 0.25));
+
+        // zero-sized rects behave like points
+        let p = Rect::new(1.0, 1.0, 0.0, 0.0);
+        assert!(a.overlaps_rotated(PI * 0.1, &p, PI * 0.3));
+        let p = Rect::new(5.0, 5.0, 0.0, 0.0);
+        assert!(!a.overlaps_rotated(PI * 0.1, &p, 0.0));
+
+        // negative extents are normalized
+        let b = Rect::new(3.0, 3.0, -2.0, -2.0);
+        assert!(a.overlaps_rotated(0.0, &b, PI * 0.25));
+    }
+
+    #[test]
+    fn headless_test_rect_transform_matrix() {
+        let r = Rect::new(0.0, 0.0, 2.0, 1.0);
+
+        let t = glam::Mat4::from_translation(glam::vec3(3.0, 4.0, 0.0));
+        assert_relative_eq!(r.transform(t), Rect::new(3.0, 4.0, 2.0, 1.0));
+
+        let s = glam::Mat4::from_scale(glam::vec3(2.0, -3.0, 1.0));
+        assert_relative_eq!(r.transform(s), Rect::new(0.0, -3.0, 4.0, 3.0));
+
+        let rot = glam::Mat4::from_rotation_z(PI * 0.5);
+        assert_relative_eq!(
+            r.transform(rot),
+            Rect::new(-1.0, 0.0, 1.0, 2.0),
+            epsilon = 1e-5
+        );
+
+        // matches the bounding box computed by `rotate`
+        let mut rotated = r;
+        rotated.rotate(PI * 0.3);
+        assert_relative_eq!(
+            r.transform(glam::Mat4::from_rotation_z(PI * 0.3)),
+            rotated,
+            epsilon = 1e-5
+        );
+    }
 }
@@ -0,0 +1,88 @@
+use super::{image::Image, ColorSpaceMode};
+use crate::conf::{WindowMode, WindowSetup};
+
+/// Identifies a secondary window opened with [`GraphicsContext::create_window`](super::GraphicsContext::create_window).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(pub(crate) u64);
+
+/// A window opened in addition to the main one, sharing its device.
+pub(crate) struct SecondaryWindow {
+    pub id: WindowId,
+    pub setup: WindowSetup,
+    pub mode: WindowMode,
+    /// `None` until the event loop gets around to creating the window.
+    pub surface: Option<WindowSurface>,
+}
+
+pub(crate) struct WindowSurface {
+    // The surface has to be dropped before the window it was created for.
+    pub surface: wgpu::Surface,
+    pub window: winit::window::Window,
+    pub config: wgpu::SurfaceConfiguration,
+    /// Off-screen image drawn to by canvases, copied to the surface in `end_frame`.
+    pub frame: Image,
+}
+
+/// Picks the format of a surface, preferring one that matches the color space.
+///
+/// If there is no format matching the color space, the frame is converted when it's copied
+/// to the surface.
+pub(crate) fn surface_format(
+    supported: &[wgpu::TextureFormat],
+    color_space: ColorSpaceMode,
+) -> wgpu::TextureFormat {
+    supported
+        .iter()
+        .copied()
+        .find(|&format| color_space.surface_format(format) == format)
+        .unwrap_or(supported[0])
+}
+
+/// Picks how the compositor blends a window's surface with what's behind it.
+///
+/// Transparent windows need premultiplied or postmultiplied alpha, whichever the platform
+/// supports; since frames are drawn with premultiplied colors, postmultiplied surfaces get
+/// them divided by alpha when copied. Other windows are opaque.
+pub(crate) fn surface_alpha_mode(
+    supported: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    use wgpu::CompositeAlphaMode::*;
+    let preferred: &[_] = if transparent {
+        &[PreMultiplied, PostMultiplied, Inherit]
+    } else {
+        &[Opaque]
+    };
+    let mode = preferred
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(Auto);
+    if transparent && mode == Auto && !supported.is_empty() {
+        log::warn!("The surface doesn't support transparency, the window will be opaque");
+    }
+    mode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::CompositeAlphaMode::*;
+
+    #[test]
+    fn headless_test_surface_alpha_mode() {
+        assert_eq!(surface_alpha_mode(&[Opaque, PreMultiplied], false), Opaque);
+        assert_eq!(
+            surface_alpha_mode(&[Opaque, PostMultiplied, PreMultiplied], true),
+            PreMultiplied
+        );
+        assert_eq!(
+            surface_alpha_mode(&[Opaque, PostMultiplied], true),
+            PostMultiplied
+        );
+        assert_eq!(surface_alpha_mode(&[Opaque, Inherit], true), Inherit);
+        assert_eq!(surface_alpha_mode(&[Opaque], true), Auto);
+        // Headless contexts have no surface at all.
+        assert_eq!(surface_alpha_mode(&[], false), Auto);
+    }
+}
@@ -0,0 +1,406 @@
+//! Named game actions, such as "jump" or "move_x", bound to keys, mouse buttons and
+//! gamepad input.
+//!
+//! Instead of checking for specific keys and buttons all over the game, register the
+//! inputs that trigger each action once and query the actions by name:
+//!
+//! ```rust
+//! # use ggez::{Context, GameResult};
+//! use ggez::input::action::{ActionMap, Binding};
+//! use ggez::input::keyboard::KeyCode;
+//! use ggez::input::mouse::MouseButton;
+//!
+//! let mut map = ActionMap::new();
+//! map.bind("fire", Binding::key(KeyCode::Space));
+//! map.bind("fire", Binding::mouse_button(MouseButton::Left));
+//! map.bind("move_x", Binding::key(KeyCode::Left).scaled(-1.));
+//! map.bind("move_x", Binding::key(KeyCode::Right));
+//!
+//! # fn t(ctx: &mut Context, map: ActionMap) -> GameResult {
+//! ctx.actions.set_map(map);
+//! // Then, in `update`:
+//! if ctx.actions.just_pressed("fire") {
+//!     // ...
+//! }
+//! let speed = 100. * ctx.actions.axis("move_x");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `gamepad` feature, gamepad buttons and analog axes can be bound too; the
+//! keys above would then give -1 or 1 and a stick anything in between.
+//!
+//! The [`ActionMap`] can be serialized, e.g. to a TOML file next to
+//! [`conf.toml`](crate::conf), so players can rebind their controls.
+//!
+//! The [`ActionContext`] is updated from the same events as the keyboard, mouse and gamepad
+//! contexts, so the actions always agree with them. If you're running your own event loop,
+//! call [`save_action_state`](ActionContext::save_action_state) at the end of each frame.
+
+use crate::input::keyboard::{KeyCode, KeyMods, ScanCode};
+use crate::input::mouse::MouseButton;
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button};
+use std::collections::{HashMap, HashSet};
+
+/// How far an axis has to be pushed for its action to count as pressed.
+#[cfg(feature = "gamepad")]
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// An input that can trigger an action, see [`Binding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum BindingInput {
+    /// A key, by its meaning in the current keyboard layout.
+    Key(KeyCode),
+    /// A key, by its position on the keyboard.
+    ScanCode(ScanCode),
+    /// A mouse button.
+    MouseButton(MouseButton),
+    /// A button on any gamepad.
+    #[cfg(feature = "gamepad")]
+    GamepadButton(Button),
+    /// An analog axis on any gamepad.
+    #[cfg(feature = "gamepad")]
+    GamepadAxis(Axis),
+}
+
+/// An input bound to an action in an [`ActionMap`].
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Binding {
+    /// The key, button or axis.
+    #[serde(flatten)]
+    pub input: BindingInput,
+    /// Modifiers that have to be held as well, for keys and mouse buttons.
+    #[serde(default)]
+    pub mods: KeyMods,
+    /// The value the binding gives an [`axis`](ActionContext::axis) while its button is
+    /// held, or the factor its axis is multiplied by.
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+impl Binding {
+    /// Creates a binding of the given input, without modifiers and a scale of 1.
+    pub fn new(input: BindingInput) -> Self {
+        Binding {
+            input,
+            mods: KeyMods::NONE,
+            scale: 1.,
+        }
+    }
+
+    /// Binds a key, by its meaning in the current keyboard layout.
+    pub fn key(key: KeyCode) -> Self {
+        Binding::new(BindingInput::Key(key))
+    }
+
+    /// Binds a key, by its position on the keyboard.
+    pub fn scancode(scancode: ScanCode) -> Self {
+        Binding::new(BindingInput::ScanCode(scancode))
+    }
+
+    /// Binds a mouse button.
+    pub fn mouse_button(button: MouseButton) -> Self {
+        Binding::new(BindingInput::MouseButton(button))
+    }
+
+    /// Binds a button on any gamepad.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_button(button: Button) -> Self {
+        Binding::new(BindingInput::GamepadButton(button))
+    }
+
+    /// Binds an analog axis on any gamepad.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_axis(axis: Axis) -> Self {
+        Binding::new(BindingInput::GamepadAxis(axis))
+    }
+
+    /// Requires the given modifiers to be held as well.
+    #[must_use]
+    pub fn with_mods(mut self, mods: KeyMods) -> Self {
+        self.mods = mods;
+        self
+    }
+
+    /// Sets the value or factor the binding gives an axis, e.g. `-1.` for the key moving left.
+    #[must_use]
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+/// The bindings of all actions, by action name.
+///
+/// This is plain data that can be serialized with serde.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ActionMap {
+    actions: HashMap<String, Vec<Binding>>,
+}
+
+impl ActionMap {
+    /// Creates a map without any actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding to an action, creating the action if needed.
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) {
+        self.actions.entry(action.into()).or_default().push(binding);
+    }
+
+    /// Removes all bindings of an action, returning them.
+    pub fn unbind(&mut self, action: &str) -> Vec<Binding> {
+        self.actions.remove(action).unwrap_or_default()
+    }
+
+    /// Returns the bindings of an action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the names of all actions.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+}
+
+/// Tracks the state of the actions of an [`ActionMap`].
+#[derive(Debug, Clone, Default)]
+pub struct ActionContext {
+    map: ActionMap,
+    mods: KeyMods,
+    pressed: HashSet<BindingInput>,
+    /// Held gamepad buttons and axis positions, by gamepad index.
+    #[cfg(feature = "gamepad")]
+    gamepad_buttons: HashSet<(usize, Button)>,
+    #[cfg(feature = "gamepad")]
+    gamepad_axes: HashMap<(usize, Axis), f32>,
+    /// The actions that were pressed at the end of the last frame.
+    previously_pressed: HashSet<String>,
+}
+
+impl ActionContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the action map.
+    pub fn map(&self) -> &ActionMap {
+        &self.map
+    }
+
+    /// Returns the action map, to change bindings.
+    pub fn map_mut(&mut self) -> &mut ActionMap {
+        &mut self.map
+    }
+
+    /// Replaces the action map, e.g. with one loaded from the player's settings.
+    pub fn set_map(&mut self, map: ActionMap) {
+        self.map = map;
+    }
+
+    /// Adds a binding to an action, see [`ActionMap::bind`].
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) {
+        self.map.bind(action, binding);
+    }
+
+    /// Returns whether any input bound to the action is held, or pushed at least halfway
+    /// for axes.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| self.state(binding).0)
+    }
+
+    /// Returns whether the action became [pressed](Self::pressed) this frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.pressed(action) && !self.previously_pressed.contains(action)
+    }
+
+    /// Returns whether the action stopped being [pressed](Self::pressed) this frame.
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.pressed(action) && self.previously_pressed.contains(action)
+    }
+
+    /// Returns the value of the action between -1 and 1, summing its bindings: held keys and
+    /// buttons give their scale and axes their position times their scale.
+    pub fn axis(&self, action: &str) -> f32 {
+        self.map
+            .bindings(action)
+            .iter()
+            .map(|binding| self.state(binding).1)
+            .sum::<f32>()
+            .clamp(-1., 1.)
+    }
+
+    /// Returns whether the binding is pressed, and its value.
+    fn state(&self, binding: &Binding) -> (bool, f32) {
+        let held = match binding.input {
+            #[cfg(feature = "gamepad")]
+            BindingInput::GamepadButton(button) => self
+                .gamepad_buttons
+                .iter()
+                .any(|&(_, pressed)| pressed == button),
+            #[cfg(feature = "gamepad")]
+            BindingInput::GamepadAxis(axis) => {
+                // The gamepad pushed furthest wins.
+                let value = self
+                    .gamepad_axes
+                    .iter()
+                    .filter(|((_, a), _)| *a == axis)
+                    .map(|(_, &value)| value)
+                    .fold(0., |a: f32, b: f32| if b.abs() > a.abs() { b } else { a });
+                return (value.abs() >= AXIS_PRESS_THRESHOLD, value * binding.scale);
+            }
+            input => self.pressed.contains(&input) && self.mods.contains(binding.mods),
+        };
+        (held, if held { binding.scale } else { 0. })
+    }
+
+    /// Remembers which actions are pressed, for [`just_pressed`](Self::just_pressed) and
+    /// [`just_released`](Self::just_released). If you are writing your own event loop, call
+    /// this at the end of every frame, like
+    /// [`save_keyboard_state`](crate::input::keyboard::KeyboardContext::save_keyboard_state).
+    pub fn save_action_state(&mut self) {
+        let pressed = self
+            .map
+            .actions()
+            .filter(|action| self.pressed(action))
+            .map(String::from)
+            .collect();
+        self.previously_pressed = pressed;
+    }
+
+    pub(crate) fn set_modifiers(&mut self, mods: KeyMods) {
+        self.mods = mods;
+    }
+
+    pub(crate) fn set_key(&mut self, scancode: ScanCode, key: Option<KeyCode>, pressed: bool) {
+        self.set_input(BindingInput::ScanCode(scancode), pressed);
+        if let Some(key) = key {
+            self.set_input(BindingInput::Key(key), pressed);
+        }
+    }
+
+    pub(crate) fn set_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.set_input(BindingInput::MouseButton(button), pressed);
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn set_gamepad_button(&mut self, pad: usize, button: Button, pressed: bool) {
+        if pressed {
+            let _ = self.gamepad_buttons.insert((pad, button));
+        } else {
+            let _ = self.gamepad_buttons.remove(&(pad, button));
+        }
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn set_gamepad_axis(&mut self, pad: usize, axis: Axis, value: f32) {
+        let _ = self.gamepad_axes.insert((pad, axis), value);
+    }
+
+    /// Forgets the state of a disconnected gamepad.
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn remove_gamepad(&mut self, pad: usize) {
+        self.gamepad_buttons.retain(|&(p, _)| p != pad);
+        self.gamepad_axes.retain(|&(p, _), _| p != pad);
+    }
+
+    fn set_input(&mut self, input: BindingInput, pressed: bool) {
+        if pressed {
+            let _ = self.pressed.insert(input);
+        } else {
+            let _ = self.pressed.remove(&input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_actions() {
+        let mut actions = ActionContext::new();
+        actions.bind("move_x", Binding::key(KeyCode::Left).scaled(-1.));
+        actions.bind("move_x", Binding::key(KeyCode::Right));
+        actions.bind("save", Binding::key(KeyCode::S).with_mods(KeyMods::CTRL));
+        actions.bind("fire", Binding::mouse_button(MouseButton::Left));
+
+        actions.set_key(30, Some(KeyCode::Left), true);
+        assert_eq!(actions.axis("move_x"), -1.);
+        assert!(actions.pressed("move_x") && actions.just_pressed("move_x"));
+        actions.set_key(32, Some(KeyCode::Right), true);
+        assert_eq!(actions.axis("move_x"), 0.);
+        actions.save_action_state();
+        actions.set_key(30, Some(KeyCode::Left), false);
+        assert_eq!(actions.axis("move_x"), 1.);
+        assert!(actions.pressed("move_x") && !actions.just_pressed("move_x"));
+
+        actions.set_key(31, Some(KeyCode::S), true);
+        assert!(!actions.pressed("save"));
+        actions.set_modifiers(KeyMods::CTRL | KeyMods::SHIFT);
+        assert!(actions.pressed("save"));
+
+        actions.set_mouse_button(MouseButton::Left, true);
+        actions.save_action_state();
+        actions.set_mouse_button(MouseButton::Left, false);
+        assert!(actions.just_released("fire"));
+        assert!(!actions.pressed("unbound"));
+
+        // Bindings survive a round trip through a settings file.
+        let saved = toml::to_string(actions.map()).unwrap();
+        let loaded: ActionMap = toml::from_str(&saved).unwrap();
+        assert_eq!(&loaded, actions.map());
+        let edited: ActionMap = toml::from_str(
+            r#"jump = [{ type = "Key", value = "Space" }, { type = "ScanCode", value = 57, mods = "SHIFT" }]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            edited.bindings("jump"),
+            [
+                Binding::key(KeyCode::Space),
+                Binding::scancode(57).with_mods(KeyMods::SHIFT)
+            ]
+        );
+    }
+
+    #[cfg(feature = "gamepad")]
+    #[test]
+    fn headless_test_gamepad_actions() {
+        let mut actions = ActionContext::new();
+        actions.bind("move_x", Binding::key(KeyCode::Left).scaled(-1.));
+        actions.bind(
+            "move_x",
+            Binding::gamepad_axis(Axis::LeftStickX).scaled(0.5),
+        );
+        actions.bind("jump", Binding::gamepad_button(Button::South));
+
+        let (a, b) = (0, 1);
+        actions.set_gamepad_axis(a, Axis::LeftStickX, 0.4);
+        actions.set_gamepad_axis(b, Axis::LeftStickX, -0.8);
+        assert_eq!(actions.axis("move_x"), -0.4);
+        assert!(actions.pressed("move_x"));
+        actions.set_gamepad_axis(b, Axis::LeftStickX, -0.2);
+        assert_eq!(actions.axis("move_x"), 0.2);
+        assert!(!actions.pressed("move_x"));
+        actions.set_key(30, Some(KeyCode::Left), true);
+        assert_eq!(actions.axis("move_x"), -0.8);
+
+        actions.set_gamepad_button(b, Button::South, true);
+        assert!(actions.pressed("jump"));
+        actions.remove_gamepad(b);
+        assert!(!actions.pressed("jump"));
+        assert_eq!(actions.axis("move_x"), -0.8);
+    }
+}
@@ -5,21 +5,224 @@
 //! cross-platform support.  Why not give it a hand?
 #![cfg(feature = "gamepad")]
 
-use gilrs::ConnectedGamepadsIterator;
-use std::fmt;
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Repeat, Replay, Ticks},
+    Axis, Button, ConnectedGamepadsIterator,
+};
+use std::{collections::HashMap, fmt, time::Duration};
+use winit::event::MouseButton;
 
-pub use gilrs::{self, Event, Gamepad, Gilrs};
+pub use gilrs::{self, Event, Gamepad, Gilrs, MappingSource};
 
 /// A unique identifier for a particular gamepad
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GamepadId(pub(crate) gilrs::GamepadId);
 
 use crate::context::Context;
-use crate::error::GameResult;
+use crate::error::{GameError, GameResult};
+
+/// Description of a gamepad, see [`GamepadContext::gamepad_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GamepadInfo {
+    /// Name of the gamepad, taken from its mapping if it has one and reported by the OS otherwise.
+    pub name: String,
+    /// Name of the SDL mapping used for the gamepad, if any.
+    pub map_name: Option<String>,
+    /// USB vendor id, if known.
+    pub vendor_id: Option<u16>,
+    /// USB product id, if known.
+    pub product_id: Option<u16>,
+    /// Where the mapping of the gamepad's buttons and axes comes from.
+    pub mapping_source: MappingSource,
+    /// Whether the gamepad supports force feedback, see [`GamepadContext::rumble`].
+    pub has_rumble: bool,
+}
+
+impl GamepadInfo {
+    /// Guesses which family of controllers the gamepad belongs to, for example to show
+    /// matching button prompts.
+    pub fn layout(&self) -> GamepadLayout {
+        match self.vendor_id {
+            Some(0x045e) => return GamepadLayout::Xbox,
+            Some(0x054c) => return GamepadLayout::PlayStation,
+            Some(0x057e) => return GamepadLayout::Nintendo,
+            _ => (),
+        }
+
+        let name = self.name.to_lowercase();
+        if name.contains("xbox") || name.contains("xinput") {
+            GamepadLayout::Xbox
+        } else if ["playstation", "ps3", "ps4", "ps5", "dualshock", "dualsense"]
+            .iter()
+            .any(|n| name.contains(n))
+        {
+            GamepadLayout::PlayStation
+        } else if name.contains("nintendo") || name.contains("switch") {
+            GamepadLayout::Nintendo
+        } else {
+            GamepadLayout::Unknown
+        }
+    }
+}
+
+/// Family of controllers a gamepad belongs to, see [`GamepadInfo::layout`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadLayout {
+    /// An Xbox or Xbox-like controller.
+    Xbox,
+    /// A PlayStation controller.
+    PlayStation,
+    /// A Nintendo controller.
+    Nintendo,
+    /// Anything else.
+    Unknown,
+}
+
+/// How gamepads drive the mouse cursor, see [`GamepadContext::set_mouse_emulation`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MouseEmulation {
+    /// How fast the cursor moves with the left stick pushed all the way, in the units of the
+    /// [`CoordinateMode`](crate::graphics::CoordinateMode) per second.
+    pub speed: f32,
+    /// How far the stick has to be pushed before the cursor moves, from 0 to 1.
+    pub dead_zone: f32,
+}
+
+impl Default for MouseEmulation {
+    fn default() -> Self {
+        MouseEmulation {
+            speed: 600.,
+            dead_zone: 0.2,
+        }
+    }
+}
+
+impl MouseEmulation {
+    /// Returns how far a stick at `(x, y)` moves the cursor in `dt`, with `y` pointing down
+    /// like window coordinates do.
+    pub(crate) fn motion(&self, x: f32, y: f32, dt: Duration) -> glam::Vec2 {
+        let stick = glam::Vec2::new(x, -y);
+        let length = stick.length().min(1.);
+        if length <= self.dead_zone {
+            return glam::Vec2::ZERO;
+        }
+        // Start from zero at the edge of the dead zone, so the cursor doesn't jump.
+        let strength = (length - self.dead_zone) / (1. - self.dead_zone);
+        stick.normalize() * strength * self.speed * dt.as_secs_f32()
+    }
+}
 
 /// A structure that contains gamepad state using `gilrs`.
 pub struct GamepadContext {
     pub(crate) gilrs: Gilrs,
+    rumbles: HashMap<GamepadId, Rumble>,
+    pub(crate) slots: PlayerSlots<GamepadId>,
+    event_times: HashMap<(GamepadId, Button), Duration>,
+    latest_event_time: Duration,
+    mouse_emulation: Option<MouseEmulation>,
+}
+
+/// Which gamepad each player slot belongs to, see [`GamepadContext::assign_slot`].
+///
+/// A slot remembers the UUID of its gamepad while it's disconnected, and the first gamepad
+/// with that UUID to connect again takes the slot back. The UUID identifies the model of a
+/// gamepad rather than the device, so with several identical gamepads the one that
+/// reconnects first gets the first free slot they used.
+#[derive(Debug)]
+pub(crate) struct PlayerSlots<Id> {
+    slots: Vec<Option<Slot<Id>>>,
+}
+
+#[derive(Debug)]
+struct Slot<Id> {
+    uuid: [u8; 16],
+    /// `None` while the gamepad is disconnected.
+    id: Option<Id>,
+}
+
+impl<Id: Copy + PartialEq> PlayerSlots<Id> {
+    fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    fn assign(&mut self, slot: usize, id: Id, uuid: [u8; 16]) {
+        self.unassign_id(id);
+        if self.slots.len() <= slot {
+            self.slots.resize_with(slot + 1, || None);
+        }
+        self.slots[slot] = Some(Slot { uuid, id: Some(id) });
+    }
+
+    fn unassign(&mut self, slot: usize) -> Option<Id> {
+        self.slots.get_mut(slot)?.take()?.id
+    }
+
+    fn unassign_id(&mut self, id: Id) {
+        if let Some(slot) = self.slot_of(id) {
+            self.slots[slot] = None;
+        }
+    }
+
+    fn slot_of(&self, id: Id) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|slot| slot.id == Some(id)))
+    }
+
+    fn gamepad(&self, slot: usize) -> Option<Id> {
+        self.slots.get(slot)?.as_ref()?.id
+    }
+
+    /// Gives a newly connected gamepad its old slot back, returning the slot.
+    pub(crate) fn connected(&mut self, id: Id, uuid: [u8; 16]) -> Option<usize> {
+        if let Some(slot) = self.slot_of(id) {
+            return Some(slot);
+        }
+        let slot = self.slots.iter().position(|slot| {
+            slot.as_ref()
+                .is_some_and(|slot| slot.id.is_none() && slot.uuid == uuid)
+        })?;
+        self.slots[slot].as_mut()?.id = Some(id);
+        Some(slot)
+    }
+
+    /// Keeps the slot of a disconnected gamepad for when it comes back, returning the slot.
+    pub(crate) fn disconnected(&mut self, id: Id) -> Option<usize> {
+        let slot = self.slot_of(id)?;
+        self.slots[slot].as_mut()?.id = None;
+        Some(slot)
+    }
+}
+
+/// Force feedback effects of a single gamepad, driving its strong and weak motors separately.
+///
+/// Both play at full magnitude; the requested strength is applied through their gain.
+struct Rumble {
+    strong: Effect,
+    weak: Effect,
+}
+
+impl Rumble {
+    fn new(gilrs: &mut Gilrs, id: GamepadId) -> GameResult<Self> {
+        let mut motor = |kind| {
+            EffectBuilder::new()
+                .add_effect(BaseEffect {
+                    kind,
+                    scheduling: Replay::default(),
+                    envelope: Default::default(),
+                })
+                .gamepads(&[id.0])
+                .finish(gilrs)
+        };
+        Ok(Rumble {
+            strong: motor(BaseEffectType::Strong {
+                magnitude: u16::MAX,
+            })?,
+            weak: motor(BaseEffectType::Weak {
+                magnitude: u16::MAX,
+            })?,
+        })
+    }
 }
 
 impl fmt::Debug for GamepadContext {
@@ -31,14 +234,21 @@ impl fmt::Debug for GamepadContext {
 impl GamepadContext {
     pub(crate) fn new() -> GameResult<Self> {
         let gilrs = Gilrs::new()?;
-        Ok(GamepadContext { gilrs })
+        Ok(GamepadContext::from(gilrs))
     }
 }
 
 impl From<Gilrs> for GamepadContext {
     /// Converts from a `Gilrs` custom instance to a `GilrsGamepadContext`
     fn from(gilrs: Gilrs) -> Self {
-        Self { gilrs }
+        Self {
+            gilrs,
+            rumbles: HashMap::new(),
+            slots: PlayerSlots::new(),
+            event_times: HashMap::new(),
+            latest_event_time: Duration::ZERO,
+            mouse_emulation: None,
+        }
     }
 }
 
@@ -59,6 +269,205 @@ impl GamepadContext {
             wrapped: self.gilrs.gamepads(),
         }
     }
+
+    /// Returns the name, ids and capabilities of a gamepad.
+    ///
+    /// This also works for gamepads that have been disconnected.
+    pub fn gamepad_info(&self, id: GamepadId) -> GamepadInfo {
+        let gamepad = self.gilrs.gamepad(id.0);
+        GamepadInfo {
+            name: gamepad.name().to_string(),
+            map_name: gamepad.map_name().map(String::from),
+            vendor_id: gamepad.vendor_id(),
+            product_id: gamepad.product_id(),
+            mapping_source: gamepad.mapping_source(),
+            has_rumble: gamepad.is_ff_supported(),
+        }
+    }
+
+    /// Makes a gamepad vibrate for `duration`, replacing any rumble that is still playing.
+    ///
+    /// `strong` and `weak` set the strength of the low and high frequency motors,
+    /// from `0.0` (off) to `1.0` (full strength).
+    ///
+    /// Does nothing for gamepads without force feedback support, and returns
+    /// [`GameError::GamepadError`] if no gamepad with the `id` is connected.
+    pub fn rumble(
+        &mut self,
+        id: GamepadId,
+        strong: f32,
+        weak: f32,
+        duration: Duration,
+    ) -> GameResult {
+        let rumble = match self.rumble_effects(id)? {
+            Some(rumble) => rumble,
+            None => return Ok(()),
+        };
+
+        let repeat = Repeat::For(Ticks::from(duration));
+        for (effect, gain) in [(&rumble.strong, strong), (&rumble.weak, weak)] {
+            effect.set_gain(gain.clamp(0., 1.))?;
+            effect.set_repeat(repeat)?;
+            effect.play()?;
+        }
+        Ok(())
+    }
+
+    /// Stops a rumble started with [`rumble`](Self::rumble) before its duration ran out.
+    ///
+    /// Like `rumble`, returns [`GameError::GamepadError`] if no gamepad with the `id` is connected.
+    pub fn stop_rumble(&mut self, id: GamepadId) -> GameResult {
+        self.check_connected(id)?;
+        if let Some(rumble) = self.rumbles.get(&id) {
+            rumble.strong.stop()?;
+            rumble.weak.stop()?;
+        }
+        Ok(())
+    }
+
+    /// Puts a gamepad in a player slot, for example when it presses a button on a
+    /// "press start to join" screen, so player 2 can keep using it by slot even if it's
+    /// unplugged and plugged back in, which gives it a new id. The gamepad leaves any slot
+    /// it was in before, and the slot's previous gamepad loses it.
+    ///
+    /// Slots are numbered from 0 and don't need to be assigned in order. When the gamepad
+    /// of a slot disconnects or reconnects,
+    /// [`EventHandler::gamepad_slot_disconnected_event`](crate::event::EventHandler::gamepad_slot_disconnected_event)
+    /// and [`gamepad_slot_connected_event`](crate::event::EventHandler::gamepad_slot_connected_event)
+    /// are called.
+    ///
+    /// Returns [`GameError::GamepadError`] if no gamepad with the `id` is connected.
+    pub fn assign_slot(&mut self, slot: usize, id: GamepadId) -> GameResult {
+        self.check_connected(id)?;
+        let uuid = self.gilrs.gamepad(id.0).uuid();
+        self.slots.assign(slot, id, uuid);
+        Ok(())
+    }
+
+    /// Puts the first connected gamepad that isn't in a slot yet into `slot`, see
+    /// [`assign_slot`](Self::assign_slot). Returns the gamepad, or `None` if every
+    /// connected gamepad already has a slot.
+    pub fn assign_next_unassigned(&mut self, slot: usize) -> Option<GamepadId> {
+        let (id, gamepad) = self
+            .gilrs
+            .gamepads()
+            .find(|&(id, _)| self.slots.slot_of(GamepadId(id)).is_none())?;
+        let uuid = gamepad.uuid();
+        self.slots.assign(slot, GamepadId(id), uuid);
+        Some(GamepadId(id))
+    }
+
+    /// Empties a player slot, returning the gamepad that was in it if it's connected.
+    pub fn unassign_slot(&mut self, slot: usize) -> Option<GamepadId> {
+        self.slots.unassign(slot)
+    }
+
+    /// Returns the player slot a gamepad is in.
+    pub fn slot_of(&self, id: GamepadId) -> Option<usize> {
+        self.slots.slot_of(id)
+    }
+
+    /// Returns the gamepad in a player slot, or `None` if the slot is empty or its
+    /// gamepad is disconnected.
+    pub fn slot_gamepad(&self, slot: usize) -> Option<GamepadId> {
+        self.slots.gamepad(slot)
+    }
+
+    /// Returns when a button of a gamepad was last pressed or released, or `None` if it
+    /// hasn't been yet.
+    ///
+    /// Like [`KeyboardContext::last_event_time`](crate::input::keyboard::KeyboardContext::last_event_time),
+    /// the time is on the clock of
+    /// [`TimeContext::time_since_start`](crate::timer::TimeContext::time_since_start). It
+    /// is taken from the timestamp `gilrs` gives the event, so it is earlier than the
+    /// frame the event is handled in.
+    pub fn last_event_time(&self, id: GamepadId, button: Button) -> Option<Duration> {
+        self.event_times.get(&(id, button)).copied()
+    }
+
+    /// Records when a button changed. Times never go backwards, even if the system clock
+    /// the event timestamps come from does.
+    pub(crate) fn set_button_event_time(&mut self, id: GamepadId, button: Button, time: Duration) {
+        self.latest_event_time = self.latest_event_time.max(time);
+        let _ = self
+            .event_times
+            .insert((id, button), self.latest_event_time);
+    }
+
+    /// Lets every connected gamepad control the mouse, for menus and games that are made for
+    /// a mouse. Off (`None`) by default.
+    ///
+    /// While it's on, the left stick moves the cursor, and the South and East buttons (A and B
+    /// on an Xbox controller) press the left and right mouse buttons. The event loop then
+    /// calls the mouse events of the [`EventHandler`](crate::event::EventHandler) as well as
+    /// the gamepad ones, and updates [`MouseContext`](crate::input::mouse::MouseContext), so
+    /// code written for the mouse works unchanged. The system cursor is moved along where the
+    /// platform allows it.
+    pub fn set_mouse_emulation(&mut self, emulation: impl Into<Option<MouseEmulation>>) {
+        self.mouse_emulation = emulation.into();
+    }
+
+    /// Returns the settings passed to [`set_mouse_emulation`](Self::set_mouse_emulation),
+    /// or `None` if it's off.
+    pub fn mouse_emulation(&self) -> Option<MouseEmulation> {
+        self.mouse_emulation
+    }
+
+    /// Returns the mouse button a gamepad button stands for while the mouse is emulated.
+    pub(crate) fn emulated_mouse_button(&self, button: Button) -> Option<MouseButton> {
+        let _ = self.mouse_emulation?;
+        match button {
+            Button::South => Some(MouseButton::Left),
+            Button::East => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+
+    /// Returns how far the left sticks of all connected gamepads move the emulated cursor
+    /// in `dt`.
+    pub(crate) fn emulated_mouse_motion(&self, dt: Duration) -> glam::Vec2 {
+        let Some(emulation) = self.mouse_emulation else {
+            return glam::Vec2::ZERO;
+        };
+        self.gilrs
+            .gamepads()
+            .map(|(_, gamepad)| {
+                let (x, y) = (
+                    gamepad.value(Axis::LeftStickX),
+                    gamepad.value(Axis::LeftStickY),
+                );
+                emulation.motion(x, y, dt)
+            })
+            .sum()
+    }
+
+    fn check_connected(&mut self, id: GamepadId) -> GameResult {
+        if self.gilrs.connected_gamepad(id.0).is_some() {
+            Ok(())
+        } else {
+            // The effects of a disconnected gamepad won't play again, even if it reconnects.
+            let _ = self.rumbles.remove(&id);
+            Err(GameError::GamepadError(format!(
+                "no gamepad with id {} is connected",
+                id.0
+            )))
+        }
+    }
+
+    /// Returns the rumble effects of a gamepad, creating them the first time,
+    /// or `None` if it has no force feedback support.
+    fn rumble_effects(&mut self, id: GamepadId) -> GameResult<Option<&Rumble>> {
+        self.check_connected(id)?;
+        if !self.gilrs.gamepad(id.0).is_ff_supported() {
+            return Ok(None);
+        }
+
+        if !self.rumbles.contains_key(&id) {
+            let rumble = Rumble::new(&mut self.gilrs, id)?;
+            let _ = self.rumbles.insert(id, rumble);
+        }
+        Ok(self.rumbles.get(&id))
+    }
 }
 
 /// An iterator of the connected gamepads
@@ -120,6 +529,80 @@ pub fn button_pressed() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn gamepad_layout() {
+        let info = |name: &str, vendor_id| GamepadInfo {
+            name: name.to_string(),
+            map_name: None,
+            vendor_id,
+            product_id: None,
+            mapping_source: MappingSource::None,
+            has_rumble: false,
+        };
+        assert_eq!(
+            info("Gamepad", Some(0x054c)).layout(),
+            GamepadLayout::PlayStation
+        );
+        assert_eq!(
+            info("Xbox Wireless Controller", None).layout(),
+            GamepadLayout::Xbox
+        );
+        assert_eq!(
+            info("Nintendo Switch Pro Controller", None).layout(),
+            GamepadLayout::Nintendo
+        );
+        assert_eq!(
+            info("Generic USB Joystick", Some(0x0079)).layout(),
+            GamepadLayout::Unknown
+        );
+    }
+
+    #[test]
+    fn player_slots() {
+        let (pad, other_pad) = ([1; 16], [2; 16]);
+        let mut slots = PlayerSlots::new();
+        slots.assign(1, 10, pad);
+        slots.assign(0, 11, other_pad);
+        assert_eq!(slots.slot_of(10), Some(1));
+        assert_eq!(slots.gamepad(0), Some(11));
+        assert_eq!(slots.gamepad(2), None);
+
+        // A gamepad reconnecting under a new id gets its slot back.
+        assert_eq!(slots.disconnected(10), Some(1));
+        assert_eq!(slots.gamepad(1), None);
+        assert_eq!(slots.connected(12, other_pad), None);
+        assert_eq!(slots.connected(13, pad), Some(1));
+        assert_eq!(slots.gamepad(1), Some(13));
+        assert_eq!(slots.disconnected(10), None);
+
+        // Assigning moves a gamepad between slots.
+        slots.assign(3, 13, pad);
+        assert_eq!(slots.slot_of(13), Some(3));
+        assert_eq!(slots.gamepad(1), None);
+        assert_eq!(slots.unassign(3), Some(13));
+        assert_eq!(slots.connected(13, pad), None);
+    }
+
+    #[test]
+    fn mouse_emulation_motion() {
+        let emulation = MouseEmulation {
+            speed: 100.,
+            dead_zone: 0.2,
+        };
+        let second = Duration::from_secs(1);
+        assert_eq!(emulation.motion(0.1, -0.1, second), glam::Vec2::ZERO);
+        // Pushing the stick up moves the cursor up the window, which is towards lower y.
+        assert!(emulation
+            .motion(0., 1., second)
+            .abs_diff_eq(glam::Vec2::new(0., -100.), 1e-4));
+        assert!(emulation
+            .motion(0.6, 0., second / 2)
+            .abs_diff_eq(glam::Vec2::new(25., 0.), 1e-4));
+        // Diagonals aren't faster than straight pushes.
+        let diagonal = emulation.motion(1., 1., second);
+        assert!((diagonal.length() - 100.).abs() < 1e-3);
+    }
+
     #[test]
     fn gilrs_init() {
         assert!(GamepadContext::new().is_ok());
@@ -109,7 +109,8 @@
 
 use crate::context::Context;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use winit::event::ModifiersState;
 pub use winit::event::ScanCode;
 /// A key code.
@@ -117,7 +118,7 @@ pub use winit::event::VirtualKeyCode as KeyCode;
 
 bitflags::bitflags! {
     /// Bitflags describing the state of keyboard modifiers, such as `Control` or `Shift`.
-    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
     pub struct KeyMods: u8 {
         /// No modifiers; equivalent to `KeyMods::default()` and
         /// [`KeyMods::empty()`](struct.KeyMods.html#method.empty).
@@ -182,6 +183,9 @@ pub struct KeyboardContext {
     // Represents the state of pressed_keys_set last frame.
     previously_pressed_keys_set: HashSet<KeyCode>,
     previously_pressed_scancodes_set: HashSet<ScanCode>,
+
+    // When each key was last pressed or released, since the start of the game.
+    event_times: HashMap<KeyCode, Duration>,
 }
 
 impl KeyboardContext {
@@ -195,6 +199,7 @@ impl KeyboardContext {
             current_pressed: None,
             previously_pressed_keys_set: HashSet::with_capacity(256),
             previously_pressed_scancodes_set: HashSet::with_capacity(256),
+            event_times: HashMap::new(),
         }
     }
 
@@ -240,6 +245,16 @@ impl KeyboardContext {
         }
     }
 
+    /// Returns when a key was last pressed or released, or `None` if it hasn't been yet.
+    ///
+    /// The time is taken as soon as the event arrives from the system, on the clock of
+    /// [`TimeContext::time_since_start`](crate::timer::TimeContext::time_since_start), so it
+    /// is never later than that and can be compared with it. This is more precise than the
+    /// frame the event is handled in, e.g. for judging the timing of a rhythm game.
+    pub fn last_event_time(&self, key: KeyCode) -> Option<Duration> {
+        self.event_times.get(&key).copied()
+    }
+
     /// Returns a reference to the set of currently pressed keys.
     pub fn pressed_keys(&self) -> &HashSet<KeyCode> {
         &self.pressed_keys_set
@@ -278,6 +293,10 @@ impl KeyboardContext {
         self.set_key_modifier(key, pressed);
     }
 
+    pub(crate) fn set_key_event_time(&mut self, key: KeyCode, time: Duration) {
+        let _ = self.event_times.insert(key, time);
+    }
+
     pub(crate) fn set_scancode(&mut self, code: ScanCode, pressed: bool) {
         if pressed {
             let _ = self.pressed_scancodes_set.insert(code);
@@ -408,6 +427,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn key_event_times() {
+        let mut keyboard = KeyboardContext::new();
+        assert_eq!(keyboard.last_event_time(KeyCode::A), None);
+        keyboard.set_key_event_time(KeyCode::A, Duration::from_millis(10));
+        keyboard.set_key_event_time(KeyCode::B, Duration::from_millis(12));
+        keyboard.set_key_event_time(KeyCode::A, Duration::from_millis(15));
+        assert_eq!(
+            keyboard.last_event_time(KeyCode::A),
+            Some(Duration::from_millis(15))
+        );
+        assert_eq!(
+            keyboard.last_event_time(KeyCode::B),
+            Some(Duration::from_millis(12))
+        );
+    }
+
     #[test]
     fn pressed_keys_tracking() {
         let mut keyboard = KeyboardContext::new();
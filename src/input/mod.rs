@@ -1,4 +1,5 @@
 //! Input handling modules for keyboard, mouse and gamepad.
+pub mod action;
 pub mod gamepad;
 pub mod keyboard;
 pub mod mouse;
@@ -3,11 +3,13 @@
 use crate::context::Context;
 use crate::error::GameError;
 use crate::error::GameResult;
-use std::collections::HashSet;
+use crate::graphics::{Canvas, DrawParam, GraphicsContext, Image};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use winit::dpi;
 pub use winit::event::MouseButton;
-use winit::window::CursorGrabMode;
 pub use winit::window::CursorIcon;
+use winit::window::{CursorGrabMode, Window};
 
 /// Stores state information for the mouse input.
 // TODO: Add "differences with window cursor" notice
@@ -18,9 +20,12 @@ pub struct MouseContext {
     delta: glam::Vec2,
     buttons_pressed: HashSet<MouseButton>,
     cursor_type: CursorIcon,
+    cursor_changed: bool,
     cursor_grabbed: bool,
     cursor_hidden: bool,
+    custom_cursor: Option<(Image, glam::Vec2)>,
     previous_buttons_pressed: HashSet<MouseButton>,
+    event_times: HashMap<MouseButton, Duration>,
 }
 
 impl MouseContext {
@@ -30,10 +35,13 @@ impl MouseContext {
             last_delta: glam::Vec2::ZERO,
             delta: glam::Vec2::ZERO,
             cursor_type: CursorIcon::Default,
+            cursor_changed: false,
             buttons_pressed: HashSet::new(),
             cursor_grabbed: false,
             cursor_hidden: false,
+            custom_cursor: None,
             previous_buttons_pressed: HashSet::new(),
+            event_times: HashMap::new(),
         }
     }
 
@@ -42,12 +50,75 @@ impl MouseContext {
         self.cursor_type
     }
 
+    /// Changes the mouse cursor of the window to one of the standard cursor icons.
+    ///
+    /// The change is applied to the window at the end of the current frame by
+    /// [`event::run()`](../../event/fn.run.html). If you're running your own event loop,
+    /// use [`set_cursor_type`](fn.set_cursor_type.html) instead.
+    pub fn set_cursor_icon(&mut self, cursor_type: CursorIcon) {
+        self.cursor_type = cursor_type;
+        self.cursor_changed = true;
+    }
+
+    /// Replaces the mouse cursor of the window with an image, with `hotspot` being the pixel
+    /// of the image that is at the mouse position.
+    ///
+    /// The windowing backend can't use images as cursors, so the system cursor is hidden and
+    /// [`event::run()`](../../event/fn.run.html) draws the image on top of every frame instead,
+    /// in the same units as [`position`](Self::position). If you're running your own event
+    /// loop, call [`draw_custom_cursor`](Self::draw_custom_cursor) before ending the frame.
+    pub fn set_custom_cursor(&mut self, image: &Image, hotspot: impl Into<mint::Point2<u32>>) {
+        let hotspot: mint::Point2<u32> = hotspot.into();
+        let hotspot = glam::Vec2::new(hotspot.x as f32, hotspot.y as f32);
+        self.custom_cursor = Some((image.clone(), hotspot));
+        self.cursor_changed = true;
+    }
+
+    /// Goes back to the system cursor after [`set_custom_cursor`](Self::set_custom_cursor).
+    pub fn clear_custom_cursor(&mut self) {
+        if self.custom_cursor.take().is_some() {
+            self.cursor_changed = true;
+        }
+    }
+
+    /// Returns the image set with [`set_custom_cursor`](Self::set_custom_cursor), if any.
+    pub fn custom_cursor(&self) -> Option<&Image> {
+        self.custom_cursor.as_ref().map(|(image, _)| image)
+    }
+
+    /// Draws the custom cursor image, if one is set, on top of the current frame.
+    ///
+    /// Does nothing while the cursor is [hidden](Self::cursor_hidden).
+    pub fn draw_custom_cursor(&self, gfx: &mut GraphicsContext) -> GameResult {
+        let Some((ref image, hotspot)) = self.custom_cursor else {
+            return Ok(());
+        };
+        if self.cursor_hidden {
+            return Ok(());
+        }
+        let mut canvas = Canvas::from_frame(gfx, None);
+        canvas.draw(image, DrawParam::new().dest(self.last_position - hotspot));
+        canvas.finish(gfx)
+    }
+
+    /// Applies a cursor change requested with [`set_cursor_icon`](Self::set_cursor_icon) or
+    /// [`set_custom_cursor`](Self::set_custom_cursor) to the window.
+    pub(crate) fn update_window_cursor(&mut self, window: Option<&Window>) {
+        if std::mem::take(&mut self.cursor_changed) {
+            if let Some(window) = window {
+                window.set_cursor_icon(self.cursor_type);
+                window.set_cursor_visible(!self.cursor_hidden && self.custom_cursor.is_none());
+            }
+        }
+    }
+
     /// Set whether or not the mouse is hidden (invisible)
     pub fn cursor_hidden(&self) -> bool {
         self.cursor_hidden
     }
 
-    /// Get the current position of the mouse cursor, in pixels.
+    /// Get the current position of the mouse cursor, in the units of the
+    /// [`CoordinateMode`](crate::graphics::CoordinateMode).
     /// Complement to [`set_position()`](fn.set_position.html).
     /// Uses strictly window-only coordinates.
     pub fn position(&self) -> mint::Point2<f32> {
@@ -136,6 +207,19 @@ impl MouseContext {
         }
     }
 
+    pub(crate) fn set_button_event_time(&mut self, button: MouseButton, time: Duration) {
+        let _ = self.event_times.insert(button, time);
+    }
+
+    /// Returns when a button was last pressed or released, or `None` if it hasn't been yet.
+    ///
+    /// Like [`KeyboardContext::last_event_time`](crate::input::keyboard::KeyboardContext::last_event_time),
+    /// the time is taken when the event arrives, on the clock of
+    /// [`TimeContext::time_since_start`](crate::timer::TimeContext::time_since_start).
+    pub fn last_event_time(&self, button: MouseButton) -> Option<Duration> {
+        self.event_times.get(&button).copied()
+    }
+
     /// Get the distance the cursor was moved between the latest two `mouse_motion_events`.
     /// Really useful only if you are writing your own event loop
     pub fn last_delta(&self) -> mint::Point2<f32> {
@@ -214,14 +298,19 @@ pub fn handle_move(ctx: &mut Context, new_x: f32, new_y: f32) {
 // TODO: Move to graphics context (This isn't input)
 pub fn set_cursor_hidden(ctx: &mut Context, hidden: bool) {
     ctx.mouse.cursor_hidden = hidden;
-    ctx.gfx.window.set_cursor_visible(!hidden);
+    if let Some(ref window) = ctx.gfx.window {
+        window.set_cursor_visible(!hidden && ctx.mouse.custom_cursor.is_none());
+    }
 }
 
 /// Modifies the mouse cursor type of the window.
 // TODO: Move to graphics context (This isn't input)
 pub fn set_cursor_type(ctx: &mut Context, cursor_type: CursorIcon) {
     ctx.mouse.cursor_type = cursor_type;
-    ctx.gfx.window.set_cursor_icon(cursor_type);
+    ctx.mouse.cursor_changed = false;
+    if let Some(ref window) = ctx.gfx.window {
+        window.set_cursor_icon(cursor_type);
+    }
 }
 
 /// Get whether or not the mouse is grabbed.
@@ -237,8 +326,11 @@ pub fn cursor_grabbed(ctx: &Context) -> bool {
 #[allow(clippy::missing_errors_doc)]
 pub fn set_cursor_grabbed(ctx: &mut Context, grabbed: bool) -> GameResult {
     ctx.mouse.cursor_grabbed = grabbed;
-    ctx.gfx
-        .window
+    let window = match ctx.gfx.window {
+        Some(ref window) => window,
+        None => return Ok(()),
+    };
+    window
         .set_cursor_grab(if grabbed {
             if cfg!(target_os = "macos") {
                 CursorGrabMode::Locked
@@ -251,7 +343,8 @@ pub fn set_cursor_grabbed(ctx: &mut Context, grabbed: bool) -> GameResult {
         .map_err(|e| GameError::WindowError(e.to_string()))
 }
 
-/// Set the current position of the mouse cursor, in pixels.
+/// Set the current position of the mouse cursor, in the units of the
+/// [`CoordinateMode`](crate::graphics::CoordinateMode).
 /// Uses strictly window-only coordinates.
 /// ### Errors
 ///
@@ -263,11 +356,15 @@ where
 {
     let point = glam::Vec2::from(point.into());
     ctx.mouse.last_position = point;
-    ctx.gfx
-        .window
-        .set_cursor_position(dpi::LogicalPosition {
-            x: f64::from(point.x),
-            y: f64::from(point.y),
+    let scale = ctx.gfx.coordinate_scale();
+    let window = match ctx.gfx.window {
+        Some(ref window) => window,
+        None => return Ok(()),
+    };
+    window
+        .set_cursor_position(dpi::PhysicalPosition {
+            x: f64::from(point.x * scale),
+            y: f64::from(point.y * scale),
         })
         .map_err(|_| GameError::WindowError("Couldn't set mouse cursor position!".to_owned()))
 }
@@ -192,6 +192,7 @@
 extern crate log;
 
 pub use glam;
+pub use lyon;
 pub use mint;
 
 pub mod audio;
@@ -202,7 +203,10 @@ pub mod event;
 pub mod filesystem;
 pub mod graphics;
 pub mod input;
+pub mod math;
+pub mod random;
 pub mod timer;
+pub mod tween;
 mod vfs;
 
 pub use crate::context::{winit, Context, ContextBuilder};
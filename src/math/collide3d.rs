@@ -0,0 +1,727 @@
+//! Intersection and raycast queries between simple 3D shapes.
+//!
+//! These only answer whether and how deeply shapes overlap; resolving the overlap is left to
+//! the game. Overlap queries return a [`Contact`] whose normal points the way the *first*
+//! shape has to move to separate from the second, and shapes that only touch count as
+//! overlapping with a depth of zero:
+//!
+//! ```rust
+//! # use ggez::{math::collide3d::{sphere_vs_aabb, Aabb, Sphere}, glam::Vec3};
+//! let ball = Sphere::new(Vec3::new(0., 0.9, 0.), 0.5);
+//! let floor = Aabb::new(Vec3::new(-10., -1., -10.), Vec3::new(10., 0.5, 10.));
+//! if let Some(contact) = sphere_vs_aabb(&ball, &floor) {
+//!     let resolved = ball.center + contact.normal * contact.depth;
+//! #   assert!((resolved.y - 1.).abs() < 1e-5);
+//! }
+//! ```
+//!
+//! Triangle meshes are given as triangles with a transform, so any vertex data can be used.
+
+use glam::{Affine3A, Quat, Vec3};
+
+/// How two shapes overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// The direction to move the first shape in to separate it from the second, of unit
+    /// length.
+    pub normal: Vec3,
+    /// How far the first shape has to move along the normal to separate.
+    pub depth: f32,
+}
+
+/// Where a [`Ray`] hits a shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// How far along the ray the hit is.
+    pub distance: f32,
+    /// The surface normal at the hit, facing against the ray.
+    pub normal: Vec3,
+}
+
+/// A half-line starting at `origin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// Where the ray starts.
+    pub origin: Vec3,
+    /// The direction of the ray, of unit length so that hit distances are in world units.
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Creates a ray, normalizing `direction`.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalize_or_zero(),
+        }
+    }
+
+    /// Returns the point `distance` along the ray.
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// A sphere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    #[allow(missing_docs)]
+    pub center: Vec3,
+    #[allow(missing_docs)]
+    pub radius: f32,
+}
+
+impl Sphere {
+    /// Creates a sphere.
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Sphere { center, radius }
+    }
+}
+
+/// An axis-aligned box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: Vec3,
+    /// The corner with the largest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Creates a box between two opposite corners, in any order.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Returns the smallest box containing all of `points`, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Aabb::new(first, first), |aabb, point| Aabb {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        }))
+    }
+
+    /// Returns the center of the box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    /// Returns half the size of the box along each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) / 2.
+    }
+
+    /// Returns whether `point` is inside the box or on its surface.
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// Returns the point in or on the box closest to `point`.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        point.clamp(self.min, self.max)
+    }
+}
+
+/// A box with any rotation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+    #[allow(missing_docs)]
+    pub center: Vec3,
+    /// Half the size of the box along each of its own axes.
+    pub half_extents: Vec3,
+    /// The rotation of the box around its center.
+    pub rotation: Quat,
+}
+
+impl Obb {
+    /// Creates a box.
+    pub fn new(center: Vec3, half_extents: Vec3, rotation: Quat) -> Self {
+        Obb {
+            center,
+            half_extents,
+            rotation,
+        }
+    }
+
+    /// Returns the box `aabb` becomes when moved by `transform`. Shearing transforms aren't
+    /// supported, and scaling must not mirror the box.
+    pub fn from_aabb(aabb: &Aabb, transform: Affine3A) -> Self {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        Obb {
+            center: rotation * (aabb.center() * scale) + translation,
+            half_extents: aabb.half_extents() * scale.abs(),
+            rotation,
+        }
+    }
+
+    /// Returns the directions of the box's own axes.
+    pub fn axes(&self) -> [Vec3; 3] {
+        [
+            self.rotation * Vec3::X,
+            self.rotation * Vec3::Y,
+            self.rotation * Vec3::Z,
+        ]
+    }
+}
+
+/// A cylinder with rounded ends: every point within `radius` of the segment from `start`
+/// to `end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    #[allow(missing_docs)]
+    pub start: Vec3,
+    #[allow(missing_docs)]
+    pub end: Vec3,
+    #[allow(missing_docs)]
+    pub radius: f32,
+}
+
+impl Capsule {
+    /// Creates a capsule.
+    pub fn new(start: Vec3, end: Vec3, radius: f32) -> Self {
+        Capsule { start, end, radius }
+    }
+}
+
+/// Returns how the sphere `a` overlaps the sphere `b`, if it does.
+pub fn sphere_vs_sphere(a: &Sphere, b: &Sphere) -> Option<Contact> {
+    let offset = a.center - b.center;
+    let distance = offset.length();
+    let depth = a.radius + b.radius - distance;
+    (depth >= 0.).then(|| Contact {
+        normal: offset.try_normalize().unwrap_or(Vec3::Y),
+        depth,
+    })
+}
+
+/// Returns how `sphere` overlaps `aabb`, if it does.
+pub fn sphere_vs_aabb(sphere: &Sphere, aabb: &Aabb) -> Option<Contact> {
+    let closest = aabb.closest_point(sphere.center);
+    if closest != sphere.center {
+        let offset = sphere.center - closest;
+        let distance = offset.length();
+        return (distance <= sphere.radius).then(|| Contact {
+            normal: offset / distance,
+            depth: sphere.radius - distance,
+        });
+    }
+
+    // The center is inside, so push the sphere out through the nearest face.
+    let (normal, distance) = nearest_face(aabb, sphere.center);
+    Some(Contact {
+        normal,
+        depth: sphere.radius + distance,
+    })
+}
+
+/// Returns the outward normal of the face of `aabb` nearest to `point`, which is inside,
+/// and how far away the face is.
+fn nearest_face(aabb: &Aabb, point: Vec3) -> (Vec3, f32) {
+    let below = point - aabb.min;
+    let above = aabb.max - point;
+    let faces = [
+        (Vec3::NEG_X, below.x),
+        (Vec3::X, above.x),
+        (Vec3::NEG_Y, below.y),
+        (Vec3::Y, above.y),
+        (Vec3::NEG_Z, below.z),
+        (Vec3::Z, above.z),
+    ];
+    faces
+        .into_iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap() // Unwrap since there are six faces.
+}
+
+/// Returns how `a` overlaps `b`, if it does, separating them along the axis where they
+/// overlap least.
+pub fn aabb_vs_aabb(a: &Aabb, b: &Aabb) -> Option<Contact> {
+    let overlap = a.max.min(b.max) - a.min.max(b.min);
+    if overlap.cmplt(Vec3::ZERO).any() {
+        return None;
+    }
+    let offset = a.center() - b.center();
+    let axis = if overlap.x <= overlap.y && overlap.x <= overlap.z {
+        Vec3::X
+    } else if overlap.y <= overlap.z {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+    let normal = if offset.dot(axis) < 0. { -axis } else { axis };
+    Some(Contact {
+        normal,
+        depth: overlap.dot(axis),
+    })
+}
+
+/// Returns how `a` overlaps `b`, if it does, using the separating axis test.
+pub fn obb_vs_obb(a: &Obb, b: &Obb) -> Option<Contact> {
+    let (axes_a, axes_b) = (a.axes(), b.axes());
+    let offset = a.center - b.center;
+    let project = |obb: &Obb, axes: &[Vec3; 3], axis: Vec3| {
+        (0..3)
+            .map(|i| obb.half_extents[i] * axes[i].dot(axis).abs())
+            .sum::<f32>()
+    };
+
+    let mut best: Option<Contact> = None;
+    let edges = axes_a
+        .iter()
+        .flat_map(|&x| axes_b.iter().map(move |&y| x.cross(y)));
+    for axis in axes_a.into_iter().chain(axes_b).chain(edges) {
+        // Parallel edges give no axis; the face axes cover that case.
+        let Some(axis) = axis.try_normalize() else {
+            continue;
+        };
+        let depth = project(a, &axes_a, axis) + project(b, &axes_b, axis) - offset.dot(axis).abs();
+        if depth < 0. {
+            return None;
+        }
+        // Prefers face axes on ties, as they come first.
+        if best.is_none_or(|best| depth < best.depth - 1e-6) {
+            let normal = if offset.dot(axis) < 0. { -axis } else { axis };
+            best = Some(Contact { normal, depth });
+        }
+    }
+    best
+}
+
+/// Returns the deepest overlap of `capsule` with any of `triangles`, after moving them by
+/// `transform`, if there is one.
+///
+/// Where the capsule's segment passes through a triangle, the contact pushes the capsule
+/// out along the triangle's normal, toward the side most of the segment is on.
+pub fn capsule_vs_triangles(
+    capsule: &Capsule,
+    triangles: &[[Vec3; 3]],
+    transform: Affine3A,
+) -> Option<Contact> {
+    let mut deepest: Option<Contact> = None;
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|p| transform.transform_point3(p));
+        let Some(contact) = capsule_vs_triangle(capsule, a, b, c) else {
+            continue;
+        };
+        if deepest.is_none_or(|deepest| contact.depth > deepest.depth) {
+            deepest = Some(contact);
+        }
+    }
+    deepest
+}
+
+fn capsule_vs_triangle(capsule: &Capsule, a: Vec3, b: Vec3, c: Vec3) -> Option<Contact> {
+    let (start, end) = (capsule.start, capsule.end);
+    let face = (b - a).cross(c - a).try_normalize()?;
+
+    if segment_vs_triangle(start, end, a, b, c) {
+        let (above, below) = (face.dot(start - a), face.dot(end - a));
+        let (normal, shallow) = if above.abs() >= below.abs() {
+            (face * above.signum(), below.abs())
+        } else {
+            (face * below.signum(), above.abs())
+        };
+        return Some(Contact {
+            normal,
+            depth: capsule.radius + shallow,
+        });
+    }
+
+    // Otherwise the closest points are at an end of the segment or on an edge.
+    let candidates = [
+        (start, closest_on_triangle(start, a, b, c)),
+        (end, closest_on_triangle(end, a, b, c)),
+        closest_between_segments(start, end, a, b),
+        closest_between_segments(start, end, b, c),
+        closest_between_segments(start, end, c, a),
+    ];
+    let (on_capsule, on_triangle) = candidates
+        .into_iter()
+        .min_by(|x, y| {
+            x.0.distance_squared(x.1)
+                .total_cmp(&y.0.distance_squared(y.1))
+        })
+        .unwrap(); // Unwrap since there are candidates.
+    let offset = on_capsule - on_triangle;
+    let distance = offset.length();
+    (distance <= capsule.radius).then(|| Contact {
+        normal: offset.try_normalize().unwrap_or(face),
+        depth: capsule.radius - distance,
+    })
+}
+
+/// Returns whether the segment passes through the triangle.
+fn segment_vs_triangle(start: Vec3, end: Vec3, a: Vec3, b: Vec3, c: Vec3) -> bool {
+    let ray = Ray::new(start, end - start);
+    ray_vs_triangle(&ray, a, b, c).is_some_and(|hit| hit.distance <= start.distance(end))
+}
+
+/// Returns the point on the triangle `abc` closest to `p`.
+fn closest_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    // From Ericson, Real-Time Collision Detection, 5.1.5: find the Voronoi region of `p`.
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0. && d2 <= 0. {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0. && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0. && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1. / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Returns the closest points between the segments `p1q1` and `p2q2`, in that order.
+fn closest_between_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    // From Ericson, Real-Time Collision Detection, 5.1.9.
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.dot(d1), d2.dot(d2), d2.dot(r));
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0., 0.)
+    } else if a <= f32::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let s = if denom > 0. {
+                ((b * f - c * e) / denom).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let t = (b * s + f) / e;
+            if t < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if t > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Returns where `ray` first hits `sphere`. A ray starting inside hits at its origin.
+pub fn ray_vs_sphere(ray: &Ray, sphere: &Sphere) -> Option<RayHit> {
+    let offset = ray.origin - sphere.center;
+    let c = offset.length_squared() - sphere.radius * sphere.radius;
+    if c <= 0. {
+        return Some(inside_hit(ray));
+    }
+    let b = offset.dot(ray.direction);
+    let discriminant = b * b - c;
+    if b > 0. || discriminant < 0. {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    Some(RayHit {
+        distance,
+        normal: (ray.at(distance) - sphere.center) / sphere.radius,
+    })
+}
+
+/// Returns where `ray` first hits `aabb`. A ray starting inside hits at its origin.
+pub fn ray_vs_aabb(ray: &Ray, aabb: &Aabb) -> Option<RayHit> {
+    if aabb.contains(ray.origin) {
+        return Some(inside_hit(ray));
+    }
+
+    // The slab method: the ray is inside the box where it's between all three pairs of
+    // planes at once.
+    let (mut enter, mut exit, mut axis) = (f32::NEG_INFINITY, f32::INFINITY, 0);
+    for i in 0..3 {
+        let (origin, direction) = (ray.origin[i], ray.direction[i]);
+        if direction == 0. {
+            if origin < aabb.min[i] || origin > aabb.max[i] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (aabb.min[i] - origin) / direction;
+        let t2 = (aabb.max[i] - origin) / direction;
+        if t1.min(t2) > enter {
+            enter = t1.min(t2);
+            axis = i;
+        }
+        exit = exit.min(t1.max(t2));
+    }
+    if exit < enter || exit < 0. {
+        return None;
+    }
+    let mut normal = Vec3::ZERO;
+    normal[axis] = -ray.direction[axis].signum();
+    Some(RayHit {
+        distance: enter,
+        normal,
+    })
+}
+
+/// Returns where `ray` first hits `obb`. A ray starting inside hits at its origin.
+pub fn ray_vs_obb(ray: &Ray, obb: &Obb) -> Option<RayHit> {
+    let inverse = obb.rotation.inverse();
+    let local = Ray {
+        origin: inverse * (ray.origin - obb.center),
+        direction: inverse * ray.direction,
+    };
+    let aabb = Aabb::new(-obb.half_extents, obb.half_extents);
+    ray_vs_aabb(&local, &aabb).map(|hit| RayHit {
+        normal: obb.rotation * hit.normal,
+        ..hit
+    })
+}
+
+/// Returns where `ray` hits the triangle `abc`, from either side.
+pub fn ray_vs_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<RayHit> {
+    // Möller-Trumbore.
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() <= f32::EPSILON {
+        return None;
+    }
+    let inverse = 1. / det;
+    let ap = ray.origin - a;
+    let u = ap.dot(p) * inverse;
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+    let q = ap.cross(ab);
+    let v = ray.direction.dot(q) * inverse;
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+    let distance = ac.dot(q) * inverse;
+    if distance < 0. {
+        return None;
+    }
+    let normal = ab.cross(ac).normalize();
+    Some(RayHit {
+        distance,
+        normal: if normal.dot(ray.direction) > 0. {
+            -normal
+        } else {
+            normal
+        },
+    })
+}
+
+/// Returns where `ray` first hits any of `triangles`, after moving them by `transform`,
+/// along with the index of the triangle hit.
+pub fn ray_vs_triangles(
+    ray: &Ray,
+    triangles: &[[Vec3; 3]],
+    transform: Affine3A,
+) -> Option<(RayHit, usize)> {
+    triangles
+        .iter()
+        .enumerate()
+        .filter_map(|(i, triangle)| {
+            let [a, b, c] = triangle.map(|p| transform.transform_point3(p));
+            Some((ray_vs_triangle(ray, a, b, c)?, i))
+        })
+        .min_by(|x, y| x.0.distance.total_cmp(&y.0.distance))
+}
+
+fn inside_hit(ray: &Ray) -> RayHit {
+    RayHit {
+        distance: 0.,
+        normal: -ray.direction,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    fn unit_box(center: Vec3) -> Aabb {
+        Aabb::new(center - Vec3::splat(0.5), center + Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn headless_test_sphere_and_aabb_overlaps() {
+        let a = Sphere::new(Vec3::ZERO, 1.);
+        // Separated, touching, overlapping and contained.
+        assert_eq!(sphere_vs_sphere(&a, &Sphere::new(Vec3::X * 3., 1.)), None);
+        let touching = sphere_vs_sphere(&a, &Sphere::new(Vec3::X * 2., 1.)).unwrap();
+        assert!(close(touching.normal, Vec3::NEG_X) && touching.depth.abs() < 1e-6);
+        let overlap = sphere_vs_sphere(&a, &Sphere::new(Vec3::Y * 1.5, 1.)).unwrap();
+        assert!(close(overlap.normal, Vec3::NEG_Y) && (overlap.depth - 0.5).abs() < 1e-6);
+        assert!(sphere_vs_sphere(&a, &Sphere::new(Vec3::ZERO, 0.1)).is_some());
+
+        let aabb = unit_box(Vec3::ZERO);
+        assert_eq!(sphere_vs_aabb(&Sphere::new(Vec3::X * 2., 1.), &aabb), None);
+        let touching = sphere_vs_aabb(&Sphere::new(Vec3::X * 1.5, 1.), &aabb).unwrap();
+        assert!(close(touching.normal, Vec3::X) && touching.depth.abs() < 1e-6);
+        // Near a corner the normal points away from it.
+        let corner = sphere_vs_aabb(&Sphere::new(Vec3::splat(0.8), 1.), &aabb).unwrap();
+        assert!(close(corner.normal, Vec3::ONE.normalize()));
+        let inside = sphere_vs_aabb(&Sphere::new(Vec3::new(0., 0.4, 0.), 0.2), &aabb).unwrap();
+        assert!(close(inside.normal, Vec3::Y) && (inside.depth - 0.3).abs() < 1e-6);
+
+        assert_eq!(aabb_vs_aabb(&aabb, &unit_box(Vec3::new(1.5, 0., 0.))), None);
+        let touching = aabb_vs_aabb(&aabb, &unit_box(Vec3::Z)).unwrap();
+        assert!(close(touching.normal, Vec3::NEG_Z) && touching.depth == 0.);
+        let overlap = aabb_vs_aabb(&aabb, &unit_box(Vec3::new(0.2, -0.9, 0.))).unwrap();
+        assert!(close(overlap.normal, Vec3::Y) && (overlap.depth - 0.1).abs() < 1e-6);
+        let big = Aabb::new(Vec3::splat(-2.), Vec3::splat(2.));
+        assert!(aabb_vs_aabb(&aabb, &big).is_some());
+
+        let points = [Vec3::ONE, Vec3::NEG_ONE, Vec3::new(3., 0., -2.)];
+        let bounds = Aabb::from_points(points).unwrap();
+        assert_eq!(
+            bounds,
+            Aabb::new(Vec3::new(-1., -1., -2.), Vec3::new(3., 1., 1.))
+        );
+        assert_eq!(Aabb::from_points([]), None);
+    }
+
+    #[test]
+    fn headless_test_obb_overlaps() {
+        let a = Obb::new(Vec3::ZERO, Vec3::splat(0.5), Quat::IDENTITY);
+        let turned = |center| Obb::new(center, Vec3::splat(0.5), Quat::from_rotation_z(FRAC_PI_4));
+
+        // A box turned by 45° reaches sqrt(0.5) along x.
+        let reach = 0.5 + 0.5f32.sqrt();
+        assert_eq!(obb_vs_obb(&a, &turned(Vec3::X * (reach + 0.01))), None);
+        let touching = obb_vs_obb(&a, &turned(Vec3::X * reach)).unwrap();
+        assert!(close(touching.normal, Vec3::NEG_X) && touching.depth.abs() < 1e-5);
+        let overlap = obb_vs_obb(&a, &turned(Vec3::X * (reach - 0.1))).unwrap();
+        assert!(close(overlap.normal, Vec3::NEG_X) && (overlap.depth - 0.1).abs() < 1e-5);
+        assert!(obb_vs_obb(&a, &turned(Vec3::ZERO)).is_some());
+
+        // Two boxes turned on different axes can be kept apart only by an edge-edge axis.
+        let x = Obb::new(
+            Vec3::ZERO,
+            Vec3::splat(0.5),
+            Quat::from_rotation_x(FRAC_PI_4),
+        );
+        let y = |d: f32| {
+            Obb::new(
+                Vec3::splat(d),
+                Vec3::splat(0.5),
+                Quat::from_rotation_y(FRAC_PI_4),
+            )
+        };
+        assert_eq!(obb_vs_obb(&x, &y(0.9)), None);
+        assert!(obb_vs_obb(&x, &y(0.7)).is_some());
+
+        let moved = Obb::from_aabb(
+            &unit_box(Vec3::ZERO),
+            Affine3A::from_scale_rotation_translation(Vec3::splat(2.), Quat::IDENTITY, Vec3::X),
+        );
+        assert_eq!(moved.half_extents, Vec3::ONE);
+        assert_eq!(moved.center, Vec3::X);
+    }
+
+    #[test]
+    fn headless_test_capsule_vs_triangles() {
+        // A 2x2 floor at y = 0, made of two triangles, raised by the transform.
+        let floor = [
+            [
+                Vec3::new(-1., 0., -1.),
+                Vec3::new(1., 0., -1.),
+                Vec3::new(1., 0., 1.),
+            ],
+            [
+                Vec3::new(-1., 0., -1.),
+                Vec3::new(1., 0., 1.),
+                Vec3::new(-1., 0., 1.),
+            ],
+        ];
+        let raised = Affine3A::from_translation(Vec3::Y);
+        let standing = |y| Capsule::new(Vec3::new(0.2, y, 0.3), Vec3::new(0.2, y + 1., 0.3), 0.5);
+
+        assert_eq!(capsule_vs_triangles(&standing(1.6), &floor, raised), None);
+        let touching = capsule_vs_triangles(&standing(1.5), &floor, raised).unwrap();
+        assert!(close(touching.normal, Vec3::Y) && touching.depth.abs() < 1e-5);
+        let resting = capsule_vs_triangles(&standing(1.25), &floor, raised).unwrap();
+        assert!(close(resting.normal, Vec3::Y) && (resting.depth - 0.25).abs() < 1e-5);
+        // Sunk through the floor, it's pushed up since most of it is above.
+        let sunk = capsule_vs_triangles(&standing(0.8), &floor, raised).unwrap();
+        assert!(close(sunk.normal, Vec3::Y) && (sunk.depth - 0.7).abs() < 1e-5);
+
+        // Next to the edge, the contact points away from it.
+        let lying = Capsule::new(Vec3::new(1.3, 1., -0.5), Vec3::new(1.3, 1., 0.5), 0.5);
+        let edge = capsule_vs_triangles(&lying, &floor, raised).unwrap();
+        assert!(close(edge.normal, Vec3::X) && (edge.depth - 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn headless_test_raycasts() {
+        let ray = Ray::new(Vec3::new(-5., 0., 0.), Vec3::X * 2.);
+        assert_eq!(ray.direction, Vec3::X);
+
+        let hit = ray_vs_sphere(&ray, &Sphere::new(Vec3::ZERO, 1.)).unwrap();
+        assert!((hit.distance - 4.).abs() < 1e-5 && close(hit.normal, Vec3::NEG_X));
+        assert_eq!(ray_vs_sphere(&ray, &Sphere::new(Vec3::Y * 2., 1.)), None);
+        assert_eq!(ray_vs_sphere(&ray, &Sphere::new(Vec3::X * -7., 1.)), None);
+        let inside = ray_vs_sphere(&ray, &Sphere::new(ray.origin, 1.)).unwrap();
+        assert_eq!(inside.distance, 0.);
+
+        let hit = ray_vs_aabb(&ray, &unit_box(Vec3::ZERO)).unwrap();
+        assert!((hit.distance - 4.5).abs() < 1e-5 && close(hit.normal, Vec3::NEG_X));
+        assert_eq!(ray_vs_aabb(&ray, &unit_box(Vec3::Y * 2.)), None);
+        // Grazing the top face still hits.
+        let grazing = Ray::new(Vec3::new(-5., 0.5, 0.), Vec3::X);
+        assert!(ray_vs_aabb(&grazing, &unit_box(Vec3::ZERO)).is_some());
+        let down = Ray::new(Vec3::new(0.1, 5., 0.2), Vec3::NEG_Y);
+        let hit = ray_vs_aabb(&down, &unit_box(Vec3::ZERO)).unwrap();
+        assert!(close(hit.normal, Vec3::Y));
+
+        let obb = Obb::new(
+            Vec3::ZERO,
+            Vec3::splat(0.5),
+            Quat::from_rotation_z(FRAC_PI_4),
+        );
+        let hit = ray_vs_obb(&ray, &obb).unwrap();
+        assert!((hit.distance - (5. - 0.5f32.sqrt())).abs() < 1e-5);
+        assert!(hit.normal.x < 0. && hit.normal.z.abs() < 1e-5);
+
+        let triangle = [
+            Vec3::new(0., -1., -1.),
+            Vec3::new(0., 1., -1.),
+            Vec3::new(0., 0., 1.),
+        ];
+        let tris = [triangle, triangle.map(|p| p + Vec3::X * 2.)];
+        let (hit, index) = ray_vs_triangles(&ray, &tris, Affine3A::IDENTITY).unwrap();
+        assert_eq!(index, 0);
+        assert!((hit.distance - 5.).abs() < 1e-5 && close(hit.normal, Vec3::NEG_X));
+        let behind = Affine3A::from_translation(Vec3::X * -10.);
+        assert_eq!(ray_vs_triangles(&ray, &tris, behind), None);
+        let shifted = Affine3A::from_translation(Vec3::Y * 3.);
+        assert_eq!(ray_vs_triangles(&ray, &tris, shifted), None);
+    }
+}
@@ -0,0 +1,26 @@
+//! Smooth paths and collision queries.
+//!
+//! A [`Spline`] is a chain of cubic Bézier segments, built from control points directly or
+//! from points to pass through with [`Spline::catmull_rom`]. It can be sampled by its
+//! parameter `t`, or by distance traveled so that things move along it at a steady speed:
+//!
+//! ```rust
+//! # use ggez::{math::Spline, glam::Vec2};
+//! let platform = Spline::catmull_rom(&[
+//!     Vec2::new(0., 0.),
+//!     Vec2::new(100., 50.),
+//!     Vec2::new(200., 0.),
+//! ]);
+//! // In `update`, moving 60 pixels per second:
+//! # let elapsed = 1.5;
+//! let position = platform.sample_by_distance(elapsed * 60.);
+//! # assert!(position.x > 0.);
+//! ```
+//!
+//! [`collide3d`] has intersection and raycast queries between simple 3D shapes, for gameplay
+//! that needs to know what touches what without a physics engine.
+
+pub mod collide3d;
+mod spline;
+
+pub use spline::*;
@@ -0,0 +1,333 @@
+//! Cubic splines, see [`Spline`].
+
+use std::ops::{Add, Mul, Sub};
+
+/// Vectors a [`Spline`] can be made of, implemented for [`glam::Vec2`] and [`glam::Vec3`].
+pub trait SplineVector:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+    /// Returns the length of the vector.
+    fn length(self) -> f32;
+}
+
+impl SplineVector for glam::Vec2 {
+    fn length(self) -> f32 {
+        glam::Vec2::length(self)
+    }
+}
+
+impl SplineVector for glam::Vec3 {
+    fn length(self) -> f32 {
+        glam::Vec3::length(self)
+    }
+}
+
+/// A cubic Bézier segment.
+#[derive(Debug, Clone, Copy)]
+struct Segment<V> {
+    start: V,
+    control1: V,
+    control2: V,
+    end: V,
+}
+
+impl<V: SplineVector> Segment<V> {
+    fn point(&self, t: f32) -> V {
+        let u = 1. - t;
+        self.start * (u * u * u)
+            + self.control1 * (3. * u * u * t)
+            + self.control2 * (3. * u * t * t)
+            + self.end * (t * t * t)
+    }
+
+    fn derivative(&self, t: f32) -> V {
+        let u = 1. - t;
+        (self.control1 - self.start) * (3. * u * u)
+            + (self.control2 - self.control1) * (6. * u * t)
+            + (self.end - self.control2) * (3. * t * t)
+    }
+}
+
+/// A smooth path made of cubic Bézier segments, with a table of arc lengths for moving
+/// along it at a steady speed.
+///
+/// The parameter `t` runs from `0.0` at the start to `1.0` at the end, with every segment
+/// taking an equal share of it; since segments differ in length, use
+/// [`sample_by_distance`](Self::sample_by_distance) for steady movement.
+#[derive(Debug, Clone)]
+pub struct Spline<V> {
+    start: V,
+    segments: Vec<Segment<V>>,
+    /// Distances from the start at `ARC_SAMPLES` evenly spaced parameters of each segment,
+    /// not including the start itself.
+    lengths: Vec<f32>,
+}
+
+impl<V: SplineVector> Spline<V> {
+    /// Samples per segment of the arc length table.
+    const ARC_SAMPLES: usize = 32;
+
+    /// Creates an empty spline at `start`, to add segments to with
+    /// [`cubic_to`](Self::cubic_to) and [`line_to`](Self::line_to).
+    pub fn new(start: V) -> Self {
+        Spline {
+            start,
+            segments: Vec::new(),
+            lengths: Vec::new(),
+        }
+    }
+
+    /// Creates a spline passing through all of `points` in order, with the curve at each
+    /// point heading from the point before it toward the point after it (uniform
+    /// Catmull-Rom).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` is empty.
+    pub fn catmull_rom(points: &[V]) -> Self {
+        assert!(!points.is_empty(), "a spline needs at least one point");
+        let mut spline = Spline::new(points[0]);
+        for i in 1..points.len() {
+            let before = points[i.saturating_sub(2)];
+            let after = points[(i + 1).min(points.len() - 1)];
+            spline.push_catmull_rom(before, points[i - 1], points[i], after);
+        }
+        spline
+    }
+
+    /// Like [`catmull_rom`](Self::catmull_rom), but also curves from the last point back to
+    /// the first, for paths that loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` is empty.
+    pub fn catmull_rom_closed(points: &[V]) -> Self {
+        assert!(!points.is_empty(), "a spline needs at least one point");
+        let n = points.len();
+        let mut spline = Spline::new(points[0]);
+        for i in 1..=n {
+            spline.push_catmull_rom(
+                points[(i + n - 2) % n],
+                points[i - 1],
+                points[i % n],
+                points[(i + 1) % n],
+            );
+        }
+        spline
+    }
+
+    fn push_catmull_rom(&mut self, before: V, start: V, end: V, after: V) {
+        self.push(Segment {
+            start,
+            control1: start + (end - before) * (1. / 6.),
+            control2: end - (after - start) * (1. / 6.),
+            end,
+        });
+    }
+
+    /// Adds a cubic Bézier segment from the end of the spline to `end`, pulled toward the
+    /// two control points.
+    #[must_use]
+    pub fn cubic_to(mut self, control1: V, control2: V, end: V) -> Self {
+        let start = self.end();
+        self.push(Segment {
+            start,
+            control1,
+            control2,
+            end,
+        });
+        self
+    }
+
+    /// Adds a straight segment from the end of the spline to `end`.
+    #[must_use]
+    pub fn line_to(self, end: V) -> Self {
+        let start = self.end();
+        let third = (end - start) * (1. / 3.);
+        self.cubic_to(start + third, end - third, end)
+    }
+
+    fn push(&mut self, segment: Segment<V>) {
+        let mut distance = self.length();
+        let mut previous = segment.start;
+        for i in 1..=Self::ARC_SAMPLES {
+            let point = segment.point(i as f32 / Self::ARC_SAMPLES as f32);
+            distance += (point - previous).length();
+            self.lengths.push(distance);
+            previous = point;
+        }
+        self.segments.push(segment);
+    }
+
+    /// Returns the point the spline ends at.
+    pub fn end(&self) -> V {
+        self.segments
+            .last()
+            .map_or(self.start, |segment| segment.end)
+    }
+
+    /// Returns how many segments the spline is made of.
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the total length of the spline, measured along the curve.
+    pub fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.)
+    }
+
+    /// Finds the segment at `t` and how far along it `t` is.
+    fn locate(&self, t: f32) -> Option<(&Segment<V>, f32)> {
+        let scaled = t.clamp(0., 1.) * self.segments.len() as f32;
+        let index = (scaled as usize).min(self.segments.len().checked_sub(1)?);
+        Some((&self.segments[index], scaled - index as f32))
+    }
+
+    /// Returns the point at `t`, clamped to `0.0..=1.0`.
+    pub fn sample(&self, t: f32) -> V {
+        self.locate(t)
+            .map_or(self.start, |(segment, t)| segment.point(t))
+    }
+
+    /// Returns the derivative of the spline with respect to `t`, clamped to `0.0..=1.0`.
+    /// It points the way the spline is heading, and is longer where `t` covers more distance.
+    pub fn tangent(&self, t: f32) -> V {
+        match self.locate(t) {
+            Some((segment, t)) => segment.derivative(t) * self.segments.len() as f32,
+            None => self.start * 0.,
+        }
+    }
+
+    /// Returns the parameter `t` at `distance` along the spline, clamped to the
+    /// [`length`](Self::length).
+    ///
+    /// Distances are looked up in a table of lengths measured when the segments were added,
+    /// so they're exact at the table's samples and approximate in between.
+    pub fn t_at_distance(&self, distance: f32) -> f32 {
+        if self.segments.is_empty() || distance <= 0. {
+            return 0.;
+        }
+        let distance = distance.min(self.length());
+        let i = self.lengths.partition_point(|&length| length < distance);
+        let (before, after) = (
+            i.checked_sub(1).map_or(0., |i| self.lengths[i]),
+            self.lengths[i],
+        );
+        let fraction = if after > before {
+            (distance - before) / (after - before)
+        } else {
+            0.
+        };
+        (i as f32 + fraction) / self.lengths.len() as f32
+    }
+
+    /// Returns the point at `distance` along the spline, for moving along it at a steady
+    /// speed. See [`t_at_distance`](Self::t_at_distance).
+    pub fn sample_by_distance(&self, distance: f32) -> V {
+        self.sample(self.t_at_distance(distance))
+    }
+}
+
+impl Spline<glam::Vec2> {
+    /// Returns the angle the spline is heading in at `t`, for
+    /// [`DrawParam::rotation`](crate::graphics::DrawParam::rotation) to face something along it.
+    ///
+    /// An angle of zero points along the x axis, like an unrotated image.
+    pub fn rotation(&self, t: f32) -> f32 {
+        let tangent = self.tangent(t);
+        tangent.y.atan2(tangent.x)
+    }
+}
+
+impl Spline<glam::Vec3> {
+    /// Returns the rotation that turns `Vec3::Z` to face the way the spline is heading at
+    /// `t`, with `Vec3::Y` turned as close to `up` as possible, e.g. for a camera flying
+    /// along it.
+    ///
+    /// Returns no rotation where the spline stands still, and keeps `up` out of the result
+    /// if the spline heads straight along it.
+    pub fn orientation(&self, t: f32, up: glam::Vec3) -> glam::Quat {
+        let forward = self.tangent(t).normalize_or_zero();
+        if forward == glam::Vec3::ZERO {
+            return glam::Quat::IDENTITY;
+        }
+        let right = up.cross(forward).normalize_or_zero();
+        if right == glam::Vec3::ZERO {
+            return glam::Quat::from_rotation_arc(glam::Vec3::Z, forward);
+        }
+        let up = forward.cross(right);
+        glam::Quat::from_mat3(&glam::Mat3::from_cols(right, up, forward))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Vec2, Vec3};
+    use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+    /// A circle of `radius` around the origin out of four Bézier quarters.
+    fn bezier_circle(radius: f32) -> Spline<Vec2> {
+        // Puts the midpoint of each quarter exactly on the circle.
+        let k = 4. / 3. * (2f32.sqrt() - 1.) * radius;
+        let mut spline = Spline::new(Vec2::new(radius, 0.));
+        for quarter in 0..4 {
+            let (a, b) = (quarter as f32 * FRAC_PI_2, (quarter + 1) as f32 * FRAC_PI_2);
+            let (from, to) = (Vec2::from_angle(a), Vec2::from_angle(b));
+            spline = spline.cubic_to(
+                from * radius + from.perp() * k,
+                to * radius - to.perp() * k,
+                to * radius,
+            );
+        }
+        spline
+    }
+
+    #[test]
+    fn headless_test_spline_lengths() {
+        let circle = bezier_circle(100.);
+        assert_eq!(circle.segment_count(), 4);
+        assert!((circle.length() - TAU * 100.).abs() < TAU * 100. * 1e-3);
+        assert!((circle.sample(1.) - Vec2::new(100., 0.)).length() < 1e-3);
+
+        let points: Vec<_> = (0..64)
+            .map(|i| Vec2::from_angle(i as f32 / 64. * TAU) * 50.)
+            .collect();
+        let circle = Spline::catmull_rom_closed(&points);
+        assert!((circle.length() - TAU * 50.).abs() < TAU * 50. * 1e-3);
+
+        // Points found by distance lie at the matching angle, around a quarter.
+        for distance in [10., 60., PI * 50., 250.] {
+            let point = circle.sample_by_distance(distance);
+            let angle = point.y.atan2(point.x).rem_euclid(TAU);
+            assert!((angle - distance / 50.).abs() < 1e-2, "{distance}");
+        }
+        let heading = circle.rotation(0.25);
+        assert!((heading.abs() - PI).abs() < 1e-2, "{heading}");
+
+        let line = Spline::new(Vec3::ZERO).line_to(Vec3::new(0., 0., -3.));
+        assert!((line.length() - 3.).abs() < 1e-4);
+        assert!((line.sample_by_distance(1.5) - Vec3::new(0., 0., -1.5)).length() < 1e-4);
+        assert_eq!(line.t_at_distance(10.), 1.);
+        let facing = line.orientation(0.5, Vec3::Y) * Vec3::Z;
+        assert!((facing - Vec3::NEG_Z).length() < 1e-4);
+    }
+
+    #[test]
+    fn headless_test_catmull_rom_passes_through_points() {
+        let points = [Vec2::ZERO, Vec2::new(10., 5.), Vec2::new(20., 0.)];
+        let spline = Spline::catmull_rom(&points);
+        assert_eq!(spline.segment_count(), 2);
+        for (i, &point) in points.iter().enumerate() {
+            assert!((spline.sample(i as f32 / 2.) - point).length() < 1e-4);
+        }
+        // Heads from the first point toward the third at the second.
+        let tangent = spline.tangent(0.5);
+        assert!(tangent.y.abs() < 1e-4 && tangent.x > 0.);
+
+        let single = Spline::catmull_rom(&[Vec2::ONE]);
+        assert_eq!(single.length(), 0.);
+        assert_eq!(single.sample(0.5), Vec2::ONE);
+        assert_eq!(single.sample_by_distance(3.), Vec2::ONE);
+    }
+}
@@ -0,0 +1,256 @@
+//! Seedable random numbers tied to the [`Context`](crate::Context).
+//!
+//! [`RandContext`] is a small PCG32 generator. Since it's owned by the context instead of
+//! living in a thread-local, the same seed always produces the same numbers in the same
+//! order, which is what replays and lockstep networking need. Set the seed with
+//! [`Conf::random_seed`](crate::conf::Conf::random_seed) or [`RandContext::set_seed`].
+//!
+//! Subsystems that should not disturb each other's sequences, like particle effects and
+//! gameplay logic, can each get their own stream with [`RandContext::fork`].
+
+use std::ops::Range;
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+/// Scrambles a number, used to turn seeds and stream ids into generator states.
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// A deterministic random number generator, available as `ctx.random`.
+///
+/// See the [module docs](self) for details.
+// Not `Copy`, so that a generator isn't duplicated by accident and repeats its numbers.
+#[allow(missing_copy_implementations)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandContext {
+    seed: u64,
+    stream: u64,
+    state: u64,
+    inc: u64,
+}
+
+impl RandContext {
+    /// Creates a generator from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+
+    /// Creates a generator from a seed taken from the system clock.
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = RandContext {
+            seed,
+            stream,
+            state: 0,
+            inc: (splitmix64(stream) << 1) | 1,
+        };
+        rng.state = splitmix64(seed).wrapping_add(rng.inc);
+        let _ = rng.u32();
+        rng
+    }
+
+    /// Restarts the sequence from the given seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        *self = Self::with_stream(seed, self.stream);
+    }
+
+    /// Returns the seed the generator was created or last reset with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns an independent generator for the given stream.
+    ///
+    /// The result only depends on the seed and `stream_id`, not on how many numbers have
+    /// been drawn so far, so forking the same stream again gives the same sequence.
+    /// Different streams are independent of each other, even though they share the seed.
+    pub fn fork(&self, stream_id: u64) -> Self {
+        Self::with_stream(self.seed, splitmix64(self.stream) ^ stream_id)
+    }
+
+    /// Returns a random `u32`.
+    pub fn u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Returns a random `u64`.
+    pub fn u64(&mut self) -> u64 {
+        (u64::from(self.u32()) << 32) | u64::from(self.u32())
+    }
+
+    /// Returns a random `f32` in `0.0..1.0`.
+    pub fn f32(&mut self) -> f32 {
+        (self.u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns a random `f64` in `0.0..1.0`.
+    pub fn f64(&mut self) -> f64 {
+        (self.u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random `bool`, which is `true` with the given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.f32() < probability
+    }
+
+    /// Returns a number below `n` without favoring any of them.
+    fn below(&mut self, n: u64) -> u64 {
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let x = self.u64();
+            if x >= threshold {
+                return x % n;
+            }
+        }
+    }
+
+    /// Returns a random number in the given range, like `rng.range(1..7)` for a die roll.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn range<T: RangeValue>(&mut self, range: Range<T>) -> T {
+        assert!(range.start < range.end, "cannot pick from an empty range");
+        T::pick(self, range)
+    }
+
+    /// Returns a random point inside the unit circle, evenly distributed over its area.
+    pub fn gen_vec2_in_circle(&mut self) -> mint::Vector2<f32> {
+        let angle = self.f32() * std::f32::consts::TAU;
+        let radius = self.f32().sqrt();
+        mint::Vector2 {
+            x: radius * angle.cos(),
+            y: radius * angle.sin(),
+        }
+    }
+
+    /// Shuffles a slice into a random order.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            slice.swap(i, j);
+        }
+    }
+
+    /// Returns a random element of a slice, or `None` if it's empty.
+    pub fn choose<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+        if slice.is_empty() {
+            None
+        } else {
+            slice.get(self.below(slice.len() as u64) as usize)
+        }
+    }
+}
+
+impl Default for RandContext {
+    fn default() -> Self {
+        Self::from_time()
+    }
+}
+
+/// Numbers that [`RandContext::range`] can pick.
+pub trait RangeValue: PartialOrd + Sized {
+    /// Picks a number in the (non-empty) range.
+    fn pick(rng: &mut RandContext, range: Range<Self>) -> Self;
+}
+
+macro_rules! impl_range_int {
+    ($($t:ty),*) => {$(
+        impl RangeValue for $t {
+            fn pick(rng: &mut RandContext, range: Range<Self>) -> Self {
+                let span = (range.end as i128 - range.start as i128) as u64;
+                (range.start as i128 + rng.below(span) as i128) as $t
+            }
+        }
+    )*};
+}
+
+impl_range_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl RangeValue for f32 {
+    fn pick(rng: &mut RandContext, range: Range<Self>) -> Self {
+        let x = range.start + rng.f32() * (range.end - range.start);
+        // Rounding can land exactly on the end.
+        if x < range.end {
+            x
+        } else {
+            range.start
+        }
+    }
+}
+
+impl RangeValue for f64 {
+    fn pick(rng: &mut RandContext, range: Range<Self>) -> Self {
+        let x = range.start + rng.f64() * (range.end - range.start);
+        if x < range.end {
+            x
+        } else {
+            range.start
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_rand_deterministic() {
+        let mut a = RandContext::new(42);
+        let mut b = RandContext::new(42);
+        let seq = (0..16).map(|_| a.u32()).collect::<Vec<_>>();
+        assert_eq!(seq, (0..16).map(|_| b.u32()).collect::<Vec<_>>());
+        let mut c = RandContext::new(43);
+        assert_ne!(seq, (0..16).map(|_| c.u32()).collect::<Vec<_>>());
+
+        a.set_seed(42);
+        assert_eq!(a.u32(), seq[0]);
+
+        // Forks don't depend on what was drawn before, and differ between streams.
+        let mut fork = a.fork(1);
+        let mut again = b.fork(1);
+        assert_eq!(fork.u64(), again.u64());
+        assert_ne!(a.fork(1).u64(), a.fork(2).u64());
+        assert_ne!(a.fork(1).u64(), RandContext::new(42).u64());
+    }
+
+    #[test]
+    fn headless_test_rand_ranges() {
+        let mut rng = RandContext::new(7);
+        let mut seen = [false; 6];
+        for _ in 0..1000 {
+            let roll = rng.range(1..7);
+            assert!((1..7).contains(&roll));
+            seen[roll as usize - 1] = true;
+
+            assert!((-3..-1).contains(&rng.range(-3i8..-1)));
+            assert!((u64::MAX - 2..u64::MAX).contains(&rng.range(u64::MAX - 2..u64::MAX)));
+            assert!((0.5..0.75).contains(&rng.range(0.5..0.75)));
+
+            let p = rng.gen_vec2_in_circle();
+            assert!(p.x * p.x + p.y * p.y <= 1.0);
+        }
+        assert!(seen.iter().all(|&s| s));
+
+        let mut deck = (0..52).collect::<Vec<_>>();
+        rng.shuffle(&mut deck);
+        assert_ne!(deck, (0..52).collect::<Vec<_>>());
+        deck.sort();
+        assert_eq!(deck, (0..52).collect::<Vec<_>>());
+        assert_eq!(rng.choose::<u8>(&[]), None);
+    }
+}
@@ -9,7 +9,12 @@
 //! really needs to.  Enabling vsync by setting
 //! [`conf.window_setup.vsync`](../conf/struct.WindowSetup.html#structfield.vsync)
 //! in your [`Conf`](../conf/struct.Conf.html) object is generally the best
-//! way to cap your displayed framerate.
+//! way to cap your displayed framerate. To run at a specific rate instead, use
+//! [`TimeContext::set_target_fps()`](struct.TimeContext.html#method.set_target_fps).
+//!
+//! Game logic can run on its own clock, which can be paused, slowed down or sped up
+//! without affecting rendering, see
+//! [`TimeContext::set_game_time_scale()`](struct.TimeContext.html#method.set_game_time_scale).
 //!
 //! For a more detailed tutorial in how to handle frame timings in games,
 //! see <http://gafferongames.com/game-physics/fix-your-timestep/>
@@ -81,6 +86,14 @@ where
     fn latest(&self) -> T {
         self.contents[self.head]
     }
+
+    /// Returns the values inserted with [`push()`](#method.push)
+    /// that are still in the buffer, oldest first.
+    fn ordered(&self) -> impl ExactSizeIterator<Item = T> + '_ {
+        let len = self.contents.len();
+        let pushed = cmp::min(self.samples - 1, len);
+        (0..pushed).map(move |i| self.contents[(self.head + len + 1 - pushed + i) % len])
+    }
 }
 
 /// A structure that contains our time-tracking state.
@@ -91,11 +104,25 @@ pub struct TimeContext {
     frame_durations: LogBuffer<time::Duration>,
     residual_update_dt: time::Duration,
     frame_count: usize,
+    target_dt: Option<time::Duration>,
+    next_frame: time::Instant,
+    game_time_scale: f64,
+    game_delta: time::Duration,
+    game_time: time::Duration,
+    /// The time step last passed to `check_update_time`, used by `step_once`.
+    update_dt: Option<time::Duration>,
+    pending_steps: u32,
+    /// Steps taken this frame that `check_update_time` still has to return `true` for.
+    step_updates: u32,
 }
 
 /// How many frames we log update times for.
 const TIME_LOG_FRAMES: usize = 200;
 
+/// How long before a frame deadline the frame limiter stops sleeping and starts spinning,
+/// since OS sleeps tend to overshoot by about this much.
+const SPIN_MARGIN: time::Duration = time::Duration::from_millis(1);
+
 impl TimeContext {
     /// Creates a new `TimeContext` and initializes the start to this instant.
     pub fn new() -> TimeContext {
@@ -106,15 +133,73 @@ impl TimeContext {
             frame_durations: LogBuffer::new(TIME_LOG_FRAMES, initial_dt),
             residual_update_dt: time::Duration::from_secs(0),
             frame_count: 0,
+            target_dt: None,
+            next_frame: time::Instant::now(),
+            game_time_scale: 1.0,
+            game_delta: initial_dt,
+            game_time: time::Duration::ZERO,
+            update_dt: None,
+            pending_steps: 0,
+            step_updates: 0,
         }
     }
 
     /// Get the time between the start of the last frame and the current one;
     /// in other words, the length of the last frame.
+    ///
+    /// This is wall clock time, which keeps running while the game clock is paused;
+    /// use it for rendering and UI animations. See [`game_delta()`](#method.game_delta).
     pub fn delta(&self) -> time::Duration {
         self.frame_durations.latest()
     }
 
+    /// Get how far the game clock advanced during the last frame.
+    ///
+    /// This is the length of the last frame scaled by the
+    /// [game time scale](#method.set_game_time_scale), plus any
+    /// [steps](#method.step_once) taken; use it for game logic that should pause
+    /// and slow down with the game.
+    pub fn game_delta(&self) -> time::Duration {
+        self.game_delta
+    }
+
+    /// Returns how far the game clock has advanced since the game started.
+    pub fn game_time(&self) -> time::Duration {
+        self.game_time
+    }
+
+    /// Sets how fast the game clock runs compared to the wall clock: `0.0` pauses it,
+    /// `0.5` is slow motion and `2.0` is double speed. The default is `1.0`.
+    ///
+    /// This changes [`game_delta()`](#method.game_delta) and how often
+    /// [`check_update_time()`](#method.check_update_time) returns `true`, while
+    /// [`delta()`](#method.delta), the FPS and the frame limiter keep following the
+    /// wall clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is negative or not a number.
+    pub fn set_game_time_scale(&mut self, scale: f32) {
+        assert!(scale >= 0.0, "game time scale must not be negative");
+        self.game_time_scale = f64::from(scale);
+    }
+
+    /// Returns the scale set with [`set_game_time_scale()`](#method.set_game_time_scale).
+    pub fn game_time_scale(&self) -> f32 {
+        self.game_time_scale as f32
+    }
+
+    /// Advances the game clock by exactly one fixed update on the next frame, which is
+    /// mostly useful for stepping through the game frame by frame while it's paused.
+    ///
+    /// The step is as long as the time step last passed to
+    /// [`check_update_time()`](#method.check_update_time), so it returns `true` once
+    /// more for each step. Games that don't use it step by the
+    /// [target frame time](#method.set_target_fps), or else the average frame time.
+    pub fn step_once(&mut self) {
+        self.pending_steps += 1;
+    }
+
     /// Gets the average time of a frame, averaged
     /// over the last 200 frames.
     pub fn average_delta(&self) -> time::Duration {
@@ -146,12 +231,88 @@ impl TimeContext {
         self.frame_count
     }
 
+    /// Returns the lengths of the last 200 frames, oldest first.
+    ///
+    /// Useful for checking how even the frame pacing is, for example after
+    /// changing [`set_target_fps()`](#method.set_target_fps).
+    pub fn frame_time_histogram(&self) -> Vec<time::Duration> {
+        self.frame_durations.ordered().collect()
+    }
+
+    /// Like [`frame_time_histogram()`](#method.frame_time_histogram), without collecting into a `Vec`.
+    pub(crate) fn frame_durations(&self) -> impl ExactSizeIterator<Item = time::Duration> + '_ {
+        self.frame_durations.ordered()
+    }
+
+    /// Limits the frame rate of the event loop to the given number of frames per second,
+    /// or removes the limit with `None`.
+    ///
+    /// After presenting each frame, [`event::run()`](../event/fn.run.html) waits until it's time
+    /// for the next one, first sleeping and then spinning for the last millisecond or so, which
+    /// gives much steadier pacing than [`sleep()`](fn.sleep.html) or [`yield_now()`](fn.yield_now.html)
+    /// while still leaving the CPU idle most of the time.
+    ///
+    /// When vsync is enabled and the target is at or above the refresh rate of the monitor,
+    /// presenting already caps the frame rate, so no extra waiting is done.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fps` is `Some(0)`.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        assert_ne!(fps, Some(0), "target fps must be greater than 0");
+        self.target_dt = fps.map(fps_as_duration);
+        self.next_frame = time::Instant::now();
+    }
+
+    /// Returns the frame rate set with [`set_target_fps()`](#method.set_target_fps).
+    pub fn target_fps(&self) -> Option<u32> {
+        self.target_dt
+            .map(|dt| (1.0 / dt.as_secs_f64()).round() as u32)
+    }
+
+    /// Waits until it's time to start the next frame, according to the limit set
+    /// with [`set_target_fps()`](#method.set_target_fps). Does nothing if there is no limit.
+    ///
+    /// It's usually not necessary to call this function yourself,
+    /// [`event::run()`](../event/fn.run.html) will do it for you after presenting
+    /// each frame. You only need to call this function if you're writing your
+    /// own custom event loop.
+    pub fn wait_for_next_frame(&mut self) {
+        let target_dt = match self.target_dt {
+            Some(dt) => dt,
+            None => return,
+        };
+
+        self.next_frame += target_dt;
+        let now = time::Instant::now();
+        if self.next_frame <= now {
+            // We're running behind; don't try to catch up by skipping waits later on.
+            self.next_frame = now;
+            return;
+        }
+
+        if let Some(sleep_for) = (self.next_frame - now).checked_sub(SPIN_MARGIN) {
+            thread::sleep(sleep_for);
+        }
+        while time::Instant::now() < self.next_frame {
+            std::hint::spin_loop();
+        }
+    }
+
     /// Returns the time since the game was initialized,
     /// as reported by the system clock.
     pub fn time_since_start(&self) -> time::Duration {
         self.init_instant.elapsed()
     }
 
+    /// Converts a system timestamp, such as that of a gamepad event, to the clock of
+    /// [`time_since_start`](Self::time_since_start), never later than now.
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn since_start_at(&self, at: time::SystemTime) -> time::Duration {
+        let age = at.elapsed().unwrap_or_default();
+        self.time_since_start().saturating_sub(age)
+    }
+
     /// Check whether or not the desired amount of time has elapsed
     /// since the last frame.
     ///
@@ -168,9 +329,17 @@ impl TimeContext {
     /// of your code. If you want to limit the frame rate in both game logic and drawing consider writing
     /// your own event loop, or using a dirty bit for when to redraw graphics, which is set whenever the game
     /// logic runs.
+    ///
+    /// This follows the game clock, so it returns `true` less often while the
+    /// [game time scale](#method.set_game_time_scale) is below 1, and not at all while
+    /// the game is paused, except once for each [step](#method.step_once).
     pub fn check_update_time(&mut self, target_fps: u32) -> bool {
         let target_dt = fps_as_duration(target_fps);
-        if self.residual_update_dt > target_dt {
+        self.update_dt = Some(target_dt);
+        if self.step_updates > 0 {
+            self.step_updates -= 1;
+            true
+        } else if self.residual_update_dt > target_dt {
             self.residual_update_dt -= target_dt;
             true
         } else {
@@ -195,6 +364,19 @@ impl TimeContext {
         self.residual_update_dt
     }
 
+    /// Returns the [`remaining_update_time()`](#method.remaining_update_time) as a fraction
+    /// of the time step last passed to [`check_update_time()`](#method.check_update_time),
+    /// from `0.0` to `1.0`.
+    ///
+    /// This is how far between the last two update states to draw, e.g. with
+    /// [`DrawParam::lerp`](crate::graphics::DrawParam::lerp). It's `0.0` until
+    /// `check_update_time()` has been called.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.update_dt.map_or(0., |update_dt| {
+            (self.residual_update_dt.as_secs_f64() / update_dt.as_secs_f64()).min(1.) as f32
+        })
+    }
+
     /// Update the state of the `TimeContext` to record that
     /// another frame has taken place.  Necessary for the FPS
     /// tracking and [`check_update_time()`](fn.check_update_time.html)
@@ -207,11 +389,32 @@ impl TimeContext {
     pub fn tick(&mut self) {
         let now = time::Instant::now();
         let time_since_last = now - self.last_instant;
-        self.frame_durations.push(time_since_last);
         self.last_instant = now;
+        self.advance(time_since_last);
+    }
+
+    /// Records a frame of the given length.
+    fn advance(&mut self, time_since_last: time::Duration) {
+        self.frame_durations.push(time_since_last);
         self.frame_count += 1;
 
-        self.residual_update_dt += time_since_last;
+        let step = self
+            .update_dt
+            .or(self.target_dt)
+            .unwrap_or_else(|| self.average_delta());
+        let scaled = time_since_last.mul_f64(self.game_time_scale);
+        self.game_delta = scaled + step * self.pending_steps;
+        self.step_updates = std::mem::take(&mut self.pending_steps);
+        self.game_time += self.game_delta;
+        self.residual_update_dt += scaled;
+    }
+
+    /// Starts timing the current frame from now, so time spent before the event loop
+    /// started isn't counted as part of the first frame.
+    pub(crate) fn restart_frame_clock(&mut self) {
+        let now = time::Instant::now();
+        self.last_instant = now;
+        self.next_frame = now;
     }
 }
 
@@ -277,15 +480,7 @@ pub fn time_since_start(ctx: &Context) -> time::Duration {
 /// logic runs.
 #[deprecated(note = "Use `ctx.time.check_update_time` instead")]
 pub fn check_update_time(ctx: &mut Context, target_fps: u32) -> bool {
-    let timedata = &mut ctx.time;
-
-    let target_dt = fps_as_duration(target_fps);
-    if timedata.residual_update_dt > target_dt {
-        timedata.residual_update_dt -= target_dt;
-        true
-    } else {
-        false
-    }
+    ctx.time.check_update_time(target_fps)
 }
 
 /// Returns the fractional amount of a frame not consumed
@@ -329,3 +524,109 @@ pub fn yield_now() {
 pub fn ticks(ctx: &Context) -> usize {
     ctx.time.frame_count
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "gamepad")]
+    fn headless_test_input_timestamps() {
+        let time = TimeContext::new();
+        thread::sleep(time::Duration::from_millis(5));
+
+        // Event times follow the order the events happened in, and are never in the future.
+        let now = time::SystemTime::now();
+        let earlier = time.since_start_at(now - time::Duration::from_millis(3));
+        let later = time.since_start_at(now);
+        assert!(earlier < later);
+        assert!(later <= time.time_since_start());
+
+        let hour = time::Duration::from_secs(3600);
+        assert_eq!(time.since_start_at(now - hour), time::Duration::ZERO);
+        assert!(time.since_start_at(now + hour) <= time.time_since_start());
+    }
+
+    #[test]
+    fn headless_test_interpolation_alpha() {
+        let mut time = TimeContext::new();
+        assert_eq!(time.interpolation_alpha(), 0.);
+        time.residual_update_dt = time::Duration::from_millis(25);
+        assert!(time.check_update_time(50));
+        assert!((time.interpolation_alpha() - 0.25).abs() < 1e-6);
+        time.residual_update_dt = time::Duration::from_secs(1);
+        assert_eq!(time.interpolation_alpha(), 1.);
+    }
+
+    #[test]
+    fn headless_test_frame_limiter() {
+        let mut time = TimeContext::new();
+        assert!(time.frame_time_histogram().is_empty());
+
+        time.set_target_fps(Some(200));
+        assert_eq!(time.target_fps(), Some(200));
+        for _ in 0..5 {
+            time.wait_for_next_frame();
+            time.tick();
+        }
+
+        let frames = time.frame_time_histogram();
+        assert_eq!(frames.len(), 5);
+        // The first frame is measured from the creation of the context.
+        assert!(frames[1..]
+            .iter()
+            .all(|&dt| dt >= time::Duration::from_millis(4)));
+        assert_eq!(frames[4], time.delta());
+    }
+
+    #[test]
+    fn headless_test_game_time_scale() {
+        let frame = time::Duration::from_millis(100);
+        let mut time = TimeContext::new();
+        let mut updates = 0;
+        let mut run = |time: &mut TimeContext, frames: usize| {
+            for _ in 0..frames {
+                time.advance(frame);
+                while time.check_update_time(10) {
+                    updates += 1;
+                }
+            }
+            updates
+        };
+
+        // The first frame only fills the residual up to one update, which isn't enough.
+        assert_eq!(run(&mut time, 4), 3);
+        time.set_game_time_scale(0.5);
+        assert_eq!(run(&mut time, 4), 5);
+        assert_eq!(time.game_delta(), frame / 2);
+        assert_eq!(time.delta(), frame);
+
+        // Paused, the game only advances when stepped, one update at a time.
+        time.set_game_time_scale(0.0);
+        assert_eq!(run(&mut time, 4), 5);
+        assert_eq!(time.game_delta(), time::Duration::ZERO);
+        time.step_once();
+        assert_eq!(run(&mut time, 1), 6);
+        assert_eq!(time.game_delta(), fps_as_duration(10));
+        assert_eq!(run(&mut time, 3), 6);
+        time.step_once();
+        time.step_once();
+        assert_eq!(run(&mut time, 1), 8);
+
+        assert_eq!(time.ticks(), 17);
+        assert_eq!(time.game_time(), frame * 6 + fps_as_duration(10) * 3);
+    }
+
+    #[test]
+    fn headless_test_frame_time_histogram_order() {
+        let mut buffer = LogBuffer::new(3, 0);
+        for i in 1..=5 {
+            buffer.push(i);
+        }
+        assert_eq!(buffer.ordered().collect::<Vec<_>>(), [3, 4, 5]);
+
+        let mut buffer = LogBuffer::new(3, 0);
+        buffer.push(1);
+        assert_eq!(buffer.ordered().collect::<Vec<_>>(), [1]);
+    }
+}
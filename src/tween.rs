@@ -0,0 +1,505 @@
+//! Animating values over time with easing functions.
+//!
+//! A [`Tween`] moves a value from one point to another over a duration, shaped by an
+//! [`Easing`]. Advance it by the frame time each update and use the value it returns:
+//!
+//! ```rust
+//! # use ggez::{tween::{Easing, Tween}, glam::Vec2};
+//! # use std::time::Duration;
+//! let mut slide = Tween::new(Vec2::ZERO, Vec2::new(300., 0.), Duration::from_millis(500), Easing::CubicOut)
+//!     .then(Vec2::new(300., 200.), Duration::from_millis(250), Easing::QuadInOut);
+//! // In `update`, with `ctx.time.delta()`:
+//! let position = slide.advance(Duration::from_millis(16));
+//! # assert!(position.x > 0.);
+//! ```
+//!
+//! Anything implementing [`Lerp`] can be tweened, including `f32`, the `glam` vectors,
+//! [`Color`], [`Rect`] and [`DrawParam`].
+
+use crate::graphics::{Color, ColorLerpSpace, DrawParam, Rect};
+use std::{f32::consts::PI, fmt, time::Duration};
+
+/// Values that can be blended, so a [`Tween`] can animate them.
+pub trait Lerp: Clone {
+    /// Returns `self` at `t = 0.0`, `other` at `t = 1.0`, and a blend in between.
+    ///
+    /// Some easings overshoot, so `t` can be a little outside of `0.0..=1.0`.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * f64::from(t)
+    }
+}
+
+impl Lerp for glam::Vec2 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glam::Vec2::lerp(*self, *other, t)
+    }
+}
+
+impl Lerp for glam::Vec3 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glam::Vec3::lerp(*self, *other, t)
+    }
+}
+
+impl Lerp for glam::Vec4 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glam::Vec4::lerp(*self, *other, t)
+    }
+}
+
+impl Lerp for mint::Point2<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glam::Vec2::from(*self)
+            .lerp(glam::Vec2::from(*other), t)
+            .into()
+    }
+}
+
+impl Lerp for mint::Vector2<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glam::Vec2::from(*self)
+            .lerp(glam::Vec2::from(*other), t)
+            .into()
+    }
+}
+
+impl Lerp for Color {
+    /// Blends the sRGB components; use [`Color::lerp`] for other color spaces.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Color::lerp(*self, *other, t, ColorLerpSpace::Srgb)
+    }
+}
+
+impl Lerp for DrawParam {
+    /// See [`DrawParam::lerp`].
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        DrawParam::lerp(self, other, t)
+    }
+}
+
+impl Lerp for Rect {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Rect::new(
+            self.x.lerp(&other.x, t),
+            self.y.lerp(&other.y, t),
+            self.w.lerp(&other.w, t),
+            self.h.lerp(&other.h, t),
+        )
+    }
+}
+
+/// How a [`Tween`] speeds up and slows down, see <https://easings.net> for pictures.
+///
+/// `In` easings start slowly, `Out` easings end slowly, and `InOut` easings do both.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    /// Pulls back a little before starting.
+    BackIn,
+    /// Overshoots a little before settling.
+    BackOut,
+    BackInOut,
+    /// Wobbles like a spring before starting.
+    ElasticIn,
+    /// Wobbles like a spring around the end.
+    ElasticOut,
+    ElasticInOut,
+    /// Bounces off the start.
+    BounceIn,
+    /// Bounces like a dropped ball at the end.
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Maps the fraction `t` of the elapsed duration, in `0.0..=1.0`, to how far along
+    /// the value should be. Always `0.0` at the start and `1.0` at the end.
+    pub fn apply(self, t: f32) -> f32 {
+        const BACK: f32 = 1.701_58;
+        const BACK_IN_OUT: f32 = BACK * 1.525;
+        const ELASTIC: f32 = 2. * PI / 3.;
+        const ELASTIC_IN_OUT: f32 = 2. * PI / 4.5;
+
+        let t = t.clamp(0., 1.);
+        // Mirrors an `In` easing for the `Out` variant.
+        let out = |f: fn(f32) -> f32| 1. - f(1. - t);
+        // Runs an `In` easing for the first half and its mirror for the second.
+        let in_out = |f: fn(f32) -> f32| {
+            if t < 0.5 {
+                f(2. * t) / 2.
+            } else {
+                1. - f(2. - 2. * t) / 2.
+            }
+        };
+
+        let quad: fn(f32) -> f32 = |t| t * t;
+        let cubic: fn(f32) -> f32 = |t| t * t * t;
+        let quart: fn(f32) -> f32 = |t| t * t * t * t;
+        let sine: fn(f32) -> f32 = |t| 1. - (t * PI / 2.).cos();
+        let expo: fn(f32) -> f32 = |t| {
+            if t <= 0. {
+                0.
+            } else {
+                2f32.powf(10. * t - 10.)
+            }
+        };
+        let circ: fn(f32) -> f32 = |t| 1. - (1. - t * t).max(0.).sqrt();
+        fn bounce_out(t: f32) -> f32 {
+            const N: f32 = 7.5625;
+            const D: f32 = 2.75;
+            if t < 1. / D {
+                N * t * t
+            } else if t < 2. / D {
+                let t = t - 1.5 / D;
+                N * t * t + 0.75
+            } else if t < 2.5 / D {
+                let t = t - 2.25 / D;
+                N * t * t + 0.9375
+            } else {
+                let t = t - 2.625 / D;
+                N * t * t + 0.984_375
+            }
+        }
+        fn bounce_in(t: f32) -> f32 {
+            1. - bounce_out(1. - t)
+        }
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => quad(t),
+            Easing::QuadOut => out(quad),
+            Easing::QuadInOut => in_out(quad),
+            Easing::CubicIn => cubic(t),
+            Easing::CubicOut => out(cubic),
+            Easing::CubicInOut => in_out(cubic),
+            Easing::QuartIn => quart(t),
+            Easing::QuartOut => out(quart),
+            Easing::QuartInOut => in_out(quart),
+            Easing::SineIn => sine(t),
+            Easing::SineOut => out(sine),
+            Easing::SineInOut => in_out(sine),
+            Easing::ExpoIn => expo(t),
+            Easing::ExpoOut => out(expo),
+            Easing::ExpoInOut => in_out(expo),
+            Easing::CircIn => circ(t),
+            Easing::CircOut => out(circ),
+            Easing::CircInOut => in_out(circ),
+            Easing::BackIn => t * t * ((BACK + 1.) * t - BACK),
+            Easing::BackOut => {
+                let t = t - 1.;
+                1. + t * t * ((BACK + 1.) * t + BACK)
+            }
+            Easing::BackInOut => {
+                let t = 2. * t;
+                if t < 1. {
+                    t * t * ((BACK_IN_OUT + 1.) * t - BACK_IN_OUT) / 2.
+                } else {
+                    let t = t - 2.;
+                    (t * t * ((BACK_IN_OUT + 1.) * t + BACK_IN_OUT) + 2.) / 2.
+                }
+            }
+            Easing::ElasticIn | Easing::ElasticOut | Easing::ElasticInOut if t <= 0. || t >= 1. => {
+                t
+            }
+            Easing::ElasticIn => -(2f32.powf(10. * t - 10.)) * ((10. * t - 10.75) * ELASTIC).sin(),
+            Easing::ElasticOut => 2f32.powf(-10. * t) * ((10. * t - 0.75) * ELASTIC).sin() + 1.,
+            Easing::ElasticInOut => {
+                let s = ((20. * t - 11.125) * ELASTIC_IN_OUT).sin();
+                if t < 0.5 {
+                    -(2f32.powf(20. * t - 10.) * s) / 2.
+                } else {
+                    2f32.powf(-20. * t + 10.) * s / 2. + 1.
+                }
+            }
+            Easing::BounceIn => bounce_in(t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(bounce_in),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    Once,
+    Loop,
+    PingPong,
+}
+
+#[derive(Debug, Clone)]
+struct Segment<T> {
+    from: T,
+    to: T,
+    duration: Duration,
+    easing: Easing,
+}
+
+/// Animates a value through one or more segments, see the [module docs](self).
+pub struct Tween<T: Lerp> {
+    segments: Vec<Segment<T>>,
+    repeat: Repeat,
+    elapsed: Duration,
+    finished: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+}
+
+impl<T: Lerp> Tween<T> {
+    /// Creates a tween from `from` to `to`, taking `duration`.
+    pub fn new(from: T, to: T, duration: Duration, easing: Easing) -> Self {
+        Tween {
+            segments: vec![Segment {
+                from,
+                to,
+                duration,
+                easing,
+            }],
+            repeat: Repeat::Once,
+            elapsed: Duration::ZERO,
+            finished: false,
+            on_complete: None,
+        }
+    }
+
+    /// Adds a segment that continues from where the previous one ends to `to`.
+    #[must_use]
+    pub fn then(mut self, to: T, duration: Duration, easing: Easing) -> Self {
+        let from = self.end().clone();
+        self.segments.push(Segment {
+            from,
+            to,
+            duration,
+            easing,
+        });
+        self
+    }
+
+    /// Makes the tween start over from the beginning once it reaches the end, forever.
+    #[must_use]
+    pub fn looping(mut self) -> Self {
+        self.repeat = Repeat::Loop;
+        self
+    }
+
+    /// Makes the tween play backwards once it reaches the end, then forwards again, forever.
+    #[must_use]
+    pub fn ping_pong(mut self) -> Self {
+        self.repeat = Repeat::PingPong;
+        self
+    }
+
+    /// Sets a function to call when the tween finishes. Looping tweens call it every
+    /// time they start over, ping-ponging ones every time they're back at the start.
+    #[must_use]
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    /// Moves the tween forward by `dt`, usually [`TimeContext::delta`](crate::timer::TimeContext::delta),
+    /// and returns the new value.
+    pub fn advance(&mut self, dt: Duration) -> T {
+        if !self.finished {
+            let cycle = self.cycle();
+            self.elapsed += dt;
+            if cycle.is_zero() || self.repeat == Repeat::Once {
+                if self.elapsed >= cycle {
+                    self.elapsed = cycle;
+                    self.finished = true;
+                    self.complete();
+                }
+            } else {
+                while self.elapsed >= cycle {
+                    self.elapsed -= cycle;
+                    self.complete();
+                }
+            }
+        }
+        self.value()
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> T {
+        let total = self.duration();
+        let mut at = self.elapsed;
+        if at > total {
+            // Only ping-pong cycles are longer than the tween; this is the way back.
+            at = total.saturating_sub(at - total);
+        }
+
+        for segment in &self.segments {
+            if at < segment.duration {
+                let t = at.as_secs_f32() / segment.duration.as_secs_f32();
+                return segment.from.lerp(&segment.to, segment.easing.apply(t));
+            }
+            at -= segment.duration;
+        }
+        self.end().clone()
+    }
+
+    /// Returns the total duration of all segments, played once.
+    pub fn duration(&self) -> Duration {
+        self.segments.iter().map(|s| s.duration).sum()
+    }
+
+    /// Returns `true` once a tween that doesn't repeat has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Starts the tween over from the beginning.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+    }
+
+    fn end(&self) -> &T {
+        // There's always at least the segment from `new`.
+        &self.segments[self.segments.len() - 1].to
+    }
+
+    fn cycle(&self) -> Duration {
+        match self.repeat {
+            Repeat::PingPong => 2 * self.duration(),
+            _ => self.duration(),
+        }
+    }
+
+    fn complete(&mut self) {
+        if let Some(f) = &mut self.on_complete {
+            f();
+        }
+    }
+}
+
+impl<T: Lerp + fmt::Debug> fmt::Debug for Tween<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tween")
+            .field("segments", &self.segments)
+            .field("repeat", &self.repeat)
+            .field("elapsed", &self.elapsed)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    const ALL: [Easing; 28] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::QuartIn,
+        Easing::QuartOut,
+        Easing::QuartInOut,
+        Easing::SineIn,
+        Easing::SineOut,
+        Easing::SineInOut,
+        Easing::ExpoIn,
+        Easing::ExpoOut,
+        Easing::ExpoInOut,
+        Easing::CircIn,
+        Easing::CircOut,
+        Easing::CircInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+    ];
+
+    #[test]
+    fn headless_test_easing_boundaries() {
+        for easing in ALL {
+            assert!(easing.apply(0.).abs() < 1e-3, "{easing:?} at 0");
+            assert!((easing.apply(1.) - 1.).abs() < 1e-3, "{easing:?} at 1");
+            assert!(easing.apply(-1.).abs() < 1e-3, "{easing:?} clamps");
+            assert!((easing.apply(2.) - 1.).abs() < 1e-3, "{easing:?} clamps");
+            let mid = easing.apply(0.5);
+            assert!((-0.5..=1.5).contains(&mid), "{easing:?} at 0.5: {mid}");
+        }
+        assert!((Easing::QuadInOut.apply(0.5) - 0.5).abs() < 1e-6);
+        assert!(Easing::CubicOut.apply(0.5) > 0.5);
+        assert!(Easing::CubicIn.apply(0.5) < 0.5);
+        assert!(Easing::BackIn.apply(0.2) < 0.);
+        assert!(Easing::BackOut.apply(0.8) > 1.);
+    }
+
+    #[test]
+    fn headless_test_tween() {
+        let ms = Duration::from_millis;
+        let done = Rc::new(Cell::new(0));
+        let counter = done.clone();
+        let mut tween = Tween::new(0.0f32, 10., ms(100), Easing::Linear)
+            .then(20., ms(100), Easing::Linear)
+            .on_complete(move || counter.set(counter.get() + 1));
+        assert_eq!(tween.duration(), ms(200));
+        assert_eq!(tween.value(), 0.);
+        assert!((tween.advance(ms(50)) - 5.).abs() < 1e-4);
+        assert!((tween.advance(ms(100)) - 15.).abs() < 1e-4);
+        assert_eq!(tween.advance(ms(100)), 20.);
+        assert!(tween.is_finished());
+        assert_eq!(tween.advance(ms(100)), 20.);
+        assert_eq!(done.get(), 1);
+        tween.reset();
+        assert_eq!(tween.value(), 0.);
+
+        let mut looping = Tween::new(0.0f32, 1., ms(100), Easing::Linear).looping();
+        assert!((looping.advance(ms(250)) - 0.5).abs() < 1e-4);
+        assert!(!looping.is_finished());
+
+        let done = Rc::new(Cell::new(0));
+        let counter = done.clone();
+        let mut ping_pong = Tween::new(0.0f32, 1., ms(100), Easing::Linear)
+            .ping_pong()
+            .on_complete(move || counter.set(counter.get() + 1));
+        assert!((ping_pong.advance(ms(75)) - 0.75).abs() < 1e-4);
+        assert!((ping_pong.advance(ms(50)) - 0.75).abs() < 1e-4);
+        assert!((ping_pong.advance(ms(100)) - 0.25).abs() < 1e-4);
+        assert_eq!(done.get(), 1);
+
+        // A tween without duration jumps to the end.
+        let mut instant = Tween::new(Color::BLACK, Color::WHITE, ms(0), Easing::CubicOut).looping();
+        assert_eq!(instant.advance(ms(16)), Color::WHITE);
+    }
+}
@@ -257,7 +257,7 @@ impl PhysicalFS {
     /// a new `PathBuf` containing the canonical
     /// absolute path you get when appending it
     /// to this filesystem's root.
-    fn to_absolute(&self, p: &Path) -> GameResult<PathBuf> {
+    pub(crate) fn to_absolute(&self, p: &Path) -> GameResult<PathBuf> {
         if let Some(safe_path) = sanitize_path(p) {
             let mut root_path = self.root.clone();
             root_path.push(safe_path);
@@ -422,31 +422,84 @@ impl VFS for PhysicalFS {
 #[derive(Debug)]
 #[allow(clippy::upper_case_acronyms)]
 pub struct OverlayFS {
-    roots: VecDeque<Box<dyn VFS>>,
+    roots: VecDeque<Root>,
+    next_id: usize,
+}
+
+/// A filesystem in an `OverlayFS`, along with what it's needed to order and identify it by.
+#[derive(Debug)]
+struct Root {
+    id: usize,
+    priority: i32,
+    fs: Box<dyn VFS>,
 }
 
 impl OverlayFS {
     pub fn new() -> Self {
         Self {
             roots: VecDeque::new(),
+            next_id: 0,
         }
     }
 
+    fn root(&mut self, fs: Box<dyn VFS>, priority: i32) -> Root {
+        let id = self.next_id;
+        self.next_id += 1;
+        Root { id, priority, fs }
+    }
+
     /// Adds a new VFS to the front of the list.
     /// Currently unused, I suppose, but good to
     /// have at least for tests.
     #[allow(dead_code)]
     pub fn push_front(&mut self, fs: Box<dyn VFS>) {
-        self.roots.push_front(fs);
+        let priority = self.roots.front().map_or(0, |root| root.priority);
+        let root = self.root(fs, priority);
+        self.roots.push_front(root);
     }
 
     /// Adds a new VFS to the end of the list.
+    #[allow(dead_code)]
     pub fn push_back(&mut self, fs: Box<dyn VFS>) {
-        self.roots.push_back(fs);
+        let priority = self.roots.back().map_or(0, |root| root.priority);
+        let root = self.root(fs, priority);
+        self.roots.push_back(root);
+    }
+
+    /// Adds a new VFS after all others with the same or a higher priority,
+    /// returning an id that identifies it.
+    pub fn insert(&mut self, fs: Box<dyn VFS>, priority: i32) -> usize {
+        let root = self.root(fs, priority);
+        let id = root.id;
+        let index = self
+            .roots
+            .iter()
+            .position(|root| root.priority < priority)
+            .unwrap_or(self.roots.len());
+        self.roots.insert(index, root);
+        id
     }
 
-    pub fn roots(&self) -> &VecDeque<Box<dyn VFS>> {
-        &self.roots
+    /// Returns the filesystems in the order they are searched.
+    pub fn roots(&self) -> impl Iterator<Item = &dyn VFS> {
+        self.roots.iter().map(|root| root.fs.as_ref())
+    }
+
+    /// Returns the filesystem with the given id.
+    pub fn get(&self, id: usize) -> Option<&dyn VFS> {
+        self.roots
+            .iter()
+            .find(|root| root.id == id)
+            .map(|root| root.fs.as_ref())
+    }
+
+    /// Returns the ids of all filesystems containing `path`, in the order they are searched.
+    pub fn resolve(&self, path: &Path) -> Vec<usize> {
+        self.roots
+            .iter()
+            .filter(|root| root.fs.exists(path))
+            .map(|root| root.id)
+            .collect()
     }
 }
 
@@ -455,7 +508,7 @@ impl VFS for OverlayFS {
     fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
         let mut tried: Vec<(PathBuf, GameError)> = vec![];
 
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             match vfs.open_options(path, open_options) {
                 Err(e) => {
                     if let Some(vfs_path) = vfs.to_path_buf() {
@@ -473,7 +526,7 @@ impl VFS for OverlayFS {
 
     /// Create a directory at the location by this path
     fn mkdir(&self, path: &Path) -> GameResult {
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             match vfs.mkdir(path) {
                 Err(_) => (),
                 f => return f,
@@ -486,7 +539,7 @@ impl VFS for OverlayFS {
 
     /// Remove a file
     fn rm(&self, path: &Path) -> GameResult {
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             match vfs.rm(path) {
                 Err(_) => (),
                 f => return f,
@@ -499,7 +552,7 @@ impl VFS for OverlayFS {
 
     /// Remove a file or directory and all its contents
     fn rmrf(&self, path: &Path) -> GameResult {
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             match vfs.rmrf(path) {
                 Err(_) => (),
                 f => return f,
@@ -512,7 +565,7 @@ impl VFS for OverlayFS {
 
     /// Check if the file exists
     fn exists(&self, path: &Path) -> bool {
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             if vfs.exists(path) {
                 return true;
             }
@@ -523,7 +576,7 @@ impl VFS for OverlayFS {
 
     /// Get the file's metadata
     fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
-        for vfs in &self.roots {
+        for vfs in self.roots() {
             match vfs.metadata(path) {
                 Err(_) => (),
                 f => return f,
@@ -538,10 +591,21 @@ impl VFS for OverlayFS {
     fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
         // This is tricky 'cause we have to actually merge iterators together...
         // Doing it the simple and stupid way works though.
+        // Entries found in several filesystems are only listed once.
         let mut v = Vec::new();
-        for fs in &self.roots {
+        for fs in self.roots() {
             if let Ok(rddir) = fs.read_dir(path) {
-                v.extend(rddir);
+                for entry in rddir {
+                    let duplicate = match &entry {
+                        Ok(path) => v
+                            .iter()
+                            .any(|e: &GameResult<PathBuf>| e.as_ref().ok() == Some(path)),
+                        Err(_) => false,
+                    };
+                    if !duplicate {
+                        v.push(entry);
+                    }
+                }
             }
         }
         Ok(Box::new(v.into_iter()))